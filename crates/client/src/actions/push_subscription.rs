@@ -12,8 +12,9 @@ use crate::utils::browser::get_web_push_manger;
 pub fn subscribe_action(
     action_updated_subscription_trigger: Trigger,
     set_error: WriteSignal<Option<String>>,
-) -> Action<(), ()> {
-    create_action(move |_| {
+) -> Action<Option<String>, ()> {
+    create_action(move |device_label: &Option<String>| {
+        let device_label = device_label.clone();
         debug!("Subscribing to push notification");
 
         // Clear the error immediately so if there was a previous one the user action
@@ -24,7 +25,7 @@ pub fn subscribe_action(
         async move {
             let result = async move {
                 let push_manager = get_web_push_manger().await?;
-                record_subscription(&push_manager).await
+                record_subscription(&push_manager, device_label).await
             }
             .await;
 
@@ -54,10 +55,11 @@ pub fn unsubscribe_action(
                 let push_manager = get_web_push_manger().await?;
                 let subscription: PushSubscription =
                     JsFuture::from(push_manager.get_subscription()?).await?.into();
+                let endpoint = subscription.endpoint();
                 JsFuture::from(subscription.unsubscribe()?).await?;
 
-                // Remove the subscription from the backend
-                remove_subscription().await?;
+                // Remove this device's subscription from the backend
+                remove_subscription(endpoint).await?;
 
                 action_updated_subscription_trigger.notify();
                 Ok::<_, FrontendError<_>>(())
@@ -0,0 +1,46 @@
+use gloo::net::http::Method;
+use shared::{
+    api::{
+        self,
+        error::{FrontendError, NoValidation, ServerError},
+        response_errors::AccountError,
+    },
+    model::User,
+    utils::fetch::json_request,
+};
+use tracing::{debug, warn};
+use web_sys::CredentialRequestOptions;
+use webauthn_rs_proto::{PublicKeyCredential as WebauthnPublicKey, RequestChallengeResponse};
+
+use crate::{api::get_credentials, db::PromiserFetcher, utils::JsValueIntoOk};
+
+/// Deletes the account on the server once one of the user's passkeys confirms
+/// it
+pub async fn delete_account() -> Result<(), FrontendError<ServerError<AccountError>>> {
+    debug!("delete_account::json_request::delete_account_start");
+    let request_challenge_response: RequestChallengeResponse =
+        json_request(Method::POST, api::Auth::DeleteAccountStart.path(), None::<&()>).await?;
+
+    let credential_request_options: CredentialRequestOptions = request_challenge_response.into();
+
+    debug!("delete_account::get_credentials");
+    let public_key_credential = get_credentials(credential_request_options).await?;
+    let public_key_credentials: WebauthnPublicKey = public_key_credential.ok()?;
+
+    debug!("delete_account::json_request::delete_account_finish");
+    json_request(
+        Method::POST,
+        api::Auth::DeleteAccountFinish.path(),
+        Some(&NoValidation(public_key_credentials)),
+    )
+    .await
+}
+
+/// Removes the user from the local database once their account is gone,
+/// everything of theirs goes with them. Failing is only logged, the server
+/// side is what matters
+pub async fn forget_user(user: &User) {
+    if let Err(e) = user.delete().await {
+        warn!("Error removing user {} from the local database: {e:?}", user.id);
+    }
+}
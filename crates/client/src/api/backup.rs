@@ -0,0 +1,21 @@
+use gloo::net::http::Method;
+use shared::{
+    api::{
+        self,
+        error::{FrontendError, ServerError},
+        payloads::{DatabaseBackup, DatabaseBackupInfo},
+        response_errors::BackupError,
+    },
+    utils::fetch::json_request,
+};
+
+pub async fn fetch_backup() -> Result<DatabaseBackup, FrontendError<ServerError<BackupError>>> {
+    json_request::<_, DatabaseBackup, _>(Method::GET, api::Object::Backup.path(), None::<&()>).await
+}
+
+pub async fn upload_backup(
+    backup: &DatabaseBackup,
+) -> Result<DatabaseBackupInfo, FrontendError<ServerError<BackupError>>> {
+    json_request::<_, DatabaseBackupInfo, _>(Method::POST, api::Object::Backup.path(), Some(backup))
+        .await
+}
@@ -3,13 +3,29 @@ use shared::{
     api::{
         self,
         error::{FrontendError, ServerError},
+        payloads::{CreateTemporaryLoginRequest, TemporaryLoginPinRequest},
         response_errors::TemporaryLoginError,
     },
-    model::TemporaryLogin,
+    model::{TemporaryLogin, User},
     utils::fetch::json_request,
 };
 
 pub async fn create_temporary_login(
+    pin: bool,
 ) -> Result<TemporaryLogin, FrontendError<ServerError<TemporaryLoginError>>> {
-    Ok(json_request(Method::POST, api::Auth::CreateTemporaryLogin.path(), None::<&()>).await?)
+    let request = CreateTemporaryLoginRequest { pin };
+    Ok(json_request(Method::POST, api::Auth::CreateTemporaryLogin.path(), Some(&request)).await?)
+}
+
+/// Removes the logged in user's outstanding temporary login
+pub async fn revoke_temporary_login() -> Result<(), FrontendError<ServerError<TemporaryLoginError>>>
+{
+    json_request(Method::POST, api::Auth::RevokeTemporaryLogin.path(), None::<&()>).await
+}
+
+/// Logs in with the pin of a temporary login made on another device
+pub async fn temporary_login_pin(
+    request: &TemporaryLoginPinRequest,
+) -> Result<User, FrontendError<ServerError<TemporaryLoginError>>> {
+    json_request(Method::POST, api::Auth::TemporaryLoginPin.path(), Some(request)).await
 }
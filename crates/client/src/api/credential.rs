@@ -0,0 +1,32 @@
+use gloo::net::http::Method;
+use shared::{
+    api::{
+        self,
+        error::{FrontendError, ServerError},
+        payloads::{CredentialInfo, RenameCredentialRequest},
+        response_errors::CredentialError,
+    },
+    utils::fetch::json_request,
+};
+
+fn credential_path(id: &str) -> String {
+    api::Object::CredentialId.path().replace(":id", id)
+}
+
+pub async fn list_credentials(
+) -> Result<Vec<CredentialInfo>, FrontendError<ServerError<CredentialError>>> {
+    json_request(Method::GET, api::Object::Credential.path(), None::<&()>).await
+}
+
+pub async fn rename_credential(
+    id: &str,
+    name: String,
+) -> Result<CredentialInfo, FrontendError<ServerError<CredentialError>>> {
+    json_request(Method::PATCH, &credential_path(id), Some(&RenameCredentialRequest { name })).await
+}
+
+pub async fn delete_credential(
+    id: &str,
+) -> Result<(), FrontendError<ServerError<CredentialError>>> {
+    json_request(Method::DELETE, &credential_path(id), None::<&()>).await
+}
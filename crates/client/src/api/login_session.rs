@@ -0,0 +1,27 @@
+use gloo::net::http::Method;
+use shared::{
+    api::{
+        self,
+        error::{FrontendError, Nothing, ServerError},
+        payloads::LoginSessionInfo,
+        response_errors::LoginSessionError,
+    },
+    types::Uuid,
+    utils::fetch::json_request,
+};
+
+pub async fn logout() -> Result<(), FrontendError<ServerError<Nothing>>> {
+    json_request(Method::POST, api::Auth::Logout.path(), None::<&()>).await
+}
+
+pub async fn list_login_sessions(
+) -> Result<Vec<LoginSessionInfo>, FrontendError<ServerError<LoginSessionError>>> {
+    json_request(Method::GET, api::Object::LoginSession.path(), None::<&()>).await
+}
+
+pub async fn revoke_login_session(
+    id: Uuid,
+) -> Result<(), FrontendError<ServerError<LoginSessionError>>> {
+    let path = api::Object::LoginSessionId.path().replace(":id", &id.to_string());
+    json_request(Method::DELETE, &path, None::<&()>).await
+}
@@ -31,6 +31,27 @@ pub use ping::*;
 mod rtc;
 pub use rtc::*;
 
+mod backup;
+pub use backup::*;
+
+mod credential;
+pub use credential::*;
+
+mod recovery;
+pub use recovery::*;
+
+mod login_session;
+pub use login_session::*;
+
+mod pairing;
+pub use pairing::*;
+
+mod profile;
+pub use profile::*;
+
+mod account;
+pub use account::*;
+
 pub async fn run_promise_with_timeout(
     promise: Promise,
     timeout: Duration,
@@ -0,0 +1,34 @@
+use gloo::net::http::Method;
+use shared::{
+    api::{
+        self,
+        error::{FrontendError, ServerError},
+        payloads::{PairingCode, PairingDecision, PairingInfo},
+        response_errors::PairingError,
+    },
+    model::User,
+    utils::fetch::json_request,
+};
+
+/// Asks to be logged in by another device
+pub async fn start_pairing() -> Result<PairingCode, FrontendError<ServerError<PairingError>>> {
+    json_request(Method::POST, api::Auth::PairingStart.path(), None::<&()>).await
+}
+
+pub async fn fetch_pairing(
+    code: &str,
+) -> Result<PairingInfo, FrontendError<ServerError<PairingError>>> {
+    let path = api::Object::PairingId.path().replace(":id", code);
+    json_request(Method::GET, &path, None::<&()>).await
+}
+
+pub async fn decide_pairing(
+    decision: &PairingDecision,
+) -> Result<(), FrontendError<ServerError<PairingError>>> {
+    json_request(Method::POST, api::Auth::PairingDecision.path(), Some(decision)).await
+}
+
+/// Logs in once another device has approved the pairing
+pub async fn finish_pairing() -> Result<User, FrontendError<ServerError<PairingError>>> {
+    json_request(Method::POST, api::Auth::PairingFinish.path(), None::<&()>).await
+}
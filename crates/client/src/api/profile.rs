@@ -0,0 +1,39 @@
+use gloo::net::http::Method;
+use shared::{
+    api::{
+        self,
+        error::{FrontendError, ServerError},
+        payloads::UpdateProfileRequest,
+        response_errors::ModelError,
+    },
+    model::{Preferences, User},
+    utils::fetch::json_request,
+};
+use tracing::warn;
+
+use crate::db::PromiserFetcher;
+
+pub async fn update_profile(
+    request: &UpdateProfileRequest,
+) -> Result<User, FrontendError<ServerError<ModelError>>> {
+    json_request(Method::PATCH, api::Object::User.path(), Some(request)).await
+}
+
+pub async fn fetch_preferences() -> Result<Preferences, FrontendError<ServerError<ModelError>>> {
+    json_request(Method::GET, api::Object::Preferences.path(), None::<&()>).await
+}
+
+/// Replaces the preferences, returning the user with them
+pub async fn update_preferences(
+    preferences: &Preferences,
+) -> Result<User, FrontendError<ServerError<ModelError>>> {
+    json_request(Method::PATCH, api::Object::Preferences.path(), Some(preferences)).await
+}
+
+/// Writes the user as the server has it into the local database so the
+/// preferences are there offline. Failing only loses that so it's just logged
+pub async fn mirror_user(user: &User) {
+    if let Err(e) = user.upsert().await {
+        warn!("Error mirroring user {} into the local database: {e:?}", user.id);
+    }
+}
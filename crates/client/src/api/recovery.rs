@@ -0,0 +1,24 @@
+use gloo::net::http::Method;
+use shared::{
+    api::{
+        self,
+        error::{FrontendError, ServerError},
+        payloads::{RecoverRequest, RecoveryCodes},
+        response_errors::RecoveryError,
+    },
+    model::User,
+    utils::fetch::json_request,
+};
+
+/// Logs in with a recovery code, using it up
+pub async fn recover(
+    request: &RecoverRequest,
+) -> Result<User, FrontendError<ServerError<RecoveryError>>> {
+    json_request(Method::POST, api::Auth::Recover.path(), Some(request)).await
+}
+
+/// Replaces the logged in user's recovery codes with new ones
+pub async fn regenerate_recovery_codes(
+) -> Result<RecoveryCodes, FrontendError<ServerError<RecoveryError>>> {
+    json_request(Method::POST, api::Auth::RecoveryCodes.path(), None::<&()>).await
+}
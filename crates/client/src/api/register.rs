@@ -3,6 +3,7 @@ use shared::{
     api::{
         self,
         error::{FrontendError, NoValidation, ServerError},
+        payloads::RecoveryCodes,
         response_errors::RegisterError,
     },
     model::RegistrationUser,
@@ -14,9 +15,11 @@ use webauthn_rs_proto::{CreationChallengeResponse, RegisterPublicKeyCredential};
 
 use crate::{api::create_credentials, utils::JsValueIntoOk};
 
+/// Registers the user and returns their recovery codes which won't be available
+/// again
 pub async fn register(
     reg_user: &RegistrationUser,
-) -> Result<(), FrontendError<ServerError<RegisterError>>> {
+) -> Result<RecoveryCodes, FrontendError<ServerError<RegisterError>>> {
     // Ask the server to start the registration process and return a challenge
     debug!("register::json_request::register_start");
     let creation_challenge_response: CreationChallengeResponse =
@@ -37,12 +40,12 @@ pub async fn register(
 
     // Complete the registration with the server
     debug!("register::json_request::register_finish");
-    json_request::<_, (), _>(
+    let recovery_codes = json_request(
         Method::POST,
         api::Auth::RegisterFinish.path(),
         Some(&NoValidation(register_public_key_credentials)),
     )
     .await?;
 
-    Ok(())
+    Ok(recovery_codes)
 }
@@ -0,0 +1,174 @@
+use leptos::{
+    component, create_action, create_local_resource, create_rw_signal, create_signal,
+    event_target_value, view, CollectView, IntoView, Signal, SignalGet, SignalSet, SignalUpdate,
+    SignalWith, Transition,
+};
+use shared::api::{
+    error::{FrontendError, ServerError},
+    payloads::CredentialInfo,
+    response_errors::CredentialError,
+};
+
+use crate::{
+    api::{delete_credential, list_credentials, rename_credential},
+    components::FrontendErrorBoundary,
+};
+
+type CredentialServerError = ServerError<CredentialError>;
+
+fn backup_status(credential: &CredentialInfo) -> &'static str {
+    match (credential.backup_eligible, credential.backup_state) {
+        (_, true) => "Synced between devices",
+        (true, false) => "Can be synced but isn't yet",
+        (false, false) => "Only on the device it was created on",
+    }
+}
+
+/// The user's passkeys with their last use and backup status. Each one can be
+/// renamed or revoked. The last one can only be revoked while the user has
+/// unused recovery codes
+#[component]
+pub fn CredentialList(
+    /// Bump to fetch the list again, after adding a key for example
+    #[prop(into)]
+    refresh: Signal<usize>,
+) -> impl IntoView {
+    let (changed, set_changed) = create_signal(0_usize);
+    let credentials =
+        create_local_resource(move || (refresh.get(), changed.get()), |_| list_credentials());
+
+    // The credential being renamed and the name so far
+    let renaming = create_rw_signal(None::<(String, String)>);
+    let (error, set_error) = create_signal(None::<String>);
+
+    let done = move |result: Result<(), FrontendError<CredentialServerError>>| match result {
+        Ok(_) => {
+            set_error.set(None);
+            set_changed.update(|v| *v += 1);
+        },
+        Err(e) => set_error.set(Some(e.to_string())),
+    };
+
+    let rename_action = create_action(move |(id, name): &(String, String)| {
+        let (id, name) = (id.clone(), name.clone());
+        async move {
+            let result = rename_credential(&id, name).await.map(|_| ());
+            if result.is_ok() {
+                renaming.set(None);
+            }
+            done(result)
+        }
+    });
+
+    let delete_action = create_action(move |id: &String| {
+        let id = id.clone();
+        async move { done(delete_credential(&id).await) }
+    });
+
+    let pending = move || rename_action.pending().get() || delete_action.pending().get();
+
+    let row = move |credential: CredentialInfo, only_one: bool| {
+        let id = credential.id.clone();
+        let name = credential.name.clone().unwrap_or_else(|| "Unnamed key".to_string());
+        let last_used = credential
+            .last_used_date
+            .map_or("Never".to_string(), |d| d.format("%Y-%m-%d %H:%M").to_string());
+
+        let is_renaming = {
+            let id = id.clone();
+            Signal::derive(move || renaming.with(|r| r.as_ref().is_some_and(|(r, _)| *r == id)))
+        };
+        let start_rename = {
+            let id = id.clone();
+            let name = credential.name.clone().unwrap_or_default();
+            move |_| renaming.set(Some((id.clone(), name.clone())))
+        };
+
+        view! {
+            <tr>
+                <td>
+                    { move || if is_renaming.get() {
+                        view! {
+                            <input
+                                type="text"
+                                prop:value=move || renaming.with(|r| r.as_ref().map(|(_, n)| n.clone()))
+                                on:input=move |ev| renaming.update(|r| {
+                                    if let Some((_, name)) = r {
+                                        *name = event_target_value(&ev);
+                                    }
+                                })
+                            />
+                        }.into_view()
+                    } else {
+                        name.clone().into_view()
+                    }}
+                </td>
+                <td>{ credential.creation_date.format("%Y-%m-%d").to_string() }</td>
+                <td>{ last_used }</td>
+                <td>{ backup_status(&credential) }</td>
+                <td>
+                    { move || if is_renaming.get() {
+                        view! {
+                            <button
+                                prop:disabled=pending
+                                on:click=move |_| if let Some(r) = renaming.get() {
+                                    rename_action.dispatch(r)
+                                }
+                            >
+                                "Save"
+                            </button>
+                            <button on:click=move |_| renaming.set(None)>"Cancel"</button>
+                        }.into_view()
+                    } else {
+                        view! {
+                            <button prop:disabled=pending on:click=start_rename.clone()>
+                                "Rename"
+                            </button>
+                        }.into_view()
+                    }}
+                    <button
+                        prop:disabled=pending
+                        title=only_one.then_some("Without this key only a recovery code can log you in")
+                        on:click={
+                            let id = id.clone();
+                            move |_| delete_action.dispatch(id.clone())
+                        }
+                    >
+                        "Revoke"
+                    </button>
+                </td>
+            </tr>
+        }
+    };
+
+    view! {
+        <h3>"Sign-in keys"</h3>
+        { move || error.get().map(|e| view! { <p class="error">{e}</p> }) }
+        <Transition fallback=move || view! { <p>"Loading..."</p> }>
+            <FrontendErrorBoundary<CredentialServerError>>
+                { move || credentials.and_then(|credentials| {
+                    let only_one = credentials.len() == 1;
+                    view! {
+                        <table>
+                            <thead>
+                                <tr>
+                                    <th>"Name"</th>
+                                    <th>"Added"</th>
+                                    <th>"Last used"</th>
+                                    <th>"Backup"</th>
+                                    <th></th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                { credentials
+                                    .iter()
+                                    .map(|c| row(c.clone(), only_one))
+                                    .collect_view() }
+                            </tbody>
+                        </table>
+                    }
+                })}
+            </FrontendErrorBoundary<CredentialServerError>>
+        </Transition>
+    }
+}
@@ -0,0 +1,90 @@
+use leptos::{
+    component, create_action, create_local_resource, create_signal, view, CollectView, IntoView,
+    SignalGet, SignalSet, SignalUpdate, Transition,
+};
+use shared::{
+    api::{error::ServerError, payloads::LoginSessionInfo, response_errors::LoginSessionError},
+    types::Uuid,
+};
+
+use crate::{
+    api::{list_login_sessions, revoke_login_session},
+    components::FrontendErrorBoundary,
+};
+
+type LoginSessionServerError = ServerError<LoginSessionError>;
+
+/// Where the user is logged in. Any session apart from this one can be logged
+/// out from here
+#[component]
+pub fn LoginSessionList() -> impl IntoView {
+    let (changed, set_changed) = create_signal(0_usize);
+    let login_sessions = create_local_resource(move || changed.get(), |_| list_login_sessions());
+    let (error, set_error) = create_signal(None::<String>);
+
+    let revoke_action = create_action(move |id: &Uuid| {
+        let id = *id;
+        async move {
+            match revoke_login_session(id).await {
+                Ok(_) => {
+                    set_error.set(None);
+                    set_changed.update(|v| *v += 1);
+                },
+                Err(e) => set_error.set(Some(e.to_string())),
+            }
+        }
+    });
+
+    let row = move |login_session: LoginSessionInfo| {
+        let id = login_session.id;
+        let device = login_session.user_agent.clone().unwrap_or_else(|| "Unknown".to_string());
+
+        view! {
+            <tr>
+                <td>{ device }</td>
+                <td>{ login_session.ip_address.clone().unwrap_or_default() }</td>
+                <td>{ login_session.last_seen_date.format("%Y-%m-%d %H:%M").to_string() }</td>
+                <td>
+                    { if login_session.current {
+                        "This device".into_view()
+                    } else {
+                        view! {
+                            <button
+                                prop:disabled=move || revoke_action.pending().get()
+                                on:click=move |_| revoke_action.dispatch(id)
+                            >
+                                "Log out"
+                            </button>
+                        }.into_view()
+                    }}
+                </td>
+            </tr>
+        }
+    };
+
+    view! {
+        <h3>"Logged in sessions"</h3>
+        { move || error.get().map(|e| view! { <p class="error">{e}</p> }) }
+        <Transition fallback=move || view! { <p>"Loading..."</p> }>
+            <FrontendErrorBoundary<LoginSessionServerError>>
+                { move || login_sessions.and_then(|login_sessions| {
+                    view! {
+                        <table>
+                            <thead>
+                                <tr>
+                                    <th>"Device"</th>
+                                    <th>"Address"</th>
+                                    <th>"Last seen"</th>
+                                    <th></th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                { login_sessions.iter().map(|s| row(s.clone())).collect_view() }
+                            </tbody>
+                        </table>
+                    }
+                })}
+            </FrontendErrorBoundary<LoginSessionServerError>>
+        </Transition>
+    }
+}
@@ -1,12 +1,27 @@
 mod container;
 pub use container::*;
 
+mod credential_list;
+pub use credential_list::*;
+
 mod footer;
 pub use footer::*;
 
 mod frontend_error_boundary;
 pub use frontend_error_boundary::*;
 
+mod login_session_list;
+pub use login_session_list::*;
+
+mod model_browser;
+pub use model_browser::*;
+
+mod recovery_codes;
+pub use recovery_codes::*;
+
+mod sql_console;
+pub use sql_console::*;
+
 mod model_list;
 
 // Used intermittently for debugging
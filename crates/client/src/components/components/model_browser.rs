@@ -0,0 +1,251 @@
+use std::marker::PhantomData;
+
+use leptos::{
+    component, create_action, create_local_resource, create_rw_signal, create_signal,
+    event_target_value, view, CollectView, IntoView, SignalGet, SignalSet, SignalUpdate,
+    SignalWith, Transition,
+};
+use sea_query::{Asterisk, Expr, Func, Iden, Order, Query, SelectStatement};
+use serde::{de::DeserializeOwned, Serialize};
+use shared::{
+    model::{model_into_view::ModelIntoView, Model},
+    types::Uuid,
+};
+
+use crate::{
+    components::FrontendErrorBoundary,
+    db::{
+        sqlite3::{SqlitePromiser, SqlitePromiserError},
+        PromiserFetcher,
+    },
+};
+
+const PAGE_SIZE: u64 = 25;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct BrowserQuery {
+    page: u64,
+    /// Field index and whether it's descending
+    sort: Option<(usize, bool)>,
+    /// Field index and the text it has to contain
+    filter: Option<(usize, String)>,
+}
+
+impl BrowserQuery {
+    /// The select for the current page and the count of all the rows matching
+    /// the filter
+    fn statements<T: Model>(&self) -> (SelectStatement, SelectStatement) {
+        let mut select = T::select_star();
+        let mut count =
+            Query::select().expr(Func::count(Expr::col(Asterisk))).from(T::table_iden()).to_owned();
+
+        if let Some((field, text)) = self.filter.as_ref().filter(|(_, t)| !t.is_empty()) {
+            let like = Expr::col(T::iden_for_field(*field)).like(format!("%{text}%"));
+            select.and_where(like.clone());
+            count.and_where(like);
+        }
+
+        match self.sort {
+            Some((field, desc)) => {
+                select.order_by(
+                    T::iden_for_field(field),
+                    if desc { Order::Desc } else { Order::Asc },
+                );
+            },
+            None => {
+                select.order_by(T::id_iden(), Order::Asc);
+            },
+        }
+
+        select.limit(PAGE_SIZE).offset(self.page * PAGE_SIZE);
+
+        (select, count)
+    }
+}
+
+/// Pages through, sorts and filters the rows of a model in the local
+/// database. Rows can be edited as json which is written back with the
+/// model's update statement
+#[component]
+pub fn ModelBrowser<T>(#[prop(optional)] _phantom: PhantomData<T>) -> impl IntoView
+where
+    T: PromiserFetcher + Serialize + DeserializeOwned + 'static,
+{
+    let promiser = SqlitePromiser::use_promiser();
+    let query = create_rw_signal(BrowserQuery::default());
+    // Bumped after an edit so the page is fetched again
+    let (refresh, set_refresh) = create_signal(0_usize);
+
+    let rows = create_local_resource(move || (query.get(), refresh.get()), {
+        let promiser = promiser.clone();
+        move |(query, _)| {
+            let promiser = promiser.clone();
+            async move {
+                let (select, count) = query.statements::<T>();
+                let rows = T::extract_fields(promiser.exec_query(&select).await?)?;
+                let total = promiser
+                    .exec_query(&count)
+                    .await?
+                    .result_rows
+                    .first()
+                    .and_then(|r| r.first())
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(0);
+                Ok::<_, SqlitePromiserError>((rows, total))
+            }
+        }
+    });
+
+    // The row being edited and its json
+    let editing = create_rw_signal(None::<(Uuid, String)>);
+    let (edit_error, set_edit_error) = create_signal(None::<String>);
+
+    let save_action = create_action(move |json: &String| {
+        let json = json.clone();
+        let promiser = promiser.clone();
+        async move {
+            let result = async {
+                let mut model: T = serde_json::from_str(&json)?;
+                promiser.exec_query(&model.update_statement()?).await?;
+                Ok::<_, SqlitePromiserError>(())
+            }
+            .await;
+
+            match result {
+                Ok(_) => {
+                    editing.set(None);
+                    set_edit_error.set(None);
+                    set_refresh.update(|v| *v += 1);
+                },
+                Err(e) => set_edit_error.set(Some(e.to_string())),
+            }
+        }
+    });
+
+    let sort_by = move |field: usize| {
+        query.update(|q| {
+            q.sort = match q.sort {
+                Some((f, false)) if f == field => Some((field, true)),
+                Some((f, true)) if f == field => None,
+                _ => Some((field, false)),
+            };
+            q.page = 0;
+        })
+    };
+
+    let headers = move || {
+        (0..T::NUM_FIELDS)
+            .map(|i| {
+                let arrow = move || match query.with(|q| q.sort) {
+                    Some((f, false)) if f == i => " ▲",
+                    Some((f, true)) if f == i => " ▼",
+                    _ => "",
+                };
+                view! {
+                    <th on:click=move |_| sort_by(i)>
+                        { T::header_str(T::iden_for_field(i)) }{ arrow }
+                    </th>
+                }
+            })
+            .collect_view()
+    };
+
+    let view_row = move |row: T| {
+        let id = *row.id();
+        let json = serde_json::to_string_pretty(&row).unwrap_or_default();
+        let is_editing =
+            move || editing.with(|e| e.as_ref().map(|(e, _)| *e == id).unwrap_or(false));
+
+        view! {
+            <tr>
+                { (0..T::NUM_FIELDS).map(|i| row.row_view(T::iden_for_field(i))).collect_view() }
+                <td>
+                    <button on:click=move |_| editing.set(Some((id, json.clone())))>"Edit"</button>
+                </td>
+            </tr>
+            { move || is_editing().then(|| view! {
+                <tr>
+                    <td colspan={T::NUM_FIELDS + 1}>
+                        <textarea
+                            rows={T::NUM_FIELDS + 2}
+                            prop:value=move || editing.with(|e| e.as_ref().map(|(_, j)| j.clone()).unwrap_or_default())
+                            on:input=move |ev| editing.update(|e| if let Some((_, j)) = e {
+                                *j = event_target_value(&ev);
+                            })
+                        />
+                        { move || edit_error.get().map(|e| view! { <p class="error">{e}</p> }) }
+                        <button
+                            prop:disabled=move || save_action.pending().get()
+                            on:click=move |_| if let Some((_, json)) = editing.get() {
+                                save_action.dispatch(json);
+                            }
+                        >
+                            "Save"
+                        </button>
+                        <button on:click=move |_| {
+                            editing.set(None);
+                            set_edit_error.set(None);
+                        }>"Cancel"</button>
+                    </td>
+                </tr>
+            })}
+        }
+    };
+
+    view! {
+        <h3>{ T::table_iden().to_string() }</h3>
+        <p>
+            "Filter "
+            <select on:change=move |ev| {
+                let field = event_target_value(&ev).parse().unwrap_or(0);
+                query.update(|q| {
+                    q.filter = Some((field, q.filter.take().map(|(_, t)| t).unwrap_or_default()));
+                    q.page = 0;
+                });
+            }>
+                { (0..T::NUM_FIELDS).map(|i| view! {
+                    <option value=i>{ T::header_str(T::iden_for_field(i)) }</option>
+                }).collect_view() }
+            </select>
+            <input
+                type="text"
+                placeholder="contains"
+                on:input=move |ev| {
+                    let text = event_target_value(&ev);
+                    query.update(|q| {
+                        q.filter = Some((q.filter.as_ref().map(|(f, _)| *f).unwrap_or(0), text));
+                        q.page = 0;
+                    });
+                }
+            />
+        </p>
+        <Transition fallback=move || view! {  <p>"Loading..."</p>} >
+            <FrontendErrorBoundary<SqlitePromiserError>>
+                { move || rows.and_then(|(rows, total)| {
+                    let total = *total;
+                    let page = query.with(|q| q.page);
+                    let first = (page * PAGE_SIZE + 1).min(total);
+                    let last = (page * PAGE_SIZE + rows.len() as u64).min(total);
+
+                    view! {
+                        <table>
+                            <tr>{ headers() }<th></th></tr>
+                            { rows.iter().cloned().map(view_row).collect_view() }
+                        </table>
+                        <p>
+                            <button
+                                prop:disabled={page == 0}
+                                on:click=move |_| query.update(|q| q.page -= 1)
+                            >"Previous"</button>
+                            { format!(" {first}-{last} of {total} ") }
+                            <button
+                                prop:disabled={last >= total}
+                                on:click=move |_| query.update(|q| q.page += 1)
+                            >"Next"</button>
+                        </p>
+                    }
+                })}
+            </FrontendErrorBoundary<SqlitePromiserError>>
+        </Transition>
+    }
+}
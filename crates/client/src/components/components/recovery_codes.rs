@@ -0,0 +1,18 @@
+use leptos::{component, view, CollectView, IntoView};
+use shared::api::payloads::RecoveryCodes;
+
+/// Newly generated recovery codes. They're only stored hashed so this is the
+/// one chance to write them down
+#[component]
+pub fn RecoveryCodeList(codes: RecoveryCodes) -> impl IntoView {
+    view! {
+        <h3>"Recovery codes"</h3>
+        <p>
+            "If you lose all of your sign-in keys one of these codes will let you log in and add \
+             a new one. Each code works once. Keep them somewhere safe, they won't be shown again."
+        </p>
+        <ul class="recovery-codes">
+            { codes.codes.into_iter().map(|c| view! { <li><code>{c}</code></li> }).collect_view() }
+        </ul>
+    }
+}
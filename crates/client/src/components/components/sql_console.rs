@@ -0,0 +1,62 @@
+use leptos::{
+    component, create_action, create_signal, event_target_value, view, CollectView, IntoView,
+    SignalGet, SignalWith,
+};
+use serde_json::Value;
+
+use crate::db::sqlite3::SqlitePromiser;
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => "NULL".to_string(),
+        v => v.to_string(),
+    }
+}
+
+/// Runs a single statement against the local database with query_only on and
+/// shows the result as a table
+#[component]
+pub fn SqlConsole() -> impl IntoView {
+    let promiser = SqlitePromiser::use_promiser();
+    let (sql, set_sql) = create_signal(String::new());
+
+    let run_action = create_action(move |sql: &String| {
+        let sql = sql.clone();
+        let promiser = promiser.clone();
+        async move { promiser.exec_query_only(sql).await.map_err(|e| e.to_string()) }
+    });
+    let result = run_action.value();
+
+    view! {
+        <h3>"SQL"</h3>
+        <textarea
+            rows=4
+            placeholder="SELECT * FROM session"
+            prop:value=sql
+            on:input=move |ev| set_sql(event_target_value(&ev))
+        />
+        <button
+            prop:disabled=move || run_action.pending().get() || sql.with(|s| s.trim().is_empty())
+            on:click=move |_| run_action.dispatch(sql.get())
+        >
+            "Run"
+        </button>
+        { move || result.with(|r| r.as_ref().map(|r| match r {
+            Ok(r) => view! {
+                <p>{ format!("{} rows", r.result_rows.len()) }</p>
+                <table>
+                    <tr>
+                        { r.column_names.iter().map(|c| view! { <th>{c.clone()}</th> }).collect_view() }
+                    </tr>
+                    { r.result_rows.iter().map(|row| view! {
+                        <tr>
+                            { row.iter().map(|v| view! { <td>{cell(v)}</td> }).collect_view() }
+                        </tr>
+                    }).collect_view() }
+                </table>
+            }.into_view(),
+            Err(e) => view! { <p class="error">{e.clone()}</p> }.into_view(),
+        }))}
+    }
+}
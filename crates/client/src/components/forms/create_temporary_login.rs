@@ -1,12 +1,17 @@
-use leptos::{component, view, Action, IntoView, Signal, SignalGet, SignalWith};
+use leptos::{
+    component, create_signal, event_target_checked, view, Action, IntoView, Signal, SignalGet,
+    SignalSet, SignalWith,
+};
 
 #[component]
 pub fn CreateTemporaryLoginForm(
-    action: Action<(), ()>,
+    /// Dispatched with whether a pin should be made too
+    action: Action<bool, ()>,
     #[prop(into)] error: Signal<Option<String>>,
     #[prop(into)] disabled: Signal<bool>,
 ) -> impl IntoView {
-    let dispatch_action = move || action.dispatch(());
+    let (pin, set_pin) = create_signal(false);
+    let dispatch_action = move || action.dispatch(pin.get());
     let button_disabled = Signal::derive(move || disabled.get());
 
     view! {
@@ -15,6 +20,16 @@ pub fn CreateTemporaryLoginForm(
                 <p style="color:red">{e}</p>
             }))}
 
+            <label>
+                <input
+                    type="checkbox"
+                    prop:checked=move || pin.get()
+                    prop:disabled=move || disabled.get()
+                    on:change=move |ev| set_pin.set(event_target_checked(&ev))
+                />
+                " Also make a pin to type in"
+            </label>
+
             <button
                 prop:disabled=move || button_disabled.get()
                 on:click=move |_| dispatch_action()
@@ -0,0 +1,41 @@
+use leptos::{
+    component, create_signal, event_target_checked, view, Action, IntoView, Signal, SignalGet,
+    SignalSet, SignalWith,
+};
+
+/// Deleting can't be undone so the button only works once they've ticked that
+/// they understand. The passkey prompt that follows is the real confirmation
+#[component]
+pub fn DeleteAccountForm(
+    action: Action<(), ()>,
+    #[prop(into)] error: Signal<Option<String>>,
+    #[prop(into)] disabled: Signal<bool>,
+) -> impl IntoView {
+    let (understood, set_understood) = create_signal(false);
+    let button_disabled = Signal::derive(move || disabled.get() || !understood.get());
+
+    view! {
+        <form on:submit=|ev| ev.prevent_default()>
+            {move || error.with(|e| e.as_ref().map(|e| view! {
+                <p style="color:red">{e}</p>
+            }))}
+
+            <label>
+                <input
+                    type="checkbox"
+                    prop:checked=move || understood.get()
+                    prop:disabled=move || disabled.get()
+                    on:change=move |ev| set_understood.set(event_target_checked(&ev))
+                />
+                " Everything will be deleted from the server and this device, for good"
+            </label>
+
+            <button
+                prop:disabled=move || button_disabled.get()
+                on:click=move |_| action.dispatch(())
+            >
+                "Delete account"
+            </button>
+        </form>
+    }
+}
@@ -4,8 +4,23 @@ pub use registration::*;
 mod login;
 pub use login::*;
 
+mod recover;
+pub use recover::*;
+
+mod temporary_login_pin;
+pub use temporary_login_pin::*;
+
 mod create_temporary_login;
 pub use create_temporary_login::*;
 
 mod add_key;
 pub use add_key::*;
+
+mod profile;
+pub use profile::*;
+
+mod preferences;
+pub use preferences::*;
+
+mod delete_account;
+pub use delete_account::*;
@@ -0,0 +1,203 @@
+use chrono::{NaiveTime, Weekday};
+use leptos::{
+    component, create_rw_signal, event_target_checked, event_target_value, view, Action,
+    CollectView, IntoView, Signal, SignalGet, SignalUpdate, SignalWith,
+};
+use shared::model::{Preferences, QuietHours, Units};
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+const TIME_FORMAT: &str = "%H:%M";
+
+/// Quiet hours start at 22:00 and end at 07:00 when first turned on
+fn default_quiet_hours() -> QuietHours {
+    QuietHours {
+        start: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+        end: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+    }
+}
+
+/// Every preference, filled in with what they are now. The whole document is
+/// dispatched when saved
+#[component]
+pub fn PreferencesForm(
+    preferences: Preferences,
+    action: Action<Preferences, ()>,
+    #[prop(into)] error: Signal<Option<String>>,
+    #[prop(into)] disabled: Signal<bool>,
+) -> impl IntoView {
+    let preferences = create_rw_signal(preferences);
+
+    let set_quiet_hours_time = move |value: String, start: bool| {
+        if let Ok(time) = NaiveTime::parse_from_str(&value, TIME_FORMAT) {
+            preferences.update(|p| {
+                if let Some(quiet_hours) = &mut p.notifications.quiet_hours {
+                    if start {
+                        quiet_hours.start = time;
+                    } else {
+                        quiet_hours.end = time;
+                    }
+                }
+            });
+        }
+    };
+    let quiet_hours_time = move |start: bool| {
+        preferences.with(|p| {
+            p.notifications
+                .quiet_hours
+                .map(|q| if start { q.start } else { q.end }.format(TIME_FORMAT).to_string())
+                .unwrap_or_default()
+        })
+    };
+    let no_quiet_hours =
+        move || disabled.get() || preferences.with(|p| p.notifications.quiet_hours.is_none());
+
+    view! {
+        <form on:submit=|ev| ev.prevent_default()>
+            {move || error.with(|e| e.as_ref().map(|e| view! {
+                <p style="color:red">{e}</p>
+            }))}
+
+            <label>
+                "Units "
+                <select
+                    prop:disabled=move || disabled.get()
+                    on:change=move |ev| {
+                        let units = match event_target_value(&ev).as_str() {
+                            "Imperial" => Units::Imperial,
+                            _ => Units::Metric,
+                        };
+                        preferences.update(|p| p.units = units);
+                    }
+                >
+                    <option value="Metric" selected=move || preferences.with(|p| p.units == Units::Metric)>
+                        "Metric (kg)"
+                    </option>
+                    <option value="Imperial" selected=move || preferences.with(|p| p.units == Units::Imperial)>
+                        "Imperial (lb)"
+                    </option>
+                </select>
+            </label>
+
+            <label>
+                "First day of the week "
+                <select
+                    prop:disabled=move || disabled.get()
+                    on:change=move |ev| {
+                        if let Ok(day) = event_target_value(&ev).parse::<Weekday>() {
+                            preferences.update(|p| p.first_day_of_week = day);
+                        }
+                    }
+                >
+                    { WEEKDAYS.iter().map(|day| {
+                        let day = *day;
+                        view! {
+                            <option
+                                value=day.to_string()
+                                selected=move || preferences.with(|p| p.first_day_of_week == day)
+                            >
+                                { day.to_string() }
+                            </option>
+                        }
+                    }).collect_view() }
+                </select>
+            </label>
+
+            <label>
+                "Timezone "
+                <input
+                    type="text"
+                    placeholder="This device's"
+                    prop:value=move || preferences.with(|p| p.timezone.clone().unwrap_or_default())
+                    prop:disabled=move || disabled.get()
+                    on:input=move |ev| {
+                        let timezone = event_target_value(&ev).trim().to_string();
+                        preferences.update(|p| p.timezone = (!timezone.is_empty()).then_some(timezone));
+                    }
+                />
+            </label>
+
+            <label>
+                "Rest timer (seconds) "
+                <input
+                    type="number"
+                    min="0"
+                    prop:value=move || preferences.with(|p| p.rest_timer.seconds.to_string())
+                    prop:disabled=move || disabled.get()
+                    on:input=move |ev| {
+                        if let Ok(seconds) = event_target_value(&ev).parse() {
+                            preferences.update(|p| p.rest_timer.seconds = seconds);
+                        }
+                    }
+                />
+            </label>
+
+            <label>
+                <input
+                    type="checkbox"
+                    prop:checked=move || preferences.with(|p| p.rest_timer.auto_start)
+                    prop:disabled=move || disabled.get()
+                    on:change=move |ev| {
+                        let checked = event_target_checked(&ev);
+                        preferences.update(|p| p.rest_timer.auto_start = checked);
+                    }
+                />
+                " Start the rest timer when a set is done"
+            </label>
+
+            <label>
+                <input
+                    type="checkbox"
+                    prop:checked=move || preferences.with(|p| p.notifications.session_reminders)
+                    prop:disabled=move || disabled.get()
+                    on:change=move |ev| {
+                        let checked = event_target_checked(&ev);
+                        preferences.update(|p| p.notifications.session_reminders = checked);
+                    }
+                />
+                " Remind me about planned sessions"
+            </label>
+
+            <label>
+                <input
+                    type="checkbox"
+                    prop:checked=move || preferences.with(|p| p.notifications.quiet_hours.is_some())
+                    prop:disabled=move || disabled.get()
+                    on:change=move |ev| {
+                        let quiet_hours = event_target_checked(&ev).then(default_quiet_hours);
+                        preferences.update(|p| p.notifications.quiet_hours = quiet_hours);
+                    }
+                />
+                " No notifications from "
+                <input
+                    type="time"
+                    prop:value=move || quiet_hours_time(true)
+                    prop:disabled=no_quiet_hours
+                    on:change=move |ev| set_quiet_hours_time(event_target_value(&ev), true)
+                />
+                " to "
+                <input
+                    type="time"
+                    prop:value=move || quiet_hours_time(false)
+                    prop:disabled=no_quiet_hours
+                    on:change=move |ev| set_quiet_hours_time(event_target_value(&ev), false)
+                />
+            </label>
+
+            <button
+                prop:disabled=move || disabled.get()
+                on:click=move |_| action.dispatch(preferences.get())
+            >
+                "Save preferences"
+            </button>
+        </form>
+    }
+}
@@ -0,0 +1,61 @@
+use leptos::{
+    component, create_signal, event_target_value, view, Action, IntoView, Signal, SignalGet,
+    SignalSet, SignalWith,
+};
+use shared::{api::payloads::UpdateProfileRequest, model::User};
+
+/// The user's email and display name, filled in with what they are now
+#[component]
+pub fn ProfileForm(
+    user: User,
+    action: Action<UpdateProfileRequest, ()>,
+    #[prop(into)] error: Signal<Option<String>>,
+    #[prop(into)] disabled: Signal<bool>,
+) -> impl IntoView {
+    let (email, set_email) = create_signal(user.email.unwrap_or_default());
+    let (display_name, set_display_name) = create_signal(user.display_name.unwrap_or_default());
+
+    let dispatch_action = move || {
+        action.dispatch(UpdateProfileRequest {
+            email: Some(email.get()),
+            display_name: Some(display_name.get()),
+        })
+    };
+
+    view! {
+        <form on:submit=|ev| ev.prevent_default()>
+            {move || error.with(|e| e.as_ref().map(|e| view! {
+                <p style="color:red">{e}</p>
+            }))}
+
+            <label>
+                "Display name "
+                <input
+                    type="text"
+                    prop:value=move || display_name.get()
+                    prop:autocomplete="nickname"
+                    prop:disabled=move || disabled.get()
+                    on:input=move |ev| set_display_name.set(event_target_value(&ev))
+                />
+            </label>
+
+            <label>
+                "Email "
+                <input
+                    type="email"
+                    prop:value=move || email.get()
+                    prop:autocomplete="email"
+                    prop:disabled=move || disabled.get()
+                    on:input=move |ev| set_email.set(event_target_value(&ev))
+                />
+            </label>
+
+            <button
+                prop:disabled=move || disabled.get()
+                on:click=move |_| dispatch_action()
+            >
+                "Save profile"
+            </button>
+        </form>
+    }
+}
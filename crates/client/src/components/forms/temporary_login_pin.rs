@@ -0,0 +1,64 @@
+use leptos::{
+    component, create_signal, event_target_value, view, Action, IntoView, Signal, SignalGet,
+    SignalUpdate, SignalWith, WriteSignal,
+};
+use wasm_bindgen::JsCast;
+
+/// Logging in with a pin from a temporary login made on another device
+#[component]
+pub fn TemporaryLoginPinForm(
+    action: Action<(String, String), ()>,
+    #[prop(into)] error: Signal<Option<String>>,
+    disabled: Signal<bool>,
+) -> impl IntoView {
+    let (name, set_name) = create_signal(String::new());
+    let (pin, set_pin) = create_signal(String::new());
+
+    let dispatch_action = move || action.dispatch((name.get(), pin.get()));
+
+    let button_disabled = Signal::derive(move || {
+        disabled.get() || name.with(|n| n.is_empty()) || pin.with(|p| p.is_empty())
+    });
+
+    fn on_change<T: JsCast>(ev: T, signal: WriteSignal<String>) {
+        let val = event_target_value(&ev);
+        signal.update(|v| *v = val)
+    }
+
+    view! {
+        <form on:submit=|ev| ev.prevent_default()>
+            {move || error.with(|e| e.as_ref().map(|e| view! {
+                <p style="color:red">{e}</p>
+            }))}
+
+            <input
+                type="text"
+                required
+                placeholder="Username"
+                prop:autocomplete="username"
+                prop:disabled=move || disabled.get()
+                on:keyup=move |ev| on_change(ev, set_name)
+                on:change=move |ev| on_change(ev, set_name)
+            />
+
+            <input
+                type="text"
+                required
+                placeholder="Pin"
+                prop:inputmode="numeric"
+                prop:autocomplete="off"
+                prop:disabled=move || disabled.get()
+                on:keyup=move |ev| on_change(ev, set_pin)
+                on:change=move |ev| on_change(ev, set_pin)
+            />
+
+            <button
+                prop:disabled=move || button_disabled.get()
+                on:click=move |_| dispatch_action()
+            >
+                "Use pin"
+            </button>
+
+        </form>
+    }
+}
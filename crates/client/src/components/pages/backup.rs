@@ -0,0 +1,152 @@
+use leptos::{
+    component, create_action, create_signal, event_target, view, window, IntoView, SignalGet,
+    SignalSet,
+};
+use shared::api::error::Nothing;
+use tracing::error;
+use web_sys::HtmlInputElement;
+
+use crate::{
+    db::{
+        backup::{backup_to_server, export_json, import_database, restore_from_server},
+        sqlite3::SqlitePromiser,
+    },
+    utils::browser::{download_file, read_file},
+};
+
+/// Reloads the page so nothing is left showing rows from the old database
+fn reload() {
+    if let Err(e) = window().location().reload() {
+        error!("Error reloading after import: {e:?}");
+    }
+}
+
+#[component]
+pub fn Backup() -> impl IntoView {
+    let promiser = SqlitePromiser::use_promiser();
+    let (status, set_status) = create_signal(None::<Result<String, String>>);
+
+    let download_action = create_action({
+        let promiser = promiser.clone();
+        move |json: &bool| {
+            let json = *json;
+            let promiser = promiser.clone();
+            async move {
+                let result = async {
+                    let downloaded = if json {
+                        let tables = export_json(&promiser).await.map_err(|e| e.to_string())?;
+                        let bytes =
+                            serde_json::to_vec_pretty(&tables).map_err(|e| e.to_string())?;
+                        download_file::<Nothing>(&bytes, "eggercise.json", "application/json")
+                    } else {
+                        let bytes = promiser.export().await.map_err(|e| e.to_string())?;
+                        download_file::<Nothing>(
+                            &bytes,
+                            "eggercise.sqlite3",
+                            "application/vnd.sqlite3",
+                        )
+                    };
+                    downloaded.map_err(|e| e.to_string())
+                }
+                .await;
+
+                set_status.set(Some(result.map(|_| "Downloaded".to_string())));
+            }
+        }
+    });
+
+    let import_action = create_action({
+        let promiser = promiser.clone();
+        move |input: &HtmlInputElement| {
+            let input = input.clone();
+            let promiser = promiser.clone();
+            async move {
+                let result = async {
+                    let file = input
+                        .files()
+                        .and_then(|f| f.get(0))
+                        .ok_or_else(|| "No file selected".to_string())?;
+                    let bytes = read_file::<Nothing>(&file).await.map_err(|e| e.to_string())?;
+                    import_database(&promiser, &bytes).await.map_err(|e| e.to_string())
+                }
+                .await;
+
+                match result {
+                    Ok(_) => reload(),
+                    Err(e) => set_status.set(Some(Err(e))),
+                }
+            }
+        }
+    });
+
+    let backup_action = create_action({
+        let promiser = promiser.clone();
+        move |_: &()| {
+            let promiser = promiser.clone();
+            async move {
+                let result = backup_to_server(&promiser).await.map(|info| {
+                    format!(
+                        "Backed up schema version {} at {}",
+                        info.schema_version, info.creation_date
+                    )
+                });
+                set_status.set(Some(result.map_err(|e| e.to_string())));
+            }
+        }
+    });
+
+    let restore_action = create_action(move |_: &()| {
+        let promiser = promiser.clone();
+        async move {
+            match restore_from_server(&promiser).await {
+                Ok(_) => reload(),
+                Err(e) => set_status.set(Some(Err(e.to_string()))),
+            }
+        }
+    });
+
+    let pending = move || {
+        download_action.pending().get()
+            || import_action.pending().get()
+            || backup_action.pending().get()
+            || restore_action.pending().get()
+    };
+
+    view! {
+        <h1>"Backup"</h1>
+        <p>
+            "Your training history is only stored on this device. Clearing the site data or \
+             resetting the device deletes it so keep a backup."
+        </p>
+        { move || status.get().map(|s| match s {
+            Ok(message) => view! { <p>{message}</p> },
+            Err(error) => view! { <p class="error">{error}</p> },
+        })}
+
+        <h2>"Download"</h2>
+        <button prop:disabled=pending on:click=move |_| download_action.dispatch(false)>
+            "Download database"
+        </button>
+        <button prop:disabled=pending on:click=move |_| download_action.dispatch(true)>
+            "Download as JSON"
+        </button>
+
+        <h2>"Import"</h2>
+        <p>"Replaces everything on this device with a downloaded database"</p>
+        <input
+            type="file"
+            accept=".sqlite3,application/vnd.sqlite3"
+            prop:disabled=pending
+            on:change=move |ev| import_action.dispatch(event_target::<HtmlInputElement>(&ev))
+        />
+
+        <h2>"Server"</h2>
+        <p>"The server keeps the latest backup only"</p>
+        <button prop:disabled=pending on:click=move |_| backup_action.dispatch(())>
+            "Back up to server"
+        </button>
+        <button prop:disabled=pending on:click=move |_| restore_action.dispatch(())>
+            "Restore from server"
+        </button>
+    }
+}
@@ -1,15 +1,31 @@
-use leptos::{component, use_context, view, For, IntoView, Resource, SignalWith};
+use leptos::{
+    component, create_signal, event_target_value, use_context, view, CollectView, For, IntoView,
+    Resource, SignalGet, SignalWith,
+};
+use shared::model::{
+    Exercise, ExerciseGroup, ExerciseGroupMember, Plan, PlanExerciseGroup, PlanInstance, Session,
+    SessionExercise, User, UserExercise,
+};
 
-// use shared::model::{
-//     Exercise, ExerciseGroup, ExerciseGroupMember, Plan, PlanExerciseGroup,
-// PlanInstance, Session,     SessionExercise, User,
-// };
 use crate::{
-    // components::ModelList,
+    components::{ModelBrowser, SqlConsole},
     db::migrations::{DatabaseVersion, MigrationError},
     utils::websocket::Websocket,
 };
 
+const TABLES: &[&str] = &[
+    "user",
+    "exercise",
+    "exercise_group",
+    "exercise_group_member",
+    "user_exercise",
+    "plan",
+    "plan_exercise_group",
+    "plan_instance",
+    "session",
+    "session_exercise",
+];
+
 #[component]
 pub fn Debug() -> impl IntoView {
     let db_version: Resource<(), Result<DatabaseVersion, MigrationError>> =
@@ -19,6 +35,20 @@ pub fn Debug() -> impl IntoView {
     let status = ws.status_signal();
     let message = ws.message_signal();
 
+    let (table, set_table) = create_signal(TABLES[0]);
+    let browser = move || match table.get() {
+        "exercise" => view! { <ModelBrowser<Exercise>/> }.into_view(),
+        "exercise_group" => view! { <ModelBrowser<ExerciseGroup>/> }.into_view(),
+        "exercise_group_member" => view! { <ModelBrowser<ExerciseGroupMember>/> }.into_view(),
+        "user_exercise" => view! { <ModelBrowser<UserExercise>/> }.into_view(),
+        "plan" => view! { <ModelBrowser<Plan>/> }.into_view(),
+        "plan_exercise_group" => view! { <ModelBrowser<PlanExerciseGroup>/> }.into_view(),
+        "plan_instance" => view! { <ModelBrowser<PlanInstance>/> }.into_view(),
+        "session" => view! { <ModelBrowser<Session>/> }.into_view(),
+        "session_exercise" => view! { <ModelBrowser<SessionExercise>/> }.into_view(),
+        _ => view! { <ModelBrowser<User>/> }.into_view(),
+    };
+
     view! {
         <h1>"Debug"</h1>
         <p>"Database Version: " { move || db_version
@@ -32,14 +62,17 @@ pub fn Debug() -> impl IntoView {
                 children=move |v| view! { <p> { v.1 } </p> }
             />
         }}</p>
+
+        <h2>"Local data"</h2>
+        <select on:change=move |ev| {
+            let value = event_target_value(&ev);
+            if let Some(t) = TABLES.iter().find(|t| **t == value) {
+                set_table(*t);
+            }
+        }>
+            { TABLES.iter().map(|t| view! { <option value=*t>{*t}</option> }).collect_view() }
+        </select>
+        { browser }
+        <SqlConsole/>
     }
-    // <ModelList<User> />
-    // <ModelList<Exercise> />
-    // <ModelList<ExerciseGroup> />
-    // <ModelList<ExerciseGroupMember> />
-    // <ModelList<Plan> />
-    // <ModelList<PlanExerciseGroup> />
-    // <ModelList<PlanInstance> />
-    // <ModelList<Session> />
-    // <ModelList<SessionExercise> />
 }
@@ -2,12 +2,15 @@ use leptos::{
     component, create_action, create_signal, view, IntoView, Show, Signal, SignalGet, SignalUpdate,
     SignalWith,
 };
-use shared::model::LoginUser;
+use shared::{
+    api::payloads::{RecoverRequest, TemporaryLoginPinRequest},
+    model::LoginUser,
+};
 use tracing::{debug, warn};
 
 use crate::{
-    api::login,
-    components::{LoginForm, OfflineFallback},
+    api::{login, recover, temporary_login_pin},
+    components::{LoginForm, OfflineFallback, RecoverForm, TemporaryLoginPinForm},
     ClientRoutes,
 };
 
@@ -16,6 +19,9 @@ pub fn Login() -> impl IntoView {
     // Signals
     let (login_response, set_login_response) = create_signal(None);
     let (login_error, set_login_error) = create_signal(None::<String>);
+    let (recover_error, set_recover_error) = create_signal(None::<String>);
+    let (pin_error, set_pin_error) = create_signal(None::<String>);
+    let (recovered, set_recovered) = create_signal(false);
     let (wait_for_response, set_wait_for_response) = create_signal(false);
     let disabled = Signal::derive(move || wait_for_response.get());
 
@@ -42,6 +48,51 @@ pub fn Login() -> impl IntoView {
         }
     });
 
+    let recover_action = create_action(move |(username, code): &(String, String)| {
+        let request = RecoverRequest { username: username.clone(), code: code.clone() };
+        debug!("Recovering user {}", request.username);
+        async move {
+            set_wait_for_response.update(|w| *w = true);
+
+            match recover(&request).await {
+                Ok(res) => {
+                    set_recovered.update(|v| *v = true);
+                    set_login_response.update(|v| *v = Some(res));
+                    set_recover_error.update(|e| *e = None);
+                },
+                Err(err) => {
+                    let msg = format!("{:?}", err);
+                    warn!("Error recovering {}: {msg}", request.username);
+                    set_recover_error.update(|e| *e = Some(msg));
+                },
+            }
+
+            set_wait_for_response.update(|w| *w = false);
+        }
+    });
+
+    let pin_action = create_action(move |(username, pin): &(String, String)| {
+        let request = TemporaryLoginPinRequest { username: username.clone(), pin: pin.clone() };
+        debug!("Logging in user {} with a pin", request.username);
+        async move {
+            set_wait_for_response.update(|w| *w = true);
+
+            match temporary_login_pin(&request).await {
+                Ok(res) => {
+                    set_login_response.update(|v| *v = Some(res));
+                    set_pin_error.update(|e| *e = None);
+                },
+                Err(err) => {
+                    let msg = format!("{:?}", err);
+                    warn!("Error logging in {} with a pin: {msg}", request.username);
+                    set_pin_error.update(|e| *e = Some(msg));
+                },
+            }
+
+            set_wait_for_response.update(|w| *w = false);
+        }
+    });
+
     view! {
         <h2>"Login"</h2>
         <OfflineFallback>
@@ -54,10 +105,34 @@ pub fn Login() -> impl IntoView {
                             error=login_error
                             disabled
                         />
+                        <p>
+                            "Logged in on another device? "
+                            { ClientRoutes::Pair.link() }
+                            " to approve this one from it."
+                        </p>
+                        <h3>"Have a pin from another device?"</h3>
+                        <TemporaryLoginPinForm
+                            action=pin_action
+                            error=pin_error
+                            disabled
+                        />
+                        <h3>"Lost your keys?"</h3>
+                        <RecoverForm
+                            action=recover_action
+                            error=recover_error
+                            disabled
+                        />
                     }
                 }
             >
                 <p>"Login complete"</p>
+                <Show when=move || recovered.get()>
+                    <p>
+                        "You used a recovery code. Add a new key from your "
+                        { ClientRoutes::Profile.link() }
+                        " so you can log in without one next time."
+                    </p>
+                </Show>
                 { ClientRoutes::Today.link() }
                 <div>{ login_response.with(|v| format!("{:?}", v)) }</div>
             </Show>
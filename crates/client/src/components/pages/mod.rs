@@ -1,6 +1,9 @@
 mod today;
 pub use today::*;
 
+mod workout;
+pub use workout::*;
+
 mod plan;
 pub use plan::*;
 
@@ -21,3 +24,9 @@ pub use chart::*;
 
 mod notifications;
 pub use notifications::*;
+
+mod backup;
+pub use backup::*;
+
+mod pair;
+pub use pair::*;
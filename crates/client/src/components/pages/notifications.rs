@@ -1,6 +1,6 @@
 use leptos::{
-    component, create_effect, create_local_resource, create_signal, create_trigger, view, IntoView,
-    SignalGet, SignalUpdate, SignalWith,
+    component, create_effect, create_local_resource, create_signal, create_trigger,
+    event_target_value, view, IntoView, SignalGet, SignalSet, SignalUpdate, SignalWith,
 };
 use shared::api::error::{FrontendError, Nothing};
 use tracing::debug;
@@ -34,6 +34,8 @@ pub fn Notificiations() -> impl IntoView {
     );
 
     let (error, set_error) = create_signal(None::<String>);
+    // Empty leaves it to the server, which uses the browser's user agent
+    let (device_label, set_device_label) = create_signal(String::new());
 
     let is_subscribed = move || {
         subscription_trigger.track();
@@ -52,7 +54,8 @@ pub fn Notificiations() -> impl IntoView {
         if is_subscribed() {
             unsubscribe_action.dispatch(());
         } else {
-            subscribe_action.dispatch(());
+            let device_label = device_label.get().trim().to_string();
+            subscribe_action.dispatch((!device_label.is_empty()).then_some(device_label));
         }
     };
 
@@ -62,9 +65,19 @@ pub fn Notificiations() -> impl IntoView {
             <p class="error">{e}</p>
         }))}
         { move || if is_subscribed() {
-            Some(view! { <p>"You are currently subscribed to receive notifications"</p> })
+            view! { <p>"You are currently subscribed to receive notifications"</p> }.into_view()
         } else {
-            None
+            view! {
+                <label>
+                    "Device name "
+                    <input
+                        type="text"
+                        placeholder="This browser"
+                        prop:value=move || device_label.get()
+                        on:input=move |ev| set_device_label.set(event_target_value(&ev))
+                    />
+                </label>
+            }.into_view()
         }}
         <button
             prop:disabled=move || is_subscribed_resource.loading().get() || is_subscribed_resource.get().map(|r| r.is_err()).unwrap_or(true)
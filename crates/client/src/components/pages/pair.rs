@@ -0,0 +1,299 @@
+use leptos::{
+    component, create_action, create_effect, create_local_resource, create_signal,
+    event_target_value, view, IntoView, Show, Signal, SignalGet, SignalSet, SignalWith,
+    SignalWithUntracked, Transition,
+};
+use leptos_router::{use_navigate, use_query_map};
+use shared::{
+    api::{
+        error::ServerError,
+        payloads::{PairingCode, PairingDecision},
+        response_errors::PairingError,
+        Auth,
+    },
+    model::User,
+    types::websocket::ServerUser,
+};
+use tracing::{debug, warn};
+
+use crate::{
+    api::{add_key, decide_pairing, fetch_pairing, finish_pairing, start_pairing},
+    components::{AddKeyForm, FrontendErrorBoundary, OfflineFallback},
+    utils::websocket::Websocket,
+    ClientRoutes,
+};
+
+type PairingServerError = ServerError<PairingError>;
+
+/// Shows a code for a logged in device to approve then logs in once it has
+#[component]
+fn RequestPairing() -> impl IntoView {
+    let (pairing, set_pairing) = create_signal(None::<PairingCode>);
+    let (user, set_user) = create_signal(None::<User>);
+    let (error, set_error) = create_signal(None::<String>);
+    let (add_key_error, set_add_key_error) = create_signal(None::<String>);
+    let (add_key_success, set_add_key_success) = create_signal(None::<String>);
+    let (wait_for_response, set_wait_for_response) = create_signal(false);
+    let disabled = Signal::derive(move || wait_for_response.get());
+
+    let start_action = create_action(move |_: &()| {
+        debug!("Starting pairing...");
+        async move {
+            set_wait_for_response.set(true);
+
+            match start_pairing().await {
+                Ok(code) => {
+                    set_pairing.set(Some(code));
+                    set_error.set(None);
+                },
+                Err(err) => {
+                    let msg = format!("{:?}", err);
+                    warn!("Error starting pairing: {msg}");
+                    set_error.set(Some(msg));
+                },
+            }
+
+            set_wait_for_response.set(false);
+        }
+    });
+
+    let finish_action = create_action(move |_: &()| {
+        debug!("Finishing pairing...");
+        async move {
+            set_wait_for_response.set(true);
+
+            match finish_pairing().await {
+                Ok(u) => {
+                    set_pairing.set(None);
+                    set_user.set(Some(u));
+                    set_error.set(None);
+                },
+                Err(err) => {
+                    let msg = format!("{:?}", err);
+                    warn!("Error finishing pairing: {msg}");
+                    set_error.set(Some(msg));
+                },
+            }
+
+            set_wait_for_response.set(false);
+        }
+    });
+
+    let add_key_action = create_action(move |_: &()| {
+        debug!("Adding key...");
+        async move {
+            set_wait_for_response.set(true);
+
+            match add_key().await {
+                Ok(_) => {
+                    set_add_key_success.set(Some("Key added successfully".to_string()));
+                    set_add_key_error.set(None);
+                },
+                Err(err) => {
+                    let msg = format!("{:?}", err);
+                    warn!("Error adding key: {msg}");
+                    set_add_key_success.set(None);
+                    set_add_key_error.set(Some(msg));
+                },
+            }
+
+            set_wait_for_response.set(false);
+        }
+    });
+
+    // The server says when the other device has decided. Only new messages
+    // matter, not starting a new pairing after an old one was decided
+    let server_user = Websocket::use_websocket().user_signal();
+    create_effect(move |_| {
+        let server_user = server_user.get();
+        if pairing.with_untracked(|p| p.is_none()) {
+            return;
+        }
+        match server_user {
+            Some(ServerUser::Pairing { approved: true }) => finish_action.dispatch(()),
+            Some(ServerUser::Pairing { approved: false }) => {
+                set_pairing.set(None);
+                set_error.set(Some("The other device denied the request".to_string()));
+            },
+            _ => {},
+        }
+    });
+
+    view! {
+        <h3>"Log this device in"</h3>
+        {move || error.get().map(|e| view! { <p style="color:red">{e}</p> })}
+        <Show
+            when=move || user.with(|u| u.is_some())
+            fallback=move || view! {
+                {move || match pairing.get() {
+                    None => view! {
+                        <p>"Get a code to approve from a device you're already logged in on."</p>
+                        <button
+                            prop:disabled=move || disabled.get()
+                            on:click=move |_| start_action.dispatch(())
+                        >
+                            "Get a code"
+                        </button>
+                    }.into_view(),
+                    Some(p) => view! {
+                        <p>
+                            "Scan the QR code or enter "
+                            <strong>{ p.code.clone() }</strong>
+                            " on the " { ClientRoutes::Pair.link() } " page of a logged in device. "
+                            "It expires at " { p.expiry_date.format("%H:%M").to_string() } "."
+                        </p>
+                        // The code busts the cache when a new one is started
+                        <img src=format!("{}?code={}", Auth::PairingQrCode.path(), p.code) />
+                        <button
+                            prop:disabled=move || disabled.get()
+                            on:click=move |_| finish_action.dispatch(())
+                        >
+                            "It's approved"
+                        </button>
+                    }.into_view(),
+                }}
+            }
+        >
+            <p>
+                "Logged in as " { move || user.with(|u| u.as_ref().map(|u| u.username.clone())) } ". "
+                "Add a sign-in key so this device can log in by itself."
+            </p>
+            <AddKeyForm
+                action=add_key_action
+                error=add_key_error
+                message=add_key_success
+                disabled
+            />
+            { ClientRoutes::Today.link() }
+        </Show>
+    }
+}
+
+/// What's asking to be logged in, with buttons to approve or deny it
+#[component]
+fn ApprovePairing(code: String) -> impl IntoView {
+    let info = {
+        let code = code.clone();
+        create_local_resource(
+            move || code.clone(),
+            |code| async move { fetch_pairing(&code).await },
+        )
+    };
+    let (decided, set_decided) = create_signal(None::<bool>);
+    let (error, set_error) = create_signal(None::<String>);
+
+    let decide_action = create_action(move |decision: &PairingDecision| {
+        let decision = decision.clone();
+        async move {
+            match decide_pairing(&decision).await {
+                Ok(_) => {
+                    set_decided.set(Some(decision.approve));
+                    set_error.set(None);
+                },
+                Err(err) => {
+                    let msg = format!("{:?}", err);
+                    warn!("Error deciding pairing: {msg}");
+                    set_error.set(Some(msg));
+                },
+            }
+        }
+    });
+
+    let decide = move |approve: bool| {
+        let code = code.clone();
+        move |_| decide_action.dispatch(PairingDecision { code: code.clone(), approve })
+    };
+
+    view! {
+        <h3>"Log another device in"</h3>
+        {move || error.get().map(|e| view! { <p style="color:red">{e}</p> })}
+        {move || match decided.get() {
+            Some(true) => view! { <p>"Approved, the other device is logging in."</p> }.into_view(),
+            Some(false) => view! { <p>"Denied."</p> }.into_view(),
+            None => view! {
+                <Transition fallback=move || view! { <p>"Loading..."</p> }>
+                    <FrontendErrorBoundary<PairingServerError>>
+                        { move || info.and_then(|info| view! {
+                            <p>"Only approve a device you're holding."</p>
+                            <table>
+                                <tr>
+                                    <th>"Code"</th>
+                                    <td>{ info.code.clone() }</td>
+                                </tr>
+                                <tr>
+                                    <th>"Device"</th>
+                                    <td>{ info.user_agent.clone().unwrap_or_else(|| "Unknown".to_string()) }</td>
+                                </tr>
+                                <tr>
+                                    <th>"Address"</th>
+                                    <td>{ info.ip_address.clone().unwrap_or_default() }</td>
+                                </tr>
+                                <tr>
+                                    <th>"Requested"</th>
+                                    <td>{ info.creation_date.format("%Y-%m-%d %H:%M").to_string() }</td>
+                                </tr>
+                            </table>
+                        })}
+                    </FrontendErrorBoundary<PairingServerError>>
+                </Transition>
+                <button
+                    prop:disabled=move || decide_action.pending().get()
+                    on:click=decide(true)
+                >
+                    "Approve"
+                </button>
+                <button
+                    prop:disabled=move || decide_action.pending().get()
+                    on:click=decide(false)
+                >
+                    "Deny"
+                </button>
+            }.into_view(),
+        }}
+    }
+}
+
+/// Logging a device in from one that's already logged in. The device that
+/// isn't shows a code and QR code of a link back here with it, the one that
+/// is approves it
+#[component]
+pub fn Pair() -> impl IntoView {
+    let query = use_query_map();
+    let code = move || query.with(|q| q.get("code").cloned());
+    let (typed_code, set_typed_code) = create_signal(String::new());
+
+    let navigate = use_navigate();
+    let open_code = move |_| {
+        navigate(
+            &format!("{}?code={}", ClientRoutes::Pair.path(), typed_code.get().trim()),
+            Default::default(),
+        );
+    };
+
+    view! {
+        <h2>"Pair device"</h2>
+        <OfflineFallback>
+            {move || match code() {
+                Some(code) => view! { <ApprovePairing code/> }.into_view(),
+                None => view! {
+                    <RequestPairing/>
+                    <h3>"Log another device in"</h3>
+                    <form on:submit=|ev| ev.prevent_default()>
+                        <input
+                            type="text"
+                            placeholder="Code from the other device"
+                            prop:autocomplete="off"
+                            on:input=move |ev| set_typed_code.set(event_target_value(&ev))
+                        />
+                        <button
+                            prop:disabled=move || typed_code.with(|c| c.trim().is_empty())
+                            on:click=open_code
+                        >
+                            "Continue"
+                        </button>
+                    </form>
+                }.into_view(),
+            }}
+        </OfflineFallback>
+    }
+}
@@ -1,18 +1,29 @@
 use leptos::{
     component, create_action, create_local_resource, create_signal, view, Action, IntoView, Show,
-    Signal, SignalGet, SignalUpdate, SignalWith,
+    Signal, SignalGet, SignalGetUntracked, SignalUpdate, SignalWith,
 };
 use shared::{
-    api::error::{Nothing, ServerError},
-    model::{TemporaryLogin, User},
+    api::{
+        error::{Nothing, ServerError},
+        payloads::{RecoveryCodes, UpdateProfileRequest},
+        Auth,
+    },
+    model::{Preferences, TemporaryLogin, User},
 };
 use tracing::{debug, warn};
 
 use crate::{
-    api::{add_key, create_temporary_login, fetch_user},
+    api::{
+        add_key, create_temporary_login, delete_account, fetch_user, forget_user, logout,
+        mirror_user, regenerate_recovery_codes, revoke_temporary_login, update_preferences,
+        update_profile,
+    },
     components::{
-        forms::CreateTemporaryLoginForm, AddKeyForm, FrontendErrorBoundary, OfflineFallback,
+        forms::{CreateTemporaryLoginForm, DeleteAccountForm, PreferencesForm, ProfileForm},
+        AddKeyForm, CredentialList, FrontendErrorBoundary, LoginSessionList, OfflineFallback,
+        RecoveryCodeList,
     },
+    ClientRoutes,
 };
 
 type ServerErrorNothing = ServerError<Nothing>;
@@ -21,22 +32,77 @@ type ServerErrorNothing = ServerError<Nothing>;
 fn ProfileWithUser(
     user: Signal<Option<User>>,
     temporary_login: Signal<Option<TemporaryLogin>>,
-    update_action: Action<(User, TemporaryLogin), ()>,
+    update_action: Action<(User, Option<TemporaryLogin>), ()>,
 ) -> impl IntoView {
     let (temporary_login_error, set_temporary_login_error) = create_signal(None::<String>);
     let (add_key_error, set_add_key_error) = create_signal(None::<String>);
     let (add_key_success, set_add_key_success) = create_signal(None::<String>);
     let (wait_for_response, set_wait_for_response) = create_signal(false);
+    // Bumped when a key is added so the list of keys is fetched again
+    let (keys_added, set_keys_added) = create_signal(0_usize);
+    let (recovery_codes, set_recovery_codes) = create_signal(None::<RecoveryCodes>);
+    let (recovery_codes_error, set_recovery_codes_error) = create_signal(None::<String>);
+    let (logged_out, set_logged_out) = create_signal(false);
+    let (logout_error, set_logout_error) = create_signal(None::<String>);
+    let (profile_error, set_profile_error) = create_signal(None::<String>);
+    let (preferences_error, set_preferences_error) = create_signal(None::<String>);
+    let (deleted, set_deleted) = create_signal(false);
+    let (delete_error, set_delete_error) = create_signal(None::<String>);
+
+    let update_profile_action = create_action(move |request: &UpdateProfileRequest| {
+        debug!("Updating profile...");
+        let request = request.clone();
+        async move {
+            set_wait_for_response.update(|w| *w = true);
+
+            match update_profile(&request).await {
+                Ok(u) => {
+                    set_profile_error.update(|e| *e = None);
+                    update_action.dispatch((u, temporary_login.get_untracked()))
+                },
+                Err(err) => {
+                    let msg = format!("{:?}", err);
+                    warn!("Error updating profile: {msg}");
+                    set_profile_error.update(|e| *e = Some(msg));
+                },
+            }
+
+            set_wait_for_response.update(|w| *w = false);
+        }
+    });
+
+    let update_preferences_action = create_action(move |preferences: &Preferences| {
+        debug!("Updating preferences...");
+        let preferences = preferences.clone();
+        async move {
+            set_wait_for_response.update(|w| *w = true);
+
+            match update_preferences(&preferences).await {
+                Ok(u) => {
+                    set_preferences_error.update(|e| *e = None);
+                    update_action.dispatch((u, temporary_login.get_untracked()))
+                },
+                Err(err) => {
+                    let msg = format!("{:?}", err);
+                    warn!("Error updating preferences: {msg}");
+                    set_preferences_error.update(|e| *e = Some(msg));
+                },
+            }
+
+            set_wait_for_response.update(|w| *w = false);
+        }
+    });
 
-    let create_temporary_login_action = create_action(move |_: &()| {
+    let create_temporary_login_action = create_action(move |pin: &bool| {
         debug!("Creating temporary login...");
+        let pin = *pin;
         async move {
             set_wait_for_response.update(|w| *w = true);
 
-            match create_temporary_login().await {
+            match create_temporary_login(pin).await {
                 Ok(tl) => {
                     set_temporary_login_error.update(|e| *e = None);
-                    update_action.dispatch((user.get().unwrap(), tl))
+                    update_action.dispatch((user.get().unwrap(), Some(tl)))
                 },
                 Err(err) => {
                     let msg = format!("{:?}", err);
@@ -49,6 +115,27 @@ fn ProfileWithUser(
         }
     });
 
+    let revoke_temporary_login_action = create_action(move |_: &()| {
+        debug!("Revoking temporary login...");
+        async move {
+            set_wait_for_response.update(|w| *w = true);
+
+            match revoke_temporary_login().await {
+                Ok(_) => {
+                    set_temporary_login_error.update(|e| *e = None);
+                    update_action.dispatch((user.get().unwrap(), None))
+                },
+                Err(err) => {
+                    let msg = format!("{:?}", err);
+                    warn!("Error revoking temporary login: {msg}");
+                    set_temporary_login_error.update(|e| *e = Some(msg));
+                },
+            }
+
+            set_wait_for_response.update(|w| *w = false);
+        }
+    });
+
     let add_key_action = create_action(move |_: &()| {
         debug!("Adding key...");
         async move {
@@ -58,6 +145,7 @@ fn ProfileWithUser(
                 Ok(_) => {
                     set_add_key_success.update(|v| *v = Some("Key added successfully".to_string()));
                     set_add_key_error.update(|e| *e = None);
+                    set_keys_added.update(|v| *v += 1);
                 },
                 Err(err) => {
                     let msg = format!("{:?}", err);
@@ -71,43 +159,197 @@ fn ProfileWithUser(
         }
     });
 
+    let regenerate_recovery_codes_action = create_action(move |_: &()| {
+        debug!("Regenerating recovery codes...");
+        async move {
+            set_wait_for_response.update(|w| *w = true);
+
+            match regenerate_recovery_codes().await {
+                Ok(codes) => {
+                    set_recovery_codes.update(|v| *v = Some(codes));
+                    set_recovery_codes_error.update(|e| *e = None);
+                },
+                Err(err) => {
+                    let msg = format!("{:?}", err);
+                    warn!("Error regenerating recovery codes: {msg}");
+                    set_recovery_codes_error.update(|e| *e = Some(msg));
+                },
+            }
+
+            set_wait_for_response.update(|w| *w = false);
+        }
+    });
+
+    let logout_action = create_action(move |_: &()| {
+        debug!("Logging out...");
+        async move {
+            set_wait_for_response.update(|w| *w = true);
+
+            match logout().await {
+                Ok(_) => {
+                    set_logout_error.update(|e| *e = None);
+                    set_logged_out.update(|v| *v = true);
+                },
+                Err(err) => {
+                    let msg = format!("{:?}", err);
+                    warn!("Error logging out: {msg}");
+                    set_logout_error.update(|e| *e = Some(msg));
+                },
+            }
+
+            set_wait_for_response.update(|w| *w = false);
+        }
+    });
+
+    let delete_account_action = create_action(move |_: &()| {
+        debug!("Deleting account...");
+        async move {
+            set_wait_for_response.update(|w| *w = true);
+
+            match delete_account().await {
+                Ok(_) => {
+                    if let Some(user) = user.get_untracked() {
+                        forget_user(&user).await;
+                    }
+                    set_delete_error.update(|e| *e = None);
+                    set_deleted.update(|v| *v = true);
+                },
+                Err(err) => {
+                    let msg = format!("{:?}", err);
+                    warn!("Error deleting account: {msg}");
+                    set_delete_error.update(|e| *e = Some(msg));
+                },
+            }
+
+            set_wait_for_response.update(|w| *w = false);
+        }
+    });
+
     view! {
         <Show
-            when=move || user.with(|u| u.is_some())
+            when=move || !logged_out.get() && !deleted.get()
             fallback=move || {
                 view! {
-                    <p>Loading...</p>
+                    <p>
+                        { move || if deleted.get() {
+                            "Your account has been deleted"
+                        } else {
+                            "You are logged out"
+                        }}
+                    </p>
+                    { ClientRoutes::Login.link() }
                 }
             }
         >
-            <h3>"You are logged in"</h3>
-            <div><span>"Username: "</span><span>{ user.with(move |u| u.as_ref().map(|u| u.username.clone() )) }</span></div>
             <Show
-                when=move || temporary_login.with(|tl| tl.is_some())
+                when=move || user.with(|u| u.is_some())
                 fallback=move || {
                     view! {
-                        <CreateTemporaryLoginForm
-                            action=create_temporary_login_action
-                            error=temporary_login_error
-                            disabled=wait_for_response
-                        />
+                        <p>Loading...</p>
                     }
                 }
             >
-                {move || temporary_login.with(move |tl| tl.as_ref().map(|tl| {
-                    view! {
-                        <a href={ &tl.url }>{ &tl.url }</a>
-                        <img src={ tl.qr_code_url() }/>
+                <h3>"You are logged in"</h3>
+                <div><span>"Username: "</span><span>{ user.with(move |u| u.as_ref().map(|u| u.username.clone() )) }</span></div>
+                {move || logout_error.get().map(|e| view! { <p style="color:red">{e}</p> })}
+                <button
+                    prop:disabled=move || wait_for_response.get()
+                    on:click=move |_| logout_action.dispatch(())
+                >
+                    "Log out"
+                </button>
+
+                <h3>"Profile"</h3>
+                {move || user.get().map(|user| view! {
+                    <ProfileForm
+                        user
+                        action=update_profile_action
+                        error=profile_error
+                        disabled=wait_for_response
+                    />
+                })}
+
+                <h3>"Preferences"</h3>
+                {move || user.get().map(|user| view! {
+                    <PreferencesForm
+                        preferences=user.preferences
+                        action=update_preferences_action
+                        error=preferences_error
+                        disabled=wait_for_response
+                    />
+                })}
+                <Show
+                    when=move || temporary_login.with(|tl| tl.is_some())
+                    fallback=move || {
+                        view! {
+                            <CreateTemporaryLoginForm
+                                action=create_temporary_login_action
+                                error=temporary_login_error
+                                disabled=wait_for_response
+                            />
+                        }
                     }
-                }))}
-            </Show>
+                >
+                    {move || temporary_login.with(move |tl| tl.as_ref().map(|tl| {
+                        view! {
+                            <a href={ &tl.url }>{ &tl.url }</a>
+                            <img src={ tl.qr_code_url() }/>
+                            { tl.pin.clone().map(|pin| view! {
+                                <div><span>"Pin: "</span><span>{ pin }</span></div>
+                            }) }
+                        }
+                    }))}
+                    {move || temporary_login_error.get().map(|e| view! { <p style="color:red">{e}</p> })}
+                    <button
+                        prop:disabled=move || wait_for_response.get()
+                        on:click=move |_| revoke_temporary_login_action.dispatch(())
+                    >
+                        "Revoke temporary login code"
+                    </button>
+                </Show>
+
+                <AddKeyForm
+                    action=add_key_action
+                    error=add_key_error
+                    message=add_key_success
+                    disabled=wait_for_response
+                />
 
-            <AddKeyForm
-                action=add_key_action
-                error=add_key_error
-                message=add_key_success
-                disabled=wait_for_response
-            />
+                <CredentialList refresh=keys_added/>
+
+                {move || recovery_codes_error.get().map(|e| view! { <p style="color:red">{e}</p> })}
+                <Show
+                    when=move || recovery_codes.with(|c| c.is_some())
+                    fallback=move || {
+                        view! {
+                            <button
+                                prop:disabled=move || wait_for_response.get()
+                                on:click=move |_| regenerate_recovery_codes_action.dispatch(())
+                            >
+                                "New recovery codes"
+                            </button>
+                            <span>" Any codes you have now will stop working"</span>
+                        }
+                    }
+                >
+                    { move || recovery_codes.get().map(|codes| view! { <RecoveryCodeList codes/> }) }
+                </Show>
+
+                <LoginSessionList/>
+
+                <h3>"Your data"</h3>
+                <p>
+                    <a href=Auth::AccountExport.path().to_string() download>
+                        "Download everything about you"
+                    </a>
+                    " as a json file"
+                </p>
+                <DeleteAccountForm
+                    action=delete_account_action
+                    error=delete_error
+                    disabled=wait_for_response
+                />
+            </Show>
         </Show>
     }
 }
@@ -115,15 +357,25 @@ fn ProfileWithUser(
 #[component]
 pub fn Profile() -> impl IntoView {
     // Resources
-    let user_and_temp_login = create_local_resource(move || (), |_| fetch_user());
+    let user_and_temp_login = create_local_resource(
+        move || (),
+        |_| async {
+            let result = fetch_user().await;
+            if let Ok((user, _)) = &result {
+                mirror_user(user).await;
+            }
+            result
+        },
+    );
 
-    let update_action = create_action(move |(user, tl): &(User, TemporaryLogin)| {
+    let update_action = create_action(move |(user, tl): &(User, Option<TemporaryLogin>)| {
         let user = user.clone();
         let tl = tl.clone();
 
         async move {
+            mirror_user(&user).await;
             user_and_temp_login.update(|v| {
-                *v = Some(Ok((user, Some(tl))));
+                *v = Some(Ok((user, tl)));
             })
         }
     });
@@ -7,7 +7,7 @@ use tracing::{debug, warn};
 
 use crate::{
     api::register,
-    components::{OfflineFallback, RegistrationForm},
+    components::{OfflineFallback, RecoveryCodeList, RegistrationForm},
     ClientRoutes,
 };
 
@@ -58,6 +58,7 @@ pub fn Register() -> impl IntoView {
                 }
             >
                 <p>"Registration complete"</p>
+                { move || register_response.get().map(|codes| view! { <RecoveryCodeList codes/> }) }
                 <span>"You can now "</span> { ClientRoutes::Login.link() }
             </Show>
         </OfflineFallback>
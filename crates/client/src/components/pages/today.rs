@@ -1,200 +1,75 @@
 use chrono::Utc;
-use futures::{future::join_all, TryFutureExt};
+use futures::try_join;
 use leptos::{
     component, create_action, create_local_resource, view, CollectView, IntoView, Resource,
     Transition,
 };
 use shared::{
     model::{
-        Exercise, ExerciseGroup, ExerciseGroupIden, ExerciseGroupMember, ExerciseGroupMemberIden,
-        ExerciseIden, Plan, PlanExerciseGroup, PlanExerciseGroupIden, PlanIden, PlanInstance,
-        PlanInstanceIden, Session, SessionExercise, SessionExerciseIden, SessionIden, User,
-        UserExercise, UserExerciseIden,
+        Exercise, ExerciseGroup, ExerciseGroupMember, ExerciseTree, Plan, PlanExerciseGroup,
+        PlanGroupTree, PlanInstance, PlanTree, PlanTreeQueries, PlanTreeRows, Session,
+        SessionExercise, UserExercise,
     },
     types::Uuid,
 };
 use tracing::debug;
 use web_time::Instant;
 
+use super::{fetch_local_user, Workouts};
 use crate::{
     components::FrontendErrorBoundary,
     db::{
         sqlite3::{SqlitePromiser, SqlitePromiserError},
-        PromiserFetcher, PromiserInserter,
+        PromiserFetcher,
     },
 };
 
-fn plan() -> Resource<
-    (),
-    Result<
-        Vec<(
-            Plan,
-            PlanInstance,
-            Vec<(
-                PlanExerciseGroup,
-                ExerciseGroup,
-                Vec<(Exercise, Option<UserExercise>, Vec<(SessionExercise, Session)>)>,
-            )>,
-        )>,
-        SqlitePromiserError,
-    >,
-> {
+fn plan() -> Resource<(), Result<Vec<PlanTree>, SqlitePromiserError>> {
     create_local_resource(
         || (),
         |_| async {
             let start = Instant::now();
-            let user = {
-                let mut users = <User as PromiserFetcher>::fetch_all().await?;
-                if users.len() != 1 {
-                    Err(SqlitePromiserError::ExecResult(format!(
-                        "Expected 1 user but got {}",
-                        users.len()
-                    )))?;
-                }
-                users.pop().unwrap()
-            };
+            let user = fetch_local_user().await?;
             debug!("User: {:?}", user);
 
-            let plan_instances = PlanInstance::fetch_by(&user.id, PlanInstanceIden::UserId).await?;
-            debug!("Plan instances: {:?}", plan_instances);
-
-            let plans = join_all(
-                plan_instances.iter().map(|pi| Plan::fetch_one_by(&pi.plan_id, PlanIden::Id)),
-            )
-            .await
-            .into_iter()
-            .collect::<Result<Vec<_>, _>>()?;
-            debug!("Plans: {:?}", plans);
-
-            let plan_exercise_groups =
-                join_all(plan_instances.iter().map(|pi| {
-                    PlanExerciseGroup::fetch_by(&pi.plan_id, PlanExerciseGroupIden::PlanId)
-                }))
-                .await
-                .into_iter()
-                .collect::<Result<Vec<_>, _>>()?;
-            debug!("Plan exercise groups: {:?}", plan_exercise_groups);
-
-            let exercise_groups = join_all(plan_exercise_groups.iter().map(|pigs| {
-                join_all(pigs.iter().map(|pig| {
-                    ExerciseGroup::fetch_one_by(&pig.exercise_group_id, ExerciseGroupIden::Id)
-                }))
-            }))
-            .await
-            .into_iter()
-            .map(|r| r.into_iter().collect::<Result<Vec<_>, _>>())
-            .collect::<Result<Vec<_>, _>>()?;
-            debug!("Exercise groups: {:?}", exercise_groups);
-
-            let exercises = join_all(exercise_groups.iter().map(|egs| {
-                join_all(egs.iter().map(|eg| {
-                    ExerciseGroupMember::fetch_by(&eg.id, ExerciseGroupMemberIden::GroupId)
-                        .and_then(|egms| async move {
-                            join_all(egms.into_iter().map(|egm| {
-                                Exercise::fetch_one_by(egm.exercise_id, ExerciseIden::Id)
-                            }))
-                            .await
-                            .into_iter()
-                            .collect::<Result<Vec<_>, _>>()
-                        })
-                }))
-            }))
-            .await
-            .into_iter()
-            .map(|r| r.into_iter().collect::<Result<Vec<_>, _>>())
-            .collect::<Result<Vec<_>, _>>()?;
-            debug!("Exercises: {:?}", exercises);
-
-            let user_exercises = join_all(exercises.iter().map(|es| {
-                join_all(es.iter().map(|es| async move {
-                    join_all(es.iter().map(|e| {
-                        UserExercise::fetch_maybe_one_by(&e.id, UserExerciseIden::ExerciseId)
-                    }))
-                    .await
-                    .into_iter()
-                    .collect::<Result<Vec<_>, _>>()
-                }))
-            }))
-            .await
-            .into_iter()
-            .map(|r| r.into_iter().collect::<Result<Vec<_>, _>>())
-            .collect::<Result<Vec<_>, _>>()?;
-            debug!("User exercises: {:?}", user_exercises);
-
-            let exercises_sessions = join_all(exercises.iter().map(|exs| {
-                join_all(exs.iter().map(|exs| async move {
-                    join_all(exs.iter().map(|e| {
-                        SessionExercise::fetch_by(&e.id, SessionExerciseIden::ExerciseId).and_then(
-                            |ses| async move {
-                                join_all(ses.into_iter().map(|se| async move {
-                                    let session =
-                                        Session::fetch_one_by(&se.session_id, SessionIden::Id)
-                                            .await?;
-                                    Ok((se, session))
-                                }))
-                                .await
-                                .into_iter()
-                                .collect::<Result<Vec<_>, _>>()
-                            },
-                        )
-                    }))
-                    .await
-                    .into_iter()
-                    .collect::<Result<Vec<_>, _>>()
-                }))
-            }))
-            .await
-            .into_iter()
-            .map(|r| r.into_iter().collect::<Result<Vec<_>, _>>())
-            .collect::<Result<Vec<_>, _>>()?;
-            debug!("Exercise sessions: {:?}", exercises_sessions);
-
-            let ret = plans
-                .into_iter()
-                .zip(plan_instances.into_iter())
-                .zip(plan_exercise_groups.into_iter())
-                .zip(exercise_groups.into_iter())
-                .zip(exercises.into_iter())
-                .zip(user_exercises.into_iter())
-                .zip(exercises_sessions.into_iter())
-                .map(
-                    |(
-                        (((((plan, plan_instance), plan_exercise_groups), exercise_groups), exercises), user_exercises),
-                        exercises_sessions,
-                    )| {
-                        (
-                            plan,
-                            plan_instance,
-                            plan_exercise_groups
-                                .into_iter()
-                                .zip(exercise_groups.into_iter())
-                                .zip(exercises.into_iter())
-                                .zip(user_exercises.into_iter())
-                                .zip(exercises_sessions.into_iter())
-                                .map(
-                                    |(
-                                        (((plan_exercise_group, exercise_group), exercises), user_exercises),
-                                        exercises_sessions,
-                                    )| {
-                                        (
-                                            plan_exercise_group,
-                                            exercise_group,
-                                            exercises
-                                                .into_iter()
-                                                .zip(user_exercises.into_iter())
-                                                .zip(exercises_sessions)
-                                                .map(|((exercise, user_exercise), exercise_sessions)| {
-                                                    (exercise, user_exercise, exercise_sessions)
-                                                })
-                                                .collect::<Vec<_>>(),
-                                        )
-                                    },
-                                )
-                                .collect::<Vec<_>>(),
-                        )
-                    },
-                )
-                .collect::<Vec<_>>();
+            // One statement per table rather than one per row so the page doesn't slow
+            // down as the history grows
+            let queries = PlanTreeQueries::new(user.id);
+            let (
+                plan_instances,
+                plans,
+                plan_exercise_groups,
+                exercise_groups,
+                exercise_group_members,
+                exercises,
+                user_exercises,
+                session_exercises,
+                sessions,
+            ) = try_join!(
+                PlanInstance::fetch_with(&queries.plan_instances()),
+                Plan::fetch_with(&queries.plans()),
+                PlanExerciseGroup::fetch_with(&queries.plan_exercise_groups()),
+                ExerciseGroup::fetch_with(&queries.exercise_groups()),
+                ExerciseGroupMember::fetch_with(&queries.exercise_group_members()),
+                Exercise::fetch_with(&queries.exercises()),
+                UserExercise::fetch_with(&queries.user_exercises()),
+                SessionExercise::fetch_with(&queries.session_exercises()),
+                Session::fetch_with(&queries.sessions()),
+            )?;
+
+            let ret = PlanTreeRows {
+                plan_instances,
+                plans,
+                plan_exercise_groups,
+                exercise_groups,
+                exercise_group_members,
+                exercises,
+                user_exercises,
+                session_exercises,
+                sessions,
+            }
+            .into_trees();
+            debug!("Plans: {:?}", ret);
 
             debug!("today resource took: {:.2}", start.elapsed().as_secs_f32());
 
@@ -211,6 +86,7 @@ pub fn Today() -> impl IntoView {
         <Transition fallback=move || view! {  <p>"Loading..."</p>} >
             <FrontendErrorBoundary<SqlitePromiserError>>
                 <h2>"Today"</h2>
+                <Workouts />
                 { move || {
                     plans.and_then(|p| p
                         .into_iter()
@@ -229,11 +105,7 @@ pub fn Today() -> impl IntoView {
 fn Plan<'a>(
     plan: &'a Plan,
     plan_instance: &'a PlanInstance,
-    groups: &'a Vec<(
-        PlanExerciseGroup,
-        ExerciseGroup,
-        Vec<(Exercise, Option<UserExercise>, Vec<(SessionExercise, Session)>)>,
-    )>,
+    groups: &'a Vec<PlanGroupTree>,
 ) -> impl IntoView {
     view! {
         <div>
@@ -252,7 +124,7 @@ fn PlanGroup<'a>(
     plan_instance: &'a PlanInstance,
     plan_group: &'a PlanExerciseGroup,
     group: &'a ExerciseGroup,
-    exercises: &'a Vec<(Exercise, Option<UserExercise>, Vec<(SessionExercise, Session)>)>,
+    exercises: &'a Vec<ExerciseTree>,
 ) -> impl IntoView {
     view! {
         <div>
@@ -261,7 +133,7 @@ fn PlanGroup<'a>(
             { plan_group.notes.as_ref().map(|n| view! { <p>Notes: { n }</p> }) }
             <div>
                 { exercises.into_iter().map(|(exercise, user_exercise, exercise_sessions)| view ! {
-                    <Exercise plan_instance_id=plan_instance.id exercise user_exercise exercise_sessions/>
+                    <Exercise plan_instance_id=plan_instance.id user_id=plan_instance.user_id exercise user_exercise exercise_sessions/>
                 }).collect_view() }
             </div>
         </div>
@@ -271,10 +143,17 @@ fn PlanGroup<'a>(
 #[component]
 fn Exercise<'a>(
     plan_instance_id: Uuid,
+    user_id: Uuid,
     exercise: &'a Exercise,
     user_exercise: &'a Option<UserExercise>,
     exercise_sessions: &'a Vec<(SessionExercise, Session)>,
 ) -> impl IntoView {
+    // Ad-hoc sessions and sessions of other instances of the plan don't belong here
+    let exercise_sessions = exercise_sessions
+        .iter()
+        .filter(|(_, session)| session.plan_instance_id == Some(plan_instance_id))
+        .collect::<Vec<_>>();
+
     let now = Utc::now();
     let _most_recent_session =
         exercise_sessions.iter().filter(|(_, session)| session.planned_date < now).max_by(
@@ -288,7 +167,8 @@ fn Exercise<'a>(
 
             let session = Session {
                 id: Uuid::new_v4(),
-                plan_instance_id: *plan_instance,
+                user_id,
+                plan_instance_id: Some(*plan_instance),
                 planned_date: now,
                 performed_date: None,
                 creation_date: now,
@@ -297,19 +177,28 @@ fn Exercise<'a>(
 
             let session_exercise = SessionExercise {
                 id: Uuid::new_v4(),
+                user_id,
                 exercise_id: *exercise,
                 session_id: session.id,
                 planned_sets: Default::default(),
                 performed_sets: Default::default(),
                 creation_date: now,
                 last_updated_date: now,
+                sort_order: 0,
+                finished: false,
             };
 
             async move {
-                promiser.exec(session.insert_sql()?).await?;
-                promiser.exec(session_exercise.insert_sql()?).await?;
-
-                Ok::<_, SqlitePromiserError>(())
+                let session_stmt = session.insert_statement()?;
+                let session_exercise_stmt = session_exercise.insert_statement()?;
+
+                promiser
+                    .transaction(|tx| async move {
+                        tx.exec_query(&session_stmt).await?;
+                        tx.exec_query(&session_exercise_stmt).await?;
+                        Ok(())
+                    })
+                    .await
             }
         });
 
@@ -0,0 +1,648 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use futures::try_join;
+use leptos::{
+    component, create_action, create_local_resource, create_signal, event_target_value, on_cleanup,
+    set_interval_with_handle, view, Action, CollectView, IntoView, Resource, SignalGet, SignalSet,
+    Transition,
+};
+use leptos_router::use_query_map;
+use sea_query::{Expr, Order, SqliteQueryBuilder, Values};
+use shared::{
+    model::{
+        Exercise, Model, Plan, PlanIden, PlanInstance, PlanInstanceIden, Reps,
+        RestTimerPreferences, Session, SessionExercise, SessionExerciseIden, SessionIden, Set,
+        User, Weight,
+    },
+    types::Uuid,
+};
+use tracing::warn;
+
+use crate::{
+    components::FrontendErrorBoundary,
+    db::{
+        sqlite3::{SqlitePromiser, SqlitePromiserError},
+        PromiserFetcher,
+    },
+    SESSION_QUERY,
+};
+
+/// Sql with the values to bind to it
+type Statement = (String, Values);
+
+type SessionWithExercises = (Session, Vec<(SessionExercise, Exercise)>);
+
+/// Everything needed to run the in progress sessions and show the ad-hoc ones
+#[derive(Debug, Clone)]
+struct WorkoutsData {
+    user: User,
+    /// The exercise library new session exercises can be picked from
+    exercises: Vec<Exercise>,
+    /// Plan instances completed ad-hoc sessions can be adopted into
+    plan_instances: Vec<(PlanInstance, Plan)>,
+    /// Sessions that haven't been performed yet, plan or ad-hoc. Session
+    /// exercises are in the order they should be performed
+    in_progress: Vec<SessionWithExercises>,
+    /// The most recently completed ad-hoc sessions, most recent first
+    completed_ad_hoc: Vec<SessionWithExercises>,
+}
+
+type WorkoutsResource = Resource<(), Result<WorkoutsData, SqlitePromiserError>>;
+
+/// Fetches the single user the local database belongs to
+pub(super) async fn fetch_local_user() -> Result<User, SqlitePromiserError> {
+    let mut users = <User as PromiserFetcher>::fetch_all().await?;
+    if users.len() != 1 {
+        Err(SqlitePromiserError::ExecResult(format!("Expected 1 user but got {}", users.len())))?;
+    }
+    Ok(users.pop().unwrap())
+}
+
+/// How many of the most recently completed ad-hoc sessions are offered to be
+/// adopted into a plan. Older ones are only in the history
+const COMPLETED_AD_HOC_SESSIONS: u64 = 5;
+
+fn workouts() -> WorkoutsResource {
+    create_local_resource(
+        || (),
+        |_| async {
+            let user = fetch_local_user().await?;
+
+            // One statement per table rather than one per row so the page doesn't slow
+            // down as the history grows
+            let in_progress = Session::select_star()
+                .and_where(Expr::col(SessionIden::UserId).eq(user.id))
+                .and_where(Expr::col(SessionIden::PerformedDate).is_null())
+                .order_by(SessionIden::PlannedDate, Order::Desc)
+                .to_owned();
+            let completed_ad_hoc = Session::select_star()
+                .and_where(Expr::col(SessionIden::UserId).eq(user.id))
+                .and_where(Expr::col(SessionIden::PerformedDate).is_not_null())
+                .and_where(Expr::col(SessionIden::PlanInstanceId).is_null())
+                .order_by(SessionIden::PerformedDate, Order::Desc)
+                .limit(COMPLETED_AD_HOC_SESSIONS)
+                .to_owned();
+            let (exercises, plan_instances, in_progress, completed_ad_hoc) = try_join!(
+                <Exercise as PromiserFetcher>::fetch_all(),
+                PlanInstance::fetch_by(&user.id, PlanInstanceIden::UserId),
+                Session::fetch_with(&in_progress),
+                Session::fetch_with(&completed_ad_hoc),
+            )?;
+
+            let plans = Plan::select_star()
+                .and_where(
+                    Expr::col(PlanIden::Id).is_in(plan_instances.iter().map(|pi| pi.plan_id)),
+                )
+                .to_owned();
+            let session_exercises = SessionExercise::select_star()
+                .and_where(
+                    Expr::col(SessionExerciseIden::SessionId)
+                        .is_in(in_progress.iter().chain(&completed_ad_hoc).map(|s| s.id)),
+                )
+                .to_owned();
+            let (plans, session_exercises) = try_join!(
+                Plan::fetch_with(&plans),
+                SessionExercise::fetch_with(&session_exercises),
+            )?;
+
+            let mut by_session = HashMap::<_, Vec<_>>::new();
+            for session_exercise in session_exercises {
+                by_session.entry(session_exercise.session_id).or_default().push(session_exercise);
+            }
+            let mut with_exercises = |sessions: Vec<Session>| {
+                sessions
+                    .into_iter()
+                    .map(|session| {
+                        let mut session_exercises =
+                            by_session.remove(&session.id).unwrap_or_default();
+                        session_exercises.sort_by_key(SessionExercise::order_key);
+                        let session_exercises = session_exercises
+                            .into_iter()
+                            .filter_map(|se| {
+                                exercises
+                                    .iter()
+                                    .find(|e| e.id == se.exercise_id)
+                                    .map(|e| (se, e.clone()))
+                            })
+                            .collect();
+                        (session, session_exercises)
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let in_progress = with_exercises(in_progress);
+            let completed_ad_hoc = with_exercises(completed_ad_hoc);
+
+            let plan_instances = plan_instances
+                .into_iter()
+                .filter_map(|pi| {
+                    let plan = plans.iter().find(|p| p.id == pi.plan_id)?.clone();
+                    Some((pi, plan))
+                })
+                .collect();
+
+            Ok(WorkoutsData { user, exercises, plan_instances, in_progress, completed_ad_hoc })
+        },
+    )
+}
+
+/// Runs the statements in a single transaction against the local database then
+/// refetches the resource so the page reflects the changes
+async fn exec_and_refetch(
+    promiser: SqlitePromiser,
+    statements: Result<Vec<Statement>, SqlitePromiserError>,
+    resource: WorkoutsResource,
+) {
+    let result = async {
+        let statements = statements?;
+        promiser
+            .transaction(|tx| async move {
+                for (sql, values) in statements {
+                    tx.exec_with(sql, values).await?;
+                }
+                Ok(())
+            })
+            .await
+    }
+    .await;
+
+    if let Err(e) = result {
+        warn!("Error updating workout: {e:?}");
+    }
+    resource.refetch();
+}
+
+/// Creates an action that writes the statements returned by `f` to the local
+/// database. Every change is written straight away so nothing is lost if the
+/// page is reloaded mid workout
+fn write_action<I: 'static>(
+    resource: WorkoutsResource,
+    f: impl Fn(&I) -> Result<Vec<Statement>, SqlitePromiserError> + 'static,
+) -> Action<I, ()> {
+    create_action(move |input: &I| {
+        let promiser = SqlitePromiser::use_promiser();
+        exec_and_refetch(promiser, f(input), resource)
+    })
+}
+
+/// Marks the session as performed now
+fn finish_session(session: &Session) -> Result<Statement, SqlitePromiserError> {
+    let mut session = session.clone();
+    session.performed_date = Some(Utc::now());
+    Ok(session.update_statement()?.build(SqliteQueryBuilder))
+}
+
+/// Replaces the number in the weight, keeping the unit
+fn with_weight_value(weight: &Weight, value: f64) -> Weight {
+    match weight {
+        Weight::Kilograms(_) => Weight::Kilograms(value),
+        Weight::Lbs(_) => Weight::Lbs(value),
+        Weight::Bodyweight => Weight::Bodyweight,
+    }
+}
+
+/// Replaces the number of reps, keeping the type
+fn with_reps_value(reps: &Reps, value: u32) -> Reps {
+    match reps {
+        Reps::Amrap(_) => Reps::Amrap(value),
+        Reps::Reps(_) => Reps::Reps(value),
+    }
+}
+
+/// In progress sessions and free form workouts that aren't tied to a plan. A
+/// session linked to from a reminder is shown first
+#[component]
+pub fn Workouts() -> impl IntoView {
+    let data = workouts();
+    let query = use_query_map();
+    // The session a reminder linked to
+    let linked_session =
+        move || query.with(|q| q.get(SESSION_QUERY).and_then(|id| Uuid::parse(id).ok()));
+
+    let start_action = write_action(data, |user_id: &Uuid| {
+        Ok(vec![Session::new_ad_hoc(*user_id).insert_statement()?.build(SqliteQueryBuilder)])
+    });
+
+    view! {
+        <Transition fallback=move || view! {  <p>"Loading..."</p>} >
+            <FrontendErrorBoundary<SqlitePromiserError>>
+                <h3>"Workouts"</h3>
+                { move || data.and_then(|d| {
+                    let user_id = d.user.id;
+                    let linked_session = linked_session();
+                    let mut in_progress = d.in_progress.iter().collect::<Vec<_>>();
+                    in_progress.sort_by_key(|(session, _)| Some(session.id) != linked_session);
+                    view! {
+                        <form on:submit=|ev| ev.prevent_default()>
+                            <button on:click=move |_| start_action.dispatch(user_id)>
+                                "Start empty workout"
+                            </button>
+                        </form>
+                        { in_progress.into_iter().map(|(session, session_exercises)| view! {
+                            <SessionRunner
+                                data
+                                session
+                                session_exercises
+                                exercises=&d.exercises
+                                rest_timer=d.user.preferences.rest_timer.clone()
+                            />
+                        }).collect_view() }
+                        { d.completed_ad_hoc.iter().map(|(session, session_exercises)| view! {
+                            <CompletedAdHocSession
+                                data
+                                session
+                                session_exercises
+                                plan_instances=&d.plan_instances
+                            />
+                        }).collect_view() }
+                    }
+                })}
+            </FrontendErrorBoundary<SqlitePromiserError>>
+        </Transition>
+    }
+}
+
+/// Steps through the exercises of the session set by set
+#[component]
+fn SessionRunner<'a>(
+    data: WorkoutsResource,
+    session: &'a Session,
+    session_exercises: &'a Vec<(SessionExercise, Exercise)>,
+    exercises: &'a Vec<Exercise>,
+    rest_timer: RestTimerPreferences,
+) -> impl IntoView {
+    let (selected_exercise, set_selected_exercise) = create_signal(None::<Uuid>);
+
+    let add_exercise_action =
+        write_action(data, |(session, exercise_id, sort_order): &(Session, Uuid, i64)| {
+            let now = Utc::now();
+            let session_exercise = SessionExercise {
+                id: Uuid::new_v4(),
+                user_id: session.user_id,
+                exercise_id: *exercise_id,
+                session_id: session.id,
+                planned_sets: Default::default(),
+                performed_sets: Default::default(),
+                creation_date: now,
+                last_updated_date: now,
+                sort_order: *sort_order,
+                finished: false,
+            };
+            Ok(vec![session_exercise.insert_statement()?.build(SqliteQueryBuilder)])
+        });
+
+    // Skips anything left over
+    let finish_action =
+        write_action(data, |(session, unfinished): &(Session, Vec<SessionExercise>)| {
+            let mut statements = unfinished
+                .iter()
+                .map(|se| {
+                    let mut se = se.clone();
+                    se.skip();
+                    Ok::<_, SqlitePromiserError>(se.update_statement()?.build(SqliteQueryBuilder))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            statements.push(finish_session(session)?);
+            Ok(statements)
+        });
+
+    // Renumbers all the session exercises in the new order
+    let reorder_action = write_action(data, |reordered: &Vec<SessionExercise>| {
+        reordered
+            .iter()
+            .enumerate()
+            .filter(|(i, se)| se.sort_order != *i as i64)
+            .map(|(i, se)| {
+                let mut se = se.clone();
+                se.sort_order = i as i64;
+                Ok(se.update_statement()?.build(SqliteQueryBuilder))
+            })
+            .collect()
+    });
+
+    let current = session_exercises.iter().position(|(se, _)| !se.finished);
+    let ordered = session_exercises.iter().map(|(se, _)| se.clone()).collect::<Vec<_>>();
+    let unfinished = ordered.iter().filter(|se| !se.finished).cloned().collect::<Vec<_>>();
+    let next_sort_order = ordered.iter().map(|se| se.sort_order + 1).max().unwrap_or(0);
+    let last_set_date = ordered
+        .iter()
+        .flat_map(|se| se.performed_sets.iter())
+        .filter_map(|set| set.completed_date)
+        .max();
+
+    let session_for_add = session.clone();
+    let session_for_finish = session.clone();
+
+    let move_exercise = move |ordered: &Vec<SessionExercise>, from: usize, to: usize| {
+        let ordered = ordered.clone();
+        move |_| {
+            let mut reordered = ordered.clone();
+            reordered.swap(from, to);
+            reorder_action.dispatch(reordered)
+        }
+    };
+
+    view! {
+        <div>
+            <h4>{ format!("Workout: {}", session.planned_date.format("%Y-%m-%d %H:%M")) }</h4>
+            <ol>
+                { session_exercises.iter().enumerate().map(|(i, (se, exercise))| {
+                    let can_move_up = !se.finished && i > 0 && !ordered[i - 1].finished;
+                    let can_move_down = !se.finished && i + 1 < ordered.len();
+                    view! {
+                        <li>
+                            { format!(
+                                "{}: {}{} sets{}",
+                                exercise.name,
+                                se.performed_sets.len(),
+                                if se.planned_sets.is_empty() {
+                                    String::new()
+                                } else {
+                                    format!("/{}", se.planned_sets.len())
+                                },
+                                if Some(i) == current {
+                                    " (current)"
+                                } else if se.finished && se.performed_sets.len() < se.planned_sets.len() {
+                                    " (skipped)"
+                                } else if se.finished {
+                                    " (done)"
+                                } else {
+                                    ""
+                                },
+                            ) }
+                            { can_move_up.then(|| view! {
+                                <button on:click=move_exercise(&ordered, i, i - 1)>"Up"</button>
+                            }) }
+                            { can_move_down.then(|| view! {
+                                <button on:click=move_exercise(&ordered, i, i + 1)>"Down"</button>
+                            }) }
+                        </li>
+                    }
+                }).collect_view() }
+            </ol>
+            { last_set_date.map(|last_set_date| view! { <RestTimer last_set_date rest_timer /> }) }
+            { current.map(|i| {
+                let (session_exercise, exercise) = &session_exercises[i];
+                // Whether this is the last exercise left, finishing it finishes the session
+                let last = unfinished.len() == 1;
+                view! {
+                    <CurrentExercise data session session_exercise exercise last />
+                }
+            }) }
+            <form on:submit=|ev| ev.prevent_default()>
+                <select on:change=move |ev| {
+                    set_selected_exercise.set(Uuid::parse(&event_target_value(&ev)).ok())
+                }>
+                    <option value="">"Choose an exercise"</option>
+                    { exercises.iter().map(|e| view! {
+                        <option value={ e.id.to_string() }>{ &e.name }</option>
+                    }).collect_view() }
+                </select>
+                <button
+                    prop:disabled=move || selected_exercise.get().is_none()
+                    on:click=move |_| {
+                        if let Some(exercise_id) = selected_exercise.get() {
+                            add_exercise_action.dispatch(
+                                (session_for_add.clone(), exercise_id, next_sort_order)
+                            )
+                        }
+                    }
+                >
+                    "Add exercise"
+                </button>
+                <button
+                    on:click=move |_| finish_action.dispatch(
+                        (session_for_finish.clone(), unfinished.clone())
+                    )
+                >
+                    "Finish workout"
+                </button>
+            </form>
+        </div>
+    }
+}
+
+/// Counts down the rest period from the user's preferences after the last
+/// performed set. Without auto start it waits for the user to start it
+#[component]
+fn RestTimer(last_set_date: DateTime<Utc>, rest_timer: RestTimerPreferences) -> impl IntoView {
+    let (now, set_now) = create_signal(Utc::now());
+    let (skipped, set_skipped) = create_signal(false);
+    let (start_date, set_start_date) =
+        create_signal(rest_timer.auto_start.then_some(last_set_date));
+
+    match set_interval_with_handle(move || set_now.set(Utc::now()), Duration::from_secs(1)) {
+        Ok(handle) => on_cleanup(move || handle.clear()),
+        Err(e) => warn!("Failed to start rest timer: {e:?}"),
+    }
+
+    let seconds = i64::from(rest_timer.seconds);
+    let remaining =
+        move || start_date.get().map(|start_date| seconds - (now.get() - start_date).num_seconds());
+
+    view! {
+        { move || (!skipped.get()).then(|| match remaining() {
+            Some(remaining) if remaining > 0 => view! {
+                <p>
+                    { format!("Rest: {}:{:02}", remaining / 60, remaining % 60) }
+                    <button on:click=move |_| set_skipped.set(true)>"Skip rest"</button>
+                </p>
+            }.into_view(),
+            Some(_) => ().into_view(),
+            None => view! {
+                <p>
+                    <button on:click=move |_| set_start_date.set(Some(Utc::now()))>
+                        "Start rest"
+                    </button>
+                </p>
+            }.into_view(),
+        }) }
+    }
+}
+
+/// Logs the sets of the exercise currently being performed
+#[component]
+fn CurrentExercise<'a>(
+    data: WorkoutsResource,
+    session: &'a Session,
+    session_exercise: &'a SessionExercise,
+    exercise: &'a Exercise,
+    last: bool,
+) -> impl IntoView {
+    // Pre-fill from the plan, falling back on the previous set so free form
+    // exercises don't have to be typed in every time
+    let template = session_exercise
+        .next_planned_set()
+        .or_else(|| session_exercise.performed_sets.last())
+        .cloned()
+        .unwrap_or_else(|| Set::new(Weight::Kilograms(0.0), Reps::Reps(0)));
+
+    let initial_weight = match template.weight {
+        Weight::Kilograms(w) | Weight::Lbs(w) => Some(w),
+        Weight::Bodyweight => None,
+    };
+    let initial_reps = match template.reps {
+        Reps::Amrap(r) | Reps::Reps(r) => r,
+    };
+    let (weight, set_weight) = create_signal(initial_weight);
+    let (reps, set_reps) = create_signal(Some(initial_reps));
+
+    let log_set_action = write_action(
+        data,
+        |(session, session_exercise, set, last): &(Session, SessionExercise, Set, bool)| {
+            let mut session_exercise = session_exercise.clone();
+            session_exercise.log_set(set.clone());
+
+            let mut statements =
+                vec![session_exercise.update_statement()?.build(SqliteQueryBuilder)];
+            if *last && session_exercise.finished {
+                statements.push(finish_session(session)?);
+            }
+            Ok(statements)
+        },
+    );
+
+    let skip_action = write_action(
+        data,
+        |(session, session_exercise, last): &(Session, SessionExercise, bool)| {
+            let mut session_exercise = session_exercise.clone();
+            session_exercise.skip();
+
+            let mut statements =
+                vec![session_exercise.update_statement()?.build(SqliteQueryBuilder)];
+            if *last {
+                statements.push(finish_session(session)?);
+            }
+            Ok(statements)
+        },
+    );
+
+    let set_number = session_exercise.performed_sets.len() + 1;
+    let planned_sets = session_exercise.planned_sets.len();
+    let is_bodyweight = initial_weight.is_none();
+    let planned = session_exercise.next_planned_set().cloned();
+
+    let session_for_log = session.clone();
+    let session_exercise_for_log = session_exercise.clone();
+    let session_for_skip = session.clone();
+    let session_exercise_for_skip = session_exercise.clone();
+
+    view! {
+        <div>
+            <h5>{ &exercise.name }</h5>
+            <p>{ if planned_sets > 0 {
+                format!("Set {set_number} of {planned_sets}")
+            } else {
+                format!("Set {set_number}")
+            }}</p>
+            { planned.map(|p| view! { <p>{ format!("Planned: {} x {}", p.weight, p.reps) }</p> }) }
+            <form on:submit=|ev| ev.prevent_default()>
+                { (!is_bodyweight).then(|| view! {
+                    <input
+                        type="number"
+                        step="any"
+                        min="0"
+                        placeholder="Weight"
+                        prop:value=move || weight.get().map(|w| w.to_string()).unwrap_or_default()
+                        on:change=move |ev| set_weight.set(event_target_value(&ev).parse().ok())
+                    />
+                }) }
+                <input
+                    type="number"
+                    min="0"
+                    placeholder="Reps"
+                    prop:value=move || reps.get().map(|r| r.to_string()).unwrap_or_default()
+                    on:change=move |ev| set_reps.set(event_target_value(&ev).parse().ok())
+                />
+                <button
+                    prop:disabled=move || (!is_bodyweight && weight.get().is_none()) || reps.get().is_none()
+                    on:click=move |_| {
+                        if let Some(reps) = reps.get() {
+                            let set = Set::new(
+                                with_weight_value(&template.weight, weight.get().unwrap_or_default()),
+                                with_reps_value(&template.reps, reps),
+                            );
+                            log_set_action.dispatch(
+                                (session_for_log.clone(), session_exercise_for_log.clone(), set, last)
+                            )
+                        }
+                    }
+                >
+                    "Log set"
+                </button>
+                <button
+                    on:click=move |_| skip_action.dispatch(
+                        (session_for_skip.clone(), session_exercise_for_skip.clone(), last)
+                    )
+                >
+                    "Skip exercise"
+                </button>
+            </form>
+        </div>
+    }
+}
+
+/// Summary of a completed ad-hoc session with the option of adding it to the
+/// history of one of the user's plans
+#[component]
+fn CompletedAdHocSession<'a>(
+    data: WorkoutsResource,
+    session: &'a Session,
+    session_exercises: &'a Vec<(SessionExercise, Exercise)>,
+    plan_instances: &'a Vec<(PlanInstance, Plan)>,
+) -> impl IntoView {
+    let (selected_plan_instance, set_selected_plan_instance) = create_signal(None::<Uuid>);
+
+    let adopt_action = write_action(data, |(session, plan_instance): &(Session, PlanInstance)| {
+        let mut session = session.clone();
+        session
+            .adopt(plan_instance)
+            .map_err(|e| SqlitePromiserError::ExecResult(e.error_messages.join(", ")))?;
+        Ok(vec![session.update_statement()?.build(SqliteQueryBuilder)])
+    });
+
+    let session_for_adopt = session.clone();
+    let plan_instances_for_adopt =
+        plan_instances.iter().map(|(pi, _)| pi.clone()).collect::<Vec<_>>();
+
+    view! {
+        <div>
+            <h4>{ format!("Workout: {}", session.planned_date.format("%Y-%m-%d %H:%M")) }</h4>
+            { session_exercises.iter().map(|(session_exercise, exercise)| view! {
+                <h5>{ &exercise.name }</h5>
+                <ol>
+                    { session_exercise.performed_sets.iter().map(|set| view! {
+                        <li>{ format!("{} x {}", set.weight, set.reps) }</li>
+                    }).collect_view() }
+                </ol>
+            }).collect_view() }
+            { (!plan_instances.is_empty()).then(|| view! {
+                <form on:submit=|ev| ev.prevent_default()>
+                    <select on:change=move |ev| {
+                        set_selected_plan_instance.set(Uuid::parse(&event_target_value(&ev)).ok())
+                    }>
+                        <option value="">"Choose a plan"</option>
+                        { plan_instances.iter().map(|(pi, plan)| view! {
+                            <option value={ pi.id.to_string() }>
+                                { format!("{} (started {})", plan.name, pi.start_date.format("%Y-%m-%d")) }
+                            </option>
+                        }).collect_view() }
+                    </select>
+                    <button
+                        prop:disabled=move || selected_plan_instance.get().is_none()
+                        on:click=move |_| {
+                            let plan_instance = selected_plan_instance.get().and_then(|id| {
+                                plan_instances_for_adopt.iter().find(|pi| pi.id == id)
+                            });
+                            if let Some(plan_instance) = plan_instance {
+                                adopt_action.dispatch((session_for_adopt.clone(), plan_instance.clone()))
+                            }
+                        }
+                    >
+                        "Add to plan history"
+                    </button>
+                </form>
+            }) }
+        </div>
+    }
+}
@@ -0,0 +1,206 @@
+//! Exporting and importing the whole local database. The history lives in
+//! OPFS which is gone as soon as the browser clears the site data so these
+//! are how users keep a copy of it, either as a download or on the server
+
+use base64::prelude::*;
+use chrono::Utc;
+use sea_query::Iden;
+use serde_json::{Map, Value};
+use shared::{
+    api::payloads::{DatabaseBackup, DatabaseBackupInfo},
+    model::*,
+};
+use thiserror::Error;
+use tracing::{error, warn};
+
+use crate::{
+    api::{fetch_backup, upload_backup},
+    db::{
+        migrations::{latest_version, run_migrations},
+        sqlite3::{SqlitePromiser, SqlitePromiserError},
+        PromiserFetcher,
+    },
+};
+
+/// Every sqlite database file starts with this
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+/// Offset of the big-endian user_version (what the migrations use as the
+/// schema version) in the database header
+const USER_VERSION_OFFSET: usize = 60;
+/// Page sizes range from 512 to 65536 so any database file is a multiple of it
+const MIN_PAGE_SIZE: usize = 512;
+
+#[derive(Debug, Clone, Error)]
+pub enum BackupFileError {
+    #[error("File isn't a sqlite database")]
+    NotSqlite,
+    #[error("Database doesn't contain any data (schema version 0)")]
+    Empty,
+    #[error(
+        "Database is from a newer version of the app (schema version {version}, this version \
+         supports up to {latest}). Update the app before importing it"
+    )]
+    TooNew { version: u32, latest: usize },
+    #[error("Sqlite Promiser error: {0}")]
+    Sql(String),
+    #[error("Importing the database failed: {0}")]
+    Import(String),
+    #[error(
+        "Importing the database failed: {error}. Restoring the previous database also failed: \
+         {restore_error}"
+    )]
+    Restore { error: String, restore_error: String },
+    #[error("Error from server: {0}")]
+    Server(String),
+    #[error("Error decoding backup: {0}")]
+    Base64(String),
+}
+
+impl From<SqlitePromiserError> for BackupFileError {
+    fn from(value: SqlitePromiserError) -> Self {
+        Self::Sql(value.to_string())
+    }
+}
+
+/// Reads the schema version out of the database header after checking
+/// `bytes` look like a database this app can import
+pub fn schema_version(bytes: &[u8]) -> Result<u32, BackupFileError> {
+    if !bytes.starts_with(SQLITE_HEADER) || bytes.len() % MIN_PAGE_SIZE != 0 {
+        Err(BackupFileError::NotSqlite)?;
+    }
+
+    let version =
+        u32::from_be_bytes(bytes[USER_VERSION_OFFSET..USER_VERSION_OFFSET + 4].try_into().unwrap());
+
+    if version == 0 {
+        Err(BackupFileError::Empty)?;
+    }
+    if version as usize > latest_version() {
+        Err(BackupFileError::TooNew { version, latest: latest_version() })?;
+    }
+
+    Ok(version)
+}
+
+/// Replaces the local database with `bytes` and migrates it to the latest
+/// version. If anything goes wrong the previous database is put back
+pub async fn import_database(conn: &SqlitePromiser, bytes: &[u8]) -> Result<(), BackupFileError> {
+    schema_version(bytes)?;
+
+    let previous = conn.export().await?;
+
+    let import = async {
+        conn.import(bytes).await?;
+        conn.configure().await?;
+        run_migrations(conn).await.map_err(|e| BackupFileError::Import(e.to_string()))?;
+        Ok::<_, BackupFileError>(())
+    };
+
+    if let Err(e) = import.await {
+        warn!("Importing database failed, restoring the previous one: {e}");
+
+        let restore = async {
+            conn.import(&previous).await?;
+            conn.configure().await?;
+            run_migrations(conn).await.map_err(|e| BackupFileError::Import(e.to_string()))?;
+            Ok::<_, BackupFileError>(())
+        };
+
+        if let Err(restore_error) = restore.await {
+            error!("Restoring the previous database failed: {restore_error}");
+            Err(BackupFileError::Restore {
+                error: e.to_string(),
+                restore_error: restore_error.to_string(),
+            })?;
+        }
+
+        Err(e)?;
+    }
+
+    Ok(())
+}
+
+async fn export_table<T: PromiserFetcher>(
+    conn: &SqlitePromiser,
+    tables: &mut Map<String, Value>,
+) -> Result<(), SqlitePromiserError> {
+    let rows = T::extract_fields(conn.exec(T::fetch_all_sql()).await?)?;
+    tables.insert(T::table_iden().to_string(), serde_json::to_value(rows)?);
+
+    Ok(())
+}
+
+/// Every row of every model, keyed by table name
+pub async fn export_json(conn: &SqlitePromiser) -> Result<Value, BackupFileError> {
+    let mut tables = Map::new();
+
+    export_table::<User>(conn, &mut tables).await?;
+    export_table::<Exercise>(conn, &mut tables).await?;
+    export_table::<ExerciseGroup>(conn, &mut tables).await?;
+    export_table::<ExerciseGroupMember>(conn, &mut tables).await?;
+    export_table::<UserExercise>(conn, &mut tables).await?;
+    export_table::<Plan>(conn, &mut tables).await?;
+    export_table::<PlanExerciseGroup>(conn, &mut tables).await?;
+    export_table::<PlanInstance>(conn, &mut tables).await?;
+    export_table::<Session>(conn, &mut tables).await?;
+    export_table::<SessionExercise>(conn, &mut tables).await?;
+
+    Ok(Value::Object(tables))
+}
+
+/// Sends the whole database to the server, replacing the previous backup
+pub async fn backup_to_server(
+    conn: &SqlitePromiser,
+) -> Result<DatabaseBackupInfo, BackupFileError> {
+    let bytes = conn.export().await?;
+    let backup = DatabaseBackup {
+        schema_version: schema_version(&bytes)?,
+        creation_date: Utc::now(),
+        data: BASE64_STANDARD.encode(bytes),
+    };
+
+    upload_backup(&backup).await.map_err(|e| BackupFileError::Server(e.to_string()))
+}
+
+/// Replaces the local database with the backup held by the server
+pub async fn restore_from_server(
+    conn: &SqlitePromiser,
+) -> Result<DatabaseBackupInfo, BackupFileError> {
+    let backup = fetch_backup().await.map_err(|e| BackupFileError::Server(e.to_string()))?;
+    let bytes =
+        BASE64_STANDARD.decode(&backup.data).map_err(|e| BackupFileError::Base64(e.to_string()))?;
+
+    import_database(conn, &bytes).await?;
+
+    Ok(DatabaseBackupInfo {
+        schema_version: backup.schema_version,
+        creation_date: backup.creation_date,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn database(version: u32) -> Vec<u8> {
+        let mut bytes = vec![0; MIN_PAGE_SIZE * 2];
+        bytes[..SQLITE_HEADER.len()].copy_from_slice(SQLITE_HEADER);
+        bytes[USER_VERSION_OFFSET..USER_VERSION_OFFSET + 4].copy_from_slice(&version.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_schema_version() {
+        assert_eq!(schema_version(&database(3)).unwrap(), 3);
+        assert!(matches!(schema_version(&database(0)), Err(BackupFileError::Empty)));
+        assert!(matches!(
+            schema_version(&database(latest_version() as u32 + 1)),
+            Err(BackupFileError::TooNew { .. })
+        ));
+        assert!(matches!(schema_version(b"not a database"), Err(BackupFileError::NotSqlite)));
+        assert!(matches!(
+            schema_version(&database(3)[..MIN_PAGE_SIZE + 1]),
+            Err(BackupFileError::NotSqlite)
+        ));
+    }
+}
@@ -3,13 +3,22 @@
 //! operating on the sqlite wasm connection we have access to here. The long
 //! term goal is to get rusqlite working on wasm and use the same crates as the
 //! server but this is sufficient to get working on some client features
+//!
+//! Unlike rusqlite_migrations the down migrations are kept in the database as
+//! they're applied so a database migrated by a newer version of the app can
+//! still be rolled back after a rollback deploy
 
 use std::fmt;
 
 use include_dir::{include_dir, Dir};
 use leptos::{view, IntoView};
+use sea_query::Values;
+use shared::model::schema::{
+    matching_version, migration_name, schema_diff, CLIENT_SCHEMA, CREATE_MIGRATION_DOWN_TABLE,
+    MIGRATION_DOWN_TABLE, SCHEMA_QUERY,
+};
 use thiserror::Error;
-use tracing::debug;
+use tracing::{debug, warn};
 
 use crate::db::sqlite3::{SqlitePromiser, SqlitePromiserError};
 
@@ -53,6 +62,13 @@ pub enum MigrationError {
     Dir(String),
     #[error("Sqlite Promiser error: {0}")]
     Sql(String),
+    #[error(
+        "Database is at version {version} which is ahead of this version of the app and there's \
+         no down migration stored for version {missing}. Manual intervention required"
+    )]
+    Ahead { version: usize, missing: usize },
+    #[error("Database schema doesn't match the expected schema: {0}")]
+    Schema(String),
 }
 
 impl From<SqlitePromiserError> for MigrationError {
@@ -67,40 +83,201 @@ async fn get_version(conn: &SqlitePromiser) -> Result<DatabaseVersion, SqlitePro
     Ok(result.unwrap_or(0).into())
 }
 
-async fn set_version(conn: &SqlitePromiser, version: usize) -> Result<(), SqlitePromiserError> {
-    conn.exec(format!("PRAGMA user_version={version}")).await?;
-    Ok(())
+/// The version the database ends up at after [`run_migrations`]
+pub fn latest_version() -> usize {
+    MIGRATIONS_DIR.dirs().count()
+}
+
+/// The up migrations in the order they're applied, for running on a native
+/// connection in tests
+#[cfg(test)]
+pub(crate) fn up_migrations() -> impl Iterator<Item = &'static str> {
+    MIGRATIONS_DIR.dirs().map(|m| read_file(m, "up.sql").unwrap().unwrap())
 }
 
+/// Migrates to the latest version and checks the result matches the schema
+/// the server expects
 pub async fn run_migrations(conn: &SqlitePromiser) -> Result<DatabaseVersion, MigrationError> {
+    let version = migrate_to(conn, latest_version()).await?;
+    verify_schema(conn).await?;
+
+    Ok(version)
+}
+
+/// Migrates up or down to `target`. Down migrations come from the database
+/// rather than the migrations dir so versions this app doesn't know about can
+/// be rolled back too
+pub async fn migrate_to(
+    conn: &SqlitePromiser,
+    target: usize,
+) -> Result<DatabaseVersion, MigrationError> {
     // To reset the db:
-    // set_version(conn, 0).await?;
-    // conn.exec("DROP TABLE session_exercise; DROP TABLE exercise; DROP TABLE
-    // session;").await?;
-    let mut version = get_version(conn).await?.into();
-    debug!("Version: {version}");
+    // migrate_to(conn, 0).await?;
+    conn.exec(CREATE_MIGRATION_DOWN_TABLE).await?;
+    let mut version: usize = get_version(conn).await?.into();
+    // Anything past the last migration that matches ours came from another
+    // version of the app, even if the version number is one we know
+    let known = known_version(conn, version).await?.min(target);
+    debug!("Version: {version}, known: {known}, target: {target}");
+    if known < version.min(target) {
+        warn!("Migrations after version {known} don't match the app, rolling them back");
+    }
+
+    // Same as the server, foreign keys are off while migrating so tables can be rebuilt
+    conn.exec("PRAGMA foreign_keys=OFF").await?;
+    let result = async {
+        if version > known {
+            roll_back(conn, &mut version, known).await?;
+        }
+        run_pending(conn, &mut version, target).await
+    }
+    .await;
+    conn.exec("PRAGMA foreign_keys=ON").await?;
+    result?;
+
+    let violations = conn.exec("PRAGMA foreign_key_check").await?;
+    if !violations.result_rows.is_empty() {
+        Err(MigrationError::Sql(format!(
+            "Foreign key violations after migrating: {:?}",
+            violations.result_rows
+        )))?;
+    }
+
+    Ok(version.into())
+}
+
+/// The name each migration is stored under, in the order they're applied
+fn migration_names() -> Vec<&'static str> {
+    MIGRATIONS_DIR
+        .dirs()
+        .map(|m| m.path().file_name().and_then(|n| n.to_str()).map_or("", migration_name))
+        .collect()
+}
+
+/// The last version where the migrations the database has applied are the
+/// same as ours, see [`matching_version`]
+async fn known_version(conn: &SqlitePromiser, version: usize) -> Result<usize, MigrationError> {
+    let stored = conn
+        .exec(format!("SELECT version, name FROM {MIGRATION_DOWN_TABLE}"))
+        .await?
+        .result_rows
+        .into_iter()
+        .filter_map(|r| match r.as_slice() {
+            [version, name] => Some((version.as_u64()? as usize, name.as_str()?.to_string())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    Ok(matching_version(&migration_names(), &stored, version))
+}
 
-    for (i, m) in MIGRATIONS_DIR.dirs().enumerate().skip(version) {
+fn read_file(m: &'static Dir, name: &str) -> Result<Option<&'static str>, MigrationError> {
+    m.files()
+        .find(|f| f.path().ends_with(name))
+        .map(|f| {
+            f.contents_utf8().ok_or(MigrationError::Dir(format!(
+                "{name} in migration directory {:?} could not be read as a utf8 string",
+                m.path()
+            )))
+        })
+        .transpose()
+}
+
+async fn run_pending(
+    conn: &SqlitePromiser,
+    version: &mut usize,
+    target: usize,
+) -> Result<(), MigrationError> {
+    let names = migration_names();
+    for (i, m) in MIGRATIONS_DIR.dirs().enumerate().skip(*version).take(target - *version) {
         let new_version = i + 1;
         debug!("Migration: {:?}, Version: {new_version}", m.path());
-        let up = m
-            .files()
-            .find(|f| f.path().ends_with("up.sql"))
-            .ok_or(MigrationError::Dir(format!(
-                "Migration directory {:?} doesn't contain up.sql",
-                m.path()
-            )))?
-            .contents_utf8()
-            .ok_or(MigrationError::Dir(format!(
-                "up.sql in migration directory {:?} could not be read as a utf8 string",
-                m.path()
-            )))?;
+        let up = read_file(m, "up.sql")?.ok_or(MigrationError::Dir(format!(
+            "Migration directory {:?} doesn't contain up.sql",
+            m.path()
+        )))?;
+        let down = read_file(m, "down.sql")?;
+        let name = names[i];
 
-        conn.exec(up).await?;
-        set_version(conn, new_version).await?;
+        // The version is bumped in the same transaction so a failed migration can be
+        // retried from a clean slate
+        conn.transaction(|tx| async move {
+            tx.exec(up).await?;
+            tx.exec_with(
+                format!(
+                    "INSERT OR REPLACE INTO {MIGRATION_DOWN_TABLE} (version, name, down) VALUES \
+                     (?, ?, ?)"
+                ),
+                Values(vec![
+                    (new_version as i64).into(),
+                    name.into(),
+                    down.map(String::from).into(),
+                ]),
+            )
+            .await?;
+            tx.exec(format!("PRAGMA user_version={new_version}")).await?;
+            Ok(())
+        })
+        .await?;
 
-        version = new_version;
+        *version = new_version;
     }
 
-    Ok(version.into())
+    Ok(())
+}
+
+async fn roll_back(
+    conn: &SqlitePromiser,
+    version: &mut usize,
+    target: usize,
+) -> Result<(), MigrationError> {
+    if *version > latest_version() {
+        warn!("Database version {version} is ahead of the app, rolling back to {target}");
+    }
+
+    for old_version in (target + 1..=*version).rev() {
+        let mut stored = conn
+            .exec(format!("SELECT down FROM {MIGRATION_DOWN_TABLE} WHERE version = {old_version}"))
+            .await?;
+        let down = stored
+            .result_rows
+            .pop()
+            .and_then(|mut r| r.pop())
+            .and_then(|v| v.as_str().map(String::from))
+            .ok_or(MigrationError::Ahead { version: *version, missing: old_version })?;
+        debug!("Rolling back version {old_version}");
+
+        conn.transaction(|tx| async move {
+            tx.exec(down).await?;
+            tx.exec(format!("DELETE FROM {MIGRATION_DOWN_TABLE} WHERE version = {old_version}"))
+                .await?;
+            tx.exec(format!("PRAGMA user_version={}", old_version - 1)).await?;
+            Ok(())
+        })
+        .await?;
+
+        *version = old_version - 1;
+    }
+
+    Ok(())
+}
+
+/// Compares the schema against the one the server migrations produce for the
+/// latest version
+async fn verify_schema(conn: &SqlitePromiser) -> Result<(), MigrationError> {
+    let actual = conn
+        .exec(SCHEMA_QUERY)
+        .await?
+        .result_rows
+        .into_iter()
+        .filter_map(|mut r| r.pop())
+        .filter_map(|v| v.as_str().map(String::from))
+        .collect::<Vec<_>>();
+
+    let diff = schema_diff(CLIENT_SCHEMA, &actual);
+    if diff.is_empty() {
+        Ok(())
+    } else {
+        Err(MigrationError::Schema(diff.join(", ")))
+    }
 }
@@ -1,24 +1,27 @@
 use std::{any::type_name, future::Future};
 
+use chrono::Utc;
 use leptos::{create_local_resource, Resource};
+use sea_query::{
+    DeleteStatement, Expr, Iden, InsertStatement, OnConflict, Query, SelectStatement,
+    SqliteQueryBuilder, UpdateStatement,
+};
 use shared::model::{
+    json_row::JsonRow,
     model_into_view::{ListOfModel, ModelIntoView},
     Model,
 };
 
+pub mod backup;
 pub mod migrations;
-pub mod model;
 pub mod sqlite3;
 
 use sqlite3::{ExecResult, SqlitePromiser, SqlitePromiserError};
 
-// TODO: should merge with PromiserFetcher once all model structs have it
-// implemented
-pub trait PromiserInserter: Model {
-    fn insert_sql(&self) -> Result<String, SqlitePromiserError>;
-}
-
-pub trait PromiserFetcher: Model + Clone + ModelIntoView {
+/// Reads and writes models through the promiser. Write statements have their
+/// values bound rather than inlined. Implemented for every model the Model
+/// derive has been used on
+pub trait PromiserFetcher: Model + Clone + ModelIntoView + JsonRow {
     fn all_resource() -> Resource<(), Result<ListOfModel<Self>, SqlitePromiserError>> {
         create_local_resource(
             || (),
@@ -36,16 +39,23 @@ pub trait PromiserFetcher: Model + Clone + ModelIntoView {
         )
     }
 
-    fn extract_fields(result: ExecResult) -> Result<Vec<Self>, SqlitePromiserError>;
+    fn extract_fields(result: ExecResult) -> Result<Vec<Self>, SqlitePromiserError> {
+        Ok(result
+            .result_rows
+            .iter()
+            .map(|row| Self::from_json_row(&result.column_names, row))
+            .collect::<Result<Vec<_>, _>>()?)
+    }
     fn fetch_by<T: Into<sea_query::Value>>(
         id: T,
         column: <Self as Model>::Iden,
     ) -> impl Future<Output = Result<Vec<Self>, SqlitePromiserError>> {
-        let sql = Self::fetch_by_column_sql(id, column, false);
+        let (sql, values) =
+            Self::fetch_by_column_query(id, column, false).build(SqliteQueryBuilder);
 
         let promiser = SqlitePromiser::use_promiser();
         async move {
-            let result = promiser.exec(sql).await?;
+            let result = promiser.exec_with(sql, values).await?;
 
             Self::extract_fields(result)
         }
@@ -54,11 +64,11 @@ pub trait PromiserFetcher: Model + Clone + ModelIntoView {
         id: T,
         column: <Self as Model>::Iden,
     ) -> impl Future<Output = Result<Self, SqlitePromiserError>> {
-        let sql = Self::fetch_by_column_sql(id, column, true);
+        let (sql, values) = Self::fetch_by_column_query(id, column, true).build(SqliteQueryBuilder);
 
         let promiser = SqlitePromiser::use_promiser();
         async move {
-            let result = promiser.exec(sql).await?;
+            let result = promiser.exec_with(sql, values).await?;
             let mut results = Self::extract_fields(result)?;
 
             if results.len() != 1 {
@@ -76,11 +86,11 @@ pub trait PromiserFetcher: Model + Clone + ModelIntoView {
         id: T,
         column: <Self as Model>::Iden,
     ) -> impl Future<Output = Result<Option<Self>, SqlitePromiserError>> {
-        let sql = Self::fetch_by_column_sql(id, column, true);
+        let (sql, values) = Self::fetch_by_column_query(id, column, true).build(SqliteQueryBuilder);
 
         let promiser = SqlitePromiser::use_promiser();
         async move {
-            let result = promiser.exec(sql).await?;
+            let result = promiser.exec_with(sql, values).await?;
             let mut results = Self::extract_fields(result)?;
 
             if results.len() > 1 {
@@ -94,6 +104,20 @@ pub trait PromiserFetcher: Model + Clone + ModelIntoView {
             }
         }
     }
+    /// Runs a select built from select_star or select_star_qualified, for
+    /// queries that need more than a single column comparison
+    fn fetch_with(
+        stmt: &SelectStatement,
+    ) -> impl Future<Output = Result<Vec<Self>, SqlitePromiserError>> {
+        let (sql, values) = stmt.build(SqliteQueryBuilder);
+
+        let promiser = SqlitePromiser::use_promiser();
+        async move {
+            let result = promiser.exec_with(sql, values).await?;
+
+            Self::extract_fields(result)
+        }
+    }
     fn fetch_all() -> impl Future<Output = Result<Vec<Self>, SqlitePromiserError>> {
         let sql = Self::fetch_all_sql();
 
@@ -104,16 +128,183 @@ pub trait PromiserFetcher: Model + Clone + ModelIntoView {
             Self::extract_fields(result)
         }
     }
+
+    fn insert_statement(&self) -> Result<InsertStatement, SqlitePromiserError> {
+        let mut stmt = Self::insert_query();
+        stmt.values(self.field_values()?)?;
+        Ok(stmt)
+    }
+    /// Inserts the row or, if one with the same id already exists, overwrites it
+    fn upsert_statement(&self) -> Result<InsertStatement, SqlitePromiserError> {
+        let mut stmt = self.insert_statement()?;
+        stmt.on_conflict(
+            OnConflict::column(Self::id_iden())
+                .update_columns(
+                    (0..Self::NUM_FIELDS)
+                        .map(Self::iden_for_field)
+                        .filter(|iden| iden.to_string() != Self::id_iden().to_string()),
+                )
+                .to_owned(),
+        );
+        Ok(stmt)
+    }
+    /// Bumps last_updated_date then writes every field
+    fn update_statement(&mut self) -> Result<UpdateStatement, SqlitePromiserError> {
+        self.set_last_updated_date(Utc::now());
+        let values = self.field_values()?;
+
+        Ok(Query::update()
+            .table(Self::table_iden())
+            .values(
+                (0..Self::NUM_FIELDS)
+                    .map(Self::iden_for_field)
+                    .zip(values)
+                    .filter(|(iden, _)| iden.to_string() != Self::id_iden().to_string()),
+            )
+            .and_where(Expr::col(Self::id_iden()).eq(self.id()))
+            .to_owned())
+    }
+    fn delete_statement(&self) -> DeleteStatement {
+        Query::delete()
+            .from_table(Self::table_iden())
+            .and_where(Expr::col(Self::id_iden()).eq(self.id()))
+            .to_owned()
+    }
+    fn insert(&self) -> impl Future<Output = Result<(), SqlitePromiserError>> {
+        let stmt = self.insert_statement();
+
+        let promiser = SqlitePromiser::use_promiser();
+        async move {
+            promiser.exec_query(&stmt?).await?;
+            Ok(())
+        }
+    }
+    fn upsert(&self) -> impl Future<Output = Result<(), SqlitePromiserError>> {
+        let stmt = self.upsert_statement();
+
+        let promiser = SqlitePromiser::use_promiser();
+        async move {
+            promiser.exec_query(&stmt?).await?;
+            Ok(())
+        }
+    }
+    fn update(&mut self) -> impl Future<Output = Result<(), SqlitePromiserError>> {
+        let stmt = self.update_statement();
+
+        let promiser = SqlitePromiser::use_promiser();
+        async move {
+            promiser.exec_query(&stmt?).await?;
+            Ok(())
+        }
+    }
+    fn delete(&self) -> impl Future<Output = Result<(), SqlitePromiserError>> {
+        let stmt = self.delete_statement();
+
+        let promiser = SqlitePromiser::use_promiser();
+        async move {
+            promiser.exec_query(&stmt).await?;
+            Ok(())
+        }
+    }
 }
 
+impl<T: Model + Clone + ModelIntoView + JsonRow> PromiserFetcher for T {}
+
 #[cfg(test)]
 mod test {
-    use std::marker::PhantomData;
+    use std::{fmt::Debug, marker::PhantomData};
 
+    use chrono::{DateTime, TimeZone, Utc};
     use model_into_view::ModelIntoView;
-    use shared::model::*;
+    use rusqlite::{
+        params_from_iter,
+        types::{Value as SqlValue, ValueRef},
+        Connection,
+    };
+    use sea_query::{Iden, QueryStatementWriter, SqliteQueryBuilder, Value};
+    use serde_json::{json, Value as J};
+    use shared::{model::*, types::Uuid};
+
+    use super::{migrations::up_migrations, sqlite3::bind_value, ExecResult, PromiserFetcher};
 
-    use super::PromiserFetcher;
+    fn epoch() -> DateTime<Utc> {
+        Utc.timestamp_opt(0, 0).unwrap()
+    }
+
+    /// Runs the statement on a native connection the way sqlite wasm would,
+    /// binding the same values and handing the rows back as json
+    fn exec<Q: QueryStatementWriter>(conn: &Connection, stmt: &Q) -> ExecResult {
+        let (sql, values) = stmt.build(SqliteQueryBuilder);
+        let values = values.0.into_iter().map(|v| match bind_value(v).unwrap() {
+            J::Null => SqlValue::Null,
+            J::Number(n) => n
+                .as_i64()
+                .map(SqlValue::Integer)
+                .unwrap_or_else(|| SqlValue::Real(n.as_f64().unwrap())),
+            J::String(s) => SqlValue::Text(s),
+            v => panic!("sqlite wasm can't bind {v}"),
+        });
+
+        let mut prepared = conn.prepare(&sql).unwrap();
+        let column_names =
+            prepared.column_names().into_iter().map(str::to_string).collect::<Vec<_>>();
+        let column_count = column_names.len();
+        let result_rows = prepared
+            .query_map(params_from_iter(values), |row| {
+                (0..column_count)
+                    .map(|i| {
+                        Ok(match row.get_ref(i)? {
+                            ValueRef::Null => J::Null,
+                            ValueRef::Integer(v) => J::from(v),
+                            ValueRef::Real(v) => J::from(v),
+                            ValueRef::Text(v) => J::from(String::from_utf8_lossy(v)),
+                            ValueRef::Blob(_) => panic!("Blobs aren't used by the models"),
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        ExecResult { sql, result_rows, column_names, extra_fields: Default::default() }
+    }
+
+    /// Writes the model with every statement on a migrated database and checks
+    /// it reads back the same each time
+    fn check_round_trip<T: PromiserFetcher + PartialEq + Debug>(model: T) {
+        let conn = Connection::open_in_memory().unwrap();
+        for up in up_migrations() {
+            conn.execute_batch(up).unwrap();
+        }
+        // The models point at made up ids
+        conn.execute_batch("PRAGMA foreign_keys=OFF").unwrap();
+
+        let id = Value::from(model.id());
+        let fetch = || {
+            let stmt = T::fetch_by_column_query(id.clone(), T::id_iden(), false);
+            T::extract_fields(exec(&conn, &stmt)).unwrap()
+        };
+
+        exec(&conn, &model.insert_statement().unwrap());
+        assert_eq!(fetch(), vec![model.clone()]);
+
+        let mut updated = model.clone();
+        exec(&conn, &updated.update_statement().unwrap());
+        assert_eq!(fetch(), vec![updated.clone()]);
+        if T::field_idens().iter().any(|i| i.to_string() == "last_updated_date") {
+            assert_ne!(updated, model, "Updating should bump last_updated_date");
+        }
+
+        exec(&conn, &model.upsert_statement().unwrap());
+        assert_eq!(fetch(), vec![model.clone()]);
+
+        exec(&conn, &model.delete_statement());
+        assert!(fetch().is_empty());
+
+        exec(&conn, &model.upsert_statement().unwrap());
+        assert_eq!(fetch(), vec![model]);
+    }
 
     #[test]
     fn test_exercise_is_promiser_fetcher() {
@@ -162,4 +353,166 @@ mod test {
         t1::<User>(PhantomData);
         t2::<User>(PhantomData);
     }
+
+    #[test]
+    fn test_user_exercise_is_promiser_fetcher() {
+        fn t1<T: Model + Clone + ModelIntoView>(_t: PhantomData<T>) {}
+        fn t2<T: PromiserFetcher>(_t: PhantomData<T>) {}
+        t1::<UserExercise>(PhantomData);
+        t2::<UserExercise>(PhantomData);
+    }
+
+    #[test]
+    fn test_plan_is_promiser_fetcher() {
+        fn t1<T: Model + Clone + ModelIntoView>(_t: PhantomData<T>) {}
+        fn t2<T: PromiserFetcher>(_t: PhantomData<T>) {}
+        t1::<Plan>(PhantomData);
+        t2::<Plan>(PhantomData);
+    }
+
+    #[test]
+    fn test_plan_instance_is_promiser_fetcher() {
+        fn t1<T: Model + Clone + ModelIntoView>(_t: PhantomData<T>) {}
+        fn t2<T: PromiserFetcher>(_t: PhantomData<T>) {}
+        t1::<PlanInstance>(PhantomData);
+        t2::<PlanInstance>(PhantomData);
+    }
+
+    #[test]
+    fn test_plan_exercise_group_is_promiser_fetcher() {
+        fn t1<T: Model + Clone + ModelIntoView>(_t: PhantomData<T>) {}
+        fn t2<T: PromiserFetcher>(_t: PhantomData<T>) {}
+        t1::<PlanExerciseGroup>(PhantomData);
+        t2::<PlanExerciseGroup>(PhantomData);
+    }
+
+    #[test]
+    fn test_extract_fields() {
+        let session_exercise = SessionExercise {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            exercise_id: Uuid::new_v4(),
+            session_id: Uuid::new_v4(),
+            planned_sets: Sets(vec![Set::new(Weight::Kilograms(60.0), Reps::Reps(5))]),
+            performed_sets: Default::default(),
+            creation_date: epoch(),
+            last_updated_date: epoch(),
+            sort_order: 1,
+            finished: true,
+        };
+        let se = &session_exercise;
+
+        // What sqlite wasm hands back: text columns as strings, json stored as text and
+        // bools as integers
+        let result: ExecResult = serde_json::from_value(json!({
+            "sql": SessionExercise::fetch_all_sql(),
+            "columnNames": SessionExercise::field_idens().iter().map(|i| i.to_string()).collect::<Vec<_>>(),
+            "resultRows": [[
+                se.id.to_string(),
+                se.user_id.to_string(),
+                se.exercise_id.to_string(),
+                se.session_id.to_string(),
+                serde_json::to_string(&se.planned_sets).unwrap(),
+                "[]",
+                "1970-01-01 00:00:00+00:00",
+                "1970-01-01T00:00:00Z",
+                1,
+                1,
+            ]],
+        }))
+        .unwrap();
+
+        assert_eq!(SessionExercise::extract_fields(result).unwrap(), vec![session_exercise]);
+    }
+
+    #[test]
+    fn test_models_round_trip() {
+        check_round_trip(Exercise {
+            id: Uuid::new_v4(),
+            owner_id: None,
+            name: "Round trip exercise".to_string(),
+            description: None,
+            base_recovery_days: 2.5,
+            creation_date: epoch(),
+            last_updated_date: epoch(),
+        });
+        check_round_trip(ExerciseGroup {
+            id: Uuid::new_v4(),
+            owner_id: Some(Uuid::new_v4()),
+            name: "Round trip group".to_string(),
+            description: Some("It's leg day".to_string()),
+            creation_date: epoch(),
+            last_updated_date: epoch(),
+        });
+        check_round_trip(ExerciseGroupMember {
+            id: Uuid::new_v4(),
+            exercise_id: Uuid::new_v4(),
+            group_id: Uuid::new_v4(),
+        });
+        check_round_trip(UserExercise {
+            id: Uuid::new_v4(),
+            exercise_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            recovery_days: Some(3.0),
+            creation_date: epoch(),
+            last_updated_date: epoch(),
+        });
+        check_round_trip(Session {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            plan_instance_id: Some(Uuid::new_v4()),
+            planned_date: epoch(),
+            performed_date: Some(Utc::now()),
+            creation_date: epoch(),
+            last_updated_date: epoch(),
+        });
+        check_round_trip(SessionExercise {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            exercise_id: Uuid::new_v4(),
+            session_id: Uuid::new_v4(),
+            planned_sets: Sets(vec![Set::new(Weight::Kilograms(60.0), Reps::Reps(5))]),
+            performed_sets: Sets(vec![Set::new(Weight::Bodyweight, Reps::Amrap(8))]),
+            creation_date: epoch(),
+            last_updated_date: epoch(),
+            sort_order: 1,
+            finished: true,
+        });
+        check_round_trip(User {
+            id: Uuid::new_v4(),
+            username: "round_trip".to_string(),
+            email: None,
+            display_name: Some("Round Trip".to_string()),
+            creation_date: epoch(),
+            last_updated_date: epoch(),
+            last_login_date: Some(epoch()),
+            preferences: Default::default(),
+        });
+        check_round_trip(Plan {
+            id: Uuid::new_v4(),
+            owner_id: Uuid::new_v4(),
+            name: "Round trip plan".to_string(),
+            description: None,
+            duration_weeks: 12,
+            creation_date: epoch(),
+            last_updated_date: epoch(),
+        });
+        check_round_trip(PlanInstance {
+            id: Uuid::new_v4(),
+            plan_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            start_date: epoch(),
+            creation_date: epoch(),
+            last_updated_date: epoch(),
+        });
+        check_round_trip(PlanExerciseGroup {
+            id: Uuid::new_v4(),
+            plan_id: Uuid::new_v4(),
+            exercise_group_id: Uuid::new_v4(),
+            notes: Some("Heavy".to_string()),
+            config: None,
+            creation_date: epoch(),
+            last_updated_date: epoch(),
+        });
+    }
 }
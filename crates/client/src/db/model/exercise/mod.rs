@@ -1,6 +0,0 @@
-mod exercise;
-mod exercise_group;
-mod exercise_group_member;
-mod session;
-mod session_exercise;
-mod user_exercise;
@@ -1,5 +0,0 @@
-//! This module mirrors shared/model and contains the client specific DB
-//! fetching code
-mod exercise;
-mod plan;
-mod user;
@@ -1,3 +0,0 @@
-mod exercise_group;
-mod instance;
-mod plan;
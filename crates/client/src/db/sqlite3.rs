@@ -1,21 +1,21 @@
 #![allow(dead_code)]
 
-use std::{any::type_name, collections::HashMap};
+use std::{any::type_name, collections::HashMap, future::Future, rc::Rc};
 
-use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use futures::lock::Mutex;
 use gloo::utils::{
     errors::{JsError, NotJsError},
     format::JsValueSerdeExt,
 };
 use leptos::{provide_context, use_context};
-use sea_query::types::Iden;
+use sea_query::{types::Iden, QueryStatementWriter, SqliteQueryBuilder, Value, Values};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use shared::types::UuidError;
+use shared::{model::json_row::JsonRowError, types::UuidError};
 use thiserror::Error;
-use tracing::{error, trace};
+use tracing::{error, trace, warn};
 use wasm_bindgen::{JsCast, JsValue};
 use wasm_bindgen_futures::JsFuture;
-use web_sys::js_sys::{self, Function, Promise};
+use web_sys::js_sys::{self, Function, Object, Promise, Reflect, Uint8Array};
 
 #[derive(Debug, Clone, Error)]
 pub enum SqlitePromiserError {
@@ -54,6 +54,9 @@ pub enum SqlitePromiserError {
 
     #[error("Error from js: {0}")]
     Js(String),
+
+    #[error("Error reading row: {0}")]
+    Row(JsonRowError),
 }
 
 impl From<sea_query::error::Error> for SqlitePromiserError {
@@ -61,6 +64,12 @@ impl From<sea_query::error::Error> for SqlitePromiserError {
         Self::SeaQuery(value.to_string())
     }
 }
+impl From<JsonRowError> for SqlitePromiserError {
+    fn from(value: JsonRowError) -> Self {
+        Self::Row(value)
+    }
+}
+
 impl From<chrono::ParseError> for SqlitePromiserError {
     fn from(value: chrono::ParseError) -> Self {
         Self::Chrono(value)
@@ -113,31 +122,21 @@ impl SqlitePromiserError {
     }
 }
 
+/// Wrapper around the sqlite wasm worker promiser. The worker API only has
+/// `exec` with no handle to a prepared statement, so statements are prepared on
+/// every call and can't be reused
 #[derive(Debug, Clone)]
 pub struct SqlitePromiser {
     inner: Function,
+    /// Held for the duration of every command and for the whole of a
+    /// transaction so statements from elsewhere can't end up inside one
+    lock: Rc<Mutex<()>>,
 }
 
-/// Copied from rusqlite FromSql
-pub fn parse_datetime(value: &str) -> Result<DateTime<Utc>, SqlitePromiserError> {
-    {
-        // Try to parse value as rfc3339 first.
-        let fmt = if value.len() >= 11 && value.as_bytes()[10] == b'T' {
-            "%FT%T%.f%#z"
-        } else {
-            "%F %T%.f%#z"
-        };
-
-        if let Ok(dt) = DateTime::parse_from_str(value, fmt) {
-            return Ok(dt.with_timezone(&Utc));
-        }
-    }
-
-    // Couldn't parse as rfc3339 - fall back to NaiveDateTime.
-    let fmt =
-        if value.len() >= 11 && value.as_bytes()[10] == b'T' { "%FT%T%.f" } else { "%F %T%.f" };
-
-    Ok(NaiveDateTime::parse_from_str(value, fmt).map(|dt| Utc.from_utc_datetime(&dt))?)
+/// Handle for running statements inside [`SqlitePromiser::transaction`]
+#[derive(Debug, Clone)]
+pub struct SqliteTransaction {
+    promiser: SqlitePromiser,
 }
 
 #[derive(Debug, Clone, Serialize, PartialEq)]
@@ -152,16 +151,82 @@ enum Args {
 #[serde(rename_all = "camelCase")]
 struct ExecArgs {
     sql: String,
+    /// Values for the `?` placeholders in the sql. Only applied to the first
+    /// statement with placeholders so multi-statement sql can't use them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    bind: Vec<serde_json::Value>,
     result_rows: Vec<serde_json::Value>,
     column_names: Vec<serde_json::Value>,
 }
 
 impl<T: Into<String>> From<T> for ExecArgs {
     fn from(value: T) -> Self {
-        ExecArgs { sql: value.into(), result_rows: Vec::new(), column_names: Vec::new() }
+        ExecArgs {
+            sql: value.into(),
+            bind: Vec::new(),
+            result_rows: Vec::new(),
+            column_names: Vec::new(),
+        }
+    }
+}
+
+impl ExecArgs {
+    fn with_values(sql: String, values: Values) -> Result<Self, SqlitePromiserError> {
+        let bind = values.0.into_iter().map(bind_value).collect::<Result<_, _>>()?;
+        Ok(ExecArgs { bind, ..sql.into() })
     }
 }
 
+/// Converts the value into something sqlite wasm can bind. Dates use the same
+/// formats as rusqlite on the server so rows written by either compare equal
+/// and keep their sub-second precision. The variants depend on the sea-query
+/// features enabled in Cargo.toml
+pub(super) fn bind_value(value: Value) -> Result<serde_json::Value, SqlitePromiserError> {
+    use serde_json::Value as J;
+
+    Ok(match value {
+        Value::Bool(v) => v.map(|v| J::from(v as i32)).unwrap_or(J::Null),
+        Value::TinyInt(v) => v.map(J::from).unwrap_or(J::Null),
+        Value::SmallInt(v) => v.map(J::from).unwrap_or(J::Null),
+        Value::Int(v) => v.map(J::from).unwrap_or(J::Null),
+        Value::BigInt(v) => v.map(J::from).unwrap_or(J::Null),
+        Value::TinyUnsigned(v) => v.map(J::from).unwrap_or(J::Null),
+        Value::SmallUnsigned(v) => v.map(J::from).unwrap_or(J::Null),
+        Value::Unsigned(v) => v.map(J::from).unwrap_or(J::Null),
+        Value::BigUnsigned(v) => v.map(J::from).unwrap_or(J::Null),
+        Value::Float(v) => v.map(J::from).unwrap_or(J::Null),
+        Value::Double(v) => v.map(J::from).unwrap_or(J::Null),
+        Value::String(v) => v.map(|v| J::from(*v)).unwrap_or(J::Null),
+        Value::Char(v) => v.map(|v| J::from(v.to_string())).unwrap_or(J::Null),
+        Value::ChronoDate(v) => v.map(|v| J::from(v.format("%F").to_string())).unwrap_or(J::Null),
+        Value::ChronoTime(v) => {
+            v.map(|v| J::from(v.format("%T%.f").to_string())).unwrap_or(J::Null)
+        },
+        Value::ChronoDateTime(v) => {
+            v.map(|v| J::from(v.format("%F %T%.f").to_string())).unwrap_or(J::Null)
+        },
+        Value::ChronoDateTimeUtc(v) => {
+            v.map(|v| J::from(v.format("%F %T%.f%:z").to_string())).unwrap_or(J::Null)
+        },
+        Value::ChronoDateTimeLocal(v) => {
+            v.map(|v| J::from(v.format("%F %T%.f%:z").to_string())).unwrap_or(J::Null)
+        },
+        Value::ChronoDateTimeWithTimeZone(v) => {
+            v.map(|v| J::from(v.format("%F %T%.f%:z").to_string())).unwrap_or(J::Null)
+        },
+        Value::Json(v) => v.map(|v| J::from(v.to_string())).unwrap_or(J::Null),
+        // Blobs would need to go across as a Uint8Array which doesn't survive the trip
+        // through serde_json and the models only use chrono for dates
+        v @ (Value::Bytes(_)
+        | Value::TimeDate(_)
+        | Value::TimeTime(_)
+        | Value::TimeDateTime(_)
+        | Value::TimeDateTimeWithTimeZone(_)) => {
+            Err(SqlitePromiserError::SeaQuery(format!("Unsupported bind value: {v:?}")))?
+        },
+    })
+}
+
 fn is_none(args: &Args) -> bool {
     *args == Args::None
 }
@@ -172,6 +237,8 @@ pub enum Type {
     ConfigGet,
     Exec,
     OpfsTree,
+    Export,
+    Import,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -324,9 +391,54 @@ pub struct Version {
     pub download_version: u64,
 }
 
+/// Whether `sql` looks like more than one statement. Semicolons in quoted
+/// strings and identifiers, in comments or at the end don't count. It only
+/// skips over those so it can be fooled by sql sqlite wouldn't accept anyway
+fn has_multiple_statements(sql: &str) -> bool {
+    let mut chars = sql.chars().peekable();
+    let mut ended = false;
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => continue,
+            '-' if chars.peek() == Some(&'-') => {
+                chars.find(|&c| c == '\n');
+                continue;
+            },
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                chars.find(|&c| {
+                    let end = previous == '*' && c == '/';
+                    previous = c;
+                    end
+                });
+                continue;
+            },
+            _ => {},
+        }
+
+        if ended {
+            return true;
+        }
+        match c {
+            ';' => ended = true,
+            // Doubled quotes used as escapes just close and reopen the string
+            '\'' | '"' | '`' => {
+                chars.find(|&q| q == c);
+            },
+            '[' => {
+                chars.find(|&q| q == ']');
+            },
+            _ => {},
+        }
+    }
+
+    false
+}
+
 impl SqlitePromiser {
     pub fn new(inner: Function) -> Self {
-        Self { inner }
+        Self { inner, lock: Default::default() }
     }
 
     pub fn provide_context(self) {
@@ -384,14 +496,95 @@ impl SqlitePromiser {
         Ok(result)
     }
 
-    pub async fn exec<T: Into<String>>(&self, sql: T) -> Result<ExecResult, SqlitePromiserError> {
-        let result = self.send_command(Type::Exec, Args::Sql(ExecArgs::from(sql))).await?;
+    async fn send_exec(&self, args: ExecArgs) -> Result<ExecResult, SqlitePromiserError> {
+        let result = self.send_command(Type::Exec, Args::Sql(args)).await?;
 
         let InnerResult::Exec(result) = result.result else { unreachable!() };
 
         Ok(result)
     }
 
+    pub async fn exec<T: Into<String>>(&self, sql: T) -> Result<ExecResult, SqlitePromiserError> {
+        let _guard = self.lock.lock().await;
+        self.send_exec(ExecArgs::from(sql)).await
+    }
+
+    /// Runs a single statement with the values bound to its `?` placeholders
+    pub async fn exec_with<T: Into<String>>(
+        &self,
+        sql: T,
+        values: Values,
+    ) -> Result<ExecResult, SqlitePromiserError> {
+        let args = ExecArgs::with_values(sql.into(), values)?;
+        let _guard = self.lock.lock().await;
+        self.send_exec(args).await
+    }
+
+    /// Runs a sea_query statement with its values bound rather than inlined
+    pub async fn exec_query<Q: QueryStatementWriter>(
+        &self,
+        query: &Q,
+    ) -> Result<ExecResult, SqlitePromiserError> {
+        let (sql, values) = query.build(SqliteQueryBuilder);
+        self.exec_with(sql, values).await
+    }
+
+    /// Runs a statement with query_only on, for ad-hoc queries from the debug
+    /// page. More than one statement isn't allowed because a later one could
+    /// turn query_only back off.
+    ///
+    /// This is a best effort rather than a guarantee the statement only reads.
+    /// The worker only exposes exec so there's no way to prepare the statement
+    /// and ask sqlite, instead it's up to query_only and the check for more
+    /// than one statement is [`has_multiple_statements`], which doesn't parse
+    /// the sql
+    pub async fn exec_query_only<T: Into<String>>(
+        &self,
+        sql: T,
+    ) -> Result<ExecResult, SqlitePromiserError> {
+        let sql = sql.into();
+        if has_multiple_statements(&sql) {
+            Err(SqlitePromiserError::ExecResult(
+                "Only a single statement can be run with query_only on".to_string(),
+            ))?;
+        }
+
+        let _guard = self.lock.lock().await;
+        self.send_exec("PRAGMA query_only=ON".into()).await?;
+        let result = self.send_exec(sql.into()).await;
+        self.send_exec("PRAGMA query_only=OFF".into()).await?;
+
+        result
+    }
+
+    /// Runs `f` inside a transaction, committing if it returns Ok and rolling
+    /// back otherwise. Nothing else can use the database until it's finished so
+    /// `f` must only use the [`SqliteTransaction`] it's given, using the
+    /// promiser directly will deadlock
+    pub async fn transaction<F, Fut, R>(&self, f: F) -> Result<R, SqlitePromiserError>
+    where
+        F: FnOnce(SqliteTransaction) -> Fut,
+        Fut: Future<Output = Result<R, SqlitePromiserError>>,
+    {
+        let _guard = self.lock.lock().await;
+        self.send_exec("BEGIN IMMEDIATE".into()).await?;
+
+        let result = f(SqliteTransaction { promiser: self.clone() }).await;
+
+        // A failed COMMIT leaves the transaction open so it needs rolling back too
+        let result = match result {
+            Ok(r) => self.send_exec("COMMIT".into()).await.map(|_| r),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = &result {
+            if let Err(rollback_e) = self.send_exec("ROLLBACK".into()).await {
+                warn!("Rollback failed after {e:?}: {rollback_e:?}");
+            }
+        }
+
+        result
+    }
+
     pub async fn get_value<T: Into<String>, V: DeserializeOwned>(
         &self,
         sql: T,
@@ -422,6 +615,54 @@ impl SqlitePromiser {
         }
     }
 
+    /// Sends a command without going through serde, which would mangle the
+    /// Uint8Arrays export and import use. Returns the `result` field of the
+    /// response
+    async fn send_raw(&self, type_: Type, args: Object) -> Result<JsValue, SqlitePromiserError> {
+        let this = JsValue::from(&self.inner);
+        let type_ = <JsValue as JsValueSerdeExt>::from_serde(&type_)?;
+        let cmd = Object::new();
+        Reflect::set(&cmd, &"type".into(), &type_).map_err(SqlitePromiserError::from_promiser)?;
+        Reflect::set(&cmd, &"args".into(), &args).map_err(SqlitePromiserError::from_promiser)?;
+        trace!("Raw command: {:#?}", cmd);
+
+        let promise: Promise =
+            self.inner.call1(&this, &cmd).map_err(SqlitePromiserError::from_promiser)?.into();
+        let result = JsFuture::from(promise).await.map_err(SqlitePromiserError::from_sqlite)?;
+
+        Reflect::get(&result, &"result".into()).map_err(SqlitePromiserError::from_promiser)
+    }
+
+    /// The whole database file
+    pub async fn export(&self) -> Result<Vec<u8>, SqlitePromiserError> {
+        let _guard = self.lock.lock().await;
+        let result = self.send_raw(Type::Export, Object::new()).await?;
+        let bytes = Reflect::get(&result, &"byteArray".into())
+            .map_err(SqlitePromiserError::from_promiser)?
+            .dyn_into::<Uint8Array>()
+            .map_err(|v| {
+                SqlitePromiserError::ExecResult(format!(
+                    "export byteArray wasn't a Uint8Array: {v:?}"
+                ))
+            })?;
+
+        Ok(bytes.to_vec())
+    }
+
+    /// Replaces the database file with `bytes` (the output of
+    /// [`Self::export`]). The connection settings are lost in the process so
+    /// [`Self::configure`] needs to be run again afterwards, as do the
+    /// migrations
+    pub async fn import(&self, bytes: &[u8]) -> Result<(), SqlitePromiserError> {
+        let _guard = self.lock.lock().await;
+        let args = Object::new();
+        Reflect::set(&args, &"byteArray".into(), &Uint8Array::from(bytes))
+            .map_err(SqlitePromiserError::from_promiser)?;
+        self.send_raw(Type::Import, args).await?;
+
+        Ok(())
+    }
+
     pub async fn opfs_tree(&self) -> Result<OpfsTreeResults, SqlitePromiserError> {
         let result = self.send_command(Type::OpfsTree, Args::None).await?;
 
@@ -431,6 +672,162 @@ impl SqlitePromiser {
     }
 }
 
-pub fn serde_stringify<T: Serialize>(v: &T) -> Result<String, SqlitePromiserError> {
-    Ok(serde_json::to_string(v)?)
+impl SqliteTransaction {
+    pub async fn exec<T: Into<String>>(&self, sql: T) -> Result<ExecResult, SqlitePromiserError> {
+        self.promiser.send_exec(ExecArgs::from(sql)).await
+    }
+
+    /// Runs a single statement with the values bound to its `?` placeholders
+    pub async fn exec_with<T: Into<String>>(
+        &self,
+        sql: T,
+        values: Values,
+    ) -> Result<ExecResult, SqlitePromiserError> {
+        self.promiser.send_exec(ExecArgs::with_values(sql.into(), values)?).await
+    }
+
+    /// Runs a sea_query statement with its values bound rather than inlined
+    pub async fn exec_query<Q: QueryStatementWriter>(
+        &self,
+        query: &Q,
+    ) -> Result<ExecResult, SqlitePromiserError> {
+        let (sql, values) = query.build(SqliteQueryBuilder);
+        self.exec_with(sql, values).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, Local, NaiveDate, TimeZone, Utc};
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_has_multiple_statements() {
+        let single = [
+            "SELECT 1",
+            "SELECT 1;",
+            "SELECT 1; ",
+            "SELECT ';' AS s;",
+            "SELECT 'it''s; fine'",
+            "SELECT \"a;b\", [c;d], `e;f` FROM t",
+            "SELECT 1 -- a; b",
+            "SELECT /* ; */ 1; /* trailing; */",
+            "SELECT 1; -- trailing; comment\n",
+        ];
+        for sql in single {
+            assert!(!has_multiple_statements(sql), "{sql}");
+        }
+
+        let multiple = [
+            "SELECT 1; SELECT 2",
+            "PRAGMA query_only=OFF; DELETE FROM session",
+            "SELECT ';'; DELETE FROM session",
+            "SELECT 1; -- comment\nDELETE FROM session",
+            "SELECT 1 /* ; */; DELETE FROM session",
+        ];
+        for sql in multiple {
+            assert!(has_multiple_statements(sql), "{sql}");
+        }
+    }
+
+    #[test]
+    fn test_bind_value_nulls() {
+        let nulls = [
+            Value::Bool(None),
+            Value::TinyInt(None),
+            Value::SmallInt(None),
+            Value::Int(None),
+            Value::BigInt(None),
+            Value::TinyUnsigned(None),
+            Value::SmallUnsigned(None),
+            Value::Unsigned(None),
+            Value::BigUnsigned(None),
+            Value::Float(None),
+            Value::Double(None),
+            Value::String(None),
+            Value::Char(None),
+            Value::Json(None),
+            Value::ChronoDate(None),
+            Value::ChronoTime(None),
+            Value::ChronoDateTime(None),
+            Value::ChronoDateTimeUtc(None),
+            Value::ChronoDateTimeLocal(None),
+            Value::ChronoDateTimeWithTimeZone(None),
+        ];
+        for value in nulls {
+            assert_eq!(bind_value(value.clone()).unwrap(), json!(null), "{value:?}");
+        }
+    }
+
+    #[test]
+    fn test_bind_value_numbers() {
+        assert_eq!(bind_value(Value::Bool(Some(true))).unwrap(), json!(1));
+        assert_eq!(bind_value(Value::Bool(Some(false))).unwrap(), json!(0));
+        assert_eq!(bind_value(Value::TinyInt(Some(-8))).unwrap(), json!(-8));
+        assert_eq!(bind_value(Value::SmallInt(Some(-16))).unwrap(), json!(-16));
+        assert_eq!(bind_value(Value::Int(Some(-32))).unwrap(), json!(-32));
+        assert_eq!(bind_value(Value::BigInt(Some(i64::MIN))).unwrap(), json!(i64::MIN));
+        assert_eq!(bind_value(Value::TinyUnsigned(Some(8))).unwrap(), json!(8));
+        assert_eq!(bind_value(Value::SmallUnsigned(Some(16))).unwrap(), json!(16));
+        assert_eq!(bind_value(Value::Unsigned(Some(32))).unwrap(), json!(32));
+        assert_eq!(bind_value(Value::BigUnsigned(Some(u64::MAX))).unwrap(), json!(u64::MAX));
+        assert_eq!(bind_value(Value::Float(Some(1.5))).unwrap(), json!(1.5));
+        assert_eq!(bind_value(Value::Double(Some(-2.25))).unwrap(), json!(-2.25));
+    }
+
+    #[test]
+    fn test_bind_value_text() {
+        assert_eq!(bind_value(Value::from("it's")).unwrap(), json!("it's"));
+        assert_eq!(bind_value(Value::Char(Some('x'))).unwrap(), json!("x"));
+        assert_eq!(
+            bind_value(Value::from(json!({ "reps": [5, 5] }))).unwrap(),
+            json!(r#"{"reps":[5,5]}"#)
+        );
+    }
+
+    #[test]
+    fn test_bind_value_dates() {
+        let date = NaiveDate::from_ymd_opt(2024, 2, 3).unwrap();
+        let date_time = date.and_hms_opt(4, 5, 6).unwrap();
+        let utc = Utc.from_utc_datetime(&date_time);
+
+        assert_eq!(bind_value(Value::from(date)).unwrap(), json!("2024-02-03"));
+        assert_eq!(bind_value(Value::from(date_time.time())).unwrap(), json!("04:05:06"));
+        assert_eq!(bind_value(Value::from(date_time)).unwrap(), json!("2024-02-03 04:05:06"));
+        assert_eq!(bind_value(Value::from(utc)).unwrap(), json!("2024-02-03 04:05:06+00:00"));
+        assert_eq!(
+            bind_value(Value::from(utc + Duration::milliseconds(123))).unwrap(),
+            json!("2024-02-03 04:05:06.123+00:00")
+        );
+        assert_eq!(
+            bind_value(Value::from(utc.fixed_offset())).unwrap(),
+            json!("2024-02-03 04:05:06+00:00")
+        );
+
+        let local = utc.with_timezone(&Local);
+        assert_eq!(
+            bind_value(Value::from(local)).unwrap(),
+            json!(local.format("%F %T%:z").to_string())
+        );
+    }
+
+    #[test]
+    fn test_bind_value_unsupported() {
+        let date = time::Date::from_calendar_date(2024, time::Month::February, 3).unwrap();
+        let unsupported = [
+            Value::from(vec![1u8, 2, 3]),
+            Value::from(date),
+            Value::from(time::Time::MIDNIGHT),
+            Value::from(time::PrimitiveDateTime::new(date, time::Time::MIDNIGHT)),
+            Value::from(time::OffsetDateTime::UNIX_EPOCH),
+        ];
+        for value in unsupported {
+            assert!(
+                matches!(bind_value(value.clone()), Err(SqlitePromiserError::SeaQuery(_))),
+                "{value:?}"
+            );
+        }
+    }
 }
@@ -1,7 +1,10 @@
 use leptos::{component, view, IntoView};
 use leptos_router::{Route, Routes, A};
+use shared::types::Uuid;
 
-use crate::components::{Chart, Debug, Login, Notificiations, Plan, Profile, Register, Today};
+use crate::components::{
+    Backup, Chart, Debug, Login, Notificiations, Pair, Plan, Profile, Register, Today,
+};
 
 macro_rules! routes {
     ($(($path:literal, $view:ident, $ui_text:literal),)+) => {
@@ -90,8 +93,18 @@ routes!(
     ("/plan", Plan, "Plan"),
     ("/register", Register, "Register"),
     ("/login", Login, "Login"),
+    ("/pair", Pair, "Pair device"),
     ("/profile", Profile, "Profile"),
+    ("/backup", Backup, "Backup"),
     ("/debug", Debug, "Debug"),
     ("/chart", Chart, "Chart"),
     ("/notifications", Notificiations, "Notificiations"),
 );
+
+/// The query parameter on [`ClientRoutes::Today`] for the session to show first
+pub const SESSION_QUERY: &str = "session";
+
+/// A link to the session on the today page, for reminders about it
+pub fn session_path(session_id: Uuid) -> String {
+    format!("{}?{SESSION_QUERY}={session_id}", ClientRoutes::Today.path())
+}
@@ -1,9 +1,13 @@
 use std::fmt::Display;
 
-use leptos::window;
+use leptos::{document, window};
 use shared::api::error::FrontendError;
+use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::JsFuture;
-use web_sys::{PushManager, ServiceWorkerRegistration};
+use web_sys::{
+    js_sys::{Array, ArrayBuffer, Uint8Array},
+    Blob, BlobPropertyBag, File, HtmlAnchorElement, PushManager, ServiceWorkerRegistration, Url,
+};
 
 pub async fn get_service_worker_registration<T: Display>(
 ) -> Result<ServiceWorkerRegistration, FrontendError<T>> {
@@ -14,3 +18,30 @@ pub async fn get_service_worker_registration<T: Display>(
 pub async fn get_web_push_manger<T: Display>() -> Result<PushManager, FrontendError<T>> {
     Ok(get_service_worker_registration().await?.push_manager()?)
 }
+
+/// Saves `bytes` to the user's device by clicking a temporary link to them
+pub fn download_file<T: Display>(
+    bytes: &[u8],
+    filename: &str,
+    mime: &str,
+) -> Result<(), FrontendError<T>> {
+    let blob = Blob::new_with_u8_array_sequence_and_options(
+        &Array::of1(&Uint8Array::from(bytes)),
+        BlobPropertyBag::new().type_(mime),
+    )?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+
+    let anchor: HtmlAnchorElement = document().create_element("a")?.unchecked_into();
+    anchor.set_href(&url);
+    anchor.set_download(filename);
+    anchor.click();
+
+    Url::revoke_object_url(&url)?;
+
+    Ok(())
+}
+
+pub async fn read_file<T: Display>(file: &File) -> Result<Vec<u8>, FrontendError<T>> {
+    let buffer: ArrayBuffer = JsFuture::from(file.array_buffer()).await?.unchecked_into();
+    Ok(Uint8Array::new(&buffer).to_vec())
+}
@@ -2,8 +2,8 @@ use std::{any::type_name, fmt::Display, time::Duration};
 
 use futures::{channel::mpsc, StreamExt};
 use leptos::{
-    provide_context, spawn_local, store_value, use_context, RwSignal, Signal, SignalUpdate,
-    StoredValue,
+    provide_context, spawn_local, store_value, use_context, RwSignal, Signal, SignalSet,
+    SignalUpdate, StoredValue,
 };
 use reconnecting_websocket::{Event, SocketBuilder, SocketSink, State};
 use shared::{
@@ -11,7 +11,7 @@ use shared::{
         error::{FrontendError, Nothing},
         Object,
     },
-    types::websocket::{ClientMessage, ServerMessage},
+    types::websocket::{ClientMessage, ServerMessage, ServerUser},
 };
 use tracing::{error, info, trace};
 
@@ -31,6 +31,8 @@ pub struct Websocket {
     sender: SocketSink<ClientMessage>,
 
     rtc_source: StoredValue<Option<RtcSource>>,
+    /// The last user message from the server
+    user_signal: RwSignal<Option<ServerUser>>,
 
     #[cfg(feature = "debug-signals")]
     message_signal: RwSignal<Vec<MessageResult>>,
@@ -59,6 +61,7 @@ impl Websocket {
 
         let (rtc_sender, rtc_receiver) = mpsc::unbounded();
         let rtc_source = store_value(Some(rtc_receiver.into()));
+        let user_signal: RwSignal<Option<ServerUser>> = Default::default();
 
         spawn_local(async move {
             loop {
@@ -89,7 +92,9 @@ impl Websocket {
                                                 error!("rtc_sender.unbounded_send err: {e:?}");
                                             }
                                         },
-                                        ServerMessage::User(_uu) => {},
+                                        ServerMessage::User(u) => {
+                                            user_signal.set(Some(u));
+                                        },
                                     }
                                 },
                                 Err(e) => {
@@ -109,6 +114,7 @@ impl Websocket {
             state_signal,
             sender,
             rtc_source,
+            user_signal,
 
             #[cfg(feature = "debug-signals")]
             message_signal,
@@ -138,6 +144,10 @@ impl Websocket {
         self.state_signal.into()
     }
 
+    pub fn user_signal(&self) -> Signal<Option<ServerUser>> {
+        self.user_signal.into()
+    }
+
     #[cfg(feature = "debug-signals")]
     pub fn message_signal(&self) -> Signal<Vec<MessageResult>> {
         self.message_signal.into()
@@ -0,0 +1,37 @@
+//! Derive for shared's `Model` trait and the helpers that go with it. Replaces the
+//! `feature_model_derives!` macro_rules which needed every field to be `pub` and in a fixed layout
+//!
+//! ```ignore
+//! #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Model)]
+//! #[cfg_attr(feature = "sea-query-enum", enum_def)]
+//! #[model(table = "session_exercise", check = "migrations/015-session_exercise_progress/up.sql")]
+//! pub struct SessionExercise {
+//!     pub id: Uuid,
+//!     /// Stored as json text
+//!     #[model(json)]
+//!     pub planned_sets: Sets,
+//!     ...
+//! }
+//! ```
+//!
+//! `check` is relative to the crate root. The fields are checked against the columns the table has
+//! after every migration up to and including that one so a model that doesn't match the database
+//! fails to compile
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+mod model;
+mod schema;
+
+/// Generates, each behind the same features the hand written versions were:
+/// - `impl shared::model::Model` (`sea-query-enum`)
+/// - rusqlite `from_row` and `insert` (`backend`)
+/// - `impl shared::model::json_row::JsonRow` for reading rows from sqlite wasm and writing them
+///   back (`wasm`)
+/// - `impl shared::model::model_into_view::DefaultModelIntoView` (`wasm`)
+#[proc_macro_derive(Model, attributes(model))]
+pub fn derive_model(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    model::derive(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
@@ -0,0 +1,474 @@
+use std::{collections::BTreeSet, env, path::PathBuf};
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Fields, GenericArgument, Ident, LitStr, PathArguments, Type};
+
+use crate::schema;
+
+struct Field {
+    ident: Ident,
+    ty: Type,
+    /// Stored in sqlite as json text
+    json: bool,
+}
+
+impl Field {
+    /// The type inside the Option if the field is one
+    fn option_inner(&self) -> Option<&Type> {
+        let Type::Path(path) = &self.ty else { return None };
+        let segment = path.path.segments.last().filter(|s| s.ident == "Option")?;
+        let PathArguments::AngleBracketed(args) = &segment.arguments else { return None };
+
+        match args.args.first() {
+            Some(GenericArgument::Type(inner)) if args.args.len() == 1 => Some(inner),
+            _ => None,
+        }
+    }
+}
+
+/// `#[model(table = "...", check = "...")]` on the struct
+struct ModelAttrs {
+    table: LitStr,
+    check: LitStr,
+}
+
+fn parse_model_attrs(input: &DeriveInput) -> syn::Result<ModelAttrs> {
+    let mut table = None;
+    let mut check = None;
+
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("model")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("table") {
+                table = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("check") {
+                check = Some(meta.value()?.parse()?);
+            } else {
+                Err(meta.error("Expected table or check"))?;
+            }
+            Ok(())
+        })?;
+    }
+
+    let missing = |name| {
+        syn::Error::new(
+            Span::call_site(),
+            format!("Model derive requires #[model({name} = \"...\")] on the struct"),
+        )
+    };
+
+    Ok(ModelAttrs {
+        table: table.ok_or_else(|| missing("table"))?,
+        check: check.ok_or_else(|| missing("check"))?,
+    })
+}
+
+fn parse_fields(input: &DeriveInput) -> syn::Result<Vec<Field>> {
+    let Data::Struct(data) = &input.data else {
+        Err(syn::Error::new_spanned(input, "Model can only be derived for structs"))?
+    };
+    let Fields::Named(fields) = &data.fields else {
+        Err(syn::Error::new_spanned(
+            input,
+            "Model can only be derived for structs with named fields",
+        ))?
+    };
+
+    fields
+        .named
+        .iter()
+        .map(|f| {
+            let mut json = false;
+            for attr in f.attrs.iter().filter(|a| a.path().is_ident("model")) {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("json") {
+                        json = true;
+                        Ok(())
+                    } else {
+                        Err(meta.error("Expected json"))
+                    }
+                })?;
+            }
+            Ok(Field { ident: f.ident.clone().unwrap(), ty: f.ty.clone(), json })
+        })
+        .collect()
+}
+
+/// snake_case to UpperCamelCase, the same as the Iden variants sea_query's
+/// enum_def generates
+fn camel(ident: &Ident) -> Ident {
+    let camel = ident
+        .to_string()
+        .split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|c| c.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect::<String>();
+    Ident::new(&camel, ident.span())
+}
+
+/// UpperCamelCase to snake_case for naming the generated tests
+fn snake(ident: &Ident) -> String {
+    let mut snake = String::new();
+    for (i, c) in ident.to_string().chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+/// Checks the fields line up with the table's columns after every migration
+/// up to `attrs.check`. Returns the files read so the caller can make the
+/// build depend on them
+fn check_migrations(attrs: &ModelAttrs, fields: &[Field]) -> syn::Result<Vec<PathBuf>> {
+    let error = |message: String| syn::Error::new(attrs.check.span(), message);
+
+    let manifest_dir =
+        env::var("CARGO_MANIFEST_DIR").map_err(|e| error(format!("CARGO_MANIFEST_DIR: {e}")))?;
+    let up_sql = PathBuf::from(manifest_dir).join(attrs.check.value());
+    if !up_sql.ends_with("up.sql") {
+        Err(error(format!("check should point at a migration's up.sql, got {up_sql:?}")))?;
+    }
+
+    let (tables, files) = schema::migrate_to(&up_sql).map_err(error)?;
+
+    let table = attrs.table.value();
+    let columns = tables.get(&table.to_lowercase()).ok_or_else(|| {
+        error(format!("Table {table} doesn't exist after applying the migrations up to {up_sql:?}"))
+    })?;
+    let columns = columns.iter().map(String::as_str).collect::<BTreeSet<_>>();
+    let field_names = fields.iter().map(|f| f.ident.to_string().to_lowercase()).collect::<Vec<_>>();
+    let field_names = field_names.iter().map(String::as_str).collect::<BTreeSet<_>>();
+
+    let mut problems = Vec::new();
+    for missing in field_names.difference(&columns) {
+        problems.push(format!("field {missing} has no column"));
+    }
+    for extra in columns.difference(&field_names) {
+        problems.push(format!("column {extra} has no field"));
+    }
+
+    if problems.is_empty() {
+        Ok(files)
+    } else {
+        Err(error(format!(
+            "Model doesn't match table {table} as of {up_sql:?}: {}",
+            problems.join(", ")
+        )))
+    }
+}
+
+pub fn derive(input: DeriveInput) -> syn::Result<TokenStream> {
+    let attrs = parse_model_attrs(&input)?;
+    let fields = parse_fields(&input)?;
+    let files = check_migrations(&attrs, &fields)?;
+
+    let name = &input.ident;
+    let iden = format_ident!("{}Iden", name);
+    let table = &attrs.table;
+    let test_mod = format_ident!("{}_tests", snake(name));
+    let test_fn = format_ident!("test_{}_fetch_all_sql", snake(name));
+
+    let field_idents = fields.iter().map(|f| &f.ident).collect::<Vec<_>>();
+    let field_strs = fields.iter().map(|f| f.ident.to_string()).collect::<Vec<_>>();
+    let variants = fields.iter().map(|f| camel(&f.ident)).collect::<Vec<_>>();
+    let variant_strs = variants.iter().map(Ident::to_string).collect::<Vec<_>>();
+    let indexes = 0..fields.len();
+    let num_fields = fields.len();
+
+    let files = files.iter().map(|f| f.to_string_lossy().to_string());
+
+    let insert_sql = format!(
+        "INSERT INTO \"{}\" ({}) VALUES ({})",
+        table.value(),
+        field_strs.iter().map(|f| format!("\"{f}\"")).collect::<Vec<_>>().join(", "),
+        (1..=fields.len()).map(|i| format!("?{i}")).collect::<Vec<_>>().join(", "),
+    );
+
+    let read_columns = fields.iter().map(|f| {
+        let ident = &f.ident;
+        let column = ident.to_string();
+        if f.json {
+            quote! { #ident: json_row::read_json_column(column_names, row, #column)? }
+        } else {
+            quote! { #ident: json_row::read_column(column_names, row, #column)? }
+        }
+    });
+
+    let field_values = fields.iter().map(|f| {
+        let ident = &f.ident;
+        if f.json {
+            quote! { json_row::json_field_value(&self.#ident)? }
+        } else {
+            quote! { self.#ident.clone().into() }
+        }
+    });
+
+    let column_types = fields.iter().map(|f| {
+        let nullable = f.option_inner().is_some();
+        let ty = f.option_inner().unwrap_or(&f.ty);
+        let sql_type = if f.json {
+            quote! { "TEXT" }
+        } else {
+            quote! { <#ty as crate::model::SqlType>::SQL_TYPE }
+        };
+        quote! { crate::model::ColumnType { sql_type: #sql_type, nullable: #nullable } }
+    });
+
+    let set_last_updated_date = fields.iter().any(|f| f.ident == "last_updated_date").then(|| {
+        quote! {
+            fn set_last_updated_date(&mut self, date: ::chrono::DateTime<::chrono::Utc>) {
+                self.last_updated_date = date;
+            }
+        }
+    });
+
+    Ok(quote! {
+        // Rebuild when the migrations change
+        #(const _: &[u8] = include_bytes!(#files);)*
+
+        #[cfg(feature = "sea-query-enum")]
+        impl crate::model::Model for #name {
+            type Iden = #iden;
+
+            const NUM_FIELDS: usize = #num_fields;
+
+            const COLUMN_TYPES: &'static [crate::model::ColumnType] = &[#(#column_types,)*];
+
+            fn iden_for_field(field: usize) -> Self::Iden {
+                match field {
+                    #(#indexes => #iden::#variants,)*
+                    _ => #iden::Table,
+                }
+            }
+
+            fn table_iden() -> Self::Iden {
+                #iden::Table
+            }
+
+            fn id_iden() -> Self::Iden {
+                #iden::Id
+            }
+
+            fn field_idens() -> &'static [Self::Iden] {
+                &[#(#iden::#variants,)*]
+            }
+
+            fn select_star() -> ::sea_query::SelectStatement {
+                ::sea_query::Query::select()
+                    .columns([#(#iden::#variants,)*])
+                    .from(#iden::Table)
+                    .to_owned()
+            }
+
+            fn select_star_qualified() -> ::sea_query::SelectStatement {
+                ::sea_query::Query::select()
+                    .columns([#((#iden::Table, #iden::#variants),)*])
+                    .from(#iden::Table)
+                    .to_owned()
+            }
+
+            fn insert_query() -> ::sea_query::InsertStatement {
+                ::sea_query::Query::insert()
+                    .into_table(#iden::Table)
+                    .columns([#(#iden::#variants,)*])
+                    .to_owned()
+            }
+
+            #[cfg(feature = "wasm")]
+            fn fetch_all_sql() -> String {
+                Self::select_star().to_string(::sea_query::SqliteQueryBuilder)
+            }
+
+            #[cfg(feature = "wasm")]
+            fn fetch_by_column_query<T: Into<::sea_query::Value>>(
+                id: T,
+                column: Self::Iden,
+                limit_1: bool,
+            ) -> ::sea_query::SelectStatement {
+                let mut stmt = Self::select_star();
+                stmt.and_where(::sea_query::Expr::col(column).eq(id.into()));
+
+                if limit_1 {
+                    stmt.limit(1);
+                }
+
+                stmt
+            }
+
+            #[cfg(feature = "backend")]
+            fn fetch_all(conn: &::rusqlite::Connection) -> Result<Vec<Self>, ::rusqlite::Error> {
+                Self::fetch_all_with(conn, &Self::select_star())
+            }
+
+            #[cfg(feature = "backend")]
+            fn fetch_all_with(
+                conn: &::rusqlite::Connection,
+                stmt: &::sea_query::SelectStatement,
+            ) -> Result<Vec<Self>, ::rusqlite::Error> {
+                use ::sea_query_rusqlite::RusqliteBinder;
+
+                let (sql, values) = stmt.build_rusqlite(::sea_query::SqliteQueryBuilder);
+                let mut stmt = conn.prepare_cached(&sql)?;
+                let results = stmt
+                    .query_and_then(&*values.as_params(), Self::from_row)?
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(results)
+            }
+
+            #[cfg(feature = "backend")]
+            fn fetch_by_id<T: Into<::sea_query::Value>>(
+                conn: &::rusqlite::Connection,
+                id: T,
+            ) -> Result<Self, ::rusqlite::Error> {
+                Self::fetch_by_column(conn, id, #iden::Id)
+            }
+
+            #[cfg(feature = "backend")]
+            fn fetch_by_column<T: Into<::sea_query::Value>>(
+                conn: &::rusqlite::Connection,
+                id: T,
+                column: Self::Iden,
+            ) -> Result<Self, ::rusqlite::Error> {
+                use ::sea_query_rusqlite::RusqliteBinder;
+
+                let (sql, values) = Self::select_star()
+                    .and_where(::sea_query::Expr::col(column).eq(id.into()))
+                    .limit(1)
+                    .build_rusqlite(::sea_query::SqliteQueryBuilder);
+                let mut stmt = conn.prepare_cached(&sql)?;
+
+                stmt.query_row(&*values.as_params(), Self::from_row)
+            }
+
+            #[cfg(feature = "backend")]
+            fn fetch_by_id_maybe<T: Into<::sea_query::Value>>(
+                conn: &::rusqlite::Connection,
+                id: T,
+            ) -> Result<Option<Self>, ::rusqlite::Error> {
+                Self::fetch_by_column_maybe(conn, id, #iden::Id)
+            }
+
+            #[cfg(feature = "backend")]
+            fn fetch_by_column_maybe<T: Into<::sea_query::Value>>(
+                conn: &::rusqlite::Connection,
+                id: T,
+                column: Self::Iden,
+            ) -> Result<Option<Self>, ::rusqlite::Error> {
+                use ::rusqlite::OptionalExtension;
+                use ::sea_query_rusqlite::RusqliteBinder;
+
+                let (sql, values) = Self::select_star()
+                    .and_where(::sea_query::Expr::col(column).eq(id.into()))
+                    .limit(1)
+                    .build_rusqlite(::sea_query::SqliteQueryBuilder);
+                let mut stmt = conn.prepare_cached(&sql)?;
+
+                stmt.query_row(&*values.as_params(), Self::from_row).optional()
+            }
+
+            #[cfg(feature = "backend")]
+            fn fetch_all_by_column<T: Into<::sea_query::Value>>(
+                conn: &::rusqlite::Connection,
+                id: T,
+                column: Self::Iden,
+            ) -> Result<Vec<Self>, ::rusqlite::Error> {
+                let mut stmt = Self::select_star();
+                stmt.and_where(::sea_query::Expr::col(column).eq(id.into()));
+
+                Self::fetch_all_with(conn, &stmt)
+            }
+        }
+
+        #[cfg(feature = "backend")]
+        impl #name {
+            pub fn from_row(row: &::rusqlite::Row<'_>) -> ::rusqlite::Result<Self> {
+                Ok(Self {
+                    #(#field_idents: row.get(#field_strs)?,)*
+                })
+            }
+
+            pub fn insert(&self, conn: &::rusqlite::Connection) -> ::rusqlite::Result<()> {
+                let mut stmt = conn.prepare_cached(#insert_sql)?;
+                stmt.execute(::rusqlite::params![#(&self.#field_idents,)*])?;
+
+                Ok(())
+            }
+        }
+
+        #[cfg(feature = "wasm")]
+        impl crate::model::json_row::JsonRow for #name {
+            fn from_json_row(
+                column_names: &[String],
+                row: &[::serde_json::Value],
+            ) -> Result<Self, crate::model::json_row::JsonRowError> {
+                use crate::model::json_row;
+
+                Ok(Self {
+                    #(#read_columns,)*
+                })
+            }
+
+            fn id(&self) -> &crate::types::Uuid {
+                &self.id
+            }
+
+            fn field_values(
+                &self,
+            ) -> Result<Vec<::sea_query::SimpleExpr>, crate::model::json_row::JsonRowError> {
+                use crate::model::json_row;
+
+                Ok(vec![#(#field_values,)*])
+            }
+
+            #set_last_updated_date
+        }
+
+        #[cfg(feature = "wasm")]
+        impl crate::model::model_into_view::DefaultModelIntoView for #name {
+            fn header_str(iden: <Self as crate::model::Model>::Iden) -> &'static str {
+                match iden {
+                    #iden::Table => #table,
+                    #(#iden::#variants => #variant_strs,)*
+                }
+            }
+
+            fn row_view(&self, iden: <Self as crate::model::Model>::Iden) -> impl ::leptos::IntoView {
+                ::leptos::view! {
+                    <td>
+                        {
+                            match iden {
+                                #iden::Table => #table.to_string(),
+                                #(#iden::#variants => format!("{:?}", &self.#field_idents),)*
+                            }
+                        }
+                    </td>
+                }
+            }
+        }
+
+        #[cfg(test)]
+        mod #test_mod {
+            #[allow(unused_imports)]
+            use super::*;
+
+            #[test]
+            #[cfg(feature = "sea-query-enum")]
+            fn #test_fn() {
+                use crate::model::Model as _;
+
+                let sql = #name::fetch_all_sql().to_lowercase();
+
+                assert!(sql.starts_with("select "));
+                assert!(sql.contains(&[#(concat!("\"", #field_strs, "\"")),*].join(", ")));
+            }
+        }
+    })
+}
@@ -0,0 +1,366 @@
+//! Just enough of a sqlite DDL interpreter to know which columns each table
+//! has after a run of migrations. Only the statements that change the set of
+//! tables or columns are understood, everything else (inserts, indexes etc) is
+//! skipped
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    iter::Peekable,
+    path::{Path, PathBuf},
+    str::Chars,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// Keywords and unquoted identifiers
+    Word(String),
+    /// "quoted", `quoted` or [quoted] identifiers
+    Quoted(String),
+    /// 'string literals'
+    Str,
+    Punct(char),
+}
+
+impl Token {
+    fn is_kw(&self, kw: &str) -> bool {
+        matches!(self, Token::Word(w) if w.eq_ignore_ascii_case(kw))
+    }
+
+    fn name(&self) -> Option<String> {
+        match self {
+            Token::Word(w) | Token::Quoted(w) => Some(w.to_lowercase()),
+            _ => None,
+        }
+    }
+}
+
+fn take_until(chars: &mut Peekable<Chars>, end: char) -> Result<String, String> {
+    let mut s = String::new();
+    loop {
+        match chars.next() {
+            // Quotes are escaped by doubling them up
+            Some(c) if c == end && end != ']' && chars.peek() == Some(&end) => {
+                chars.next();
+                s.push(c);
+            },
+            Some(c) if c == end => return Ok(s),
+            Some(c) => s.push(c),
+            None => return Err(format!("Unterminated {end} in sql")),
+        }
+    }
+}
+
+fn tokenize(sql: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = sql.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {},
+            '-' if chars.peek() == Some(&'-') => while chars.next().is_some_and(|c| c != '\n') {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if last == '*' => break,
+                        Some(c) => last = c,
+                        None => Err("Unterminated /* comment in sql")?,
+                    }
+                }
+            },
+            '\'' => {
+                take_until(&mut chars, '\'')?;
+                tokens.push(Token::Str);
+            },
+            '"' => tokens.push(Token::Quoted(take_until(&mut chars, '"')?)),
+            '`' => tokens.push(Token::Quoted(take_until(&mut chars, '`')?)),
+            '[' => tokens.push(Token::Quoted(take_until(&mut chars, ']')?)),
+            c if c.is_alphanumeric() || c == '_' || c == '$' => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_alphanumeric() || c == '_' || c == '$' {
+                        word.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Word(word));
+            },
+            c => tokens.push(Token::Punct(c)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Splits on the semicolons that aren't inside parentheses or a trigger body
+fn statements(tokens: Vec<Token>) -> Vec<Vec<Token>> {
+    let mut statements = Vec::new();
+    let mut current = Vec::new();
+    let mut depth = 0;
+
+    for token in tokens {
+        match token {
+            Token::Punct('(') => depth += 1,
+            Token::Punct(')') => depth -= 1,
+            Token::Punct(';') if depth == 0 => {
+                let in_trigger = current.iter().take(3).any(|t: &Token| t.is_kw("TRIGGER"));
+                let ended = current.last().is_some_and(|t| t.is_kw("END"));
+                if !in_trigger || ended {
+                    statements.push(std::mem::take(&mut current));
+                    continue;
+                }
+            },
+            _ => {},
+        }
+        current.push(token);
+    }
+    if !current.is_empty() {
+        statements.push(current);
+    }
+
+    statements
+}
+
+/// Table name to column names, all lowercase
+pub type Tables = BTreeMap<String, Vec<String>>;
+
+/// Keywords that start a table constraint rather than a column definition
+const CONSTRAINTS: &[&str] = &["CONSTRAINT", "PRIMARY", "FOREIGN", "UNIQUE", "CHECK"];
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, kw: &str) -> bool {
+        let matched = self.peek().is_some_and(|t| t.is_kw(kw));
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let name = self.next().and_then(Token::name).ok_or("Expected a name")?;
+        // Drop the schema from schema.table
+        if self.peek() == Some(&Token::Punct('.')) {
+            self.pos += 1;
+            return self.name();
+        }
+        Ok(name)
+    }
+}
+
+fn apply(tables: &mut Tables, statement: &[Token]) -> Result<(), String> {
+    let mut p = Parser { tokens: statement, pos: 0 };
+
+    if p.eat("CREATE") {
+        if !p.eat("TEMP") {
+            p.eat("TEMPORARY");
+        }
+        if !p.eat("TABLE") {
+            return Ok(());
+        }
+        if p.eat("IF") && !(p.eat("NOT") && p.eat("EXISTS")) {
+            Err("Expected IF NOT EXISTS")?;
+        }
+        let table = p.name()?;
+        if p.next() != Some(&Token::Punct('(')) {
+            Err(format!("Only CREATE TABLE {table} (...) is supported"))?;
+        }
+
+        let mut columns = Vec::new();
+        let mut depth = 0;
+        let mut item_start = true;
+        while let Some(token) = p.next() {
+            match token {
+                Token::Punct('(') => depth += 1,
+                Token::Punct(')') if depth == 0 => break,
+                Token::Punct(')') => depth -= 1,
+                Token::Punct(',') if depth == 0 => {
+                    item_start = true;
+                    continue;
+                },
+                t if item_start && !CONSTRAINTS.iter().any(|c| t.is_kw(c)) => {
+                    columns.push(t.name().ok_or("Expected a column name")?);
+                },
+                _ => {},
+            }
+            item_start = false;
+        }
+
+        tables.insert(table, columns);
+    } else if p.eat("ALTER") {
+        if !p.eat("TABLE") {
+            return Ok(());
+        }
+        let table = p.name()?;
+        let columns =
+            tables.get_mut(&table).ok_or(format!("ALTER TABLE {table} before it exists"))?;
+
+        if p.eat("RENAME") {
+            if p.eat("TO") {
+                let new_name = p.name()?;
+                let columns = tables.remove(&table).unwrap();
+                tables.insert(new_name, columns);
+            } else {
+                p.eat("COLUMN");
+                let old = p.name()?;
+                if !p.eat("TO") {
+                    Err("Expected RENAME COLUMN a TO b")?;
+                }
+                let new = p.name()?;
+                let column = columns
+                    .iter_mut()
+                    .find(|c| **c == old)
+                    .ok_or(format!("Renaming missing column {table}.{old}"))?;
+                *column = new;
+            }
+        } else if p.eat("ADD") {
+            p.eat("COLUMN");
+            columns.push(p.name()?);
+        } else if p.eat("DROP") {
+            p.eat("COLUMN");
+            let column = p.name()?;
+            columns.retain(|c| *c != column);
+        }
+    } else if p.eat("DROP") {
+        if !p.eat("TABLE") {
+            return Ok(());
+        }
+        if p.eat("IF") {
+            p.eat("EXISTS");
+        }
+        let table = p.name()?;
+        tables.remove(&table);
+    }
+
+    Ok(())
+}
+
+/// Applies the statements in `sql` on top of `tables`
+pub fn apply_sql(tables: &mut Tables, sql: &str) -> Result<(), String> {
+    for statement in statements(tokenize(sql)?) {
+        apply(tables, &statement)?;
+    }
+    Ok(())
+}
+
+/// Replays the up.sql of every migration up to and including the one
+/// `up_sql` belongs to. Returns the resulting tables and the files read
+pub fn migrate_to(up_sql: &Path) -> Result<(Tables, Vec<PathBuf>), String> {
+    let migration = up_sql.parent().ok_or("Migration has no parent dir")?;
+    let migrations = migration.parent().ok_or("Migration dir has no parent dir")?;
+
+    let mut dirs = fs::read_dir(migrations)
+        .map_err(|e| format!("Error reading {migrations:?}: {e}"))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_dir() && p.as_path() <= migration)
+        .collect::<Vec<_>>();
+    dirs.sort();
+
+    let mut tables = Tables::new();
+    let mut files = Vec::new();
+    for dir in dirs {
+        let file = dir.join("up.sql");
+        let sql = fs::read_to_string(&file).map_err(|e| format!("Error reading {file:?}: {e}"))?;
+        apply_sql(&mut tables, &sql).map_err(|e| format!("{file:?}: {e}"))?;
+        files.push(file);
+    }
+
+    Ok((tables, files))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tables(sql: &str) -> Tables {
+        let mut tables = Tables::new();
+        apply_sql(&mut tables, sql).unwrap();
+        tables
+    }
+
+    #[test]
+    fn create_table() {
+        let tables = tables(
+            "-- A comment; with a semicolon
+            CREATE TABLE IF NOT EXISTS \"user\" (
+                id          TEXT PRIMARY KEY,
+                name        TEXT NOT NULL DEFAULT 'a, b',
+                [group]     TEXT CHECK (length(name) > 0),
+                /* another; comment */
+                owner_id    TEXT,
+
+                FOREIGN KEY (owner_id) REFERENCES user(id) ON DELETE CASCADE,
+                UNIQUE (name, owner_id)
+            ) STRICT;
+            CREATE INDEX user_name ON user (name);
+            INSERT INTO user (id, name) VALUES ('1', 'x;y');",
+        );
+
+        assert_eq!(tables["user"], vec!["id", "name", "group", "owner_id"]);
+    }
+
+    #[test]
+    fn rebuild_and_alter() {
+        let tables = tables(
+            "CREATE TABLE session (id TEXT PRIMARY KEY, plan_id TEXT);
+            CREATE TABLE session_new (id TEXT PRIMARY KEY, user_id TEXT, plan_id TEXT);
+            INSERT INTO session_new SELECT id, NULL, plan_id FROM session;
+            DROP TABLE session;
+            ALTER TABLE session_new RENAME TO session;
+            ALTER TABLE session ADD COLUMN sort_order INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE session ADD finished INTEGER;
+            ALTER TABLE session RENAME COLUMN plan_id TO plan_instance_id;
+            ALTER TABLE session DROP COLUMN finished;
+            CREATE TABLE gone (id TEXT);
+            DROP TABLE IF EXISTS gone;",
+        );
+
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables["session"], vec!["id", "user_id", "plan_instance_id", "sort_order"]);
+    }
+
+    #[test]
+    fn triggers_are_skipped() {
+        let tables = tables(
+            "CREATE TABLE a (id TEXT);
+            CREATE TRIGGER a_insert AFTER INSERT ON a BEGIN
+                DROP TABLE a;
+                ALTER TABLE a ADD COLUMN b TEXT;
+            END;
+            ALTER TABLE a ADD COLUMN c TEXT;",
+        );
+
+        assert_eq!(tables["a"], vec!["id", "c"]);
+    }
+
+    #[test]
+    fn repo_migrations() {
+        let migrations = Path::new(env!("CARGO_MANIFEST_DIR")).join("../shared/migrations");
+        let latest = fs::read_dir(&migrations).unwrap().map(|e| e.unwrap().path()).max().unwrap();
+
+        let (tables, files) = migrate_to(&latest.join("up.sql")).unwrap();
+
+        assert_eq!(files.len(), fs::read_dir(&migrations).unwrap().count());
+        assert!(tables["session_exercise"].contains(&"finished".to_string()));
+        assert!(tables["exercise"].contains(&"owner_id".to_string()));
+        assert!(!tables.contains_key("exercise_new"));
+    }
+}
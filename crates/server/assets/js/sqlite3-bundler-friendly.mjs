@@ -10601,6 +10601,30 @@ sqlite3.initWorker1API = function(){
       if(!sqlite3.opfs) toss("OPFS support is unavailable.");
       const response = await sqlite3.opfs.treeList();
       return response;
+    },
+
+    // Not part of upstream sqlite-wasm. Replaces the open OPFS database with
+    // args.byteArray (the output of 'export'). The database is closed while the
+    // file is replaced and reopened under the same name afterwards
+    'import': async function(ev){
+      if(!sqlite3.opfs) toss("OPFS support is unavailable.");
+      const args = ev.args || Object.create(null);
+      if(!(args.byteArray instanceof Uint8Array)){
+        toss("'import' requires a Uint8Array byteArray.");
+      }
+      // importDb deletes the file if it fails so check it up front
+      util.affirmIsDb(args.byteArray);
+      const db = getMsgDb(ev);
+      const filename = db.filename, vfs = db.dbVfsName();
+      if('opfs' !== vfs) toss("'import' only supports the opfs vfs, not", vfs);
+      wState.close(db, false);
+      let bytes;
+      try {
+        bytes = await sqlite3.oo1.OpfsDb.importDb(filename, args.byteArray);
+      }finally{
+        wState.open({filename, vfs});
+      }
+      return {filename, bytes};
     }
   };
 
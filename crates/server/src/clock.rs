@@ -0,0 +1,20 @@
+use chrono::{DateTime, Duration, Utc};
+
+/// Where the background tasks get the time from so tests can move it along
+pub trait Clock: Send + Sync + 'static {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// For adding the std durations in [`crate::constants`] to dates
+pub fn chrono_duration(duration: std::time::Duration) -> Duration {
+    Duration::from_std(duration).expect("Constant durations fit in a chrono::Duration")
+}
@@ -1,2 +1,68 @@
 /// The limit for websocket mpmc channel
 pub const WEBSOCKET_CHANNEL_BOUND: usize = 200;
+
+/// How many recovery codes are generated for a user at a time
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// How many times a username can be used to redeem a recovery code within
+/// [`RECOVERY_ATTEMPT_WINDOW`], successful or not
+pub const RECOVERY_ATTEMPT_LIMIT: usize = 5;
+
+pub const RECOVERY_ATTEMPT_WINDOW: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// How often a logged in session's last seen date and address are updated
+pub const LOGIN_SESSION_SEEN_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How many temporary logins an address (for codes) or username (for pins) can
+/// try within [`TEMPORARY_LOGIN_ATTEMPT_WINDOW`] before it's turned away,
+/// successful or not
+pub const TEMPORARY_LOGIN_ATTEMPT_LIMIT: usize = 5;
+
+pub const TEMPORARY_LOGIN_ATTEMPT_WINDOW: std::time::Duration =
+    std::time::Duration::from_secs(15 * 60);
+
+/// How long a device has to be approved after asking to be paired
+pub const PAIRING_EXPIRY: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// How many notifications in a row can be given up on for a push subscription
+/// before it's removed. Ones the push service says are gone are removed
+/// straight away
+pub const PUSH_SUBSCRIPTION_FAILURE_LIMIT: u32 = 5;
+
+/// How often the reminder scheduler looks for reminders to send
+pub const REMINDER_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How long before a planned session the reminder about it is sent
+pub const UPCOMING_REMINDER_LEAD_TIME: std::time::Duration =
+    std::time::Duration::from_secs(60 * 60);
+
+/// The hour in the user's timezone the day after a session that the missed
+/// session reminder is sent
+pub const MISSED_REMINDER_HOUR: u32 = 9;
+
+/// How long a missed session reminder can be held back by quiet hours before
+/// it's not worth sending
+pub const MISSED_REMINDER_EXPIRY: std::time::Duration =
+    std::time::Duration::from_secs(24 * 60 * 60);
+
+/// How long the snooze action on a reminder puts it off for. It's also how
+/// long the snoozed reminder can be held back by quiet hours. The action's
+/// title says 1h
+pub const REMINDER_SNOOZE: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// How often the notifier looks for queued push notifications to send
+pub const NOTIFIER_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// The most push notifications the notifier sends at once. Anything more waits
+/// for the next interval
+pub const NOTIFIER_BATCH_SIZE: u64 = 100;
+
+/// How many times sending a push notification is tried before it's given up
+/// on. It's given up on sooner if it expires first
+pub const PUSH_ATTEMPT_LIMIT: u32 = 8;
+
+/// How long to wait after the first failed send. It doubles with each failure
+/// after that up to [`PUSH_RETRY_MAX_DELAY`]
+pub const PUSH_RETRY_BASE_DELAY: std::time::Duration = std::time::Duration::from_secs(30);
+
+pub const PUSH_RETRY_MAX_DELAY: std::time::Duration = std::time::Duration::from_secs(60 * 60);
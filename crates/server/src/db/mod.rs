@@ -5,15 +5,24 @@ use std::{
     time::{Duration, Instant},
 };
 
+use deadpool_sqlite::{Config, Hook, Pool, Runtime};
 use include_dir::{include_dir, Dir};
-use rusqlite::{Connection, OpenFlags, TransactionBehavior};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, TransactionBehavior};
 use rusqlite_migration::{Migrations, SchemaVersion};
 use shared::{
     api::error::{Nothing, ServerError},
-    model::{NewServiceVersion, ServiceVersion},
+    model::{
+        schema::{
+            matching_version, migration_name, schema_diff, CREATE_MIGRATION_DOWN_TABLE,
+            MIGRATION_DOWN_TABLE, SCHEMA_QUERY,
+        },
+        NewServiceVersion, ServiceVersion,
+    },
     other_error,
 };
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use crate::AppError;
 mod database_connection;
 pub use database_connection::*;
 
@@ -59,6 +68,26 @@ pub fn configure_new_connection(conn: &mut Connection) -> Result<(), ServerError
     Ok(())
 }
 
+/// Creates the pool the routes get their connections from. Migrations should
+/// have already been run with [`run_migrations`]
+pub fn create_pool(connection_string: &str) -> Result<Pool, ServerError<Nothing>> {
+    Config::new(connection_string)
+        .builder(Runtime::Tokio1)
+        .map_err(|e| other_error!("Config::builder: {e:?}"))?
+        .post_create(Hook::async_fn(|object, _| {
+            Box::pin(async move {
+                object
+                    .interact(|conn| configure_new_connection(conn))
+                    .await
+                    .map_err(AppError::from)?
+                    .map_err(AppError::from)?;
+                Ok(())
+            })
+        }))
+        .build()
+        .map_err(|e| other_error!("PoolBuilder::build: {e:?}"))
+}
+
 #[instrument(skip(conn))]
 pub fn run_pragmas(conn: &Connection) -> Result<(), ServerError<Nothing>> {
     conn.pragma_update(None, "journal_mode", "WAL")?;
@@ -67,6 +96,168 @@ pub fn run_pragmas(conn: &Connection) -> Result<(), ServerError<Nothing>> {
     Ok(())
 }
 
+/// Runs the migrations with foreign keys turned off so tables can be rebuilt without the
+/// drop cascading to (or failing because of) the tables referencing them. Anything the
+/// migrations left dangling is reported once they're back on
+#[instrument(skip(conn, migrations))]
+pub fn migrate_to_latest(
+    conn: &mut Connection,
+    migrations: &Migrations,
+) -> Result<(), ServerError<Nothing>> {
+    conn.pragma_update(None, "foreign_keys", "OFF")?;
+    let result =
+        migrations.to_latest(conn).map_err(|e| other_error!("Migrations::to_latest: {:?}", e));
+    conn.pragma_update(None, "foreign_keys", "ON")?;
+    result?;
+
+    check_foreign_keys(conn)?;
+    store_down_migrations(conn)
+}
+
+fn check_foreign_keys(conn: &Connection) -> Result<(), ServerError<Nothing>> {
+    let violations = conn
+        .prepare("PRAGMA foreign_key_check")?
+        .query_map((), |row| {
+            Ok(format!(
+                "{}.rowid={:?} -> {}",
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<i64>>(1)?,
+                row.get::<_, String>(2)?
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(other_error!("Foreign key violations after migrating: {}", violations.join(", ")))
+    }
+}
+
+/// The name and down.sql of each migration in order. Migrations without a
+/// down.sql are `None`
+fn down_migrations() -> Result<Vec<(&'static str, Option<&'static str>)>, ServerError<Nothing>> {
+    let mut dirs = MIGRATIONS_DIR.dirs().collect::<Vec<_>>();
+    dirs.sort_by_key(|d| d.path());
+    dirs.into_iter()
+        .map(|d| {
+            let name =
+                d.path().file_name().and_then(|n| n.to_str()).map(migration_name).ok_or_else(
+                    || other_error!("Migration directory {:?} has no name", d.path()),
+                )?;
+            let down = d
+                .files()
+                .find(|f| f.path().ends_with("down.sql"))
+                .map(|f| {
+                    f.contents_utf8().ok_or_else(|| {
+                        other_error!(
+                            "down.sql in {:?} could not be read as a utf8 string",
+                            d.path()
+                        )
+                    })
+                })
+                .transpose()?;
+            Ok::<_, ServerError<Nothing>>((name, down))
+        })
+        .collect()
+}
+
+/// Keeps a copy of the down migrations in the database so an older version
+/// that doesn't know about them can still roll them back
+fn store_down_migrations(conn: &Connection) -> Result<(), ServerError<Nothing>> {
+    conn.execute(CREATE_MIGRATION_DOWN_TABLE, ())?;
+    let mut insert = conn.prepare(&format!(
+        "INSERT OR REPLACE INTO {MIGRATION_DOWN_TABLE} (version, name, down) VALUES (?1, ?2, ?3)"
+    ))?;
+    for (i, (name, down)) in down_migrations()?.into_iter().enumerate() {
+        insert.execute((i + 1, name, down))?;
+    }
+    Ok(())
+}
+
+/// The version the database can be trusted to be at. The version number alone
+/// isn't enough as another release can have a different migration at the same
+/// version so it's the last one where the stored migration names match ours
+fn known_version(conn: &Connection, version: usize) -> Result<usize, ServerError<Nothing>> {
+    conn.execute(CREATE_MIGRATION_DOWN_TABLE, ())?;
+    let stored = conn
+        .prepare(&format!("SELECT version, name FROM {MIGRATION_DOWN_TABLE}"))?
+        .query_map((), |row| Ok((row.get::<_, usize>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    let local = down_migrations()?.into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+
+    Ok(matching_version(&local, &stored, version))
+}
+
+/// Rolls the database back from `from` to `to` using the down migrations a
+/// newer version stored. This is what happens after a rollback deploy: the
+/// database was migrated by code that's no longer running
+#[instrument(skip(conn))]
+pub fn roll_back_unknown_migrations(
+    conn: &mut Connection,
+    from: usize,
+    to: usize,
+) -> Result<(), ServerError<Nothing>> {
+    warn!("Schema version {from} doesn't match the known migrations, rolling back to {to}");
+
+    conn.pragma_update(None, "foreign_keys", "OFF")?;
+    let result = (to + 1..=from).rev().try_for_each(|version| {
+        let down: Option<String> = conn
+            .query_row(
+                &format!("SELECT down FROM {MIGRATION_DOWN_TABLE} WHERE version = ?1"),
+                [version],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()?
+            .flatten();
+        let down = down.ok_or_else(|| {
+            other_error!(
+                "Schema version {from} is outside of known schema migrations and there's no \
+                 stored down migration for version {version}. Manual intervention required"
+            )
+        })?;
+
+        debug!("Rolling back version {version}");
+        let tx = conn.transaction()?;
+        tx.execute_batch(&down)?;
+        tx.execute(&format!("DELETE FROM {MIGRATION_DOWN_TABLE} WHERE version = ?1"), [version])?;
+        tx.pragma_update(None, "user_version", version - 1)?;
+        tx.commit()?;
+
+        Ok::<_, ServerError<Nothing>>(())
+    });
+    conn.pragma_update(None, "foreign_keys", "ON")?;
+    result?;
+
+    check_foreign_keys(conn)?;
+    verify_schema(conn, to)
+}
+
+/// Compares the schema against a fresh database migrated to `version` so a
+/// down migration that didn't undo everything is caught before migrating up
+/// again
+fn verify_schema(conn: &Connection, version: usize) -> Result<(), ServerError<Nothing>> {
+    let mut expected = Connection::open_in_memory()?;
+    expected.pragma_update(None, "foreign_keys", "OFF")?;
+    get_migrations()?
+        .to_version(&mut expected, version)
+        .map_err(|e| other_error!("Migrations::to_version({version}): {:?}", e))?;
+
+    let diff = schema_diff(&schema(&expected)?.join("\n"), &schema(conn)?);
+    if diff.is_empty() {
+        Ok(())
+    } else {
+        Err(other_error!(
+            "Schema doesn't match version {version} after rolling back: {}",
+            diff.join(", ")
+        ))
+    }
+}
+
+fn schema(conn: &Connection) -> Result<Vec<String>, ServerError<Nothing>> {
+    Ok(conn.prepare(SCHEMA_QUERY)?.query_map((), |row| row.get(0))?.collect::<Result<_, _>>()?)
+}
+
 #[instrument]
 pub fn run_migrations(
     connection_string: &str,
@@ -78,7 +269,11 @@ pub fn run_migrations(
     CONFIG_LOG.call_once(|| unsafe {
         config_result = rusqlite::trace::config_log(Some(sqlite_log_callback));
     });
-    config_result?;
+    if let Err(e) = config_result {
+        // Only possible if a connection was opened first, which happens when the tests run
+        // in parallel. The database still works, sqlite just won't log through tracing
+        warn!("Configuring the sqlite log callback failed: {e}");
+    }
 
     let open_flags = OpenFlags::SQLITE_OPEN_READ_WRITE
         | OpenFlags::SQLITE_OPEN_URI
@@ -99,17 +294,18 @@ pub fn run_migrations(
             .current_version(&conn)
             .map_err(|e| other_error!("Migrations::current_version: {:?}", e))?
         {
-            SchemaVersion::Inside(n) => Ok(n.into()),
-            SchemaVersion::Outside(n) => Err(other_error!(
-                "Schema version {n} is outside of known schema migrations. Manual intervention \
-                 required"
-            )),
+            SchemaVersion::Inside(n) | SchemaVersion::Outside(n) => {
+                let version = n.into();
+                let known = known_version(&conn, version)?;
+                if known < version {
+                    roll_back_unknown_migrations(&mut conn, version, known)?;
+                }
+                Ok(known)
+            },
             SchemaVersion::NoneSet => Ok(0),
         }?;
 
-        migrations
-            .to_latest(&mut conn)
-            .map_err(|e| other_error!("Migrations::to_latest: {:?}", e))?;
+        migrate_to_latest(&mut conn, &migrations)?;
 
         let final_version: usize = match migrations
             .current_version(&conn)
@@ -189,3 +385,408 @@ pub fn vacuum_database(conn: &Connection) -> Result<Duration, ServerError<Nothin
     conn.execute("VACUUM", ())?;
     Ok(start.elapsed())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use chrono::Utc;
+    use sea_query::Iden;
+    use shared::{
+        model::{
+            schema::{CLIENT_SCHEMA, SERVER_ONLY_TABLES},
+            Backup, Credential, Exercise, ExerciseGroup, ExerciseGroupMember,
+            ExerciseGroupMemberIden, LoginSession, Model, NewUser, Plan, PlanExerciseGroup,
+            PlanExerciseGroupIden, PlanInstance, PlanInstanceIden, PlanTree, PlanTreeRows,
+            PushSubscription, RecoveryCode, ServiceVersion, Session, SessionExercise,
+            SessionExerciseIden, TemporaryLogin, TemporaryLoginAudit, User, UserExercise,
+            UserExerciseIden,
+        },
+        types::Uuid,
+    };
+
+    use super::*;
+    use crate::test_support::connection;
+
+    thread_local! {
+        static STATEMENTS: Cell<usize> = Cell::new(0);
+    }
+
+    fn count_statement(_query: &str) {
+        STATEMENTS.with(|s| s.set(s.get() + 1));
+    }
+
+    /// Runs `f` and returns its result along with how many statements it ran
+    fn count_statements<T>(conn: &mut Connection, f: impl FnOnce(&Connection) -> T) -> (T, usize) {
+        STATEMENTS.with(|s| s.set(0));
+        conn.trace(Some(count_statement));
+        let result = f(conn);
+        conn.trace(None);
+        (result, STATEMENTS.with(Cell::get))
+    }
+
+    /// Loads the trees the way the Today page used to, a query per row
+    fn fetch_plan_trees_per_row(
+        conn: &Connection,
+        user_id: Uuid,
+    ) -> Result<Vec<PlanTree>, rusqlite::Error> {
+        PlanInstance::fetch_all_by_column(conn, user_id, PlanInstanceIden::UserId)?
+            .into_iter()
+            .map(|plan_instance| {
+                let plan = <Plan as Model>::fetch_by_id(conn, plan_instance.plan_id)?;
+                let groups = PlanExerciseGroup::fetch_all_by_column(
+                    conn,
+                    plan.id,
+                    PlanExerciseGroupIden::PlanId,
+                )?
+                .into_iter()
+                .map(|peg| {
+                    let group = <ExerciseGroup as Model>::fetch_by_id(conn, peg.exercise_group_id)?;
+                    let exercises = ExerciseGroupMember::fetch_all_by_column(
+                        conn,
+                        group.id,
+                        ExerciseGroupMemberIden::GroupId,
+                    )?
+                    .into_iter()
+                    .map(|egm| {
+                        let exercise = <Exercise as Model>::fetch_by_id(conn, egm.exercise_id)?;
+                        let user_exercise = UserExercise::fetch_all_by_column(
+                            conn,
+                            exercise.id,
+                            UserExerciseIden::ExerciseId,
+                        )?
+                        .into_iter()
+                        .find(|ue| ue.user_id == user_id);
+                        let sessions = SessionExercise::fetch_all_by_column(
+                            conn,
+                            exercise.id,
+                            SessionExerciseIden::ExerciseId,
+                        )?
+                        .into_iter()
+                        .filter(|se| se.user_id == user_id)
+                        .map(|se| {
+                            let session = <Session as Model>::fetch_by_id(conn, se.session_id)?;
+                            Ok((se, session))
+                        })
+                        .collect::<Result<Vec<_>, rusqlite::Error>>()?;
+                        Ok((exercise, user_exercise, sessions))
+                    })
+                    .collect::<Result<Vec<_>, rusqlite::Error>>()?;
+                    Ok((peg, group, exercises))
+                })
+                .collect::<Result<Vec<_>, rusqlite::Error>>()?;
+                Ok((plan, plan_instance, groups))
+            })
+            .collect()
+    }
+
+    /// Puts every level of the trees in a fixed order so the two ways of
+    /// loading them can be compared
+    fn sorted(mut trees: Vec<PlanTree>) -> Vec<PlanTree> {
+        trees.sort_by_key(|(_, plan_instance, _)| plan_instance.id.to_string());
+        for (_, _, groups) in trees.iter_mut() {
+            groups.sort_by_key(|(peg, ..)| peg.id.to_string());
+            for (_, _, exercises) in groups.iter_mut() {
+                exercises.sort_by_key(|(exercise, ..)| exercise.id.to_string());
+                for (_, _, sessions) in exercises.iter_mut() {
+                    sessions.sort_by_key(|(se, _)| se.id.to_string());
+                }
+            }
+        }
+        trees
+    }
+
+    /// Adds a session for every exercise in the plan
+    fn add_sessions(conn: &mut Connection, plan_instance: &PlanInstance, exercises: &[Uuid]) {
+        let now = Utc::now();
+        let session = Session::create::<Nothing>(conn, Session {
+            id: Uuid::new_v4(),
+            user_id: plan_instance.user_id,
+            plan_instance_id: Some(plan_instance.id),
+            planned_date: now,
+            performed_date: None,
+            creation_date: now,
+            last_updated_date: now,
+        })
+        .unwrap();
+
+        for (i, exercise_id) in exercises.iter().enumerate() {
+            SessionExercise::create::<Nothing>(conn, SessionExercise {
+                id: Uuid::new_v4(),
+                user_id: plan_instance.user_id,
+                exercise_id: *exercise_id,
+                session_id: session.id,
+                planned_sets: Default::default(),
+                performed_sets: Default::default(),
+                creation_date: now,
+                last_updated_date: now,
+                sort_order: i as i64,
+                finished: false,
+            })
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn plan_tree_round_trips_dont_grow_with_history() {
+        let mut conn = connection();
+        let now = Utc::now();
+        let user_id = Uuid::new_v4();
+        User::create::<Nothing>(&mut conn, NewUser::new(user_id, "a")).unwrap();
+
+        let plan = Plan::fetch_all(&conn).unwrap().remove(0);
+        let plan_instance = PlanInstance::create::<Nothing>(&mut conn, PlanInstance {
+            id: Uuid::new_v4(),
+            plan_id: plan.id,
+            user_id,
+            start_date: now,
+            creation_date: now,
+            last_updated_date: now,
+        })
+        .unwrap();
+
+        let exercises = PlanTreeRows::fetch(&conn, user_id).unwrap().exercises;
+        let exercises = exercises.iter().map(|e| e.id).collect::<Vec<_>>();
+        assert!(!exercises.is_empty(), "Test data plan should have exercises");
+
+        let mut previous_per_row = 0;
+        for history in [1, 10, 50] {
+            while SessionExercise::fetch_all_by_column(&conn, user_id, SessionExerciseIden::UserId)
+                .unwrap()
+                .len()
+                < history * exercises.len()
+            {
+                add_sessions(&mut conn, &plan_instance, &exercises);
+            }
+
+            let (joined, joined_count) = count_statements(&mut conn, |conn| {
+                PlanTreeRows::fetch(conn, user_id).unwrap().into_trees()
+            });
+            let (per_row, per_row_count) = count_statements(&mut conn, |conn| {
+                fetch_plan_trees_per_row(conn, user_id).unwrap()
+            });
+
+            assert_eq!(sorted(joined), sorted(per_row));
+            // One statement per table regardless of how much history there is
+            assert_eq!(joined_count, 9, "{history} sessions");
+            // While fetching per row grows with it
+            assert!(per_row_count > previous_per_row, "{history} sessions");
+            assert!(
+                per_row_count > joined_count,
+                "{history} sessions: {joined_count} statements vs {per_row_count} per row"
+            );
+
+            previous_per_row = per_row_count;
+        }
+    }
+
+    fn user_version(conn: &Connection) -> usize {
+        conn.pragma_query_value(None, "user_version", |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn client_schema_matches_migrations() {
+        let conn = connection();
+        let schema = schema(&conn)
+            .unwrap()
+            .into_iter()
+            .filter(|l| !SERVER_ONLY_TABLES.iter().any(|t| l.starts_with(&format!("{t} "))))
+            .collect::<Vec<_>>();
+
+        let diff = schema_diff(CLIENT_SCHEMA, &schema);
+        assert!(
+            diff.is_empty(),
+            "crates/shared/schema/client.txt doesn't match the migrations:\n{}\n\nIt should \
+             be:\n{}",
+            diff.join("\n"),
+            schema.join("\n")
+        );
+    }
+
+    /// Compares the fields of `T` with what `PRAGMA table_info` says about its
+    /// table
+    fn column_problems<T: Model>(conn: &Connection) -> Vec<String> {
+        let table = T::table_iden().to_string();
+        let columns = conn
+            .prepare(&format!("SELECT name, type, \"notnull\" FROM pragma_table_info('{table}')"))
+            .unwrap()
+            .query_map((), |row| Ok((row.get::<_, String>(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<Vec<(String, String, bool)>, _>>()
+            .unwrap();
+
+        let fields =
+            T::field_idens().iter().map(|i| i.to_string()).zip(T::COLUMN_TYPES).collect::<Vec<_>>();
+
+        let mut problems = fields
+            .iter()
+            .filter_map(|(field, column_type)| {
+                let Some((_, sql_type, not_null)) = columns.iter().find(|(c, ..)| c == field)
+                else {
+                    return Some(format!("{table}.{field} has no column"));
+                };
+                if sql_type != column_type.sql_type {
+                    Some(format!(
+                        "{table}.{field} is {} but the column is {sql_type}",
+                        column_type.sql_type
+                    ))
+                } else if column_type.nullable == *not_null {
+                    Some(format!(
+                        "{table}.{field} is {} but the column is {}",
+                        if column_type.nullable { "an Option" } else { "not an Option" },
+                        if *not_null { "NOT NULL" } else { "nullable" },
+                    ))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        problems.extend(
+            columns
+                .iter()
+                .filter(|(c, ..)| !fields.iter().any(|(f, _)| f == c))
+                .map(|(c, ..)| format!("{table}.{c} has no field")),
+        );
+
+        problems
+    }
+
+    #[test]
+    fn models_match_migrations() {
+        let conn = connection();
+        let problems = [
+            column_problems::<User>(&conn),
+            column_problems::<Credential>(&conn),
+            column_problems::<TemporaryLogin>(&conn),
+            column_problems::<Exercise>(&conn),
+            column_problems::<ExerciseGroup>(&conn),
+            column_problems::<ExerciseGroupMember>(&conn),
+            column_problems::<UserExercise>(&conn),
+            column_problems::<Plan>(&conn),
+            column_problems::<PlanExerciseGroup>(&conn),
+            column_problems::<PlanInstance>(&conn),
+            column_problems::<Session>(&conn),
+            column_problems::<SessionExercise>(&conn),
+            column_problems::<ServiceVersion>(&conn),
+            column_problems::<Backup>(&conn),
+            column_problems::<RecoveryCode>(&conn),
+            column_problems::<LoginSession>(&conn),
+            column_problems::<PushSubscription>(&conn),
+            column_problems::<TemporaryLoginAudit>(&conn),
+        ]
+        .concat();
+
+        assert!(problems.is_empty(), "Models don't match the migrations:\n{}", problems.join("\n"));
+    }
+
+    #[test]
+    fn push_subscriptions_shared_between_users_are_kept_once() {
+        let migrations = get_migrations().unwrap();
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "foreign_keys", "OFF").unwrap();
+        // Just before 024-push_subscription
+        migrations.to_version(&mut conn, 23).unwrap();
+
+        let subscription = |endpoint: &str, key: &str| {
+            format!(r#"{{ "endpoint": "{endpoint}", "key": "{key}", "auth": "auth" }}"#)
+        };
+        let (older, newer, other) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        for (id, username, push_notification_subscription, last_updated_date) in [
+            (older, "older", subscription("https://push/shared", "old"), "2024-01-01 00:00:00"),
+            (newer, "newer", subscription("https://push/shared", "new"), "2024-02-01 00:00:00"),
+            (other, "other", subscription("https://push/other", "other"), "2024-01-15 00:00:00"),
+        ] {
+            conn.execute(
+                "INSERT INTO user (id, username, push_notification_subscription, \
+                 last_updated_date) VALUES (?1, ?2, ?3, ?4)",
+                (id, username, push_notification_subscription, last_updated_date),
+            )
+            .unwrap();
+        }
+
+        migrations.to_version(&mut conn, 24).unwrap();
+
+        let subscriptions = conn
+            .prepare("SELECT user_id, endpoint, key FROM push_subscription ORDER BY endpoint")
+            .unwrap()
+            .query_map((), |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<Vec<(Uuid, String, String)>, _>>()
+            .unwrap();
+        assert_eq!(subscriptions, vec![
+            (other, "https://push/other".to_string(), "other".to_string()),
+            (newer, "https://push/shared".to_string(), "new".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn stored_down_migrations_restore_each_version() {
+        let migrations = get_migrations().unwrap();
+        let latest = down_migrations().unwrap().len();
+
+        for version in (1..latest).rev() {
+            let mut expected = Connection::open_in_memory().unwrap();
+            expected.pragma_update(None, "foreign_keys", "OFF").unwrap();
+            migrations.to_version(&mut expected, version).unwrap();
+
+            let mut conn = connection();
+            roll_back_unknown_migrations(&mut conn, latest, version).unwrap();
+
+            assert_eq!(user_version(&conn), version);
+            assert_eq!(schema(&conn).unwrap(), schema(&expected).unwrap(), "Version {version}");
+        }
+    }
+
+    #[test]
+    fn migrations_from_a_newer_version_are_rolled_back() {
+        let mut conn = connection();
+        let latest = user_version(&conn);
+
+        // What a newer version would have left behind
+        conn.execute_batch(&format!(
+            "CREATE TABLE from_the_future (id TEXT PRIMARY KEY) STRICT;
+            INSERT INTO {MIGRATION_DOWN_TABLE} (version, name, down)
+            VALUES ({}, 'from_the_future', 'DROP TABLE from_the_future');
+            PRAGMA user_version={};",
+            latest + 1,
+            latest + 1,
+        ))
+        .unwrap();
+
+        roll_back_unknown_migrations(&mut conn, latest + 1, latest).unwrap();
+        assert_eq!(user_version(&conn), latest);
+        assert!(schema(&conn).unwrap().iter().all(|l| !l.starts_with("from_the_future ")));
+        migrate_to_latest(&mut conn, &get_migrations().unwrap()).unwrap();
+
+        // Nothing to roll back with so it has to be fixed by hand
+        conn.pragma_update(None, "user_version", latest + 1).unwrap();
+        assert!(roll_back_unknown_migrations(&mut conn, latest + 1, latest).is_err());
+    }
+
+    #[test]
+    fn a_different_migration_at_a_known_version_is_rolled_back() {
+        let mut conn = connection();
+        let latest = user_version(&conn);
+        let expected = schema(&conn).unwrap();
+
+        // A newer version that added a migration before the test data, which
+        // scripts/link_migrations always puts last
+        roll_back_unknown_migrations(&mut conn, latest, latest - 1).unwrap();
+        conn.execute_batch(&format!(
+            "CREATE TABLE from_the_future (id TEXT PRIMARY KEY) STRICT;
+            INSERT INTO {MIGRATION_DOWN_TABLE} (version, name, down)
+            VALUES ({latest}, 'from_the_future', 'DROP TABLE from_the_future');
+            PRAGMA user_version={latest};"
+        ))
+        .unwrap();
+
+        assert_eq!(known_version(&conn, latest).unwrap(), latest - 1);
+        roll_back_unknown_migrations(&mut conn, latest, latest - 1).unwrap();
+        migrate_to_latest(&mut conn, &get_migrations().unwrap()).unwrap();
+
+        assert_eq!(user_version(&conn), latest);
+        assert_eq!(known_version(&conn, latest).unwrap(), latest);
+        assert_eq!(schema(&conn).unwrap(), expected);
+    }
+}
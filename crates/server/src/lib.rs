@@ -5,6 +5,9 @@ pub mod db;
 mod utils;
 pub use utils::*;
 
+mod clock;
+pub use clock::*;
+
 mod errors;
 pub use errors::*;
 
@@ -16,8 +19,18 @@ pub use state::*;
 
 pub mod routes;
 
+mod router;
+pub use router::*;
+
 pub mod cli;
 
 pub mod middleware;
 
 pub mod constants;
+
+pub mod notifier;
+
+pub mod reminders;
+
+#[cfg(test)]
+mod test_support;
@@ -0,0 +1,95 @@
+//! Keeps a [`LoginSession`] for every logged in session so users can see where
+//! they're logged in. Logging in by any route is picked up here rather than in
+//! each of them
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use deadpool_sqlite::Pool;
+use shared::{
+    api::error::{Nothing, ServerError},
+    model::{LoginSession, Model},
+    other_error,
+    types::Uuid,
+};
+use tower_sessions::Session;
+use tracing::error;
+
+use crate::{constants::LOGIN_SESSION_SEEN_INTERVAL, ClientAddress, SessionValue, UserState};
+
+pub async fn track_login_session(
+    State(pool): State<Pool>,
+    session: Session,
+    request: Request,
+    next: Next,
+) -> Response {
+    let ClientAddress { ip_address, user_agent } =
+        ClientAddress::new(request.headers(), request.extensions());
+
+    let response = next.run(request).await;
+
+    // The handler may have just logged the session in or out
+    if let Some(user_state) = SessionValue::user_state_of(&session).await {
+        if let Err(e) = record(&pool, &session, user_state, user_agent, ip_address).await {
+            error!("Error recording login session: {e}");
+        }
+    }
+
+    response
+}
+
+async fn record(
+    pool: &Pool,
+    session: &Session,
+    user_state: UserState,
+    user_agent: Option<String>,
+    ip_address: Option<String>,
+) -> Result<(), ServerError<Nothing>> {
+    let session_id = SessionValue::saved_id(session).await?.to_string();
+    let conn = pool.get().await.map_err(|e| other_error!("Getting a connection: {e}"))?;
+
+    conn.interact(move |conn| {
+        let now = Utc::now();
+        let user_id = *user_state.id;
+
+        match LoginSession::fetch_by_session_id(conn, &session_id)? {
+            None => {
+                LoginSession {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    session_id,
+                    user_agent,
+                    ip_address,
+                    creation_date: now,
+                    last_seen_date: now,
+                }
+                .insert(conn)?;
+            },
+            Some(mut login_session) => {
+                let user_changed = login_session.user_id != user_id;
+                let seen_a_while_ago = (now - login_session.last_seen_date)
+                    .to_std()
+                    .is_ok_and(|d| d >= LOGIN_SESSION_SEEN_INTERVAL);
+
+                if user_changed || seen_a_while_ago {
+                    if user_changed {
+                        login_session.user_id = user_id;
+                        login_session.creation_date = now;
+                    }
+                    login_session.user_agent = user_agent;
+                    login_session.ip_address = ip_address;
+                    login_session.last_seen_date = now;
+                    login_session.update(conn)?;
+                }
+            },
+        }
+
+        Ok::<_, ServerError<Nothing>>(())
+    })
+    .await??;
+
+    Ok(())
+}
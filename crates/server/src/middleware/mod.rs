@@ -1,2 +1,5 @@
 mod csrf;
 pub use csrf::*;
+
+mod login_session;
+pub use login_session::*;
@@ -0,0 +1,448 @@
+//! Delivers push notifications. Anything that wants to send one [`queue`]s it
+//! in the `push_outbox` table and the [`Notifier`] sends it from there, trying
+//! again with a growing delay when the push service can't take it and pruning
+//! subscriptions that are gone or keep failing
+
+mod transport;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use deadpool_sqlite::Pool;
+use futures::future::join_all;
+use rusqlite::Connection;
+use shared::{
+    api::{
+        error::{Nothing, ServerError},
+        payloads::{Notification, NotificationKind},
+    },
+    model::{Model, PushSubscription, QueuedPush},
+    other_error,
+};
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{debug, error, warn};
+pub use transport::*;
+use web_push::{Urgency, WebPushError};
+
+use crate::{
+    chrono_duration,
+    constants::{
+        NOTIFIER_BATCH_SIZE, NOTIFIER_INTERVAL, PUSH_ATTEMPT_LIMIT, PUSH_RETRY_BASE_DELAY,
+        PUSH_RETRY_MAX_DELAY, PUSH_SUBSCRIPTION_FAILURE_LIMIT,
+    },
+    Clock,
+};
+
+/// How long each kind of notification is worth delivering for
+pub fn ttl(kind: NotificationKind) -> Duration {
+    match kind {
+        NotificationKind::Update => Duration::from_hours(24),
+        // The session will have started by then
+        NotificationKind::UpcomingReminder => Duration::from_hours(1),
+        NotificationKind::MissedReminder => Duration::from_hours(12),
+    }
+}
+
+/// How hard the push service should try to wake the device for each kind of
+/// notification
+pub fn urgency(kind: NotificationKind) -> Urgency {
+    match kind {
+        NotificationKind::Update => Urgency::Low,
+        NotificationKind::UpcomingReminder => Urgency::High,
+        NotificationKind::MissedReminder => Urgency::Normal,
+    }
+}
+
+/// How long to wait before trying again after `attempts` failures
+pub fn backoff(attempts: u32) -> Duration {
+    PUSH_RETRY_BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempts.saturating_sub(1)))
+        .min(PUSH_RETRY_MAX_DELAY)
+}
+
+/// Queues the notification for each of the subscriptions. The [`Notifier`]
+/// sends it the next time it runs
+pub fn queue(
+    conn: &Connection,
+    push_subscriptions: &[PushSubscription],
+    kind: NotificationKind,
+    notification: &Notification,
+    now: DateTime<Utc>,
+) -> Result<(), ServerError<Nothing>> {
+    let expiry_date = now + chrono_duration(ttl(kind));
+    for push_subscription in push_subscriptions {
+        QueuedPush::new(push_subscription.id, kind, notification.clone(), expiry_date, now)
+            .insert(conn)?;
+    }
+
+    Ok(())
+}
+
+/// What to do with a queued push after trying to send it
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Outcome {
+    Delivered,
+    /// The subscription won't ever work again
+    Gone,
+    /// Might work later, but not before the delay if the push service gave one
+    Retry(Option<Duration>),
+    /// This notification won't ever be accepted
+    Failed,
+}
+
+impl From<&Result<(), WebPushError>> for Outcome {
+    fn from(result: &Result<(), WebPushError>) -> Self {
+        match result {
+            Ok(()) => Self::Delivered,
+            Err(
+                WebPushError::EndpointNotValid
+                | WebPushError::EndpointNotFound
+                | WebPushError::InvalidUri
+                | WebPushError::MissingCryptoKeys
+                | WebPushError::InvalidCryptoKeys,
+            ) => Self::Gone,
+            Err(
+                WebPushError::Unauthorized
+                | WebPushError::BadRequest(_)
+                | WebPushError::NotImplemented
+                | WebPushError::PayloadTooLarge
+                | WebPushError::InvalidPackageName
+                | WebPushError::InvalidTtl
+                | WebPushError::InvalidTopic
+                | WebPushError::InvalidClaims,
+            ) => Self::Failed,
+            Err(WebPushError::ServerError(retry_after)) => Self::Retry(*retry_after),
+            // Connection problems and anything unexpected from the push service
+            Err(_) => Self::Retry(None),
+        }
+    }
+}
+
+fn outgoing(
+    queued: &QueuedPush,
+    push_subscription: &PushSubscription,
+    now: DateTime<Utc>,
+) -> Result<OutgoingPush, WebPushError> {
+    Ok(OutgoingPush {
+        subscription: push_subscription.subscription(),
+        payload: serde_json::to_vec(&queued.notification)?,
+        // Retries don't get any longer than the first attempt did
+        ttl: (queued.expiry_date - now).num_seconds().max(0) as u32,
+        urgency: urgency(queued.kind),
+    })
+}
+
+/// Deals with the result of sending the queued push, pruning the subscription
+/// if it's gone or has had too many notifications given up on. Only the
+/// subscription that failed is pruned, the user's other devices keep theirs
+fn record_result(
+    conn: &Connection,
+    mut queued: QueuedPush,
+    result: Result<(), WebPushError>,
+    now: DateTime<Utc>,
+) -> Result<(), ServerError<Nothing>> {
+    // An earlier result in the same batch might have pruned it
+    let Some(mut push_subscription) =
+        PushSubscription::fetch_by_id_maybe(conn, queued.push_subscription_id)?
+    else {
+        return Ok(());
+    };
+    let (id, user_id) = (push_subscription.id, push_subscription.user_id);
+
+    let outcome = Outcome::from(&result);
+    if let Err(e) = &result {
+        queued.attempts += 1;
+        queued.last_error = Some(format!("{e:?}"));
+        warn!(
+            "Sending push {} to subscription {id} of user_id {user_id} failed (attempt {}): {e:?}",
+            queued.id, queued.attempts
+        );
+    }
+
+    match outcome {
+        Outcome::Delivered => {
+            queued.delete::<Nothing>(conn)?;
+            push_subscription.record_success::<Nothing>(conn)?;
+        },
+        Outcome::Gone => {
+            // Takes anything else queued for it along with it
+            push_subscription.delete::<Nothing>(conn)?;
+            // TODO: send a message to the service worker to resubscribe
+            error!("Push subscription {id} of user_id {user_id} was invalid. It's been removed");
+        },
+        Outcome::Retry(retry_after) if queued.attempts < PUSH_ATTEMPT_LIMIT => {
+            let delay = retry_after.unwrap_or_default().max(backoff(queued.attempts));
+            queued.next_attempt_date = now + chrono_duration(delay);
+            queued.update::<Nothing>(conn)?;
+        },
+        Outcome::Retry(_) | Outcome::Failed => {
+            queued.delete::<Nothing>(conn)?;
+            push_subscription.record_failure::<Nothing>(conn)?;
+            if push_subscription.failure_count >= PUSH_SUBSCRIPTION_FAILURE_LIMIT {
+                push_subscription.delete::<Nothing>(conn)?;
+                error!(
+                    "Push subscription {id} of user_id {user_id} failed \
+                     {PUSH_SUBSCRIPTION_FAILURE_LIMIT} times in a row. It's been removed"
+                );
+            }
+        },
+    }
+
+    Ok(())
+}
+
+/// Sends whatever's in the outbox every [`NOTIFIER_INTERVAL`]
+pub struct Notifier<T, C> {
+    pool: Pool,
+    transport: T,
+    clock: C,
+}
+
+impl<T: PushTransport, C: Clock> Notifier<T, C> {
+    pub fn new(pool: Pool, transport: T, clock: C) -> Self {
+        Self { pool, transport, clock }
+    }
+
+    pub async fn run(self) {
+        let mut interval = interval(NOTIFIER_INTERVAL);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.flush().await {
+                error!("Error sending push notifications: {e}");
+            }
+        }
+    }
+
+    /// Tries to send a batch of what's ready, dropping anything that's
+    /// expired. Returns how many were delivered
+    pub async fn flush(&self) -> Result<usize, ServerError<Nothing>> {
+        let now = self.clock.now();
+        let conn = self.pool.get().await.map_err(|e| other_error!("Getting a connection: {e}"))?;
+
+        let ready = conn
+            .interact(move |conn| {
+                let mut ready = Vec::new();
+                for queued in QueuedPush::fetch_ready::<Nothing>(conn, now, NOTIFIER_BATCH_SIZE)? {
+                    if queued.expiry_date <= now {
+                        debug!("Push {} expired after {} attempts", queued.id, queued.attempts);
+                        queued.delete::<Nothing>(conn)?;
+                        continue;
+                    }
+                    let push_subscription =
+                        PushSubscription::fetch_by_id(conn, queued.push_subscription_id)?;
+                    ready.push((queued, push_subscription));
+                }
+                Ok::<_, ServerError<Nothing>>(ready)
+            })
+            .await??;
+
+        if ready.is_empty() {
+            return Ok(0);
+        }
+
+        let results = join_all(ready.iter().map(|(queued, push_subscription)| async move {
+            self.transport.send(outgoing(queued, push_subscription, now)?).await
+        }))
+        .await;
+        let delivered = results.iter().filter(|r| r.is_ok()).count();
+
+        conn.interact(move |conn| {
+            let tx = conn.transaction()?;
+            for ((queued, _), result) in ready.into_iter().zip(results) {
+                record_result(&tx, queued, result, now)?;
+            }
+            tx.commit()?;
+
+            Ok::<_, ServerError<Nothing>>(())
+        })
+        .await??;
+
+        Ok(delivered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use chrono::Duration as ChronoDuration;
+    use shared::model::User;
+
+    use super::*;
+    use crate::test_support::{FakePushEndpoint, MockClock, TestApp};
+
+    async fn subscribe(app: &TestApp, endpoint: &FakePushEndpoint, user: &User, device: &str) {
+        let push_subscription =
+            PushSubscription::new(user.id, endpoint.subscription(device), Some(device.to_string()));
+        app.interact(move |conn| push_subscription.insert(conn).unwrap()).await;
+    }
+
+    async fn queue_for(app: &TestApp, user: &User, kind: NotificationKind, now: DateTime<Utc>) {
+        let user_id = user.id;
+        app.interact(move |conn| {
+            let push_subscriptions =
+                PushSubscription::fetch_for_user::<Nothing>(conn, &user_id).unwrap();
+            let notification = Notification {
+                title: "Hello".to_string(),
+                body: None,
+                icon: None,
+                sent: now,
+                url: None,
+                actions: Vec::new(),
+                data: None,
+            };
+            queue(conn, &push_subscriptions, kind, &notification, now).unwrap();
+        })
+        .await
+    }
+
+    async fn outbox(app: &TestApp) -> Vec<QueuedPush> {
+        app.interact(|conn| QueuedPush::fetch_all(conn).unwrap()).await
+    }
+
+    async fn subscriptions_of(app: &TestApp, user: &User) -> Vec<PushSubscription> {
+        let user_id = user.id;
+        app.interact(move |conn| PushSubscription::fetch_for_user::<Nothing>(conn, &user_id))
+            .await
+            .unwrap()
+    }
+
+    async fn setup() -> (TestApp, FakePushEndpoint, MockClock, Notifier<WebPushTransport, MockClock>)
+    {
+        let app = TestApp::new().await;
+        let endpoint = FakePushEndpoint::start().await;
+        let clock = MockClock::new(Utc::now());
+        let transport = WebPushTransport::new(app.state.vapid_private_key.clone()).unwrap();
+        let notifier = Notifier::new(app.state.pool.clone(), transport, clock.clone());
+        (app, endpoint, clock, notifier)
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_limit() {
+        assert_eq!(backoff(1), PUSH_RETRY_BASE_DELAY);
+        assert_eq!(backoff(2), PUSH_RETRY_BASE_DELAY * 2);
+        assert_eq!(backoff(3), PUSH_RETRY_BASE_DELAY * 4);
+        assert_eq!(backoff(100), PUSH_RETRY_MAX_DELAY);
+    }
+
+    #[tokio::test]
+    async fn queued_pushes_are_delivered_with_their_kinds_ttl_and_urgency() {
+        let (app, endpoint, clock, notifier) = setup().await;
+        let user = app.create_user("someone").await;
+        subscribe(&app, &endpoint, &user, "phone").await;
+        subscribe(&app, &endpoint, &user, "laptop").await;
+
+        queue_for(&app, &user, NotificationKind::UpcomingReminder, clock.now()).await;
+        assert_eq!(outbox(&app).await.len(), 2);
+
+        assert_eq!(notifier.flush().await.unwrap(), 2);
+        assert_eq!(outbox(&app).await, vec![]);
+
+        let received = endpoint.received();
+        assert_eq!(received.len(), 2);
+        for push in received {
+            assert_eq!(push.ttl, Some(ttl(NotificationKind::UpcomingReminder).as_secs() as u32));
+            assert_eq!(push.urgency.as_deref(), Some("high"));
+            assert!(push.body_len > 0);
+        }
+        for push_subscription in subscriptions_of(&app, &user).await {
+            assert!(push_subscription.last_success_date.is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_pushes_are_retried_with_backoff() {
+        let (app, endpoint, clock, notifier) = setup().await;
+        let user = app.create_user("someone").await;
+        subscribe(&app, &endpoint, &user, "phone").await;
+        endpoint.respond_with("phone", StatusCode::INTERNAL_SERVER_ERROR);
+        endpoint.respond_with("phone", StatusCode::SERVICE_UNAVAILABLE);
+
+        queue_for(&app, &user, NotificationKind::Update, clock.now()).await;
+
+        assert_eq!(notifier.flush().await.unwrap(), 0);
+        let queued = outbox(&app).await;
+        assert_eq!(queued[0].attempts, 1);
+        assert_eq!(queued[0].next_attempt_date, clock.now() + chrono_duration(backoff(1)));
+
+        // Nothing's tried again until the delay is up
+        assert_eq!(notifier.flush().await.unwrap(), 0);
+        assert_eq!(endpoint.received().len(), 1);
+
+        clock.advance(chrono_duration(backoff(1)));
+        assert_eq!(notifier.flush().await.unwrap(), 0);
+        let queued = outbox(&app).await;
+        assert_eq!(queued[0].attempts, 2);
+        assert_eq!(queued[0].next_attempt_date, clock.now() + chrono_duration(backoff(2)));
+
+        clock.advance(chrono_duration(backoff(2)));
+        assert_eq!(notifier.flush().await.unwrap(), 1);
+        assert_eq!(outbox(&app).await, vec![]);
+        let received = endpoint.received();
+        assert_eq!(received.len(), 3);
+        assert!(received.iter().all(|push| push.device == "phone"));
+        assert_eq!(subscriptions_of(&app, &user).await[0].failure_count, 0);
+    }
+
+    #[tokio::test]
+    async fn pushes_are_sent_in_batches() {
+        let (app, endpoint, clock, notifier) = setup().await;
+        let user = app.create_user("someone").await;
+        subscribe(&app, &endpoint, &user, "phone").await;
+
+        for _ in 0..NOTIFIER_BATCH_SIZE + 1 {
+            queue_for(&app, &user, NotificationKind::Update, clock.now()).await;
+        }
+
+        assert_eq!(notifier.flush().await.unwrap(), NOTIFIER_BATCH_SIZE as usize);
+        assert_eq!(outbox(&app).await.len(), 1);
+        assert_eq!(notifier.flush().await.unwrap(), 1);
+        assert_eq!(outbox(&app).await, vec![]);
+    }
+
+    #[tokio::test]
+    async fn expired_pushes_are_dropped() {
+        let (app, endpoint, clock, notifier) = setup().await;
+        let user = app.create_user("someone").await;
+        subscribe(&app, &endpoint, &user, "phone").await;
+        endpoint.respond_with("phone", StatusCode::INTERNAL_SERVER_ERROR);
+
+        queue_for(&app, &user, NotificationKind::UpcomingReminder, clock.now()).await;
+        assert_eq!(notifier.flush().await.unwrap(), 0);
+
+        clock.advance(chrono_duration(ttl(NotificationKind::UpcomingReminder)));
+        assert_eq!(notifier.flush().await.unwrap(), 0);
+        assert_eq!(outbox(&app).await, vec![]);
+        assert_eq!(endpoint.received().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn gone_and_failing_subscriptions_are_pruned() {
+        let (app, endpoint, clock, notifier) = setup().await;
+        let user = app.create_user("someone").await;
+        subscribe(&app, &endpoint, &user, "gone").await;
+        subscribe(&app, &endpoint, &user, "rejecting").await;
+        subscribe(&app, &endpoint, &user, "fine").await;
+        endpoint.respond_with("gone", StatusCode::GONE);
+
+        queue_for(&app, &user, NotificationKind::Update, clock.now()).await;
+        assert_eq!(notifier.flush().await.unwrap(), 2);
+        let devices = |subscriptions: Vec<PushSubscription>| {
+            let mut devices =
+                subscriptions.into_iter().filter_map(|s| s.device_label).collect::<Vec<_>>();
+            devices.sort();
+            devices
+        };
+        assert_eq!(devices(subscriptions_of(&app, &user).await), vec!["fine", "rejecting"]);
+
+        // Each notification that's refused counts as one failure
+        for _ in 0..PUSH_SUBSCRIPTION_FAILURE_LIMIT {
+            endpoint.respond_with("rejecting", StatusCode::PAYLOAD_TOO_LARGE);
+            queue_for(&app, &user, NotificationKind::Update, clock.now()).await;
+            notifier.flush().await.unwrap();
+            clock.advance(ChronoDuration::seconds(1));
+        }
+        assert_eq!(devices(subscriptions_of(&app, &user).await), vec!["fine"]);
+        assert_eq!(outbox(&app).await, vec![]);
+    }
+}
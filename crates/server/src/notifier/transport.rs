@@ -0,0 +1,71 @@
+use std::{future::Future, sync::Arc};
+
+use shared::model::PushNotificationSubscription;
+use tracing::debug;
+use web_push::{
+    ContentEncoding, IsahcWebPushClient, SubscriptionInfo, Urgency, VapidSignatureBuilder,
+    WebPushClient, WebPushError, WebPushMessage, WebPushMessageBuilder,
+};
+
+use crate::VapidPrivateKey;
+
+/// Everything needed to send one push notification
+#[derive(Debug)]
+pub struct OutgoingPush {
+    pub subscription: PushNotificationSubscription,
+    /// The json encoded notification
+    pub payload: Vec<u8>,
+    /// Seconds the push service should keep trying to deliver it for
+    pub ttl: u32,
+    pub urgency: Urgency,
+}
+
+/// How push notifications leave the server
+pub trait PushTransport: Send + Sync + 'static {
+    fn send(&self, push: OutgoingPush) -> impl Future<Output = Result<(), WebPushError>> + Send;
+}
+
+/// Sends to the push service the subscription belongs to, signed with the
+/// server's VAPID key
+#[derive(Clone)]
+pub struct WebPushTransport {
+    vapid_private_key: VapidPrivateKey,
+    client: Arc<IsahcWebPushClient>,
+}
+
+impl WebPushTransport {
+    pub fn new(vapid_private_key: VapidPrivateKey) -> Result<Self, WebPushError> {
+        Ok(Self { vapid_private_key, client: Arc::new(IsahcWebPushClient::new()?) })
+    }
+
+    fn message(&self, push: OutgoingPush) -> Result<WebPushMessage, WebPushError> {
+        let OutgoingPush { subscription, payload, ttl, urgency } = push;
+        let PushNotificationSubscription { endpoint, key: p256dh, auth } = subscription;
+        let subscription_info = SubscriptionInfo::new(endpoint, p256dh, auth);
+
+        let sig_builder =
+            VapidSignatureBuilder::from_pem(self.vapid_private_key.cursor(), &subscription_info)?
+                .build()?;
+
+        let mut message_builder = WebPushMessageBuilder::new(&subscription_info);
+        message_builder.set_payload(ContentEncoding::Aes128Gcm, &payload);
+        message_builder.set_vapid_signature(sig_builder);
+        message_builder.set_urgency(urgency);
+        message_builder.set_ttl(ttl);
+
+        message_builder.build()
+    }
+}
+
+impl PushTransport for WebPushTransport {
+    fn send(&self, push: OutgoingPush) -> impl Future<Output = Result<(), WebPushError>> + Send {
+        let message = self.message(push);
+        let client = self.client.clone();
+
+        async move {
+            client.send(message?).await?;
+            debug!("Push sent ok");
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,576 @@
+//! Push notifications reminding users about their planned sessions. What's
+//! been scheduled and sent is kept in the [`Reminder`] table so restarting the
+//! server neither drops nor repeats any. They're delivered by the
+//! [`Notifier`](crate::notifier::Notifier)
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Days, Duration, TimeZone, Utc};
+use chrono_tz::Tz;
+use client::session_path;
+use deadpool_sqlite::Pool;
+use rusqlite::Connection;
+use shared::{
+    api::{
+        error::{Nothing, ServerError},
+        payloads::{Notification, NotificationAction, NotificationData, NotificationKind},
+    },
+    model::{Model, PushSubscription, Reminder, ReminderKind, ReminderState, Session, User},
+    other_error,
+    types::Uuid,
+};
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{debug, error};
+
+use crate::{
+    chrono_duration,
+    constants::{
+        MISSED_REMINDER_EXPIRY, MISSED_REMINDER_HOUR, REMINDER_CHECK_INTERVAL, REMINDER_SNOOZE,
+        UPCOMING_REMINDER_LEAD_TIME,
+    },
+    notifier::queue,
+    Clock,
+};
+
+/// The user's timezone, UTC if they haven't set one. The server can't see the
+/// device's timezone that the client falls back to
+fn timezone(user: &User) -> Tz {
+    user.preferences.timezone.as_deref().and_then(|t| t.parse().ok()).unwrap_or(Tz::UTC)
+}
+
+/// When the reminder should go out for a session planned for `planned_date`
+fn due_date(kind: ReminderKind, planned_date: DateTime<Utc>, timezone: Tz) -> DateTime<Utc> {
+    match kind {
+        ReminderKind::Upcoming => planned_date - chrono_duration(UPCOMING_REMINDER_LEAD_TIME),
+        ReminderKind::Missed => {
+            let day_after = planned_date.with_timezone(&timezone).date_naive() + Days::new(1);
+            day_after
+                .and_hms_opt(MISSED_REMINDER_HOUR, 0, 0)
+                .and_then(|morning| timezone.from_local_datetime(&morning).earliest())
+                .map(|morning| morning.with_timezone(&Utc))
+                // The hour was skipped by a DST change
+                .unwrap_or(planned_date + Duration::days(1))
+        },
+    }
+}
+
+/// Whether it's too late for the reminder to be any use
+fn expired(reminder: &Reminder, now: DateTime<Utc>) -> bool {
+    if let Some(snoozed_until) = reminder.snoozed_until {
+        // The user asked for it so it's fine after the session's planned date
+        return now >= snoozed_until + chrono_duration(REMINDER_SNOOZE);
+    }
+
+    match reminder.kind {
+        ReminderKind::Upcoming => now >= reminder.planned_date,
+        ReminderKind::Missed => now >= reminder.due_date + chrono_duration(MISSED_REMINDER_EXPIRY),
+    }
+}
+
+fn notification_kind(kind: ReminderKind) -> NotificationKind {
+    match kind {
+        ReminderKind::Upcoming => NotificationKind::UpcomingReminder,
+        ReminderKind::Missed => NotificationKind::MissedReminder,
+    }
+}
+
+fn notification(reminder: &Reminder, timezone: Tz, now: DateTime<Utc>) -> Notification {
+    use NotificationAction::*;
+
+    let (title, body, actions) = match reminder.kind {
+        ReminderKind::Upcoming => (
+            "Workout coming up".to_string(),
+            format!(
+                "Your session is planned for {}",
+                reminder.planned_date.with_timezone(&timezone).format("%H:%M")
+            ),
+            vec![StartWorkout, Snooze, Skip],
+        ),
+        // Skipping would only dismiss it, which the notification can do
+        // already
+        ReminderKind::Missed => (
+            "You missed yesterday's session".to_string(),
+            "It's still there if you want to catch up today".to_string(),
+            vec![StartWorkout, Snooze],
+        ),
+    };
+
+    Notification {
+        title,
+        body: Some(body),
+        icon: None,
+        sent: now,
+        url: Some(session_path(reminder.session_id)),
+        actions,
+        data: Some(NotificationData::Reminder {
+            reminder_id: reminder.id,
+            session_id: reminder.session_id,
+        }),
+    }
+}
+
+/// Fetches users once per pass rather than once per reminder
+#[derive(Default)]
+struct Users(HashMap<Uuid, User>);
+
+impl Users {
+    fn get(&mut self, conn: &Connection, id: Uuid) -> Result<&User, ServerError<Nothing>> {
+        if !self.0.contains_key(&id) {
+            self.0.insert(id, User::fetch_by_id(conn, id)?);
+        }
+        Ok(&self.0[&id])
+    }
+}
+
+/// Makes sure every planned session that hasn't been done has its reminders,
+/// moving them along with the session if it's been rescheduled
+pub fn schedule(conn: &Connection, now: DateTime<Utc>) -> Result<(), ServerError<Nothing>> {
+    // Anything older would only have expired missed reminders. The extra day
+    // covers the user's timezone being a day ahead
+    let since = now - Duration::days(2) - chrono_duration(MISSED_REMINDER_EXPIRY);
+    // Anything later isn't due until after the next pass
+    let until = now + chrono_duration(UPCOMING_REMINDER_LEAD_TIME + REMINDER_CHECK_INTERVAL);
+
+    let sessions = Session::fetch_all_planned_not_performed(conn, since, until)?;
+    let session_ids = sessions.iter().map(|s| s.id).collect::<Vec<_>>();
+    let mut reminders_by_session = HashMap::<_, Vec<_>>::new();
+    for reminder in Reminder::fetch_for_sessions::<Nothing>(conn, &session_ids)? {
+        reminders_by_session.entry(reminder.session_id).or_default().push(reminder);
+    }
+
+    let mut users = Users::default();
+    for session in sessions {
+        let timezone = timezone(users.get(conn, session.user_id)?);
+        let reminders = reminders_by_session.remove(&session.id).unwrap_or_default();
+
+        for kind in [ReminderKind::Upcoming, ReminderKind::Missed] {
+            let due_date = due_date(kind, session.planned_date, timezone);
+
+            match reminders.iter().find(|r| r.kind == kind) {
+                None => {
+                    Reminder::new(
+                        session.user_id,
+                        session.id,
+                        kind,
+                        session.planned_date,
+                        due_date,
+                        now,
+                    )
+                    .insert(conn)?;
+                },
+                // A session that's moved gets reminded about again. One that's
+                // still waiting follows any change to the user's timezone
+                Some(reminder)
+                    if reminder.planned_date != session.planned_date
+                        || (reminder.state == ReminderState::Pending
+                            && reminder.due_date != due_date) =>
+                {
+                    let mut reminder = reminder.clone();
+                    if reminder.planned_date != session.planned_date {
+                        reminder.snoozed_until = None;
+                    }
+                    reminder.planned_date = session.planned_date;
+                    reminder.due_date = due_date;
+                    reminder.set_state::<Nothing>(conn, ReminderState::Pending, now)?;
+                },
+                Some(_) => {},
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A reminder that's ready to go out and the devices it's going to
+#[derive(Debug)]
+pub struct DueReminder {
+    pub reminder: Reminder,
+    pub notification: Notification,
+    pub push_subscriptions: Vec<PushSubscription>,
+}
+
+/// The pending reminders that should be sent now. Ones that aren't needed any
+/// more are marked as skipped and ones in the user's quiet hours are left
+/// until they're over
+pub fn fetch_due(
+    conn: &Connection,
+    now: DateTime<Utc>,
+) -> Result<Vec<DueReminder>, ServerError<Nothing>> {
+    let mut users = Users::default();
+    let mut due = Vec::new();
+
+    for mut reminder in Reminder::fetch_pending::<Nothing>(conn, now)? {
+        let user = users.get(conn, reminder.user_id)?;
+        let preferences = &user.preferences.notifications;
+        let timezone = timezone(user);
+        let session = Session::fetch_by_id_maybe(conn, reminder.session_id)?;
+        // A session moved out of the scheduling window takes its reminders with
+        // it, they're made pending again once it's back in range
+        let stale = match session {
+            Some(session) => {
+                session.performed_date.is_some() || session.planned_date != reminder.planned_date
+            },
+            None => true,
+        };
+
+        if stale || !preferences.session_reminders || expired(&reminder, now) {
+            debug!("Skipping {:?} reminder {}", reminder.kind, reminder.id);
+            reminder.set_state::<Nothing>(conn, ReminderState::Skipped, now)?;
+            continue;
+        }
+
+        let local_time = now.with_timezone(&timezone).time();
+        if preferences.quiet_hours.is_some_and(|q| q.contains(local_time)) {
+            continue;
+        }
+
+        let push_subscriptions = PushSubscription::fetch_for_user::<Nothing>(conn, &user.id)?;
+        if push_subscriptions.is_empty() {
+            reminder.set_state::<Nothing>(conn, ReminderState::Skipped, now)?;
+            continue;
+        }
+
+        let notification = notification(&reminder, timezone, now);
+        due.push(DueReminder { reminder, notification, push_subscriptions });
+    }
+
+    Ok(due)
+}
+
+/// Checks for reminders every [`REMINDER_CHECK_INTERVAL`] and queues the ones
+/// that are due
+pub struct ReminderScheduler<C> {
+    pool: Pool,
+    clock: C,
+}
+
+impl<C: Clock> ReminderScheduler<C> {
+    pub fn new(pool: Pool, clock: C) -> Self {
+        Self { pool, clock }
+    }
+
+    pub async fn run(self) {
+        let mut interval = interval(REMINDER_CHECK_INTERVAL);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.tick().await {
+                error!("Error sending reminders: {e}");
+            }
+        }
+    }
+
+    /// Schedules reminders for any new or moved sessions and queues the ones
+    /// that are due. Returns how many were queued
+    pub async fn tick(&self) -> Result<usize, ServerError<Nothing>> {
+        let now = self.clock.now();
+        let conn = self.pool.get().await.map_err(|e| other_error!("Getting a connection: {e}"))?;
+
+        conn.interact(move |conn| {
+            let tx = conn.transaction()?;
+            schedule(&tx, now)?;
+
+            let due = fetch_due(&tx, now)?;
+            let queued = due.len();
+            // Queued in the same transaction as it's marked sent so it can't go
+            // out twice
+            for DueReminder { mut reminder, notification, push_subscriptions } in due {
+                debug!(
+                    "Sending {:?} reminder {} to user_id {}",
+                    reminder.kind, reminder.id, reminder.user_id
+                );
+                let kind = notification_kind(reminder.kind);
+                queue(&tx, &push_subscriptions, kind, &notification, now)?;
+                reminder.set_state::<Nothing>(&tx, ReminderState::Sent, now)?;
+            }
+            tx.commit()?;
+
+            Ok::<_, ServerError<Nothing>>(queued)
+        })
+        .await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveTime;
+    use shared::model::{PushNotificationSubscription, QueuedPush, QuietHours};
+
+    use super::*;
+    use crate::test_support::{MockClock, TestApp};
+
+    /// The titles of everything in the outbox, oldest first
+    async fn queued_titles(app: &TestApp) -> Vec<String> {
+        let mut queued = app.interact(|conn| QueuedPush::fetch_all(conn).unwrap()).await;
+        queued.sort_by_key(|q| q.creation_date);
+        queued.into_iter().map(|q| q.notification.title).collect()
+    }
+
+    const UPCOMING: &str = "Workout coming up";
+    const MISSED: &str = "You missed yesterday's session";
+
+    fn date(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    /// A user with a push subscription and a session planned for
+    /// `planned_date` in a plan they're following
+    async fn user_with_session(
+        app: &TestApp,
+        username: &str,
+        planned_date: DateTime<Utc>,
+    ) -> (User, Session) {
+        let user = app.create_user(username).await;
+        let plan = app.create_plan(&user).await;
+        let plan_instance = app.create_plan_instance(&user, &plan).await;
+        let session = app.create_session(&user, Some(&plan_instance), planned_date).await;
+        subscribe(app, &user).await;
+
+        (user, session)
+    }
+
+    /// Gives the user a push subscription for reminders to go to
+    async fn subscribe(app: &TestApp, user: &User) {
+        let user_id = user.id;
+        app.interact(move |conn| {
+            PushSubscription::new(
+                user_id,
+                PushNotificationSubscription {
+                    endpoint: format!("https://push.example.com/{user_id}"),
+                    key: "key".to_string(),
+                    auth: "auth".to_string(),
+                },
+                None,
+            )
+            .insert(conn)
+            .unwrap()
+        })
+        .await;
+    }
+
+    async fn update_user(app: &TestApp, mut user: User, f: impl FnOnce(&mut User)) {
+        f(&mut user);
+        app.interact(move |conn| user.update::<Nothing>(conn).unwrap()).await;
+    }
+
+    async fn update_session(app: &TestApp, mut session: Session, f: impl FnOnce(&mut Session)) {
+        f(&mut session);
+        app.interact(move |conn| session.update::<Nothing>(conn).unwrap()).await;
+    }
+
+    async fn reminder_states(
+        app: &TestApp,
+        session: &Session,
+    ) -> Vec<(ReminderKind, ReminderState)> {
+        let id = session.id;
+        let mut reminders = app
+            .interact(move |conn| Reminder::fetch_for_session::<Nothing>(conn, &id).unwrap())
+            .await
+            .into_iter()
+            .map(|r| (r.kind, r.state))
+            .collect::<Vec<_>>();
+        reminders.sort_by_key(|(kind, _)| *kind == ReminderKind::Missed);
+        reminders
+    }
+
+    #[tokio::test]
+    async fn reminders_are_sent_once_even_across_restarts() {
+        let app = TestApp::new().await;
+        let planned_date = date("2026-03-02T18:00:00Z");
+        user_with_session(&app, "someone", planned_date).await;
+
+        let clock = MockClock::new(planned_date - Duration::hours(2));
+        let scheduler = ReminderScheduler::new(app.state.pool.clone(), clock.clone());
+
+        assert_eq!(scheduler.tick().await.unwrap(), 0);
+
+        clock.set(planned_date - Duration::minutes(59));
+        assert_eq!(scheduler.tick().await.unwrap(), 1);
+        assert_eq!(scheduler.tick().await.unwrap(), 0);
+
+        // A new scheduler only has the database to go on
+        let restarted = ReminderScheduler::new(app.state.pool.clone(), clock.clone());
+        assert_eq!(restarted.tick().await.unwrap(), 0);
+
+        clock.set(date("2026-03-03T09:00:00Z"));
+        assert_eq!(restarted.tick().await.unwrap(), 1);
+        assert_eq!(scheduler.tick().await.unwrap(), 0);
+
+        assert_eq!(queued_titles(&app).await, vec![UPCOMING, MISSED]);
+    }
+
+    #[tokio::test]
+    async fn missed_reminders_wait_for_quiet_hours_in_the_users_timezone() {
+        let app = TestApp::new().await;
+        // 19:00 in Tokyo, which is UTC+9 all year round
+        let planned_date = date("2026-01-10T10:00:00Z");
+        let (user, session) = user_with_session(&app, "someone", planned_date).await;
+        let time = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+        update_user(&app, user, |user| {
+            user.preferences.timezone = Some("Asia/Tokyo".to_string());
+            user.preferences.notifications.quiet_hours =
+                Some(QuietHours { start: time(8), end: time(12) });
+        })
+        .await;
+
+        // The upcoming one is already too late
+        let clock = MockClock::new(planned_date + Duration::minutes(1));
+        let scheduler = ReminderScheduler::new(app.state.pool.clone(), clock.clone());
+        assert_eq!(scheduler.tick().await.unwrap(), 0);
+        assert_eq!(reminder_states(&app, &session).await, vec![
+            (ReminderKind::Upcoming, ReminderState::Skipped),
+            (ReminderKind::Missed, ReminderState::Pending),
+        ]);
+
+        // 09:00 the next morning in Tokyo is during quiet hours
+        clock.set(date("2026-01-11T00:00:00Z"));
+        assert_eq!(scheduler.tick().await.unwrap(), 0);
+
+        clock.set(date("2026-01-11T03:00:00Z"));
+        assert_eq!(scheduler.tick().await.unwrap(), 1);
+        assert_eq!(queued_titles(&app).await, vec![MISSED]);
+    }
+
+    #[tokio::test]
+    async fn reminders_are_skipped_when_not_wanted() {
+        let app = TestApp::new().await;
+        let planned_date = date("2026-03-02T18:00:00Z");
+        let (_, performed) = user_with_session(&app, "performed", planned_date).await;
+        let (user, _) = user_with_session(&app, "unwanted", planned_date).await;
+
+        update_session(&app, performed.clone(), |session| {
+            session.performed_date = Some(planned_date - Duration::hours(3));
+        })
+        .await;
+        update_user(&app, user, |user| user.preferences.notifications.session_reminders = false)
+            .await;
+
+        let clock = MockClock::new(planned_date - Duration::minutes(30));
+        let scheduler = ReminderScheduler::new(app.state.pool.clone(), clock.clone());
+        assert_eq!(scheduler.tick().await.unwrap(), 0);
+
+        // Performed sessions don't get any
+        assert_eq!(reminder_states(&app, &performed).await, vec![]);
+
+        clock.set(date("2026-03-03T09:00:00Z"));
+        assert_eq!(scheduler.tick().await.unwrap(), 0);
+        assert!(queued_titles(&app).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rescheduled_sessions_are_reminded_about_again() {
+        let app = TestApp::new().await;
+        let planned_date = date("2026-03-02T18:00:00Z");
+        let (_, session) = user_with_session(&app, "someone", planned_date).await;
+
+        let clock = MockClock::new(planned_date - Duration::minutes(30));
+        let scheduler = ReminderScheduler::new(app.state.pool.clone(), clock.clone());
+        assert_eq!(scheduler.tick().await.unwrap(), 1);
+
+        let moved_date = planned_date + Duration::hours(4);
+        update_session(&app, session.clone(), |session| session.planned_date = moved_date).await;
+        assert_eq!(scheduler.tick().await.unwrap(), 0);
+
+        clock.set(moved_date - Duration::minutes(30));
+        assert_eq!(scheduler.tick().await.unwrap(), 1);
+        assert_eq!(queued_titles(&app).await, vec![UPCOMING, UPCOMING]);
+    }
+
+    #[tokio::test]
+    async fn sessions_moved_further_out_take_their_reminders_with_them() {
+        let app = TestApp::new().await;
+        let planned_date = date("2026-03-02T18:00:00Z");
+        let (_, session) = user_with_session(&app, "someone", planned_date).await;
+
+        // Scheduled, but not due yet
+        let clock = MockClock::new(planned_date - Duration::minutes(61));
+        let scheduler = ReminderScheduler::new(app.state.pool.clone(), clock.clone());
+        assert_eq!(scheduler.tick().await.unwrap(), 0);
+
+        let moved_date = planned_date + Duration::days(7);
+        update_session(&app, session.clone(), |session| session.planned_date = moved_date).await;
+
+        clock.set(planned_date - Duration::minutes(59));
+        assert_eq!(scheduler.tick().await.unwrap(), 0);
+        assert_eq!(reminder_states(&app, &session).await, vec![
+            (ReminderKind::Upcoming, ReminderState::Skipped),
+            (ReminderKind::Missed, ReminderState::Pending),
+        ]);
+
+        clock.set(moved_date - Duration::minutes(30));
+        assert_eq!(scheduler.tick().await.unwrap(), 1);
+        assert_eq!(queued_titles(&app).await, vec![UPCOMING]);
+    }
+
+    #[tokio::test]
+    async fn ad_hoc_sessions_are_reminded_about_once_theyre_close() {
+        let app = TestApp::new().await;
+        let planned_date = date("2026-03-02T18:00:00Z");
+        let user = app.create_user("someone").await;
+        subscribe(&app, &user).await;
+        let session = app.create_session(&user, None, planned_date).await;
+
+        let clock = MockClock::new(planned_date - Duration::days(7));
+        let scheduler = ReminderScheduler::new(app.state.pool.clone(), clock.clone());
+        assert_eq!(scheduler.tick().await.unwrap(), 0);
+        assert_eq!(reminder_states(&app, &session).await, vec![]);
+
+        clock.set(planned_date - Duration::minutes(30));
+        assert_eq!(scheduler.tick().await.unwrap(), 1);
+        assert_eq!(reminder_states(&app, &session).await, vec![
+            (ReminderKind::Upcoming, ReminderState::Sent),
+            (ReminderKind::Missed, ReminderState::Pending),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn snoozed_reminders_are_sent_again() {
+        let app = TestApp::new().await;
+        let planned_date = date("2026-03-02T18:00:00Z");
+        let (_, session) = user_with_session(&app, "someone", planned_date).await;
+
+        let clock = MockClock::new(planned_date - Duration::minutes(59));
+        let scheduler = ReminderScheduler::new(app.state.pool.clone(), clock.clone());
+        assert_eq!(scheduler.tick().await.unwrap(), 1);
+
+        // What the snooze action on the notification does
+        let now = clock.now();
+        let session_id = session.id;
+        let reminder = app
+            .interact(move |conn| {
+                let mut reminder = Reminder::fetch_for_session::<Nothing>(conn, &session_id)
+                    .unwrap()
+                    .into_iter()
+                    .find(|r| r.kind == ReminderKind::Upcoming)
+                    .unwrap();
+                reminder
+                    .snooze::<Nothing>(conn, now + chrono_duration(REMINDER_SNOOZE), now)
+                    .unwrap();
+                reminder
+            })
+            .await;
+
+        clock.advance(Duration::minutes(30));
+        assert_eq!(scheduler.tick().await.unwrap(), 0);
+
+        // It's after the session's planned date but the user asked for it
+        clock.set(now + Duration::hours(1));
+        assert_eq!(scheduler.tick().await.unwrap(), 1);
+        assert_eq!(queued_titles(&app).await, vec![UPCOMING, UPCOMING]);
+
+        let mut queued = app.interact(|conn| QueuedPush::fetch_all(conn).unwrap()).await;
+        queued.sort_by_key(|q| q.creation_date);
+        let notification = queued.pop().unwrap().notification;
+        assert_eq!(notification.url, Some(format!("/?session={session_id}")));
+        assert_eq!(notification.actions, vec![
+            NotificationAction::StartWorkout,
+            NotificationAction::Snooze,
+            NotificationAction::Skip
+        ]);
+        assert_eq!(
+            notification.data,
+            Some(NotificationData::Reminder { reminder_id: reminder.id, session_id })
+        );
+    }
+}
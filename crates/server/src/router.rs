@@ -0,0 +1,315 @@
+use std::time::Duration;
+
+use axum::{
+    extract::{DefaultBodyLimit, MatchedPath, Request},
+    http::{HeaderName, HeaderValue, Method, StatusCode, Uri},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{delete, get, patch, post},
+    Router,
+};
+use client::ROUTE_URLS;
+use shared::api::{payloads::MAX_BACKUP_SIZE, Auth, Object, CSRF_HEADER};
+use tower::ServiceBuilder;
+use tower_http::{
+    classify::ServerErrorsFailureClass,
+    compression::CompressionLayer,
+    cors::CorsLayer,
+    services::{ServeDir, ServeFile},
+    set_header::SetResponseHeaderLayer,
+    trace::TraceLayer,
+};
+use tower_sessions::{cookie::time::Duration as CookieDuration, Expiry, SessionManagerLayer};
+use tracing::{info, info_span, Span};
+
+use crate::{
+    middleware::{track_login_session, CsrfLayer, RegenerateToken},
+    routes::{
+        auth::*,
+        backup::{fetch_backup, upload_backup},
+        logging,
+        notifications::{
+            reminder_action, remove_push_subscription, update_push_subscription, vapid,
+        },
+        ping::ping,
+        rtc::offer_handler,
+        training::*,
+        websocket::websocket_handler,
+    },
+    AppError, AppState,
+};
+
+/// All the routes and the layers around them
+pub fn router(state: AppState) -> Result<Router, anyhow::Error> {
+    let args = state.args.clone();
+    let session_store = state.session_store.clone();
+    let log_span_events = args.log_span_events;
+
+    // Map all routes the client can handle to the index.html
+    let client_routes = {
+        let mut router = Router::new();
+        for path in ROUTE_URLS.iter().filter(|p| **p != "/") {
+            router = router.nest_service(path, ServeFile::new(args.assets_dir.join("index.html")));
+        }
+        router
+    };
+
+    Ok(Router::new()
+        .nest(Object::Log.path(), logging::router())
+        .merge(client_routes)
+        // User/auth routes
+        .route(Auth::RegisterStart.path(), post(register_start))
+        .route(Auth::RegisterFinish.path(), post(register_finish))
+        .route(Auth::LoginStart.path(), post(login_start))
+        .route(Auth::LoginFinish.path(), post(login_finish))
+        .route(Auth::RegisterNewKeyStart.path(), post(register_new_key_start))
+        .route(Auth::RegisterNewKeyFinish.path(), post(register_new_key_finish))
+        .route(Auth::TemporaryLogin.path(), get(temporary_login))
+        .route(Auth::TemporaryLoginPin.path(), post(temporary_login_pin))
+        .route(Object::User.path(), get(fetch_user).patch(update_profile))
+        .route(Object::Preferences.path(), get(fetch_preferences).patch(update_preferences))
+        .route(Auth::CreateTemporaryLogin.path(), post(create_temporary_login))
+        .route(Auth::RevokeTemporaryLogin.path(), post(revoke_temporary_login))
+        .route(Object::QrCodeId.path(), get(generate_qr_code))
+        .route(Object::Credential.path(), get(list_credentials))
+        .route(Object::CredentialId.path(), patch(rename_credential).delete(delete_credential))
+        .route(Auth::Recover.path(), post(recover))
+        .route(Auth::RecoveryCodes.path(), post(regenerate_recovery_codes))
+        .route(Auth::Logout.path(), post(logout))
+        .route(Object::LoginSession.path(), get(list_login_sessions))
+        .route(Object::LoginSessionId.path(), delete(revoke_login_session))
+        .route(Auth::PairingStart.path(), post(start_pairing))
+        .route(Auth::PairingQrCode.path(), get(pairing_qr_code))
+        .route(Object::PairingId.path(), get(fetch_pairing))
+        .route(Auth::PairingDecision.path(), post(decide_pairing))
+        .route(Auth::PairingFinish.path(), post(finish_pairing))
+        .route(Auth::AccountExport.path(), get(export_account))
+        .route(Auth::DeleteAccountStart.path(), post(delete_account_start))
+        .route(Auth::DeleteAccountFinish.path(), post(delete_account_finish))
+        // Notification routes
+        .route(Object::Vapid.path(), get(vapid))
+        .route(
+            Object::PushSubscription.path(),
+            post(update_push_subscription).delete(remove_push_subscription),
+        )
+        .route(Object::ReminderId.path(), post(reminder_action))
+        .route(Object::Ping.path(), get(ping))
+        .route(Object::Websocket.path(), get(websocket_handler))
+        .route(Object::RtcOffer.path(), post(offer_handler))
+        // Exercise & plan routes
+        .route(Object::Exercise.path(), get(list_exercises).post(create_exercise))
+        .route(
+            Object::ExerciseId.path(),
+            get(fetch_exercise).patch(update_exercise).delete(delete_exercise),
+        )
+        .route(Object::ExerciseGroup.path(), get(list_exercise_groups).post(create_exercise_group))
+        .route(
+            Object::ExerciseGroupId.path(),
+            get(fetch_exercise_group).patch(update_exercise_group).delete(delete_exercise_group),
+        )
+        .route(Object::Plan.path(), get(list_plans).post(create_plan))
+        .route(Object::PlanId.path(), get(fetch_plan).patch(update_plan).delete(delete_plan))
+        .route(Object::PlanInstance.path(), get(list_plan_instances).post(create_plan_instance))
+        .route(
+            Object::PlanInstanceId.path(),
+            get(fetch_plan_instance).patch(update_plan_instance).delete(delete_plan_instance),
+        )
+        .route(Object::Session.path(), get(list_sessions).post(create_session))
+        .route(
+            Object::SessionId.path(),
+            get(fetch_session).patch(update_session).delete(delete_session),
+        )
+        .route(
+            Object::SessionExercise.path(),
+            get(list_session_exercises).post(create_session_exercise),
+        )
+        .route(
+            Object::SessionExerciseId.path(),
+            get(fetch_session_exercise)
+                .patch(update_session_exercise)
+                .delete(delete_session_exercise),
+        )
+        // Backups are a whole database so they get more room than the default body limit
+        .route(
+            Object::Backup.path(),
+            get(fetch_backup)
+                .post(upload_backup)
+                .layer(DefaultBodyLimit::max(MAX_BACKUP_SIZE + 1024)),
+        )
+        .nest_service(
+            "/wasm/service_worker.js",
+            ServiceBuilder::new()
+                .layer(SetResponseHeaderLayer::if_not_present(
+                    HeaderName::from_static("service-worker-allowed"),
+                    HeaderValue::from_static("/"),
+                ))
+                .service(ServeFile::new(args.assets_dir.join("wasm/service_worker.js"))),
+        )
+        .nest_service("/", ServeDir::new(&args.assets_dir))
+        .layer(middleware::map_response(fallback_layer))
+        // Inside the session layer so it can see who the handlers logged in
+        .layer(middleware::from_fn_with_state(state.clone(), track_login_session))
+        .layer(
+            CsrfLayer::new()
+                .regenerate(RegenerateToken::PerSession)
+                .request_header(CSRF_HEADER)
+                .response_header(CSRF_HEADER),
+        )
+        .layer(
+            ServiceBuilder::new()
+                .layer(
+                    TraceLayer::new_for_http()
+                        .make_span_with(|request: &Request<_>| {
+                            let span = info_span!(
+                                "http_log",
+                                status = tracing::field::Empty,
+                                method = ?request.method(),
+                                matched_path = tracing::field::Empty,
+                                path = tracing::field::Empty,
+                            );
+
+                            if let Some(matched_path) =
+                                request.extensions().get::<MatchedPath>().map(MatchedPath::as_str)
+                            {
+                                span.record("matched_path", matched_path);
+                            } else {
+                                // Fallback if the path isn't matched
+                                span.record("path", request.uri().to_string());
+                            }
+
+                            span
+                        })
+                        .on_response(move |response: &Response, _latency: Duration, span: &Span| {
+                            span.record("status", response.status().to_string());
+                            // If enter/exit events are off we want to make sure
+                            // http_log
+                            // generates something anyway
+                            if !log_span_events {
+                                info!(target: "http_log", parent: span, "");
+                            }
+                        })
+                        .on_failure(
+                            move |error: ServerErrorsFailureClass,
+                                  _latency: Duration,
+                                  span: &Span| {
+                                if let ServerErrorsFailureClass::StatusCode(code) = error {
+                                    span.record("status", code.to_string());
+                                }
+                                // If enter/exit events are off we want to make sure
+                                // http_log
+                                // generates something anyway
+                                if !log_span_events {
+                                    info!(target: "http_log", parent: span, "");
+                                }
+                            },
+                        ),
+                )
+                .layer(
+                    SessionManagerLayer::new(session_store)
+                        .with_secure(args.secure_sessions)
+                        .with_expiry(Expiry::OnInactivity(CookieDuration::days(
+                            args.session_expiry_days,
+                        ))),
+                )
+                .layer(SetResponseHeaderLayer::if_not_present(
+                    HeaderName::from_static("cross-origin-opener-policy"),
+                    HeaderValue::from_static("same-origin"),
+                ))
+                .layer(SetResponseHeaderLayer::if_not_present(
+                    HeaderName::from_static("cross-origin-embedder-policy"),
+                    HeaderValue::from_static("require-corp"),
+                ))
+                .layer(
+                    CorsLayer::new()
+                        .allow_methods([Method::GET, Method::POST, Method::DELETE])
+                        .allow_origin(args.origin.parse::<HeaderValue>()?),
+                ),
+        )
+        .layer(CompressionLayer::new())
+        .with_state(state))
+}
+
+async fn fallback_layer(uri: Uri, method: Method, response: Response) -> impl IntoResponse {
+    let code = response.status();
+
+    match code {
+        StatusCode::NOT_FOUND => Err(AppError::new(code, format!("Not found: {}", uri))),
+        StatusCode::METHOD_NOT_ALLOWED => {
+            Err(AppError::new(code, format!("Method not allowed: {}: {}", method, uri)))
+        },
+
+        _ => Ok(response),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{Method, Request, StatusCode},
+    };
+    use shared::{
+        api::{Auth, Object, CSRF_HEADER},
+        model::LoginUser,
+    };
+
+    use crate::test_support::{body_string, TestApp};
+
+    fn post_with_token(path: &str, token: Option<&str>) -> Request<Body> {
+        let builder = Request::builder().method(Method::POST).uri(path);
+        let builder = match token {
+            Some(token) => builder.header(CSRF_HEADER, token),
+            None => builder,
+        };
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn safe_requests_are_given_a_csrf_token() {
+        let mut app = TestApp::new().await;
+
+        let response = app.get(Object::Ping.path()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().contains_key(CSRF_HEADER));
+    }
+
+    #[tokio::test]
+    async fn unsafe_requests_need_the_csrf_token() {
+        let mut app = TestApp::new().await;
+        let path = Auth::LoginStart.path();
+
+        let response = app.send(post_with_token(path, None)).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        // Even a rejection hands out a token to try again with
+        assert!(response.headers().contains_key(CSRF_HEADER));
+
+        let response = app.send(post_with_token(path, Some("not the token"))).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // Through to the handler which doesn't know the user
+        let response = app.post(path, &LoginUser::new("nobody")).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn csrf_tokens_only_work_for_their_session() {
+        let mut app = TestApp::new().await;
+        let mut other = app.client();
+
+        let response = app.get(Object::Ping.path()).await;
+        let token = response.headers()[CSRF_HEADER].to_str().unwrap().to_string();
+        other.get(Object::Ping.path()).await;
+
+        let response = other.send(post_with_token(Auth::LoginStart.path(), Some(&token))).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn unknown_paths_are_not_found() {
+        let mut app = TestApp::new().await;
+
+        let response = app.get("/api/nothing_here").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(body_string(response).await, "Not found: /api/nothing_here");
+    }
+}
@@ -0,0 +1,503 @@
+//! Everything the server holds about a user, to download, and deleting their
+//! account. Deleting is confirmed by logging in with a passkey again and
+//! relies on the foreign keys cascading from the user to everything they own,
+//! once the plans other users follow have been handed over
+
+use std::str::FromStr;
+
+use axum::{extract::State, http::header, response::IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use shared::{
+    api::{
+        error::{Nothing, ServerError},
+        payloads::{CredentialInfo, DatabaseBackup, LoginSessionInfo, PushSubscriptionInfo},
+        response_errors::AccountError,
+    },
+    model::{
+        Backup, Credential, Exercise, ExerciseGroup, ExerciseGroupMember, ExerciseGroupMemberIden,
+        LoginSession, Model, Plan, PlanExerciseGroup, PlanExerciseGroupIden, PlanIden,
+        PlanInstance, PlanInstanceIden, PushSubscription, RecoveryCode, Reminder, Session,
+        SessionExercise, TemporaryLoginAudit, User, UserExercise, UserExerciseIden,
+    },
+    types::Uuid,
+};
+use tower_sessions::{session::Id, SessionStore};
+use tower_sessions_deadpool_sqlite_store::DeadpoolSqliteStore;
+use tracing::{info, warn};
+use webauthn_rs::prelude::{PublicKeyCredential, RequestChallengeResponse};
+
+use super::login_session::{send_logout, store_error};
+use crate::{
+    db::DatabaseConnection, ClientsBySessionId, PasskeyAuthenticationState, SessionId,
+    SessionValue, UserState, Webauthn,
+};
+
+/// Everything about a user. Passkeys, recovery codes and push subscriptions
+/// are only described, the secrets themselves stay on the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountExport {
+    pub export_date: DateTime<Utc>,
+    pub user: User,
+    pub credentials: Vec<CredentialInfo>,
+    pub unused_recovery_codes: usize,
+    pub login_sessions: Vec<LoginSessionInfo>,
+    pub push_subscriptions: Vec<PushSubscriptionInfo>,
+    pub reminders: Vec<Reminder>,
+    pub temporary_login_audit: Vec<TemporaryLoginAudit>,
+    /// Only the ones the user owns, not the global ones
+    pub exercises: Vec<Exercise>,
+    pub exercise_groups: Vec<ExerciseGroup>,
+    pub exercise_group_members: Vec<ExerciseGroupMember>,
+    pub user_exercises: Vec<UserExercise>,
+    pub plans: Vec<Plan>,
+    pub plan_exercise_groups: Vec<PlanExerciseGroup>,
+    pub plan_instances: Vec<PlanInstance>,
+    pub sessions: Vec<Session>,
+    pub session_exercises: Vec<SessionExercise>,
+    pub backup: Option<DatabaseBackup>,
+}
+
+impl AccountExport {
+    fn fetch(
+        conn: &Connection,
+        user_id: &Uuid,
+        current_session_id: Option<&str>,
+    ) -> Result<Self, ServerError<Nothing>> {
+        let owned = |owner_id: &Option<Uuid>| owner_id.as_ref() == Some(user_id);
+
+        let exercises = Exercise::fetch_all_for_user(conn, user_id)?
+            .into_iter()
+            .filter(|e| owned(&e.owner_id))
+            .collect::<Vec<_>>();
+        let exercise_groups = ExerciseGroup::fetch_all_for_user(conn, user_id)?
+            .into_iter()
+            .filter(|g| owned(&g.owner_id))
+            .collect::<Vec<_>>();
+        let mut exercise_group_members = Vec::new();
+        for group in &exercise_groups {
+            exercise_group_members.extend(ExerciseGroupMember::fetch_all_by_column(
+                conn,
+                group.id,
+                ExerciseGroupMemberIden::GroupId,
+            )?);
+        }
+
+        let plans = Plan::fetch_all_by_column(conn, *user_id, PlanIden::OwnerId)?;
+        let mut plan_exercise_groups = Vec::new();
+        for plan in &plans {
+            plan_exercise_groups.extend(PlanExerciseGroup::fetch_all_by_column(
+                conn,
+                plan.id,
+                PlanExerciseGroupIden::PlanId,
+            )?);
+        }
+
+        let backup = Backup::fetch_for_user(conn, user_id)?.map(|b| DatabaseBackup {
+            schema_version: b.schema_version,
+            creation_date: b.creation_date,
+            data: b.data,
+        });
+
+        Ok(Self {
+            export_date: Utc::now(),
+            user: User::fetch_by_id(conn, *user_id)?,
+            credentials: Credential::fetch_for_user(conn, user_id)?
+                .iter()
+                .map(CredentialInfo::from)
+                .collect(),
+            unused_recovery_codes: RecoveryCode::fetch_unused_for_user(conn, user_id)?.len(),
+            login_sessions: LoginSession::fetch_for_user(conn, user_id)?
+                .iter()
+                .map(|s| LoginSessionInfo::new(s, current_session_id == Some(&s.session_id)))
+                .collect(),
+            push_subscriptions: PushSubscription::fetch_for_user(conn, user_id)?
+                .iter()
+                .map(PushSubscriptionInfo::from)
+                .collect(),
+            reminders: Reminder::fetch_for_user(conn, user_id)?,
+            temporary_login_audit: TemporaryLoginAudit::fetch_for_user(conn, user_id)?,
+            exercises,
+            exercise_groups,
+            exercise_group_members,
+            user_exercises: UserExercise::fetch_all_by_column(
+                conn,
+                *user_id,
+                UserExerciseIden::UserId,
+            )?,
+            plans,
+            plan_exercise_groups,
+            plan_instances: PlanInstance::fetch_all_for_user(conn, user_id)?,
+            sessions: Session::fetch_all_for_user(conn, user_id)?,
+            session_exercises: SessionExercise::fetch_all_for_user(conn, user_id)?,
+            backup,
+        })
+    }
+}
+
+/// Plans other users follow outlive their owner. Each goes to whoever has
+/// followed it longest, along with the owner's exercise groups it uses and the
+/// owner's exercises in them, so nobody else loses anything
+fn hand_over_followed_plans(
+    tx: &Connection,
+    user_id: &Uuid,
+) -> Result<(), ServerError<AccountError>> {
+    for mut plan in Plan::fetch_all_by_column(tx, *user_id, PlanIden::OwnerId)? {
+        let follower = PlanInstance::fetch_all_by_column(tx, plan.id, PlanInstanceIden::PlanId)?
+            .into_iter()
+            .filter(|i| i.user_id != *user_id)
+            .min_by_key(|i| i.creation_date);
+        let Some(follower) = follower else {
+            continue;
+        };
+        let new_owner_id = follower.user_id;
+
+        plan.owner_id = new_owner_id;
+        plan.update::<AccountError>(tx)?;
+
+        for plan_exercise_group in
+            PlanExerciseGroup::fetch_all_by_column(tx, plan.id, PlanExerciseGroupIden::PlanId)?
+        {
+            let mut group = ExerciseGroup::fetch_by_id(tx, plan_exercise_group.exercise_group_id)?;
+            if group.owner_id.as_ref() != Some(user_id) {
+                continue;
+            }
+            group.owner_id = Some(new_owner_id);
+            group.update::<AccountError>(tx)?;
+
+            for member in ExerciseGroupMember::fetch_all_by_column(
+                tx,
+                group.id,
+                ExerciseGroupMemberIden::GroupId,
+            )? {
+                let mut exercise = Exercise::fetch_by_id(tx, member.exercise_id)?;
+                if exercise.owner_id.as_ref() == Some(user_id) {
+                    exercise.owner_id = Some(new_owner_id);
+                    exercise.update::<AccountError>(tx)?;
+                }
+            }
+        }
+
+        info!("Handed plan {} over to {new_owner_id}", plan.id);
+    }
+
+    Ok(())
+}
+
+/// The user's data as a json file to download
+pub async fn export_account(
+    DatabaseConnection(conn): DatabaseConnection,
+    session: SessionValue,
+    user_state: UserState,
+) -> Result<impl IntoResponse, ServerError<Nothing>> {
+    let current = session.session_id().map(|id| id.to_string());
+
+    let export = conn
+        .interact(move |conn| AccountExport::fetch(conn, &user_state.id, current.as_deref()))
+        .await??;
+
+    let filename = format!("eggercise-{}.json", export.export_date.format("%Y-%m-%d"));
+
+    Ok((
+        [(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{filename}\""))],
+        Json(export),
+    ))
+}
+
+/// Challenges the user to log in with one of their passkeys again before the
+/// account is deleted
+pub async fn delete_account_start(
+    DatabaseConnection(conn): DatabaseConnection,
+    webauthn: Webauthn,
+    mut session: SessionValue,
+    user_state: UserState,
+) -> Result<Json<RequestChallengeResponse>, ServerError<AccountError>> {
+    session.take_account_deletion_state().await?;
+
+    let user_id = *user_state.id;
+    let passkeys = conn
+        .interact(move |conn| Credential::fetch_passkeys::<AccountError>(conn, &user_id))
+        .await??;
+    if passkeys.is_empty() {
+        Err(AccountError::NoCredentials)?;
+    }
+
+    let (request_challenge_response, passkey_authentication) =
+        webauthn.start_passkey_authentication(&passkeys)?;
+
+    session
+        .set_account_deletion_state(PasskeyAuthenticationState::new(
+            user_id,
+            passkey_authentication,
+        ))
+        .await?;
+
+    Ok(Json(request_challenge_response))
+}
+
+/// Deletes the account once the passkey checks out. Every session it's logged
+/// in with is logged out
+pub async fn delete_account_finish(
+    DatabaseConnection(conn): DatabaseConnection,
+    webauthn: Webauthn,
+    State(store): State<DeadpoolSqliteStore>,
+    clients_by_session_id: ClientsBySessionId,
+    mut session: SessionValue,
+    user_state: UserState,
+    Json(public_key_credential): Json<PublicKeyCredential>,
+) -> Result<Json<()>, ServerError<AccountError>> {
+    let PasskeyAuthenticationState { user_id, passkey_authentication } = session
+        .take_account_deletion_state()
+        .await?
+        .filter(|s| s.user_id == *user_state.id)
+        .ok_or(AccountError::NotConfirmed)?;
+
+    let authentication_result = webauthn
+        .finish_passkey_authentication(&public_key_credential, &passkey_authentication)
+        .map_err(|e| {
+            warn!("Account deletion for {user_id} not confirmed: {e}");
+            AccountError::NotConfirmed
+        })?;
+
+    let login_sessions = conn
+        .interact(move |conn| {
+            let tx = conn.transaction()?;
+
+            let id = authentication_result.cred_id().clone().into();
+            let credential = Credential::fetch(&tx, &id)?;
+            if credential.user_id != user_id {
+                Err(AccountError::NotConfirmed)?;
+            }
+
+            let login_sessions = LoginSession::fetch_for_user(&tx, &user_id)?;
+            hand_over_followed_plans(&tx, &user_id)?;
+            User::fetch_by_id(&tx, user_id)?.delete(&tx)?;
+
+            tx.commit()?;
+
+            Ok::<_, ServerError<AccountError>>(login_sessions)
+        })
+        .await??;
+
+    info!("Deleted account {user_id}");
+
+    let current = session.clone().session_id();
+    session.take_user_state().await?;
+
+    for login_session in login_sessions {
+        // A session id that doesn't parse can't be in the store either
+        let Ok(id) = Id::from_str(&login_session.session_id) else {
+            continue;
+        };
+        // Deleting the current one from the store would only see it saved again at the
+        // end of this request
+        if current != Some(id) {
+            store.delete(&id).await.map_err(store_error)?;
+        }
+        if let Some(clients) = clients_by_session_id.get(&SessionId::from(id)) {
+            send_logout(clients).await;
+        }
+    }
+
+    Ok(Json(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{header::CONTENT_DISPOSITION, StatusCode};
+    use chrono::{Duration, Utc};
+    use serde_json::Value;
+    use shared::{
+        api::{Auth, Object},
+        model::{
+            Exercise, ExerciseGroup, Model, Plan, PlanExerciseGroup, PlanExerciseGroupIden,
+            PlanInstance, PlanInstanceIden, Session, User,
+        },
+    };
+
+    use super::AccountExport;
+    use crate::test_support::{body_json, with_id, SoftAuthenticator, TestApp};
+
+    /// Registers and logs in with a passkey, returning the authenticator that
+    /// holds it
+    async fn passkey_user(app: &mut TestApp, username: &str) -> (User, SoftAuthenticator) {
+        let mut authenticator = SoftAuthenticator::new(&app.state.args);
+        app.register_with(&mut authenticator, username).await;
+        let user = body_json(app.login_with(&mut authenticator, username).await).await;
+        (user, authenticator)
+    }
+
+    /// A plan owned by the user, with an instance of it, and a session in that
+    /// instance
+    async fn follow_new_plan(app: &TestApp, user: &User) -> (Plan, PlanInstance, Session) {
+        let plan = app.create_plan(user).await;
+        let plan_instance = app.create_plan_instance(user, &plan).await;
+        let session = app.create_session(user, Some(&plan_instance), Utc::now()).await;
+        (plan, plan_instance, session)
+    }
+
+    /// Deletes the logged in user's account, confirming with their passkey
+    async fn delete_account(app: &mut TestApp, authenticator: &mut SoftAuthenticator) {
+        let response = app.post(Auth::DeleteAccountStart.path(), &()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let challenge: Value = body_json(response).await;
+        let response = app
+            .post(Auth::DeleteAccountFinish.path(), &authenticator.authenticate(&challenge))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn accounts_need_a_login() {
+        let mut app = TestApp::new().await;
+
+        assert_eq!(app.get(Auth::AccountExport.path()).await.status(), StatusCode::UNAUTHORIZED);
+        let response = app.post(Auth::DeleteAccountStart.path(), &()).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.post(Auth::DeleteAccountFinish.path(), &Value::Null).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn exports_have_everything_the_user_owns() {
+        let mut app = TestApp::new().await;
+        let (user, _) = passkey_user(&mut app, "someone").await;
+        let (plan, plan_instance, session) = follow_new_plan(&app, &user).await;
+        let other = app.create_user("someone else").await;
+        follow_new_plan(&app, &other).await;
+
+        let response = app.get(Auth::AccountExport.path()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let disposition = response.headers()[CONTENT_DISPOSITION].to_str().unwrap().to_string();
+        assert!(disposition.starts_with("attachment; filename=\"eggercise-"), "{disposition}");
+
+        let export: AccountExport = body_json(response).await;
+        assert_eq!(export.user.id, user.id);
+        assert_eq!(export.credentials.len(), 1);
+        assert_eq!(export.plans, vec![plan]);
+        assert_eq!(export.plan_instances, vec![plan_instance]);
+        assert_eq!(export.sessions, vec![session]);
+        assert!(export.exercises.is_empty());
+        assert_eq!(export.login_sessions.len(), 1);
+        assert!(export.login_sessions[0].current);
+    }
+
+    #[tokio::test]
+    async fn deleting_needs_a_passkey() {
+        let mut app = TestApp::new().await;
+        app.login("someone").await;
+
+        // Logged in with a temporary login and never added a passkey
+        let response = app.post(Auth::DeleteAccountStart.path(), &()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let (_, mut authenticator) = passkey_user(&mut app, "someone else").await;
+        let mut mallory = SoftAuthenticator::new(&app.state.args);
+        app.client().register_with(&mut mallory, "mallory").await;
+
+        let response = app.post(Auth::DeleteAccountStart.path(), &()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let challenge: Value = body_json(response).await;
+        let response =
+            app.post(Auth::DeleteAccountFinish.path(), &mallory.assertion(&challenge, 0)).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // The challenge went with the failed attempt
+        let response = app
+            .post(Auth::DeleteAccountFinish.path(), &authenticator.authenticate(&challenge))
+            .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        assert_eq!(app.get(Object::User.path()).await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn deleting_removes_everything_and_logs_out() {
+        let mut app = TestApp::new().await;
+        let (user, mut authenticator) = passkey_user(&mut app, "someone").await;
+        let (plan, plan_instance, session) = follow_new_plan(&app, &user).await;
+        let other = app.create_user("someone else").await;
+        let (other_plan, ..) = follow_new_plan(&app, &other).await;
+
+        // Logged in somewhere else too
+        let mut other_device = app.client();
+        let temporary_login =
+            other_device.create_temporary_login(&user, Duration::minutes(1)).await;
+        let response =
+            other_device.get(&with_id(Auth::TemporaryLogin.path(), temporary_login.id)).await;
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(other_device.get(Object::User.path()).await.status(), StatusCode::OK);
+
+        delete_account(&mut app, &mut authenticator).await;
+
+        assert_eq!(app.get(Object::User.path()).await.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(other_device.get(Object::User.path()).await.status(), StatusCode::UNAUTHORIZED);
+
+        let user_id = user.id;
+        let (user, plan, plan_instance, session, other_plan) = app
+            .interact(move |conn| {
+                (
+                    User::fetch_by_id_maybe(conn, user_id).unwrap(),
+                    Plan::fetch_by_id_maybe(conn, plan.id).unwrap(),
+                    PlanInstance::fetch_by_id_maybe(conn, plan_instance.id).unwrap(),
+                    Session::fetch_by_id_maybe(conn, session.id).unwrap(),
+                    Plan::fetch_by_id_maybe(conn, other_plan.id).unwrap(),
+                )
+            })
+            .await;
+        assert_eq!(user, None);
+        assert_eq!(plan, None);
+        assert_eq!(plan_instance, None);
+        assert_eq!(session, None);
+        assert!(other_plan.is_some());
+    }
+
+    #[tokio::test]
+    async fn deleting_hands_followed_plans_over() {
+        let mut app = TestApp::new().await;
+        let (user, mut authenticator) = passkey_user(&mut app, "someone").await;
+        let (plan, ..) = follow_new_plan(&app, &user).await;
+
+        // The plan uses one of the owner's groups of their own exercises
+        let exercise = app.create_exercise(&user, "Squat").await;
+        let group = app.create_exercise_group(&user, "Legs", &[&exercise]).await;
+        let plan_exercise_group = app.create_plan_exercise_group(&plan, &group).await;
+        let (plan_id, exercise_id) = (plan.id, exercise.id);
+
+        // Two other users follow it, the one who started first gets it
+        let first = app.create_user("first").await;
+        let first_instance = app.create_plan_instance(&first, &plan).await;
+        let first_session = app.create_session(&first, Some(&first_instance), Utc::now()).await;
+        let second = app.create_user("second").await;
+        let second_instance = app.create_plan_instance(&second, &plan).await;
+
+        delete_account(&mut app, &mut authenticator).await;
+
+        let (plan, instances, session, plan_exercise_groups, group, exercise) = app
+            .interact(move |conn| {
+                (
+                    Plan::fetch_by_id(conn, plan_id).unwrap(),
+                    PlanInstance::fetch_all_by_column(conn, plan_id, PlanInstanceIden::PlanId)
+                        .unwrap(),
+                    Session::fetch_by_id(conn, first_session.id).unwrap(),
+                    PlanExerciseGroup::fetch_all_by_column(
+                        conn,
+                        plan_id,
+                        PlanExerciseGroupIden::PlanId,
+                    )
+                    .unwrap(),
+                    ExerciseGroup::fetch_by_id(conn, group.id).unwrap(),
+                    Exercise::fetch_by_id(conn, exercise_id).unwrap(),
+                )
+            })
+            .await;
+        assert_eq!(plan.owner_id, first.id);
+        assert_eq!(instances.len(), 2);
+        assert!(instances.contains(&first_instance) && instances.contains(&second_instance));
+        assert_eq!(session, first_session);
+        assert_eq!(plan_exercise_groups, vec![plan_exercise_group]);
+        assert_eq!(group.owner_id, Some(first.id));
+        assert_eq!(exercise.owner_id, Some(first.id));
+    }
+}
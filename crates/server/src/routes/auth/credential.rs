@@ -0,0 +1,237 @@
+//! Listing, naming and revoking the passkeys a user has registered
+
+use axum::{extract::Path, Json};
+use chrono::Utc;
+use rusqlite::Connection;
+use shared::{
+    api::{
+        error::ServerError,
+        payloads::{CredentialInfo, RenameCredentialRequest},
+        response_errors::CredentialError,
+    },
+    model::{Credential, RecoveryCode, ValidateModel},
+    types::Uuid,
+};
+
+use crate::{db::DatabaseConnection, UserState};
+
+/// Fetches the credential if it belongs to the user. Credentials belonging to
+/// other users are reported as not existing
+fn owned_credential(
+    conn: &Connection,
+    user_id: &Uuid,
+    id: &str,
+) -> Result<(Credential, usize), ServerError<CredentialError>> {
+    let credentials = Credential::fetch_for_user(conn, user_id)?;
+    let count = credentials.len();
+    let credential = credentials
+        .into_iter()
+        .find(|c| c.id.to_base64() == id)
+        .ok_or(CredentialError::NotFound)?;
+
+    Ok((credential, count))
+}
+
+/// Lists the user's credentials, oldest first
+pub async fn list_credentials(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+) -> Result<Json<Vec<CredentialInfo>>, ServerError<CredentialError>> {
+    let mut credentials = conn
+        .interact(move |conn| Credential::fetch_for_user::<CredentialError>(conn, &user_state.id))
+        .await??;
+    credentials.sort_by_key(|c| c.creation_date);
+
+    Ok(Json(credentials.iter().map(CredentialInfo::from).collect()))
+}
+
+pub async fn rename_credential(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<String>,
+    Json(request): Json<RenameCredentialRequest>,
+) -> Result<Json<CredentialInfo>, ServerError<CredentialError>> {
+    request.validate().map_err(CredentialError::from)?;
+
+    let credential = conn
+        .interact(move |conn| {
+            let (mut credential, _) = owned_credential(conn, &user_state.id, &id)?;
+
+            let name = request.name.trim();
+            credential.name = (!name.is_empty()).then(|| name.to_string());
+            credential.last_updated_date = Utc::now();
+            credential.update(conn)?;
+
+            Ok::<_, ServerError<_>>(credential)
+        })
+        .await??;
+
+    Ok(Json((&credential).into()))
+}
+
+/// Removes the credential so it can't be used to log in any more. Without an
+/// unused recovery code the last credential is the only way in so it can't be
+/// removed
+pub async fn delete_credential(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<String>,
+) -> Result<Json<()>, ServerError<CredentialError>> {
+    conn.interact(move |conn| {
+        let tx = conn.transaction()?;
+
+        let (credential, count) = owned_credential(&tx, &user_state.id, &id)?;
+        if count == 1
+            && RecoveryCode::fetch_unused_for_user::<CredentialError>(&tx, &user_state.id)?
+                .is_empty()
+        {
+            Err(CredentialError::LastCredential)?;
+        }
+        credential.delete(&tx)?;
+
+        tx.commit()?;
+
+        Ok::<_, ServerError<_>>(())
+    })
+    .await??;
+
+    Ok(Json(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use shared::{
+        api::{
+            error::Nothing,
+            payloads::{CredentialInfo, RenameCredentialRequest},
+            Auth, Object,
+        },
+        model::{Model, RecoveryCode, User},
+    };
+
+    use crate::test_support::{body_json, with_id, SoftAuthenticator, TestApp};
+
+    /// A user registered with `keys` passkeys and logged in with the first
+    async fn user_with_keys(app: &mut TestApp, keys: usize) -> Vec<SoftAuthenticator> {
+        let mut authenticators = vec![SoftAuthenticator::new(&app.state.args)];
+        app.register_with(&mut authenticators[0], "someone").await;
+        let response = app.login_with(&mut authenticators[0], "someone").await;
+        let _: User = body_json(response).await;
+
+        for _ in 1..keys {
+            let mut authenticator = SoftAuthenticator::new(&app.state.args);
+            let response = app.post(Auth::RegisterNewKeyStart.path(), &()).await;
+            let challenge = authenticator.register(&body_json(response).await);
+            let response = app.post(Auth::RegisterNewKeyFinish.path(), &challenge).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            authenticators.push(authenticator);
+        }
+
+        authenticators
+    }
+
+    async fn list(app: &mut TestApp) -> Vec<CredentialInfo> {
+        let response = app.get(Object::Credential.path()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        body_json(response).await
+    }
+
+    #[tokio::test]
+    async fn credentials_need_a_login() {
+        let mut app = TestApp::new().await;
+        let path = with_id(Object::CredentialId.path(), "abc");
+
+        assert_eq!(app.get(Object::Credential.path()).await.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(app.delete(&path).await.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn credentials_are_listed_with_their_use() {
+        let mut app = TestApp::new().await;
+        user_with_keys(&mut app, 2).await;
+
+        let credentials = list(&mut app).await;
+        assert_eq!(credentials.len(), 2);
+        // Only the first has been used to log in
+        assert!(credentials[0].last_used_date.is_some());
+        assert!(credentials[1].last_used_date.is_none());
+        assert!(credentials.iter().all(|c| c.name.is_none() && !c.backup_eligible));
+    }
+
+    #[tokio::test]
+    async fn credentials_can_be_renamed() {
+        let mut app = TestApp::new().await;
+        user_with_keys(&mut app, 1).await;
+        let id = list(&mut app).await[0].id.clone();
+        let path = with_id(Object::CredentialId.path(), &id);
+
+        let request = RenameCredentialRequest { name: "  Phone ".to_string() };
+        let response = app.patch(&path, &request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let renamed: CredentialInfo = body_json(response).await;
+        assert_eq!(renamed.name.as_deref(), Some("Phone"));
+        assert_eq!(list(&mut app).await[0], renamed);
+
+        let request = RenameCredentialRequest { name: "x".repeat(65) };
+        assert_eq!(app.patch(&path, &request).await.status(), StatusCode::BAD_REQUEST);
+
+        let request = RenameCredentialRequest { name: String::new() };
+        let renamed: CredentialInfo = body_json(app.patch(&path, &request).await).await;
+        assert_eq!(renamed.name, None);
+    }
+
+    #[tokio::test]
+    async fn the_last_credential_cant_be_revoked() {
+        let mut app = TestApp::new().await;
+        let mut authenticators = user_with_keys(&mut app, 2).await;
+        let credentials = list(&mut app).await;
+        // Use up the recovery codes
+        app.interact(|conn| {
+            for code in RecoveryCode::fetch_all(conn).unwrap() {
+                RecoveryCode::redeem::<Nothing>(conn, &code.user_id, &code.code_hash).unwrap();
+            }
+        })
+        .await;
+
+        let response = app.delete(&with_id(Object::CredentialId.path(), &credentials[1].id)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(list(&mut app).await, credentials[..1]);
+
+        let response = app.delete(&with_id(Object::CredentialId.path(), &credentials[0].id)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // The revoked key can't log in any more
+        let mut other = app.client();
+        let challenge = other.login_challenge("someone").await;
+        let response =
+            other.post(Auth::LoginFinish.path(), &authenticators[1].assertion(&challenge, 0)).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn the_last_credential_can_be_revoked_with_recovery_codes() {
+        let mut app = TestApp::new().await;
+        user_with_keys(&mut app, 1).await;
+        let credentials = list(&mut app).await;
+
+        let response = app.delete(&with_id(Object::CredentialId.path(), &credentials[0].id)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(list(&mut app).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn other_users_credentials_are_not_found() {
+        let mut app = TestApp::new().await;
+        user_with_keys(&mut app, 2).await;
+        let id = list(&mut app).await[0].id.clone();
+
+        let mut other = app.client();
+        other.login("another").await;
+        let path = with_id(Object::CredentialId.path(), &id);
+        assert_eq!(other.delete(&path).await.status(), StatusCode::NOT_FOUND);
+        let request = RenameCredentialRequest { name: "Mine".to_string() };
+        assert_eq!(other.patch(&path, &request).await.status(), StatusCode::NOT_FOUND);
+        assert!(list(&mut other).await.is_empty());
+    }
+}
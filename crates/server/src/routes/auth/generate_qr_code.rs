@@ -1,3 +1,5 @@
+use std::error::Error;
+
 use axum::{
     extract::Path,
     http::{header, HeaderMap, HeaderValue},
@@ -45,7 +47,12 @@ pub async fn generate_qr_code(
     _user_state: UserState,
     payload: Path<String>,
 ) -> Result<impl IntoResponse, ServerError<Nothing>> {
-    let segments = QrSegment::make_segments(&payload);
+    qr_code_response(&payload)
+}
+
+/// An svg response with the payload encoded as a QR code
+pub fn qr_code_response<T: Error>(payload: &str) -> Result<impl IntoResponse, ServerError<T>> {
+    let segments = QrSegment::make_segments(payload);
     let code = QrCode::encode_segments_advanced(
         &segments,
         QrCodeEcc::Medium,
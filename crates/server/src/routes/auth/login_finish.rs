@@ -3,7 +3,7 @@ use chrono::Utc;
 use shared::{
     api::error::{Nothing, ServerError},
     ensure_server,
-    model::{Credential, User},
+    model::{Credential, Model, User},
     unauthorized_error,
 };
 use tracing::error;
@@ -84,7 +84,7 @@ pub async fn login_finish(
 
             credential.update(&tx)?;
 
-            let mut user = User::fetch_by_id(&tx, &credential.user_id)?;
+            let mut user = User::fetch_by_id(&tx, credential.user_id)?;
             user.last_updated_date = now;
             user.last_login_date = Some(now);
             user.update(&tx)?;
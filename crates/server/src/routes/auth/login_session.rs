@@ -0,0 +1,293 @@
+//! Logging out, and listing and revoking the other sessions a user is logged in
+//! with. The sessions themselves live in the tower_sessions store, the
+//! [`LoginSession`]s kept by
+//! [`track_login_session`](crate::middleware::track_login_session) are what the
+//! user sees of them
+
+use std::{cmp::Reverse, str::FromStr};
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use shared::{
+    api::{
+        error::{Nothing, ServerError},
+        payloads::LoginSessionInfo,
+        response_errors::LoginSessionError,
+    },
+    model::LoginSession,
+    other_error,
+    types::Uuid,
+};
+use tower_sessions::{session::Id, session_store, SessionStore};
+use tower_sessions_deadpool_sqlite_store::DeadpoolSqliteStore;
+use tracing::error;
+
+use crate::{
+    db::DatabaseConnection, ClientControlMessage, ClientsBySessionId, SessionClients, SessionId,
+    SessionValue, UserState,
+};
+
+pub(super) fn store_error<T: std::error::Error>(e: session_store::Error) -> ServerError<T> {
+    other_error!("Session store: {e}")
+}
+
+/// Tells the session's websockets it's been logged out so every open tab
+/// notices
+pub(super) async fn send_logout(clients: SessionClients) {
+    for client in clients.clients {
+        if let Err(e) = client.send(ClientControlMessage::Logout).await {
+            error!("Error sending ClientControlMessage::Logout: {e:?}");
+        }
+    }
+}
+
+pub async fn logout(
+    DatabaseConnection(conn): DatabaseConnection,
+    mut session: SessionValue,
+    clients: Option<SessionClients>,
+) -> Result<Json<()>, ServerError<Nothing>> {
+    let user_state = session.take_user_state().await?;
+
+    if let (Some(_), Some(id)) = (user_state, session.session_id()) {
+        let session_id = id.to_string();
+        conn.interact(move |conn| LoginSession::delete_by_session_id::<Nothing>(conn, &session_id))
+            .await??;
+    }
+
+    if let Some(clients) = clients {
+        send_logout(clients).await;
+    }
+
+    Ok(Json(()))
+}
+
+/// Lists the sessions the user is logged in with, most recently seen first.
+/// Sessions can expire or be logged out of by logging in as someone else so
+/// each is checked against the store and forgotten if it's no longer the user's
+pub async fn list_login_sessions(
+    DatabaseConnection(conn): DatabaseConnection,
+    State(store): State<DeadpoolSqliteStore>,
+    session: SessionValue,
+    user_state: UserState,
+) -> Result<Json<Vec<LoginSessionInfo>>, ServerError<LoginSessionError>> {
+    let current = session.session_id().map(|id| id.to_string());
+
+    let user_id = user_state.id.clone();
+    let login_sessions = conn
+        .interact(move |conn| LoginSession::fetch_for_user::<LoginSessionError>(conn, &user_id))
+        .await??;
+
+    let mut live = Vec::new();
+    let mut stale = Vec::new();
+    for login_session in login_sessions {
+        let record = match Id::from_str(&login_session.session_id) {
+            Ok(id) => store.load(&id).await.map_err(store_error)?,
+            Err(_) => None,
+        };
+
+        if record
+            .as_ref()
+            .and_then(SessionValue::record_user_state)
+            .is_some_and(|u| u.id == user_state.id)
+        {
+            live.push(login_session);
+        } else {
+            stale.push(login_session);
+        }
+    }
+
+    if !stale.is_empty() {
+        conn.interact(move |conn| {
+            for login_session in stale {
+                login_session.delete(conn)?;
+            }
+            Ok::<_, ServerError<LoginSessionError>>(())
+        })
+        .await??;
+    }
+
+    live.sort_by_key(|s| Reverse(s.last_seen_date));
+
+    Ok(Json(
+        live.iter()
+            .map(|s| LoginSessionInfo::new(s, current.as_deref() == Some(&s.session_id)))
+            .collect(),
+    ))
+}
+
+/// Logs one of the user's sessions out, which can be the current one
+pub async fn revoke_login_session(
+    DatabaseConnection(conn): DatabaseConnection,
+    State(store): State<DeadpoolSqliteStore>,
+    clients_by_session_id: ClientsBySessionId,
+    mut session: SessionValue,
+    user_state: UserState,
+    Path(id): Path<Uuid>,
+) -> Result<Json<()>, ServerError<LoginSessionError>> {
+    let login_session = conn
+        .interact(move |conn| {
+            // Sessions belonging to other users are reported as not existing
+            let login_session = LoginSession::fetch_for_user(conn, &user_state.id)?
+                .into_iter()
+                .find(|s| s.id == id)
+                .ok_or(LoginSessionError::NotFound)?;
+            login_session.delete(conn)?;
+
+            Ok::<_, ServerError<_>>(login_session)
+        })
+        .await??;
+
+    // A session id that doesn't parse can't be in the store either
+    if let Ok(id) = Id::from_str(&login_session.session_id) {
+        if session.clone().session_id() == Some(id) {
+            // Deleting it from the store would only see it saved again at the end of this
+            // request
+            session.take_user_state().await?;
+        } else {
+            store.delete(&id).await.map_err(store_error)?;
+        }
+
+        if let Some(clients) = clients_by_session_id.get(&SessionId::from(id)) {
+            send_logout(clients).await;
+        }
+    }
+
+    Ok(Json(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header::USER_AGENT, Method, StatusCode},
+    };
+    use chrono::Duration;
+    use shared::{
+        api::{payloads::LoginSessionInfo, Auth, Object},
+        model::{LoginSession, Model, RegistrationUser, User},
+        types::Uuid,
+    };
+
+    use crate::test_support::{body_json, with_id, TestApp};
+
+    async fn list(app: &mut TestApp) -> Vec<LoginSessionInfo> {
+        let response = app.get(Object::LoginSession.path()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        body_json(response).await
+    }
+
+    /// Another browser logged in as the same user
+    async fn log_in_again(app: &TestApp, user: &User, user_agent: &str) -> TestApp {
+        let mut other = app.client();
+        let temporary_login = other.create_temporary_login(user, Duration::minutes(1)).await;
+        let request = other
+            .request(Method::GET, &with_id(Auth::TemporaryLogin.path(), temporary_login.id))
+            .header(USER_AGENT, user_agent)
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(other.send(request).await.status(), StatusCode::SEE_OTHER);
+        other
+    }
+
+    #[tokio::test]
+    async fn sessions_need_a_login() {
+        let mut app = TestApp::new().await;
+        let path = with_id(Object::LoginSessionId.path(), Uuid::new_v4());
+
+        assert_eq!(app.get(Object::LoginSession.path()).await.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(app.delete(&path).await.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn logging_in_is_recorded() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        let _phone = log_in_again(&app, &user, "Phone browser").await;
+
+        let sessions = list(&mut app).await;
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions.iter().filter(|s| s.current).count(), 1);
+        let other = sessions.iter().find(|s| !s.current).unwrap();
+        assert_eq!(other.user_agent.as_deref(), Some("Phone browser"));
+
+        // The store's ids are the session cookies so they stay on the server
+        let stored = app.interact(|conn| LoginSession::fetch_all(conn).unwrap()).await;
+        let body = serde_json::to_string(&sessions).unwrap();
+        assert!(stored.iter().all(|s| !body.contains(&s.session_id)));
+    }
+
+    #[tokio::test]
+    async fn logout_ends_the_session() {
+        let mut app = TestApp::new().await;
+        app.login("someone").await;
+        assert_eq!(app.get(Object::User.path()).await.status(), StatusCode::OK);
+
+        let response = app.post(Auth::Logout.path(), &()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(app.get(Object::User.path()).await.status(), StatusCode::UNAUTHORIZED);
+        assert!(app.interact(|conn| LoginSession::fetch_all(conn).unwrap()).await.is_empty());
+
+        // Logging out again is harmless
+        assert_eq!(app.post(Auth::Logout.path(), &()).await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn other_sessions_can_be_revoked() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        let mut phone = log_in_again(&app, &user, "Phone browser").await;
+        assert_eq!(phone.get(Object::User.path()).await.status(), StatusCode::OK);
+
+        let sessions = list(&mut app).await;
+        let other = sessions.iter().find(|s| !s.current).unwrap();
+        let response = app.delete(&with_id(Object::LoginSessionId.path(), other.id)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        assert_eq!(phone.get(Object::User.path()).await.status(), StatusCode::UNAUTHORIZED);
+        let sessions = list(&mut app).await;
+        assert_eq!(sessions.len(), 1);
+        assert!(sessions[0].current);
+    }
+
+    #[tokio::test]
+    async fn sessions_no_longer_logged_in_are_forgotten() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        let mut phone = log_in_again(&app, &user, "Phone browser").await;
+
+        // Starting a registration logs the session out without going through logout
+        let response =
+            phone.post(Auth::RegisterStart.path(), &RegistrationUser::new("new user")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        assert_eq!(list(&mut app).await.len(), 1);
+        assert_eq!(app.interact(|conn| LoginSession::fetch_all(conn).unwrap()).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn the_current_session_can_be_revoked() {
+        let mut app = TestApp::new().await;
+        app.login("someone").await;
+        let id = list(&mut app).await[0].id;
+
+        let response = app.delete(&with_id(Object::LoginSessionId.path(), id)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(app.get(Object::User.path()).await.status(), StatusCode::UNAUTHORIZED);
+        assert!(app.interact(|conn| LoginSession::fetch_all(conn).unwrap()).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn other_users_sessions_are_not_found() {
+        let mut app = TestApp::new().await;
+        app.login("someone").await;
+        let id = list(&mut app).await[0].id;
+
+        let mut other = app.client();
+        other.login("another").await;
+        let path = with_id(Object::LoginSessionId.path(), id);
+        assert_eq!(other.delete(&path).await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(app.get(Object::User.path()).await.status(), StatusCode::OK);
+    }
+}
@@ -26,3 +26,288 @@ pub use user::*;
 
 mod temporary_login;
 pub use temporary_login::*;
+
+mod credential;
+pub use credential::*;
+
+mod recovery;
+pub use recovery::*;
+
+mod login_session;
+pub use login_session::*;
+
+mod pairing;
+pub use pairing::*;
+
+mod profile;
+pub use profile::*;
+
+mod account;
+pub use account::*;
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{
+        header::{CONTENT_TYPE, LOCATION},
+        StatusCode,
+    };
+    use chrono::Duration;
+    use client::ClientRoutes;
+    use serde_json::Value;
+    use shared::{
+        api::{payloads::CreateTemporaryLoginRequest, Auth, Object},
+        model::{Credential, LoginUser, Model, RegistrationUser, TemporaryLogin, User},
+    };
+
+    use crate::test_support::{body_json, body_string, with_id, SoftAuthenticator, TestApp};
+
+    async fn credentials_of(app: &TestApp, user: &User) -> Vec<Credential> {
+        let user_id = user.id;
+        app.interact(move |conn| Credential::fetch_all(conn).unwrap())
+            .await
+            .into_iter()
+            .filter(|c| c.user_id == user_id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn register_start_checks_the_username() {
+        let mut app = TestApp::new().await;
+        app.create_user("taken").await;
+
+        let response = app.post(Auth::RegisterStart.path(), &RegistrationUser::new("abc")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app.post(Auth::RegisterStart.path(), &RegistrationUser::new("taken")).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app.post(Auth::RegisterStart.path(), &RegistrationUser::new("free")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let challenge: Value = body_json(response).await;
+        assert_eq!(challenge["publicKey"]["user"]["name"], "free");
+    }
+
+    #[tokio::test]
+    async fn login_start_needs_a_user_with_a_passkey() {
+        let mut app = TestApp::new().await;
+        app.create_user("no passkey").await;
+
+        let response = app.post(Auth::LoginStart.path(), &LoginUser::new("abc")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app.post(Auth::LoginStart.path(), &LoginUser::new("nobody")).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app.post(Auth::LoginStart.path(), &LoginUser::new("no passkey")).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn fetch_user_needs_a_session() {
+        let mut app = TestApp::new().await;
+
+        let response = app.get(Object::User.path()).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let user = app.login("someone").await;
+        let response = app.get(Object::User.path()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let (fetched, temporary_login): (User, Option<TemporaryLogin>) = body_json(response).await;
+        assert_eq!(fetched.id, user.id);
+        assert_eq!(temporary_login, None);
+    }
+
+    #[tokio::test]
+    async fn temporary_login_can_only_be_used_once() {
+        let mut app = TestApp::new().await;
+        let user = app.create_user("someone").await;
+        let temporary_login = app.create_temporary_login(&user, Duration::minutes(1)).await;
+        let path = with_id(Auth::TemporaryLogin.path(), temporary_login.id);
+
+        let response = app.get(&path).await;
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(response.headers()[LOCATION], ClientRoutes::Profile.path());
+        let (fetched, _): (User, Option<TemporaryLogin>) =
+            body_json(app.get(Object::User.path()).await).await;
+        assert_eq!(fetched.id, user.id);
+
+        let mut other = app.client();
+        let response = other.get(&path).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(other.get(Object::User.path()).await.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn expired_temporary_logins_are_removed() {
+        let mut app = TestApp::new().await;
+        let user = app.create_user("someone").await;
+        let temporary_login = app.create_temporary_login(&user, Duration::minutes(-1)).await;
+
+        let response = app.get(&with_id(Auth::TemporaryLogin.path(), temporary_login.id)).await;
+        assert_eq!(response.status(), StatusCode::GONE);
+        assert_eq!(app.get(Object::User.path()).await.status(), StatusCode::UNAUTHORIZED);
+
+        let remaining = app.interact(|conn| TemporaryLogin::fetch_all(conn).unwrap()).await;
+        assert!(remaining.is_empty());
+
+        let response = app.get(&with_id(Auth::TemporaryLogin.path(), "not-a-uuid")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn create_temporary_login_lets_another_device_log_in() {
+        let mut app = TestApp::new().await;
+        let request = CreateTemporaryLoginRequest::default();
+
+        let response = app.post(Auth::CreateTemporaryLogin.path(), &request).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let user = app.login("someone").await;
+        let response = app.post(Auth::CreateTemporaryLogin.path(), &request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let temporary_login: TemporaryLogin = body_json(response).await;
+        assert_eq!(temporary_login.user_id, user.id);
+
+        // Only one at a time
+        let response = app.post(Auth::CreateTemporaryLogin.path(), &request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let (_, fetched): (User, Option<TemporaryLogin>) =
+            body_json(app.get(Object::User.path()).await).await;
+        assert_eq!(fetched, Some(temporary_login.clone()));
+
+        let path = temporary_login.url.strip_prefix(&app.state.args.origin).unwrap();
+        let mut other = app.client();
+        assert_eq!(other.get(path).await.status(), StatusCode::SEE_OTHER);
+        let (fetched, _): (User, Option<TemporaryLogin>) =
+            body_json(other.get(Object::User.path()).await).await;
+        assert_eq!(fetched.id, user.id);
+    }
+
+    #[tokio::test]
+    async fn qr_codes_are_svgs_for_logged_in_users() {
+        let mut app = TestApp::new().await;
+        let path = with_id(Object::QrCodeId.path(), "some-text");
+
+        assert_eq!(app.get(&path).await.status(), StatusCode::UNAUTHORIZED);
+
+        app.login("someone").await;
+        let response = app.get(&path).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], mime::IMAGE_SVG.essence_str());
+        assert!(body_string(response).await.contains("<svg"));
+    }
+
+    #[tokio::test]
+    async fn passkeys_can_register_and_log_in() {
+        let mut app = TestApp::new().await;
+        let mut authenticator = SoftAuthenticator::new(&app.state.args);
+
+        let response = app.register_with(&mut authenticator, "someone").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        // Registering doesn't log in
+        assert_eq!(app.get(Object::User.path()).await.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app.login_with(&mut authenticator, "someone").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let user: User = body_json(response).await;
+        assert_eq!(user.username, "someone");
+        assert!(user.last_login_date.is_some());
+
+        let (fetched, _): (User, Option<TemporaryLogin>) =
+            body_json(app.get(Object::User.path()).await).await;
+        assert_eq!(fetched.id, user.id);
+
+        let credentials = credentials_of(&app, &user).await;
+        assert_eq!(credentials.len(), 1);
+        assert_eq!(credentials[0].counter, 1);
+        assert!(credentials[0].last_used_date.is_some());
+    }
+
+    #[tokio::test]
+    async fn logins_update_the_counter_and_reject_it_going_backwards() {
+        let mut app = TestApp::new().await;
+        let mut authenticator = SoftAuthenticator::new(&app.state.args);
+        app.register_with(&mut authenticator, "someone").await;
+
+        app.login_with(&mut authenticator, "someone").await;
+        let response = app.login_with(&mut authenticator, "someone").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let user: User = body_json(response).await;
+        assert_eq!(credentials_of(&app, &user).await[0].counter, 2);
+
+        // A cloned authenticator would be behind the original
+        authenticator.credentials[0].counter = 0;
+        let response = app.login_with(&mut authenticator, "someone").await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(app.get(Object::User.path()).await.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(credentials_of(&app, &user).await[0].counter, 2);
+    }
+
+    #[tokio::test]
+    async fn challenges_cant_be_replayed() {
+        let mut app = TestApp::new().await;
+        let mut authenticator = SoftAuthenticator::new(&app.state.args);
+
+        let response =
+            app.post(Auth::RegisterStart.path(), &RegistrationUser::new("someone")).await;
+        let registration = authenticator.register(&body_json(response).await);
+        let response = app.post(Auth::RegisterFinish.path(), &registration).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.post(Auth::RegisterFinish.path(), &registration).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let challenge = app.login_challenge("someone").await;
+        let assertion = authenticator.authenticate(&challenge);
+        let response = app.post(Auth::LoginFinish.path(), &assertion).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.post(Auth::LoginFinish.path(), &assertion).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Nor is the old answer any good for a new challenge
+        app.login_challenge("someone").await;
+        let response = app.post(Auth::LoginFinish.path(), &assertion).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(app.get(Object::User.path()).await.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn unknown_credentials_are_rejected() {
+        let mut app = TestApp::new().await;
+        let mut alice = SoftAuthenticator::new(&app.state.args);
+        let mut mallory = SoftAuthenticator::new(&app.state.args);
+        app.register_with(&mut alice, "alice").await;
+        app.register_with(&mut mallory, "mallory").await;
+
+        let challenge = app.login_challenge("alice").await;
+        let response = app.post(Auth::LoginFinish.path(), &mallory.assertion(&challenge, 0)).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(app.get(Object::User.path()).await.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn new_keys_can_be_added_to_an_account() {
+        let mut app = TestApp::new().await;
+        let mut phone = SoftAuthenticator::new(&app.state.args);
+        let mut laptop = SoftAuthenticator::new(&app.state.args);
+        app.register_with(&mut phone, "someone").await;
+
+        let response = app.post(Auth::RegisterNewKeyStart.path(), &()).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let user: User = body_json(app.login_with(&mut phone, "someone").await).await;
+        let response = app.post(Auth::RegisterNewKeyStart.path(), &()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let challenge: Value = body_json(response).await;
+        // The existing key is excluded so it isn't registered twice
+        assert_eq!(challenge["publicKey"]["excludeCredentials"].as_array().unwrap().len(), 1);
+
+        let response =
+            app.post(Auth::RegisterNewKeyFinish.path(), &laptop.register(&challenge)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(credentials_of(&app, &user).await.len(), 2);
+
+        let mut other = app.client();
+        let response = other.login_with(&mut laptop, "someone").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,292 @@
+//! Logging a new device in from one that's already logged in. The new device
+//! shows a code (and a QR code of a link with it) which the logged in device
+//! opens or types in and approves. The new device hears about it over the
+//! websocket and finishes the pairing to log in, then it can add its own
+//! passkey
+
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use client::ClientRoutes;
+use shared::{
+    api::{
+        error::ServerError,
+        payloads::{PairingCode, PairingDecision, PairingInfo},
+        response_errors::PairingError,
+    },
+    model::{Model, User, ValidateModel},
+};
+use tower_sessions::Session;
+use tracing::{error, info, warn};
+
+use crate::{
+    db::DatabaseConnection, routes::auth::qr_code_response, state::Args, ClientAddress,
+    ClientControlMessage, ClientsBySessionId, PairingRequests, SessionClients, SessionId,
+    SessionValue, UserState,
+};
+
+/// Where the logged in device approves the request
+fn pairing_url(args: &Args, code: &str) -> String {
+    format!("{}{}?code={code}", args.origin, ClientRoutes::Pair.path())
+}
+
+/// Asks to be logged in by another device. Any request the session already
+/// had is replaced
+pub async fn start_pairing(
+    State(pairings): State<PairingRequests>,
+    args: Args,
+    session: Session,
+    address: ClientAddress,
+) -> Result<Json<PairingCode>, ServerError<PairingError>> {
+    let session_id = SessionId::from(SessionValue::saved_id(&session).await?);
+    let (code, request) = pairings.start(session_id, address);
+
+    Ok(Json(PairingCode { url: pairing_url(&args, &code), code, expiry_date: request.expiry_date }))
+}
+
+/// The QR code for the session's pairing request. It doesn't need a login as
+/// it's shown on the device that isn't logged in yet
+pub async fn pairing_qr_code(
+    State(pairings): State<PairingRequests>,
+    args: Args,
+    session_id: Option<SessionId>,
+) -> Result<impl IntoResponse, ServerError<PairingError>> {
+    let (code, _) =
+        session_id.and_then(|id| pairings.for_session(&id)).ok_or(PairingError::NotFound)?;
+
+    qr_code_response(&pairing_url(&args, &code))
+}
+
+/// What's asking to be logged in, for the user to check before approving
+pub async fn fetch_pairing(
+    _user_state: UserState,
+    State(pairings): State<PairingRequests>,
+    Path(code): Path<String>,
+) -> Result<Json<PairingInfo>, ServerError<PairingError>> {
+    let request = pairings.get(&code).ok_or(PairingError::NotFound)?;
+
+    Ok(Json(request.info(&code)))
+}
+
+/// Approves or denies a pairing request and lets the device that made it know
+pub async fn decide_pairing(
+    user_state: UserState,
+    State(pairings): State<PairingRequests>,
+    clients_by_session_id: ClientsBySessionId,
+    Json(decision): Json<PairingDecision>,
+) -> Result<Json<()>, ServerError<PairingError>> {
+    decision.validate().map_err(PairingError::from)?;
+
+    let user_id = user_state.id.clone();
+    let request = if decision.approve {
+        pairings.approve(&decision.code, user_state)
+    } else {
+        pairings.remove(&decision.code)
+    }
+    .ok_or(PairingError::NotFound)?;
+
+    info!(
+        "Pairing {} for {user_id} from {:?}",
+        if decision.approve { "approved" } else { "denied" },
+        request.address
+    );
+
+    if let Some(clients) = clients_by_session_id.get(&request.session_id) {
+        for client in clients.clients {
+            let message = ClientControlMessage::Pairing { approved: decision.approve };
+            if let Err(e) = client.send(message).await {
+                error!("Error sending ClientControlMessage for pairing: {e:?}");
+            }
+        }
+    }
+
+    Ok(Json(()))
+}
+
+/// Logs the session in as the user that approved its pairing request
+pub async fn finish_pairing(
+    DatabaseConnection(conn): DatabaseConnection,
+    State(pairings): State<PairingRequests>,
+    mut session: SessionValue,
+    session_id: Option<SessionId>,
+    clients: Option<SessionClients>,
+) -> Result<Json<User>, ServerError<PairingError>> {
+    let session_id = session_id.ok_or(PairingError::NotFound)?;
+    let (code, request) = pairings.for_session(&session_id).ok_or(PairingError::NotFound)?;
+    let Some(user_state) = request.approved_by else {
+        return Err(PairingError::NotApproved.into());
+    };
+    pairings.remove(&code);
+
+    let user = conn
+        .interact(move |conn| {
+            let mut user = user_state.id.fetch_full_user(conn)?;
+
+            let now = Utc::now();
+            user.last_updated_date = now;
+            user.last_login_date = Some(now);
+            user.update(conn)?;
+
+            Ok::<_, ServerError<PairingError>>(user)
+        })
+        .await??;
+
+    warn!("Pairing used to log in as {} ({})", user.username, user.id);
+    session.set_user_state(&user).await?;
+
+    if let Some(clients) = clients {
+        for client in clients.clients {
+            if let Err(e) = client.send(ClientControlMessage::Login((&user).into())).await {
+                error!("Error sending ClientControlMessage for user {user:?}: {e:?}");
+            }
+        }
+    }
+
+    Ok(Json(user))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{header::CONTENT_TYPE, StatusCode};
+    use shared::{
+        api::{
+            payloads::{PairingCode, PairingDecision, PairingInfo},
+            Auth, Object,
+        },
+        model::User,
+    };
+
+    use crate::{
+        test_support::{body_json, with_id, TestApp},
+        Client, ClientControlMessage,
+    };
+
+    async fn start(app: &mut TestApp) -> PairingCode {
+        let response = app.post(Auth::PairingStart.path(), &()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        body_json(response).await
+    }
+
+    fn decision(code: &PairingCode, approve: bool) -> PairingDecision {
+        PairingDecision { code: code.code.clone(), approve }
+    }
+
+    #[tokio::test]
+    async fn only_the_starting_session_gets_the_qr_code() {
+        let mut app = TestApp::new().await;
+        let mut other = app.client();
+
+        assert_eq!(app.get(Auth::PairingQrCode.path()).await.status(), StatusCode::NOT_FOUND);
+
+        let code = start(&mut app).await;
+        assert!(code.url.ends_with(&format!("?code={}", code.code)));
+
+        let response = app.get(Auth::PairingQrCode.path()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], mime::IMAGE_SVG.essence_str());
+
+        assert_eq!(other.get(Auth::PairingQrCode.path()).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn pairing_info_needs_a_login() {
+        let mut app = TestApp::new().await;
+        let code = start(&mut app).await;
+        let path = with_id(Object::PairingId.path(), &code.code);
+
+        let mut other = app.client();
+        assert_eq!(other.get(&path).await.status(), StatusCode::UNAUTHORIZED);
+
+        other.login("someone").await;
+        let response = other.get(&path).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let info: PairingInfo = body_json(response).await;
+        assert_eq!(info.code, code.code);
+        assert_eq!(info.expiry_date, code.expiry_date);
+
+        // Codes can be typed in lowercase
+        let response =
+            other.get(&with_id(Object::PairingId.path(), code.code.to_lowercase())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = other.get(&with_id(Object::PairingId.path(), "NOTACODE")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn approved_pairings_log_in_once() {
+        let mut app = TestApp::new().await;
+        let code = start(&mut app).await;
+
+        let response = app.post(Auth::PairingFinish.path(), &()).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let mut other = app.client();
+        let user = other.login("someone").await;
+        let response = other.post(Auth::PairingDecision.path(), &decision(&code, true)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app.post(Auth::PairingFinish.path(), &()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let logged_in: User = body_json(response).await;
+        assert_eq!(logged_in.id, user.id);
+        assert!(logged_in.last_login_date.is_some());
+        assert_eq!(app.get(Object::User.path()).await.status(), StatusCode::OK);
+
+        let response = app.post(Auth::PairingFinish.path(), &()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn denied_pairings_are_removed() {
+        let mut app = TestApp::new().await;
+        let code = start(&mut app).await;
+
+        let mut other = app.client();
+        let response = other.post(Auth::PairingDecision.path(), &decision(&code, true)).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        other.login("someone").await;
+        let response = other.post(Auth::PairingDecision.path(), &decision(&code, false)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app.post(Auth::PairingFinish.path(), &()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = other.post(Auth::PairingDecision.path(), &decision(&code, true)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn starting_again_replaces_the_code() {
+        let mut app = TestApp::new().await;
+        let first = start(&mut app).await;
+        let second = start(&mut app).await;
+
+        assert!(app.state.pairing_requests.get(&first.code).is_none());
+        assert!(app.state.pairing_requests.get(&second.code).is_some());
+    }
+
+    #[tokio::test]
+    async fn the_requesting_session_is_told_about_decisions() {
+        let mut app = TestApp::new().await;
+        let code = start(&mut app).await;
+        let session_id = app.state.pairing_requests.get(&code.code).unwrap().session_id;
+
+        let (sender, receiver) = loole::bounded(1);
+        app.state
+            .websocket_clients_by_user_id
+            .add(session_id, Client::new(([127, 0, 0, 1], 1234).into(), sender));
+
+        let mut other = app.client();
+        other.login("someone").await;
+        other.post(Auth::PairingDecision.path(), &decision(&code, true)).await;
+
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(ClientControlMessage::Pairing { approved: true })
+        ));
+    }
+}
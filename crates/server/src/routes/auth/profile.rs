@@ -0,0 +1,165 @@
+//! The parts of the user they can edit themselves and their preferences
+
+use axum::Json;
+use chrono::Utc;
+use shared::{
+    api::{error::ServerError, payloads::UpdateProfileRequest, response_errors::ModelError},
+    model::{Preferences, User, ValidateModel},
+};
+
+use crate::{db::DatabaseConnection, UserState};
+
+/// Sets the user's email and display name
+pub async fn update_profile(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Json(request): Json<UpdateProfileRequest>,
+) -> Result<Json<User>, ServerError<ModelError>> {
+    request.validate().map_err(ModelError::from)?;
+
+    let user = conn
+        .interact(move |conn| {
+            let mut user = user_state.id.fetch_full_user(conn)?;
+
+            user.email = request.email();
+            user.display_name = request.display_name();
+            user.last_updated_date = Utc::now();
+            user.update(conn)?;
+
+            Ok::<_, ServerError<_>>(user)
+        })
+        .await??;
+
+    Ok(Json(user))
+}
+
+pub async fn fetch_preferences(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+) -> Result<Json<Preferences>, ServerError<ModelError>> {
+    let user = conn.interact(move |conn| user_state.id.fetch_full_user(conn)).await??;
+
+    Ok(Json(user.preferences))
+}
+
+/// Replaces the user's preferences with the ones given
+pub async fn update_preferences(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Json(preferences): Json<Preferences>,
+) -> Result<Json<User>, ServerError<ModelError>> {
+    preferences.validate().map_err(ModelError::from)?;
+
+    let user = conn
+        .interact(move |conn| {
+            let mut user = user_state.id.fetch_full_user(conn)?;
+
+            user.preferences = preferences;
+            user.last_updated_date = Utc::now();
+            user.update(conn)?;
+
+            Ok::<_, ServerError<_>>(user)
+        })
+        .await??;
+
+    Ok(Json(user))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use chrono::Weekday;
+    use shared::{
+        api::{payloads::UpdateProfileRequest, Object},
+        model::{Preferences, Units, User},
+    };
+
+    use crate::test_support::{body_json, TestApp};
+
+    #[tokio::test]
+    async fn profiles_need_a_login() {
+        let mut app = TestApp::new().await;
+
+        let response = app.patch(Object::User.path(), &UpdateProfileRequest::default()).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(app.get(Object::Preferences.path()).await.status(), StatusCode::UNAUTHORIZED);
+        let response = app.patch(Object::Preferences.path(), &Preferences::default()).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn profiles_can_be_updated() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        assert_eq!(user.email, None);
+
+        let request = UpdateProfileRequest {
+            email: Some(" someone@example.com ".to_string()),
+            display_name: Some("Some One".to_string()),
+        };
+        let response = app.patch(Object::User.path(), &request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let updated: User = body_json(response).await;
+        assert_eq!(updated.email.as_deref(), Some("someone@example.com"));
+        assert_eq!(updated.display_name.as_deref(), Some("Some One"));
+        assert!(updated.last_updated_date > user.last_updated_date);
+
+        // Blank removes them
+        let request = UpdateProfileRequest {
+            email: Some(String::new()),
+            display_name: Some(" ".to_string()),
+        };
+        let updated: User = body_json(app.patch(Object::User.path(), &request).await).await;
+        assert_eq!((updated.email, updated.display_name), (None, None));
+    }
+
+    #[tokio::test]
+    async fn invalid_profiles_are_rejected() {
+        let mut app = TestApp::new().await;
+        app.login("someone").await;
+
+        for request in [
+            UpdateProfileRequest { email: Some("not an email".to_string()), display_name: None },
+            UpdateProfileRequest {
+                email: Some("nobody@localhost".to_string()),
+                display_name: None,
+            },
+            UpdateProfileRequest { email: None, display_name: Some("x".repeat(65)) },
+        ] {
+            let response = app.patch(Object::User.path(), &request).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{request:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn preferences_default_then_can_be_replaced() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        assert_eq!(user.preferences, Preferences::default());
+
+        let response = app.get(Object::Preferences.path()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let preferences: Preferences = body_json(response).await;
+        assert_eq!(preferences, Preferences::default());
+
+        let mut preferences = Preferences {
+            units: Units::Imperial,
+            first_day_of_week: Weekday::Sun,
+            timezone: Some("Pacific/Auckland".to_string()),
+            ..Default::default()
+        };
+        preferences.rest_timer.seconds = 120;
+        preferences.notifications.session_reminders = false;
+        let response = app.patch(Object::Preferences.path(), &preferences).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let updated: User = body_json(response).await;
+        assert_eq!(updated.preferences, preferences);
+
+        let fetched: Preferences = body_json(app.get(Object::Preferences.path()).await).await;
+        assert_eq!(fetched, preferences);
+
+        preferences.timezone = Some("Not a timezone".to_string());
+        let response = app.patch(Object::Preferences.path(), &preferences).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,273 @@
+//! One-time recovery codes to get back into an account after losing every
+//! passkey. Only a hash of each code is stored
+
+use axum::{extract::State, Json};
+use base64::prelude::{Engine as _, BASE64_STANDARD_NO_PAD};
+use chrono::Utc;
+use rand::{seq::SliceRandom, thread_rng};
+use rusqlite::Connection;
+use sha2::{Digest, Sha256};
+use shared::{
+    api::{
+        error::ServerError,
+        payloads::{normalize_recovery_code, RecoverRequest, RecoveryCodes, RECOVERY_CODE_LENGTH},
+        response_errors::RecoveryError,
+    },
+    model::{Model, RecoveryCode, User, UserIden, ValidateModel},
+    types::Uuid,
+};
+use tracing::{error, warn};
+
+use crate::{
+    constants::RECOVERY_CODE_COUNT, db::DatabaseConnection, ClientControlMessage, RecoveryAttempts,
+    SessionClients, SessionValue, UserState,
+};
+
+/// Leaves out characters that are easily confused like 0/O and 1/I
+const RECOVERY_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// Codes are shown in groups of this many characters
+const RECOVERY_CODE_GROUP: usize = 4;
+
+/// Hashes the code once normalized. The user id is included so identical codes
+/// for different users don't have the same hash
+fn hash_code(user_id: &Uuid, code: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(user_id.to_string());
+    hasher.update(normalize_recovery_code(code));
+    BASE64_STANDARD_NO_PAD.encode(hasher.finalize())
+}
+
+fn generate_code() -> String {
+    let mut rng = thread_rng();
+    let code = (0..RECOVERY_CODE_LENGTH)
+        .map(|_| *RECOVERY_CODE_ALPHABET.choose(&mut rng).unwrap() as char)
+        .collect::<Vec<_>>();
+
+    code.chunks(RECOVERY_CODE_GROUP)
+        .map(|g| g.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Replaces the user's recovery codes with new ones and returns them. This is
+/// the only time the codes are available. Callers need to be in a transaction
+pub fn create_recovery_codes<T: std::error::Error>(
+    tx: &Connection,
+    user_id: &Uuid,
+) -> Result<RecoveryCodes, ServerError<T>> {
+    let codes = (0..RECOVERY_CODE_COUNT).map(|_| generate_code()).collect::<Vec<_>>();
+    let hashes = codes.iter().map(|c| hash_code(user_id, c)).collect();
+
+    RecoveryCode::replace_for_user(tx, user_id, hashes)?;
+
+    Ok(RecoveryCodes { codes })
+}
+
+/// Logs the user in with one of their recovery codes, using it up. From there
+/// they can register a new passkey. Every attempt is logged and limited per
+/// username
+pub async fn recover(
+    DatabaseConnection(conn): DatabaseConnection,
+    State(attempts): State<RecoveryAttempts>,
+    mut session: SessionValue,
+    clients: Option<SessionClients>,
+    Json(request): Json<RecoverRequest>,
+) -> Result<Json<User>, ServerError<RecoveryError>> {
+    // Whatever happens the current user is logged out
+    session.take_user_state().await?;
+
+    request.validate().map_err(RecoveryError::from)?;
+
+    let username = request.username.clone();
+    if !attempts.attempt(&username) {
+        warn!("Recovery attempt for {username} rejected, too many attempts");
+        Err(RecoveryError::TooManyAttempts)?;
+    }
+
+    let user = conn
+        .interact(move |conn| {
+            let tx = conn.transaction()?;
+
+            // Unknown users get the same error as a wrong code so usernames can't be
+            // discovered this way
+            let Some(mut user) =
+                User::fetch_by_column_maybe(&tx, &request.username, UserIden::Username)?
+            else {
+                return Ok(None);
+            };
+
+            let code_hash = hash_code(&user.id, &request.code);
+            if !RecoveryCode::redeem(&tx, &user.id, &code_hash)? {
+                return Ok(None);
+            }
+
+            let now = Utc::now();
+            user.last_updated_date = now;
+            user.last_login_date = Some(now);
+            user.update(&tx)?;
+
+            tx.commit()?;
+
+            Ok::<_, ServerError<RecoveryError>>(Some(user))
+        })
+        .await??;
+
+    let Some(user) = user else {
+        warn!("Recovery attempt for {username} failed, invalid code");
+        return Err(RecoveryError::InvalidCode.into());
+    };
+
+    warn!("Recovery code used to log in as {username} ({})", user.id);
+    session.set_user_state(&user).await?;
+
+    if let Some(clients) = clients {
+        for client in clients.clients {
+            if let Err(e) = client.send(ClientControlMessage::Login((&user).into())).await {
+                error!("Error sending ClientControlMessage for user {user:?}: {e:?}");
+            }
+        }
+    }
+
+    Ok(Json(user))
+}
+
+/// Replaces the logged in user's recovery codes, used or not, with a new set
+pub async fn regenerate_recovery_codes(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+) -> Result<Json<RecoveryCodes>, ServerError<RecoveryError>> {
+    let user_id = user_state.id.clone();
+    let codes = conn
+        .interact(move |conn| {
+            let tx = conn.transaction()?;
+            let codes = create_recovery_codes(&tx, &user_id)?;
+            tx.commit()?;
+            Ok::<_, ServerError<_>>(codes)
+        })
+        .await??;
+
+    warn!("Recovery codes regenerated for {}", user_state.id);
+
+    Ok(Json(codes))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use shared::{
+        api::{
+            payloads::{RecoverRequest, RecoveryCodes},
+            Auth, Object,
+        },
+        model::User,
+    };
+
+    use crate::{
+        constants::{RECOVERY_ATTEMPT_LIMIT, RECOVERY_CODE_COUNT},
+        test_support::{body_json, SoftAuthenticator, TestApp},
+    };
+
+    fn request(username: &str, code: &str) -> RecoverRequest {
+        RecoverRequest { username: username.to_string(), code: code.to_string() }
+    }
+
+    async fn register(app: &mut TestApp) -> RecoveryCodes {
+        let mut authenticator = SoftAuthenticator::new(&app.state.args);
+        let response = app.register_with(&mut authenticator, "someone").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        body_json(response).await
+    }
+
+    #[tokio::test]
+    async fn registering_gives_recovery_codes() {
+        let mut app = TestApp::new().await;
+        let RecoveryCodes { codes } = register(&mut app).await;
+
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert!(codes.iter().all(|c| c.len() == 19 && c.split('-').count() == 4));
+    }
+
+    #[tokio::test]
+    async fn a_code_logs_in_once() {
+        let mut app = TestApp::new().await;
+        let RecoveryCodes { codes } = register(&mut app).await;
+
+        // Separators and case don't matter
+        let code = codes[0].replace('-', " ").to_lowercase();
+        let response = app.post(Auth::Recover.path(), &request("someone", &code)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let user: User = body_json(response).await;
+        assert_eq!(user.username, "someone");
+        assert!(user.last_login_date.is_some());
+
+        // Logged in well enough to add a new passkey
+        let response = app.get(Object::User.path()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut authenticator = SoftAuthenticator::new(&app.state.args);
+        let response = app.post(Auth::RegisterNewKeyStart.path(), &()).await;
+        let challenge = authenticator.register(&body_json(response).await);
+        let response = app.post(Auth::RegisterNewKeyFinish.path(), &challenge).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let mut other = app.client();
+        let response = other.post(Auth::Recover.path(), &request("someone", &codes[0])).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = other.post(Auth::Recover.path(), &request("someone", &codes[1])).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn wrong_codes_and_users_are_rejected() {
+        let mut app = TestApp::new().await;
+        let RecoveryCodes { codes } = register(&mut app).await;
+
+        let response = app.post(Auth::Recover.path(), &request("someone", "AAAA-AAAA")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response =
+            app.post(Auth::Recover.path(), &request("someone", "AAAA-AAAA-AAAA-AAAA")).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.post(Auth::Recover.path(), &request("nobody", &codes[0])).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        assert_eq!(app.get(Object::User.path()).await.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn attempts_are_limited_per_username() {
+        let mut app = TestApp::new().await;
+        let RecoveryCodes { codes } = register(&mut app).await;
+
+        for _ in 0..RECOVERY_ATTEMPT_LIMIT {
+            let response =
+                app.post(Auth::Recover.path(), &request("someone", "AAAA-AAAA-AAAA-AAAA")).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        // Even the right code is refused now
+        let response = app.post(Auth::Recover.path(), &request("someone", &codes[0])).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn regenerating_replaces_the_codes() {
+        let mut app = TestApp::new().await;
+        assert_eq!(
+            app.post(Auth::RecoveryCodes.path(), &()).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let RecoveryCodes { codes: old } = register(&mut app).await;
+        app.post(Auth::Recover.path(), &request("someone", &old[0])).await;
+
+        let response = app.post(Auth::RecoveryCodes.path(), &()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let RecoveryCodes { codes: new } = body_json(response).await;
+        assert_eq!(new.len(), RECOVERY_CODE_COUNT);
+
+        let response = app.post(Auth::Recover.path(), &request("someone", &old[1])).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.post(Auth::Recover.path(), &request("someone", &new[0])).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -1,6 +1,9 @@
 use axum::Json;
 use shared::{
-    api::error::{Nothing, ServerError},
+    api::{
+        error::{Nothing, ServerError},
+        payloads::RecoveryCodes,
+    },
     model::NewUserWithPasskey,
     unauthorized_error,
 };
@@ -8,8 +11,8 @@ use tracing::error;
 use webauthn_rs::prelude::RegisterPublicKeyCredential;
 
 use crate::{
-    db::DatabaseConnection, ClientControlMessage, PasskeyRegistrationState, SessionClients,
-    SessionValue, Webauthn,
+    db::DatabaseConnection, routes::auth::create_recovery_codes, ClientControlMessage,
+    PasskeyRegistrationState, SessionClients, SessionValue, Webauthn,
 };
 
 pub async fn register_finish(
@@ -18,7 +21,7 @@ pub async fn register_finish(
     mut session: SessionValue,
     clients: Option<SessionClients>,
     Json(register_public_key_credential): Json<RegisterPublicKeyCredential>,
-) -> Result<Json<()>, ServerError<Nothing>> {
+) -> Result<Json<RecoveryCodes>, ServerError<Nothing>> {
     // Get the challenge from the session
     let PasskeyRegistrationState { username, id, passkey_registration } =
         session.take_passkey_registration_state().await?.ok_or(unauthorized_error!(
@@ -30,10 +33,18 @@ pub async fn register_finish(
     let passkey = webauthn
         .finish_passkey_registration(&register_public_key_credential, &passkey_registration)?;
 
-    // Create the new user with their passkey
+    // Create the new user with their passkey and the recovery codes to use if it's lost. Both
+    // go in together so a user is never left without recovery codes
     let new_user = NewUserWithPasskey::new(id, username, passkey);
-    let (user, _) =
-        conn.interact(move |conn| Ok::<_, ServerError<_>>(new_user.create(conn)?)).await??;
+    let (user, recovery_codes) = conn
+        .interact(move |conn| {
+            let tx = conn.transaction()?;
+            let (user, _) = new_user.create(&tx)?;
+            let recovery_codes = create_recovery_codes(&tx, &user.id)?;
+            tx.commit()?;
+            Ok::<_, ServerError<_>>((user, recovery_codes))
+        })
+        .await??;
 
     if let Some(clients) = clients {
         for client in clients.clients {
@@ -43,5 +54,5 @@ pub async fn register_finish(
         }
     }
 
-    Ok(Json(()))
+    Ok(Json(recovery_codes))
 }
@@ -2,7 +2,6 @@ use axum::Json;
 use shared::{
     api::{error::ServerError, response_errors::RegisterError},
     model::Credential,
-    unauthorized_error,
 };
 use webauthn_rs::prelude::CreationChallengeResponse;
 
@@ -24,7 +23,8 @@ pub async fn register_new_key_start(
         conn.interact(move |conn| {
             // We need the username for the challenge so fetch the full user
             let user = user_id.fetch_full_user(conn)?;
-            // Fetch the existing passkeys for this user
+            // Fetch the existing passkeys for this user. There may be none if they logged in
+            // with a recovery code
             let passkeys = Credential::fetch_passkeys(conn, &*user_id)?
                 .into_iter()
                 // We only want the ID for this step
@@ -36,14 +36,6 @@ pub async fn register_new_key_start(
         .await??
     };
 
-    if existing_key_ids.is_empty() {
-        // Log the user out
-        let _ = session.take_user_state().await?;
-        Err(unauthorized_error!(
-            "No existing keys found. It is now impossible to log in to this account"
-        ))?;
-    }
-
     // Start the registration challenge
     let (creation_challenge_response, passkey_registration) = webauthn.start_passkey_registration(
         *user.id,
@@ -1,58 +1,359 @@
 use axum::{
-    extract::Path,
+    extract::{Path, State},
     http::StatusCode,
     response::{IntoResponse, Redirect},
+    Json,
 };
 use client::ClientRoutes;
 use shared::{
-    api::error::{Nothing, ServerError},
+    api::{
+        error::{Nothing, ServerError},
+        payloads::TemporaryLoginPinRequest,
+        response_errors::TemporaryLoginError,
+    },
     bad_request_error,
-    model::{TemporaryLogin, User},
+    model::{
+        Model, TemporaryLogin, TemporaryLoginAudit, TemporaryLoginEvent, User, UserIden,
+        ValidateModel,
+    },
     status_code_error,
     types::Uuid,
 };
+use tracing::{error, warn};
 
-use crate::{db::DatabaseConnection, SessionValue};
+use crate::{
+    db::DatabaseConnection, ClientAddress, ClientControlMessage, SessionClients, SessionValue,
+    TemporaryLoginAttempts,
+};
+
+/// Codes are limited by the address they're tried from as there's no username
+/// to go on
+fn address_key(address: &ClientAddress) -> String {
+    format!("code:{}", address.ip_address.as_deref().unwrap_or("unknown"))
+}
+
+fn username_key(username: &str) -> String {
+    format!("pin:{username}")
+}
 
 pub async fn temporary_login(
     DatabaseConnection(conn): DatabaseConnection,
+    State(attempts): State<TemporaryLoginAttempts>,
     mut session: SessionValue,
+    address: ClientAddress,
     code: Path<String>,
 ) -> Result<impl IntoResponse, ServerError<Nothing>> {
-    let id =
-        Uuid::parse(&code).map_err(|_| bad_request_error!("Invalid code ({})", code.as_str()))?;
+    let key = address_key(&address);
+    if !attempts.attempt(&key) {
+        warn!("Temporary login from {key} rejected, too many attempts");
+        Err(status_code_error!(StatusCode::TOO_MANY_REQUESTS, "Too many attempts"))?;
+    }
+
+    let Ok(id) = Uuid::parse(&code) else {
+        return Err(bad_request_error!("Invalid code ({})", code.as_str()));
+    };
 
     let user = conn
         .interact(move |conn| {
+            let tx = conn.transaction()?;
+
             // Find the temporary login for the given code
-            let temporary_login = if let Some(tl) = TemporaryLogin::fetch_maybe(conn, &id)? {
-                // Check if it has expired
-                if tl.expired() {
-                    tl.delete(conn)?;
-                    Err(status_code_error!(
-                        StatusCode::GONE,
-                        "The code has expired ({})",
-                        code.as_str()
-                    ))
-                } else {
-                    Ok(tl)
-                }
-            } else {
-                Err(bad_request_error!("Code does not exist ({})", code.as_str()))
-            }?;
+            let Some(temporary_login) = TemporaryLogin::fetch_maybe(&tx, &id)? else {
+                return Ok(None);
+            };
+            // Check if it has expired, the delete is committed before the error so
+            // it isn't rolled back with it
+            if temporary_login.expired() {
+                temporary_login.delete(&tx)?;
+                tx.commit()?;
+                return Err(status_code_error!(
+                    StatusCode::GONE,
+                    "The code has expired ({})",
+                    code.as_str()
+                ));
+            }
 
             // Get the user to log in with this code
-            let user = User::fetch_by_id(conn, &temporary_login.user_id)?;
+            let user = User::fetch_by_id(&tx, temporary_login.user_id)?;
             // Delete the code now it's been used
-            temporary_login.delete(conn)?;
+            temporary_login.delete(&tx)?;
+            TemporaryLoginAudit::record(
+                &tx,
+                &user.id,
+                &temporary_login.id,
+                TemporaryLoginEvent::Used,
+                address.ip_address,
+                address.user_agent,
+            )?;
+            tx.commit()?;
 
-            Ok::<_, ServerError<_>>(user)
+            Ok::<_, ServerError<Nothing>>(Some(user))
         })
         .await??;
 
+    let Some(user) = user else {
+        return Err(bad_request_error!("Code does not exist ({id})"));
+    };
+
     // Log the user in
     session.set_user_state(&user).await?;
 
     // Redirect the user to the index page
     Ok(Redirect::to(ClientRoutes::Profile.path()))
 }
+
+/// Logs in with the pin of the user's outstanding temporary login, using it up.
+/// Attempts are limited per username
+pub async fn temporary_login_pin(
+    DatabaseConnection(conn): DatabaseConnection,
+    State(attempts): State<TemporaryLoginAttempts>,
+    mut session: SessionValue,
+    clients: Option<SessionClients>,
+    address: ClientAddress,
+    Json(request): Json<TemporaryLoginPinRequest>,
+) -> Result<Json<User>, ServerError<TemporaryLoginError>> {
+    request.validate().map_err(TemporaryLoginError::from)?;
+
+    let username = request.username.clone();
+    let key = username_key(&username);
+    if !attempts.attempt(&key) {
+        warn!("Temporary login pin for {username} rejected, too many attempts");
+        Err(TemporaryLoginError::TooManyAttempts)?;
+    }
+
+    let user = conn
+        .interact(move |conn| {
+            let tx = conn.transaction()?;
+
+            // Unknown users get the same error as a wrong pin so usernames can't be
+            // discovered this way
+            let Some(user) =
+                User::fetch_by_column_maybe(&tx, &request.username, UserIden::Username)?
+            else {
+                return Ok(None);
+            };
+            let Some(temporary_login) = TemporaryLogin::fetch_by_user_id(&tx, &user.id)? else {
+                return Ok(None);
+            };
+
+            let event = if temporary_login.expired() {
+                temporary_login.delete(&tx)?;
+                None
+            } else if temporary_login.pin.as_deref() == Some(request.pin.trim()) {
+                temporary_login.delete(&tx)?;
+                Some(TemporaryLoginEvent::Used)
+            } else {
+                Some(TemporaryLoginEvent::Failed)
+            };
+
+            if let Some(event) = event {
+                TemporaryLoginAudit::record(
+                    &tx,
+                    &user.id,
+                    &temporary_login.id,
+                    event,
+                    address.ip_address,
+                    address.user_agent,
+                )?;
+            }
+            tx.commit()?;
+
+            Ok::<_, ServerError<TemporaryLoginError>>(
+                (event == Some(TemporaryLoginEvent::Used)).then_some(user),
+            )
+        })
+        .await??;
+
+    let Some(user) = user else {
+        warn!("Temporary login pin for {username} failed");
+        return Err(TemporaryLoginError::InvalidPin.into());
+    };
+
+    session.set_user_state(&user).await?;
+
+    if let Some(clients) = clients {
+        for client in clients.clients {
+            if let Err(e) = client.send(ClientControlMessage::Login((&user).into())).await {
+                error!("Error sending ClientControlMessage for user {user:?}: {e:?}");
+            }
+        }
+    }
+
+    Ok(Json(user))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use shared::{
+        api::{
+            error::Nothing,
+            payloads::{
+                CreateTemporaryLoginRequest, TemporaryLoginPinRequest, TEMPORARY_LOGIN_PIN_LENGTH,
+            },
+            Auth, Object,
+        },
+        model::{TemporaryLogin, TemporaryLoginAudit, TemporaryLoginEvent, User},
+        types::Uuid,
+    };
+
+    use crate::{
+        constants::TEMPORARY_LOGIN_ATTEMPT_LIMIT,
+        test_support::{body_json, with_id, TestApp},
+    };
+
+    fn request(username: &str, pin: &str) -> TemporaryLoginPinRequest {
+        TemporaryLoginPinRequest { username: username.to_string(), pin: pin.to_string() }
+    }
+
+    async fn create(app: &mut TestApp, pin: bool) -> TemporaryLogin {
+        let response =
+            app.post(Auth::CreateTemporaryLogin.path(), &CreateTemporaryLoginRequest { pin }).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        body_json(response).await
+    }
+
+    async fn events(app: &TestApp, user: &User) -> Vec<TemporaryLoginEvent> {
+        let user_id = user.id;
+        let mut audit = app
+            .interact(move |conn| {
+                TemporaryLoginAudit::fetch_for_user::<Nothing>(conn, &user_id).unwrap()
+            })
+            .await;
+        audit.sort_by_key(|a| a.creation_date);
+        audit.into_iter().map(|a| a.event).collect()
+    }
+
+    /// Not the pin, but the same length so it gets past validation
+    fn wrong_pin(temporary_login: &TemporaryLogin) -> String {
+        let pin = temporary_login.pin.as_deref().unwrap();
+        pin.chars().map(|c| if c == '0' { '1' } else { '0' }).collect()
+    }
+
+    #[tokio::test]
+    async fn pins_are_only_made_when_asked_for() {
+        let mut app = TestApp::new().await;
+        app.login("someone").await;
+
+        assert_eq!(create(&mut app, false).await.pin, None);
+        app.post(Auth::RevokeTemporaryLogin.path(), &()).await;
+
+        let pin = create(&mut app, true).await.pin.unwrap();
+        assert_eq!(pin.len(), TEMPORARY_LOGIN_PIN_LENGTH);
+        assert!(pin.chars().all(|c| c.is_ascii_digit()));
+    }
+
+    #[tokio::test]
+    async fn a_pin_logs_another_device_in_once() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        let temporary_login = create(&mut app, true).await;
+        let pin = temporary_login.pin.clone().unwrap();
+
+        let mut other = app.client();
+        let response = other.post(Auth::TemporaryLoginPin.path(), &request("someone", &pin)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let logged_in: User = body_json(response).await;
+        assert_eq!(logged_in.id, user.id);
+        assert_eq!(other.get(Object::User.path()).await.status(), StatusCode::OK);
+
+        let mut another = app.client();
+        let response =
+            another.post(Auth::TemporaryLoginPin.path(), &request("someone", &pin)).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        // The url went with it
+        let path = with_id(Auth::TemporaryLogin.path(), temporary_login.id);
+        assert_eq!(another.get(&path).await.status(), StatusCode::BAD_REQUEST);
+
+        // The first use is the test login
+        assert_eq!(events(&app, &user).await, [
+            TemporaryLoginEvent::Used,
+            TemporaryLoginEvent::Created,
+            TemporaryLoginEvent::Used
+        ]);
+    }
+
+    #[tokio::test]
+    async fn wrong_pins_are_limited_per_username() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        let temporary_login = create(&mut app, true).await;
+        let pin = temporary_login.pin.clone().unwrap();
+
+        let mut other = app.client();
+        let response = other.post(Auth::TemporaryLoginPin.path(), &request("someone", "12")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = other.post(Auth::TemporaryLoginPin.path(), &request("nobody", &pin)).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        for _ in 0..TEMPORARY_LOGIN_ATTEMPT_LIMIT {
+            let request = request("someone", &wrong_pin(&temporary_login));
+            let response = other.post(Auth::TemporaryLoginPin.path(), &request).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        // Even the right pin is refused now
+        let response = other.post(Auth::TemporaryLoginPin.path(), &request("someone", &pin)).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(other.get(Object::User.path()).await.status(), StatusCode::UNAUTHORIZED);
+
+        let events = events(&app, &user).await;
+        assert_eq!(
+            events.iter().filter(|e| **e == TemporaryLoginEvent::Failed).count(),
+            TEMPORARY_LOGIN_ATTEMPT_LIMIT
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_codes_are_limited_per_address() {
+        let mut app = TestApp::new().await;
+        let user = app.create_user("someone").await;
+        let temporary_login = app.create_temporary_login(&user, chrono::Duration::minutes(1)).await;
+
+        for _ in 0..TEMPORARY_LOGIN_ATTEMPT_LIMIT {
+            let path = with_id(Auth::TemporaryLogin.path(), Uuid::new_v4());
+            assert_eq!(app.get(&path).await.status(), StatusCode::BAD_REQUEST);
+        }
+
+        let path = with_id(Auth::TemporaryLogin.path(), temporary_login.id);
+        assert_eq!(app.get(&path).await.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(app.get(Object::User.path()).await.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn outstanding_codes_can_be_revoked() {
+        let mut app = TestApp::new().await;
+        assert_eq!(
+            app.post(Auth::RevokeTemporaryLogin.path(), &()).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let user = app.login("someone").await;
+        let temporary_login = create(&mut app, true).await;
+
+        let response = app.post(Auth::RevokeTemporaryLogin.path(), &()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let (_, fetched): (User, Option<TemporaryLogin>) =
+            body_json(app.get(Object::User.path()).await).await;
+        assert_eq!(fetched, None);
+
+        let mut other = app.client();
+        let path = with_id(Auth::TemporaryLogin.path(), temporary_login.id);
+        assert_eq!(other.get(&path).await.status(), StatusCode::BAD_REQUEST);
+        let pin = temporary_login.pin.unwrap();
+        let response = other.post(Auth::TemporaryLoginPin.path(), &request("someone", &pin)).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Nothing left to revoke is fine, and a new one can be made
+        let response = app.post(Auth::RevokeTemporaryLogin.path(), &()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        create(&mut app, false).await;
+
+        assert_eq!(events(&app, &user).await, [
+            TemporaryLoginEvent::Used,
+            TemporaryLoginEvent::Created,
+            TemporaryLoginEvent::Revoked,
+            TemporaryLoginEvent::Created
+        ]);
+    }
+}
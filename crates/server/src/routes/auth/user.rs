@@ -2,17 +2,25 @@ use std::time::Duration;
 
 use axum::Json;
 use chrono::Utc;
+use rand::{thread_rng, Rng};
 use shared::{
     api::{
         error::ServerError,
+        payloads::{CreateTemporaryLoginRequest, TEMPORARY_LOGIN_PIN_LENGTH},
         response_errors::{FetchError, TemporaryLoginError},
         Auth,
     },
-    model::{TemporaryLogin, User},
+    model::{TemporaryLogin, TemporaryLoginAudit, TemporaryLoginEvent, User},
     types::Uuid,
 };
+use tracing::info;
 
-use crate::{db::DatabaseConnection, state::Args, UserState};
+use crate::{db::DatabaseConnection, state::Args, ClientAddress, UserState};
+
+fn generate_pin() -> String {
+    let max = 10_u32.pow(TEMPORARY_LOGIN_PIN_LENGTH as u32);
+    format!("{:0width$}", thread_rng().gen_range(0..max), width = TEMPORARY_LOGIN_PIN_LENGTH)
+}
 
 pub async fn fetch_user(
     DatabaseConnection(conn): DatabaseConnection,
@@ -39,10 +47,15 @@ pub async fn fetch_user(
     Ok(Json(res))
 }
 
+/// Creates a code the user can log in with on another device. Only one can be
+/// outstanding at a time, it has to expire or be revoked before there can be
+/// another
 pub async fn create_temporary_login(
     DatabaseConnection(conn): DatabaseConnection,
     user_state: UserState,
     args: Args,
+    address: ClientAddress,
+    Json(request): Json<CreateTemporaryLoginRequest>,
 ) -> Result<Json<TemporaryLogin>, ServerError<TemporaryLoginError>> {
     let temporary_login = conn
         .interact(move |conn| {
@@ -69,11 +82,49 @@ pub async fn create_temporary_login(
                 user_id: (*user_state.id).clone(),
                 expiry_date,
                 url,
+                pin: request.pin.then(generate_pin),
             })?;
 
+            TemporaryLoginAudit::record(
+                conn,
+                &temporary_login.user_id,
+                &temporary_login.id,
+                TemporaryLoginEvent::Created,
+                address.ip_address,
+                address.user_agent,
+            )?;
+
             Ok::<_, ServerError<_>>(temporary_login)
         })
         .await??;
 
+    info!("Temporary login {} created for {}", temporary_login.id, temporary_login.user_id);
+
     Ok(Json(temporary_login))
 }
+
+/// Removes the user's outstanding code, if they have one, so it can't be used
+pub async fn revoke_temporary_login(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    address: ClientAddress,
+) -> Result<Json<()>, ServerError<TemporaryLoginError>> {
+    conn.interact(move |conn| {
+        if let Some(temporary_login) = TemporaryLogin::fetch_by_user_id(conn, &user_state.id)? {
+            temporary_login.delete(conn)?;
+            TemporaryLoginAudit::record(
+                conn,
+                &temporary_login.user_id,
+                &temporary_login.id,
+                TemporaryLoginEvent::Revoked,
+                address.ip_address,
+                address.user_agent,
+            )?;
+        }
+
+        Ok::<_, ServerError<TemporaryLoginError>>(())
+    })
+    .await??;
+
+    Ok(Json(()))
+}
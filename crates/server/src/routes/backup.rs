@@ -0,0 +1,116 @@
+//! Server held copies of the client database. The server never looks inside
+//! them, they're only stored so a user can get their training history back
+//! after losing their browser storage
+
+use axum::Json;
+use chrono::Utc;
+use shared::{
+    api::{
+        error::ServerError,
+        payloads::{DatabaseBackup, DatabaseBackupInfo},
+        response_errors::BackupError,
+    },
+    model::{Backup, ValidateModel},
+    types::Uuid,
+};
+
+use crate::{db::DatabaseConnection, UserState};
+
+pub async fn fetch_backup(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+) -> Result<Json<DatabaseBackup>, ServerError<BackupError>> {
+    let backup = conn
+        .interact(move |conn| Backup::fetch_for_user::<BackupError>(conn, &user_state.id))
+        .await??
+        .ok_or(BackupError::NoBackup)?;
+
+    Ok(Json(DatabaseBackup {
+        schema_version: backup.schema_version,
+        creation_date: backup.creation_date,
+        data: backup.data,
+    }))
+}
+
+/// Stores the backup, replacing the previous one. The creation_date in the
+/// response is when the server received it
+pub async fn upload_backup(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Json(backup): Json<DatabaseBackup>,
+) -> Result<Json<DatabaseBackupInfo>, ServerError<BackupError>> {
+    backup.validate().map_err(BackupError::from)?;
+
+    let backup = conn
+        .interact(move |conn| {
+            Backup::replace::<BackupError>(conn, Backup {
+                id: Uuid::new_v4(),
+                user_id: user_state.id.id,
+                schema_version: backup.schema_version,
+                data: backup.data,
+                creation_date: Utc::now(),
+            })
+        })
+        .await??;
+
+    Ok(Json(DatabaseBackupInfo {
+        schema_version: backup.schema_version,
+        creation_date: backup.creation_date,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::{
+        api::error::Nothing,
+        model::{Model, NewUser, User},
+    };
+
+    use super::*;
+    use crate::test_support::connection;
+
+    fn backup(user_id: Uuid, data: &str) -> Backup {
+        Backup {
+            id: Uuid::new_v4(),
+            user_id,
+            schema_version: 1,
+            data: data.to_string(),
+            creation_date: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn only_the_latest_backup_is_kept() {
+        let mut conn = connection();
+        let a = Uuid::new_v4();
+        let b = Uuid::new_v4();
+        User::create::<Nothing>(&mut conn, NewUser::new(a, "a")).unwrap();
+        User::create::<Nothing>(&mut conn, NewUser::new(b, "b")).unwrap();
+
+        Backup::replace::<Nothing>(&mut conn, backup(a, "first")).unwrap();
+        Backup::replace::<Nothing>(&mut conn, backup(b, "other user")).unwrap();
+        Backup::replace::<Nothing>(&mut conn, backup(a, "second")).unwrap();
+
+        let latest = Backup::fetch_for_user::<Nothing>(&conn, &a).unwrap().unwrap();
+        assert_eq!(latest.data, "second");
+        assert_eq!(Backup::fetch_all(&conn).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn backups_are_validated() {
+        let valid = DatabaseBackup {
+            schema_version: 1,
+            creation_date: Utc::now(),
+            data: "U1FMaXRl".to_string(),
+        };
+        assert!(valid.validate().is_ok());
+        assert!(DatabaseBackup { schema_version: 0, ..valid.clone() }.validate().is_err());
+        assert!(DatabaseBackup { data: String::new(), ..valid.clone() }.validate().is_err());
+        assert!(DatabaseBackup {
+            data: "A".repeat(shared::api::payloads::MAX_BACKUP_SIZE + 1),
+            ..valid
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -87,3 +87,48 @@ async fn handler(
 
     Ok(Json(()))
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{
+            header::{CONTENT_LENGTH, CONTENT_TYPE},
+            Method, StatusCode,
+        },
+    };
+    use serde_json::json;
+    use shared::api::Object;
+
+    use super::LOG_MAX_BYTES;
+    use crate::test_support::TestApp;
+
+    #[tokio::test]
+    async fn logs_are_accepted_with_or_without_a_login() {
+        let mut app = TestApp::new().await;
+        let log = json!({ "level": "info", "message": "hello", "extra": 1 });
+
+        let response = app.post(Object::Log.path(), &log).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        app.login("someone").await;
+        let response = app.post(Object::Log.path(), &log).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn oversized_logs_are_rejected() {
+        let mut app = TestApp::new().await;
+        app.get(Object::Ping.path()).await;
+
+        let body = vec![b' '; LOG_MAX_BYTES + 1];
+        let request = app
+            .request(Method::POST, Object::Log.path())
+            .header(CONTENT_TYPE, mime::APPLICATION_JSON.essence_str())
+            .header(CONTENT_LENGTH, body.len())
+            .body(Body::from(body))
+            .unwrap();
+        let response = app.send(request).await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
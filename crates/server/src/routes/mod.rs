@@ -1,6 +1,8 @@
 pub mod auth;
+pub mod backup;
 pub mod logging;
 pub mod notifications;
 pub mod ping;
 pub mod rtc;
+pub mod training;
 pub mod websocket;
@@ -6,3 +6,285 @@ pub use update_push_subscription::*;
 
 mod remove_push_subscription;
 pub use remove_push_subscription::*;
+
+mod reminder_action;
+pub use reminder_action::*;
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{
+            header::{ACCEPT, CONTENT_TYPE},
+            Method, Request, StatusCode,
+        },
+    };
+    use chrono::{Duration, Utc};
+    use shared::{
+        api::{
+            error::Nothing,
+            payloads::{
+                NotificationAction, ReminderActionRequest, RemoveSubscriptionRequest,
+                UpdateSubscriptionRequest, VapidResponse,
+            },
+            Object, CSRF_HEADER,
+        },
+        model::{
+            Model, PushNotificationSubscription, PushSubscription, Reminder, ReminderKind,
+            ReminderState, User,
+        },
+        types::Uuid,
+    };
+
+    use crate::test_support::{body_json, with_id, TestApp};
+
+    async fn subscriptions_of(app: &TestApp, user: &User) -> Vec<PushSubscription> {
+        let id = user.id;
+        let mut subscriptions = app
+            .interact(move |conn| PushSubscription::fetch_for_user::<Nothing>(conn, &id))
+            .await
+            .unwrap();
+        subscriptions.sort_by(|a, b| a.endpoint.cmp(&b.endpoint));
+        subscriptions
+    }
+
+    fn subscription(device: &str) -> PushNotificationSubscription {
+        PushNotificationSubscription {
+            endpoint: format!("https://push.example.com/{device}"),
+            key: "key".to_string(),
+            auth: "auth".to_string(),
+        }
+    }
+
+    fn request(device: &str, device_label: Option<&str>) -> UpdateSubscriptionRequest {
+        UpdateSubscriptionRequest {
+            subscription: subscription(device),
+            device_label: device_label.map(str::to_string),
+        }
+    }
+
+    fn remove_request(device: &str) -> RemoveSubscriptionRequest {
+        RemoveSubscriptionRequest { endpoint: subscription(device).endpoint }
+    }
+
+    #[tokio::test]
+    async fn vapid_key_is_only_for_logged_in_users() {
+        let mut app = TestApp::new().await;
+        assert_eq!(app.get(Object::Vapid.path()).await.status(), StatusCode::UNAUTHORIZED);
+
+        app.login("someone").await;
+        let response = app.get(Object::Vapid.path()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let vapid: VapidResponse = body_json(response).await;
+        assert_eq!(vapid.key, app.state.vapid_pub_key.bytes());
+    }
+
+    #[tokio::test]
+    async fn push_subscriptions_can_be_added_and_removed() {
+        let mut app = TestApp::new().await;
+
+        let response = app.post(Object::PushSubscription.path(), &request("a", None)).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.delete_with(Object::PushSubscription.path(), &remove_request("a")).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let user = app.login("someone").await;
+
+        let response = app.post(Object::PushSubscription.path(), &request("a", None)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let subscriptions = subscriptions_of(&app, &user).await;
+        assert_eq!(subscriptions.len(), 1);
+        assert_eq!(subscriptions[0].subscription(), subscription("a"));
+
+        let response = app.delete_with(Object::PushSubscription.path(), &remove_request("a")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(subscriptions_of(&app, &user).await, vec![]);
+
+        // Removing it again is only a warning
+        let response = app.delete_with(Object::PushSubscription.path(), &remove_request("a")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn each_device_has_its_own_subscription() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+
+        app.post(Object::PushSubscription.path(), &request("laptop", Some(" Laptop "))).await;
+        app.post(Object::PushSubscription.path(), &request("phone", None)).await;
+
+        let subscriptions = subscriptions_of(&app, &user).await;
+        assert_eq!(subscriptions.len(), 2);
+        assert_eq!(subscriptions[0].device_label.as_deref(), Some("Laptop"));
+        // Without a label or user agent there's nothing to call it
+        assert_eq!(subscriptions[1].device_label, None);
+
+        // Subscribing the same endpoint again updates it
+        let mut renewed = request("phone", Some("Phone"));
+        renewed.subscription.auth = "new auth".to_string();
+        app.post(Object::PushSubscription.path(), &renewed).await;
+        let subscriptions = subscriptions_of(&app, &user).await;
+        assert_eq!(subscriptions.len(), 2);
+        assert_eq!(subscriptions[1].device_label.as_deref(), Some("Phone"));
+        assert_eq!(subscriptions[1].auth, "new auth");
+
+        // Unsubscribing one device leaves the other
+        app.delete_with(Object::PushSubscription.path(), &remove_request("phone")).await;
+        let subscriptions = subscriptions_of(&app, &user).await;
+        assert_eq!(subscriptions.len(), 1);
+        assert_eq!(subscriptions[0].subscription(), subscription("laptop"));
+    }
+
+    #[tokio::test]
+    async fn subscriptions_are_only_removed_by_their_user() {
+        let mut app = TestApp::new().await;
+        let someone = app.login("someone").await;
+        app.post(Object::PushSubscription.path(), &request("shared", None)).await;
+
+        let mut other_app = app.client();
+        let other = other_app.login("other").await;
+        let response =
+            other_app.delete_with(Object::PushSubscription.path(), &remove_request("shared")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(subscriptions_of(&app, &someone).await.len(), 1);
+
+        // Subscribing on the same device moves it to whoever did it last
+        other_app.post(Object::PushSubscription.path(), &request("shared", None)).await;
+        assert_eq!(subscriptions_of(&app, &someone).await, vec![]);
+        assert_eq!(subscriptions_of(&app, &other).await.len(), 1);
+    }
+
+    /// An ad-hoc session an hour away with both its reminders, the upcoming
+    /// one already sent
+    async fn session_with_reminders(app: &TestApp, user: &User) -> (Reminder, Reminder) {
+        let now = Utc::now();
+        let planned_date = now + Duration::hours(1);
+        let session = app.create_session(user, None, planned_date).await;
+
+        let user_id = user.id;
+        app.interact(move |conn| {
+            let mut upcoming =
+                Reminder::new(user_id, session.id, ReminderKind::Upcoming, planned_date, now, now);
+            upcoming.state = ReminderState::Sent;
+            upcoming.insert(conn).unwrap();
+            let missed = Reminder::new(
+                user_id,
+                session.id,
+                ReminderKind::Missed,
+                planned_date,
+                planned_date + Duration::days(1),
+                now,
+            );
+            missed.insert(conn).unwrap();
+
+            (upcoming, missed)
+        })
+        .await
+    }
+
+    async fn fetch_reminder(app: &TestApp, reminder: &Reminder) -> Reminder {
+        let id = reminder.id;
+        app.interact(move |conn| Reminder::fetch_by_id(conn, id).unwrap()).await
+    }
+
+    fn reminder_path(reminder: &Reminder) -> String {
+        with_id(Object::ReminderId.path(), reminder.id)
+    }
+
+    fn action(action: NotificationAction) -> ReminderActionRequest {
+        ReminderActionRequest { action }
+    }
+
+    #[tokio::test]
+    async fn reminders_can_be_snoozed() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        let (upcoming, missed) = session_with_reminders(&app, &user).await;
+
+        let before = Utc::now();
+        let response =
+            app.post(&reminder_path(&upcoming), &action(NotificationAction::Snooze)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let snoozed = fetch_reminder(&app, &upcoming).await;
+        assert_eq!(snoozed.state, ReminderState::Pending);
+        let snoozed_until = snoozed.snoozed_until.unwrap();
+        assert!(snoozed_until >= before + Duration::hours(1));
+        assert!(snoozed_until <= Utc::now() + Duration::hours(1));
+        assert_eq!(snoozed.send_date(), snoozed_until);
+
+        assert_eq!(fetch_reminder(&app, &missed).await, missed);
+    }
+
+    #[tokio::test]
+    async fn skipped_reminders_cant_be_snoozed() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        let (upcoming, _) = session_with_reminders(&app, &user).await;
+
+        app.post(&reminder_path(&upcoming), &action(NotificationAction::Skip)).await;
+        let skipped = fetch_reminder(&app, &upcoming).await;
+
+        let response =
+            app.post(&reminder_path(&upcoming), &action(NotificationAction::Snooze)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(fetch_reminder(&app, &upcoming).await, skipped);
+    }
+
+    #[tokio::test]
+    async fn skipping_drops_the_rest_of_the_sessions_reminders() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        let (upcoming, missed) = session_with_reminders(&app, &user).await;
+
+        let response = app.post(&reminder_path(&upcoming), &action(NotificationAction::Skip)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(fetch_reminder(&app, &upcoming).await.state, ReminderState::Skipped);
+        assert_eq!(fetch_reminder(&app, &missed).await.state, ReminderState::Skipped);
+    }
+
+    #[tokio::test]
+    async fn reminder_actions_work_with_a_token_fetched_for_them() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        let (upcoming, _) = session_with_reminders(&app, &user).await;
+
+        // What the service worker does as it has nowhere to keep a token
+        let response = app.get(Object::Ping.path()).await;
+        let token = response.headers()[CSRF_HEADER].clone();
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(reminder_path(&upcoming))
+            .header(CSRF_HEADER, token)
+            .header(ACCEPT, mime::APPLICATION_JSON.essence_str())
+            .header(CONTENT_TYPE, mime::APPLICATION_JSON.essence_str())
+            .body(Body::from(serde_json::to_vec(&action(NotificationAction::Skip)).unwrap()))
+            .unwrap();
+        let response = app.send(request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(fetch_reminder(&app, &upcoming).await.state, ReminderState::Skipped);
+    }
+
+    #[tokio::test]
+    async fn reminder_actions_are_only_for_their_user() {
+        let mut app = TestApp::new().await;
+        let path = with_id(Object::ReminderId.path(), Uuid::new_v4());
+        let response = app.post(&path, &action(NotificationAction::Snooze)).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let user = app.login("someone").await;
+        let (upcoming, _) = session_with_reminders(&app, &user).await;
+
+        let mut other_app = app.client();
+        other_app.login("other").await;
+        let response =
+            other_app.post(&reminder_path(&upcoming), &action(NotificationAction::Skip)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(fetch_reminder(&app, &upcoming).await, upcoming);
+
+        // Opening the app is up to the service worker
+        let response =
+            app.post(&reminder_path(&upcoming), &action(NotificationAction::StartWorkout)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,71 @@
+use axum::{extract::Path, Json};
+use chrono::Utc;
+use shared::{
+    api::{
+        error::ServerError,
+        payloads::{NotificationAction, ReminderActionRequest},
+        response_errors::ModelError,
+    },
+    model::{Model, Reminder, ReminderState},
+    types::Uuid,
+};
+use tracing::debug;
+
+use crate::{chrono_duration, constants::REMINDER_SNOOZE, db::DatabaseConnection, UserState};
+
+/// The actions on a reminder's notification that don't need the app. The
+/// service worker calls this without opening it
+pub async fn reminder_action(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<Uuid>,
+    Json(req): Json<ReminderActionRequest>,
+) -> Result<Json<()>, ServerError<ModelError>> {
+    conn.interact(move |conn| {
+        let now = Utc::now();
+        let user_id = *user_state.id;
+
+        // Other users' reminders are reported as not found so they don't leak
+        let mut reminder = Reminder::fetch_by_id_maybe(conn, id)?
+            .filter(|r| r.user_id == user_id)
+            .ok_or(ModelError::NotFound { id })?;
+
+        debug!("{:?} on reminder {id} for user_id {user_id}", req.action);
+        match req.action {
+            // A skipped reminder isn't brought back by snoozing an old
+            // notification of it
+            NotificationAction::Snooze => {
+                if !matches!(reminder.state, ReminderState::Sent | ReminderState::Pending) {
+                    return Err(ModelError::Invalid {
+                        error_messages: vec![format!(
+                            "Can't snooze a reminder that's {:?}",
+                            reminder.state
+                        )],
+                    }
+                    .into());
+                }
+                reminder.snooze::<ModelError>(conn, now + chrono_duration(REMINDER_SNOOZE), now)?;
+            },
+            // The missed reminder is dropped along with the one clicked on
+            NotificationAction::Skip => {
+                let session_id = reminder.session_id;
+                for mut reminder in Reminder::fetch_for_session::<ModelError>(conn, &session_id)? {
+                    if reminder.state == ReminderState::Pending || reminder.id == id {
+                        reminder.set_state::<ModelError>(conn, ReminderState::Skipped, now)?;
+                    }
+                }
+            },
+            NotificationAction::StartWorkout => {
+                return Err(ModelError::Invalid {
+                    error_messages: vec!["Starting a workout is done in the app".to_string()],
+                }
+                .into());
+            },
+        }
+
+        Ok::<_, ServerError<_>>(())
+    })
+    .await??;
+
+    Ok(Json(()))
+}
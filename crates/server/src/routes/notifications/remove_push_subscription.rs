@@ -1,5 +1,11 @@
 use axum::Json;
-use shared::api::error::{Nothing, ServerError};
+use shared::{
+    api::{
+        error::{Nothing, ServerError},
+        payloads::RemoveSubscriptionRequest,
+    },
+    model::PushSubscription,
+};
 use tracing::{debug, warn};
 
 use crate::{db::DatabaseConnection, UserState};
@@ -7,18 +13,22 @@ use crate::{db::DatabaseConnection, UserState};
 pub async fn remove_push_subscription(
     DatabaseConnection(conn): DatabaseConnection,
     user_state: UserState,
+    Json(req): Json<RemoveSubscriptionRequest>,
 ) -> Result<Json<()>, ServerError<Nothing>> {
     conn.interact(move |conn| {
-        let mut user = user_state.id.fetch_full_user(conn)?;
-        if user.push_notification_subscription.is_some() {
-            debug!("Removing push subscription for user_id {}", user.id);
-            user.push_notification_subscription = None;
-            user.update(conn)?;
-        } else {
-            warn!(
-                "Attempt to remove push subscription for user_id {} but it wasn't configured",
-                user.id
-            );
+        let user_id = *user_state.id;
+
+        match PushSubscription::fetch_by_endpoint(conn, &req.endpoint)? {
+            Some(push_subscription) if push_subscription.user_id == user_id => {
+                debug!("Removing push subscription {} for user_id {user_id}", push_subscription.id);
+                push_subscription.delete(conn)?;
+            },
+            _ => {
+                warn!(
+                    "Attempt to remove push subscription for user_id {user_id} but they don't \
+                     have it"
+                );
+            },
         }
 
         Ok::<_, ServerError<_>>(())
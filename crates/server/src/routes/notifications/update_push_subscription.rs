@@ -1,24 +1,59 @@
 use axum::Json;
-use shared::api::{
-    error::{Nothing, ServerError},
-    payloads::{UpdateSubscriptionRequest, UpdateSubscriptionResponse},
+use chrono::Utc;
+use shared::{
+    api::{
+        error::{Nothing, ServerError},
+        payloads::{
+            UpdateSubscriptionRequest, UpdateSubscriptionResponse, MAX_DEVICE_LABEL_LENGTH,
+        },
+    },
+    model::PushSubscription,
 };
-use tracing::warn;
+use tracing::{debug, warn};
 
-use crate::{db::DatabaseConnection, UserState};
+use crate::{db::DatabaseConnection, ClientAddress, UserState};
 
+/// Subscriptions are keyed by their endpoint. One that's already known is
+/// updated, and moved to this user if the device was subscribed by someone
+/// else before
 pub async fn update_push_subscription(
     DatabaseConnection(conn): DatabaseConnection,
     user_state: UserState,
+    client_address: ClientAddress,
     Json(req): Json<UpdateSubscriptionRequest>,
 ) -> Result<Json<UpdateSubscriptionResponse>, ServerError<Nothing>> {
+    let device_label = req
+        .device_label
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .or(client_address.user_agent)
+        .map(|l| l.chars().take(MAX_DEVICE_LABEL_LENGTH).collect::<String>());
+
     conn.interact(move |conn| {
-        let mut user = user_state.id.fetch_full_user(conn)?;
-        if user.push_notification_subscription.is_some() {
-            warn!("Overwriting push subscription for user_id {}", user.id);
+        let user_id = *user_state.id;
+
+        match PushSubscription::fetch_by_endpoint(conn, &req.subscription.endpoint)? {
+            None => {
+                debug!("Adding push subscription for user_id {user_id}");
+                PushSubscription::new(user_id, req.subscription, device_label).insert(conn)?;
+            },
+            Some(mut push_subscription) => {
+                if push_subscription.user_id != user_id {
+                    warn!(
+                        "Moving push subscription {} from user_id {} to {user_id}",
+                        push_subscription.id, push_subscription.user_id
+                    );
+                    push_subscription.user_id = user_id;
+                    push_subscription.creation_date = Utc::now();
+                    push_subscription.last_success_date = None;
+                }
+                push_subscription.key = req.subscription.key;
+                push_subscription.auth = req.subscription.auth;
+                push_subscription.device_label = device_label;
+                push_subscription.failure_count = 0;
+                push_subscription.update(conn)?;
+            },
         }
-        user.push_notification_subscription = Some(req.subscription);
-        user.update(conn)?;
 
         Ok::<_, ServerError<_>>(())
     })
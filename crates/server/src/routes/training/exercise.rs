@@ -0,0 +1,178 @@
+use axum::{extract::Path, Json};
+use chrono::Utc;
+use shared::{
+    api::{error::ServerError, response_errors::ModelError},
+    model::{Exercise, Model, ValidateModel},
+    types::Uuid,
+};
+
+use super::{ensure_id_matches, ensure_owner, owned_exercise, visible_exercise};
+use crate::{db::DatabaseConnection, UserState};
+
+/// Lists the global exercises and the ones owned by the user
+pub async fn list_exercises(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+) -> Result<Json<Vec<Exercise>>, ServerError<ModelError>> {
+    let exercises = conn
+        .interact(move |conn| {
+            Ok::<_, ServerError<_>>(Exercise::fetch_all_for_user(conn, &user_state.id)?)
+        })
+        .await??;
+
+    Ok(Json(exercises))
+}
+
+pub async fn fetch_exercise(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Exercise>, ServerError<ModelError>> {
+    let exercise = conn.interact(move |conn| visible_exercise(conn, &user_state.id, &id)).await??;
+
+    Ok(Json(exercise))
+}
+
+/// Creates a custom exercise. Global exercises can't be created through the API
+pub async fn create_exercise(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Json(exercise): Json<Exercise>,
+) -> Result<Json<Exercise>, ServerError<ModelError>> {
+    exercise.validate().map_err(ModelError::from)?;
+    ensure_owner(exercise.owner_id.as_ref(), &user_state.id, &exercise.id)?;
+
+    let exercise = conn.interact(move |conn| Exercise::create(conn, exercise)).await??;
+
+    Ok(Json(exercise))
+}
+
+pub async fn update_exercise(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<Uuid>,
+    Json(mut exercise): Json<Exercise>,
+) -> Result<Json<Exercise>, ServerError<ModelError>> {
+    ensure_id_matches(id, exercise.id)?;
+    exercise.validate().map_err(ModelError::from)?;
+    ensure_owner(exercise.owner_id.as_ref(), &user_state.id, &id)?;
+
+    let exercise = conn
+        .interact(move |conn| {
+            let existing = owned_exercise(conn, &user_state.id, &id)?;
+
+            exercise.creation_date = existing.creation_date;
+            exercise.last_updated_date = Utc::now();
+            exercise.update(conn)?;
+
+            Ok::<_, ServerError<_>>(Exercise::fetch_by_id(conn, id)?)
+        })
+        .await??;
+
+    Ok(Json(exercise))
+}
+
+pub async fn delete_exercise(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<Uuid>,
+) -> Result<Json<()>, ServerError<ModelError>> {
+    conn.interact(move |conn| {
+        let tx = conn.transaction()?;
+        let exercise = owned_exercise(&tx, &user_state.id, &id)?;
+        if exercise.in_use(&tx)? {
+            Err(ModelError::InUse { id })?;
+        }
+        exercise.delete(&tx)?;
+        tx.commit()?;
+
+        Ok::<_, ServerError<_>>(())
+    })
+    .await??;
+
+    Ok(Json(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use chrono::Utc;
+    use shared::{
+        api::{error::ServerError, response_errors::ModelError, Object},
+        model::Exercise,
+        types::Uuid,
+    };
+
+    use crate::test_support::{body_json, with_id, TestApp};
+
+    #[tokio::test]
+    async fn exercises_are_validated_and_owned() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        let existing = app.create_exercise(&user, "Curl").await;
+        let exercise = Exercise { id: Uuid::new_v4(), name: "Row".to_string(), ..existing.clone() };
+
+        let unnamed = Exercise { name: " ".to_string(), ..exercise.clone() };
+        let response = app.post(Object::Exercise.path(), &unnamed).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let global = Exercise { owner_id: None, ..exercise.clone() };
+        let response = app.post(Object::Exercise.path(), &global).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app.post(Object::Exercise.path(), &exercise).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let created: Exercise = body_json(response).await;
+        assert_eq!(created.name, "Row");
+
+        let path = with_id(Object::ExerciseId.path(), existing.id);
+        let response = app.patch(&path, &Exercise { id: exercise.id, ..existing.clone() }).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = app.delete(&path).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn other_users_exercises_are_not_found() {
+        let mut app = TestApp::new().await;
+        let someone = app.login("someone").await;
+        let exercise = app.create_exercise(&someone, "Curl").await;
+
+        let mut other_app = app.client();
+        let other = other_app.login("other").await;
+        let path = with_id(Object::ExerciseId.path(), exercise.id);
+
+        assert_eq!(other_app.get(&path).await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(other_app.delete(&path).await.status(), StatusCode::NOT_FOUND);
+        // Claiming it for themselves doesn't help
+        let claimed = Exercise { owner_id: Some(other.id), ..exercise.clone() };
+        assert_eq!(other_app.patch(&path, &claimed).await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(other_app.patch(&path, &exercise).await.status(), StatusCode::FORBIDDEN);
+
+        let exercises: Vec<Exercise> =
+            body_json(other_app.get(Object::Exercise.path()).await).await;
+        assert!(!exercises.contains(&exercise));
+    }
+
+    #[tokio::test]
+    async fn exercises_in_use_are_not_deleted() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        let performed = app.create_exercise(&user, "Squat").await;
+        let session = app.create_session(&user, None, Utc::now()).await;
+        app.create_session_exercise(&session, &performed).await;
+        let grouped = app.create_exercise(&user, "Lunge").await;
+        app.create_exercise_group(&user, "Legs", &[&grouped]).await;
+
+        for exercise in [performed, grouped] {
+            let path = with_id(Object::ExerciseId.path(), exercise.id);
+            let response = app.delete(&path).await;
+            assert_eq!(response.status(), StatusCode::CONFLICT);
+            let error: ServerError<ModelError> = body_json(response).await;
+            assert!(matches!(
+                error,
+                ServerError::Inner { inner: ModelError::InUse { id }, .. } if id == exercise.id
+            ));
+            assert_eq!(app.get(&path).await.status(), StatusCode::OK);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use axum::{extract::Path, Json};
+use chrono::Utc;
+use shared::{
+    api::{error::ServerError, response_errors::ModelError},
+    model::{ExerciseGroup, Model, ValidateModel},
+    types::Uuid,
+};
+
+use super::{ensure_id_matches, ensure_owner, owned_exercise_group, visible_exercise_group};
+use crate::{db::DatabaseConnection, UserState};
+
+/// Lists the global exercise groups and the ones owned by the user
+pub async fn list_exercise_groups(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+) -> Result<Json<Vec<ExerciseGroup>>, ServerError<ModelError>> {
+    let exercise_groups = conn
+        .interact(move |conn| {
+            Ok::<_, ServerError<_>>(ExerciseGroup::fetch_all_for_user(conn, &user_state.id)?)
+        })
+        .await??;
+
+    Ok(Json(exercise_groups))
+}
+
+pub async fn fetch_exercise_group(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ExerciseGroup>, ServerError<ModelError>> {
+    let exercise_group =
+        conn.interact(move |conn| visible_exercise_group(conn, &user_state.id, &id)).await??;
+
+    Ok(Json(exercise_group))
+}
+
+/// Creates a custom exercise group. Global exercise groups can't be created through the API
+pub async fn create_exercise_group(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Json(exercise_group): Json<ExerciseGroup>,
+) -> Result<Json<ExerciseGroup>, ServerError<ModelError>> {
+    exercise_group.validate().map_err(ModelError::from)?;
+    ensure_owner(exercise_group.owner_id.as_ref(), &user_state.id, &exercise_group.id)?;
+
+    let exercise_group =
+        conn.interact(move |conn| ExerciseGroup::create(conn, exercise_group)).await??;
+
+    Ok(Json(exercise_group))
+}
+
+pub async fn update_exercise_group(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<Uuid>,
+    Json(mut exercise_group): Json<ExerciseGroup>,
+) -> Result<Json<ExerciseGroup>, ServerError<ModelError>> {
+    ensure_id_matches(id, exercise_group.id)?;
+    exercise_group.validate().map_err(ModelError::from)?;
+    ensure_owner(exercise_group.owner_id.as_ref(), &user_state.id, &id)?;
+
+    let exercise_group = conn
+        .interact(move |conn| {
+            let existing = owned_exercise_group(conn, &user_state.id, &id)?;
+
+            exercise_group.creation_date = existing.creation_date;
+            exercise_group.last_updated_date = Utc::now();
+            exercise_group.update(conn)?;
+
+            Ok::<_, ServerError<_>>(ExerciseGroup::fetch_by_id(conn, id)?)
+        })
+        .await??;
+
+    Ok(Json(exercise_group))
+}
+
+pub async fn delete_exercise_group(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<Uuid>,
+) -> Result<Json<()>, ServerError<ModelError>> {
+    conn.interact(move |conn| {
+        let tx = conn.transaction()?;
+        let exercise_group = owned_exercise_group(&tx, &user_state.id, &id)?;
+        if exercise_group.in_use(&tx)? {
+            Err(ModelError::InUse { id })?;
+        }
+        exercise_group.delete(&tx)?;
+        tx.commit()?;
+
+        Ok::<_, ServerError<_>>(())
+    })
+    .await??;
+
+    Ok(Json(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use chrono::Utc;
+    use shared::{
+        api::{error::ServerError, response_errors::ModelError, Object},
+        model::ExerciseGroup,
+        types::Uuid,
+    };
+
+    use crate::test_support::{body_json, with_id, TestApp};
+
+    fn exercise_group(owner_id: Option<Uuid>, name: &str) -> ExerciseGroup {
+        let now = Utc::now();
+        ExerciseGroup {
+            id: Uuid::new_v4(),
+            owner_id,
+            name: name.to_string(),
+            description: None,
+            creation_date: now,
+            last_updated_date: now,
+        }
+    }
+
+    #[tokio::test]
+    async fn exercise_groups_are_validated_and_owned() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+
+        let response =
+            app.post(Object::ExerciseGroup.path(), &exercise_group(Some(user.id), "")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response =
+            app.post(Object::ExerciseGroup.path(), &exercise_group(None, "Global")).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let group = exercise_group(Some(user.id), "Legs");
+        let response = app.post(Object::ExerciseGroup.path(), &group).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let path = with_id(Object::ExerciseGroupId.path(), group.id);
+        let renamed = ExerciseGroup { name: "Leg day".to_string(), ..group.clone() };
+        let response = app.patch(&path, &renamed).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let updated: ExerciseGroup = body_json(response).await;
+        assert_eq!(updated.name, "Leg day");
+
+        let mut other_app = app.client();
+        other_app.login("other").await;
+        assert_eq!(other_app.get(&path).await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(other_app.delete(&path).await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(other_app.patch(&path, &renamed).await.status(), StatusCode::FORBIDDEN);
+
+        assert_eq!(app.delete(&path).await.status(), StatusCode::OK);
+        assert_eq!(app.get(&path).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn exercise_groups_in_plans_are_not_deleted() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        let group = app.create_exercise_group(&user, "Legs", &[]).await;
+        let plan = app.create_plan(&user).await;
+        app.create_plan_exercise_group(&plan, &group).await;
+
+        let path = with_id(Object::ExerciseGroupId.path(), group.id);
+        let response = app.delete(&path).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let error: ServerError<ModelError> = body_json(response).await;
+        assert!(matches!(
+            error,
+            ServerError::Inner { inner: ModelError::InUse { id }, .. } if id == group.id
+        ));
+        assert_eq!(app.get(&path).await.status(), StatusCode::OK);
+    }
+}
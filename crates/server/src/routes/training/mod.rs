@@ -0,0 +1,347 @@
+//! CRUD routes for the exercise and plan models. Exercises and exercise groups
+//! are either global (read only, no owner) or owned by the user that created
+//! them. Plans are readable by everyone but only writable by their owner.
+//! Everything else belongs to a single user and is invisible to the others
+
+use rusqlite::Connection;
+use shared::{
+    api::{error::ServerError, response_errors::ModelError},
+    model::{Exercise, ExerciseGroup, PlanInstance, Session, SessionExercise},
+    types::Uuid,
+};
+
+mod exercise;
+pub use exercise::*;
+
+mod exercise_group;
+pub use exercise_group::*;
+
+mod plan;
+pub use plan::*;
+
+mod plan_instance;
+pub use plan_instance::*;
+
+mod session;
+pub use session::*;
+
+mod session_exercise;
+pub use session_exercise::*;
+
+/// Fetches the exercise if it's global or owned by the user. Exercises owned
+/// by other users are reported as not found so their existence doesn't leak
+fn visible_exercise(
+    conn: &Connection,
+    user_id: &Uuid,
+    id: &Uuid,
+) -> Result<Exercise, ServerError<ModelError>> {
+    Ok(Exercise::fetch_for_user(conn, user_id, id)?.ok_or(ModelError::NotFound { id: *id })?)
+}
+
+/// Fetches the exercise, failing if it isn't visible to the user or is global
+/// (and therefore read only)
+fn owned_exercise(
+    conn: &Connection,
+    user_id: &Uuid,
+    id: &Uuid,
+) -> Result<Exercise, ServerError<ModelError>> {
+    let exercise = visible_exercise(conn, user_id, id)?;
+    ensure_owner(exercise.owner_id.as_ref(), user_id, id)?;
+
+    Ok(exercise)
+}
+
+/// Fetches the exercise group if it's global or owned by the user
+fn visible_exercise_group(
+    conn: &Connection,
+    user_id: &Uuid,
+    id: &Uuid,
+) -> Result<ExerciseGroup, ServerError<ModelError>> {
+    Ok(ExerciseGroup::fetch_for_user(conn, user_id, id)?.ok_or(ModelError::NotFound { id: *id })?)
+}
+
+/// Fetches the exercise group, failing if it isn't visible to the user or is
+/// global (and therefore read only)
+fn owned_exercise_group(
+    conn: &Connection,
+    user_id: &Uuid,
+    id: &Uuid,
+) -> Result<ExerciseGroup, ServerError<ModelError>> {
+    let exercise_group = visible_exercise_group(conn, user_id, id)?;
+    ensure_owner(exercise_group.owner_id.as_ref(), user_id, id)?;
+
+    Ok(exercise_group)
+}
+
+/// Fetches the plan instance, failing if it doesn't exist or belongs to
+/// another user
+fn owned_plan_instance(
+    conn: &Connection,
+    user_id: &Uuid,
+    id: &Uuid,
+) -> Result<PlanInstance, ServerError<ModelError>> {
+    Ok(PlanInstance::fetch_for_user(conn, user_id, id)?.ok_or(ModelError::NotFound { id: *id })?)
+}
+
+/// Fetches the session, failing if it doesn't exist or belongs to another user
+fn owned_session(
+    conn: &Connection,
+    user_id: &Uuid,
+    id: &Uuid,
+) -> Result<Session, ServerError<ModelError>> {
+    Ok(Session::fetch_for_user(conn, user_id, id)?.ok_or(ModelError::NotFound { id: *id })?)
+}
+
+/// Fetches the session exercise, failing if it doesn't exist or belongs to
+/// another user
+fn owned_session_exercise(
+    conn: &Connection,
+    user_id: &Uuid,
+    id: &Uuid,
+) -> Result<SessionExercise, ServerError<ModelError>> {
+    Ok(SessionExercise::fetch_for_user(conn, user_id, id)?
+        .ok_or(ModelError::NotFound { id: *id })?)
+}
+
+/// Rejects models that claim to belong to someone other than the user. Used
+/// for both creation and updates so ownership can't be transferred
+fn ensure_owner(
+    owner_id: Option<&Uuid>,
+    user_id: &Uuid,
+    id: &Uuid,
+) -> Result<(), ServerError<ModelError>> {
+    if owner_id != Some(user_id) {
+        Err(ModelError::NotOwner { id: *id })?;
+    }
+
+    Ok(())
+}
+
+/// Rejects updates where the id in the path doesn't match the body
+fn ensure_id_matches(path_id: Uuid, body_id: Uuid) -> Result<(), ServerError<ModelError>> {
+    if path_id != body_id {
+        Err(ModelError::IdMismatch { path_id, body_id })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use shared::{
+        api::error::Nothing,
+        model::{Model, NewUser, Plan, User},
+    };
+
+    use super::*;
+    use crate::test_support::connection;
+
+    /// A user with one of everything
+    struct Owned {
+        user_id: Uuid,
+        exercise: Exercise,
+        exercise_group: ExerciseGroup,
+        plan_instance: PlanInstance,
+        session: Session,
+        session_exercise: SessionExercise,
+    }
+
+    fn create_user_with_data(conn: &mut Connection, username: &str) -> Owned {
+        let now = Utc::now();
+        let user_id = Uuid::new_v4();
+        User::create::<Nothing>(conn, NewUser::new(user_id, username)).unwrap();
+
+        let exercise = Exercise::create::<Nothing>(conn, Exercise {
+            id: Uuid::new_v4(),
+            owner_id: Some(user_id),
+            // Same name for everyone to check names are only unique per owner
+            name: "Custom exercise".to_string(),
+            description: None,
+            base_recovery_days: 2.0,
+            creation_date: now,
+            last_updated_date: now,
+        })
+        .unwrap();
+
+        let exercise_group = ExerciseGroup::create::<Nothing>(conn, ExerciseGroup {
+            id: Uuid::new_v4(),
+            owner_id: Some(user_id),
+            name: "Custom group".to_string(),
+            description: None,
+            creation_date: now,
+            last_updated_date: now,
+        })
+        .unwrap();
+
+        let plan = Plan::fetch_all(conn).unwrap().remove(0);
+        let plan_instance = PlanInstance::create::<Nothing>(conn, PlanInstance {
+            id: Uuid::new_v4(),
+            plan_id: plan.id,
+            user_id,
+            start_date: now,
+            creation_date: now,
+            last_updated_date: now,
+        })
+        .unwrap();
+
+        let session = Session::create::<Nothing>(conn, Session {
+            id: Uuid::new_v4(),
+            user_id,
+            plan_instance_id: Some(plan_instance.id),
+            planned_date: now,
+            performed_date: None,
+            creation_date: now,
+            last_updated_date: now,
+        })
+        .unwrap();
+
+        let session_exercise = SessionExercise::create::<Nothing>(conn, SessionExercise {
+            id: Uuid::new_v4(),
+            user_id,
+            exercise_id: exercise.id,
+            session_id: session.id,
+            planned_sets: Default::default(),
+            performed_sets: Default::default(),
+            creation_date: now,
+            last_updated_date: now,
+            sort_order: 0,
+            finished: false,
+        })
+        .unwrap();
+
+        Owned { user_id, exercise, exercise_group, plan_instance, session, session_exercise }
+    }
+
+    fn assert_not_found<T: std::fmt::Debug>(result: Result<T, ServerError<ModelError>>) {
+        assert!(
+            matches!(result, Err(ServerError::Inner { inner: ModelError::NotFound { .. }, .. })),
+            "Expected NotFound, got {result:?}"
+        );
+    }
+
+    fn assert_not_owner<T: std::fmt::Debug>(result: Result<T, ServerError<ModelError>>) {
+        assert!(
+            matches!(result, Err(ServerError::Inner { inner: ModelError::NotOwner { .. }, .. })),
+            "Expected NotOwner, got {result:?}"
+        );
+    }
+
+    #[test]
+    fn owner_can_access_their_rows() {
+        let mut conn = connection();
+        let a = create_user_with_data(&mut conn, "a");
+
+        assert_eq!(owned_exercise(&conn, &a.user_id, &a.exercise.id).unwrap(), a.exercise);
+        assert_eq!(
+            owned_exercise_group(&conn, &a.user_id, &a.exercise_group.id).unwrap(),
+            a.exercise_group
+        );
+        assert_eq!(
+            owned_plan_instance(&conn, &a.user_id, &a.plan_instance.id).unwrap(),
+            a.plan_instance
+        );
+        assert_eq!(owned_session(&conn, &a.user_id, &a.session.id).unwrap(), a.session);
+        assert_eq!(
+            owned_session_exercise(&conn, &a.user_id, &a.session_exercise.id).unwrap(),
+            a.session_exercise
+        );
+    }
+
+    #[test]
+    fn other_users_rows_are_not_found() {
+        let mut conn = connection();
+        let a = create_user_with_data(&mut conn, "a");
+        let b = create_user_with_data(&mut conn, "b");
+
+        // Fetch, update and delete all go through these
+        assert_not_found(visible_exercise(&conn, &b.user_id, &a.exercise.id));
+        assert_not_found(owned_exercise(&conn, &b.user_id, &a.exercise.id));
+        assert_not_found(visible_exercise_group(&conn, &b.user_id, &a.exercise_group.id));
+        assert_not_found(owned_exercise_group(&conn, &b.user_id, &a.exercise_group.id));
+        assert_not_found(owned_plan_instance(&conn, &b.user_id, &a.plan_instance.id));
+        assert_not_found(owned_session(&conn, &b.user_id, &a.session.id));
+        assert_not_found(owned_session_exercise(&conn, &b.user_id, &a.session_exercise.id));
+    }
+
+    #[test]
+    fn lists_only_contain_visible_rows() {
+        let mut conn = connection();
+        let a = create_user_with_data(&mut conn, "a");
+        let b = create_user_with_data(&mut conn, "b");
+
+        let exercises = Exercise::fetch_all_for_user(&conn, &b.user_id).unwrap();
+        assert!(exercises.contains(&b.exercise));
+        assert!(!exercises.contains(&a.exercise));
+        assert!(exercises.iter().all(|e| e.owner_id.is_none() || e.owner_id == Some(b.user_id)));
+        // The global exercises are shared
+        assert!(exercises.iter().any(|e| e.owner_id.is_none()));
+
+        let exercise_groups = ExerciseGroup::fetch_all_for_user(&conn, &b.user_id).unwrap();
+        assert!(exercise_groups.contains(&b.exercise_group));
+        assert!(!exercise_groups.contains(&a.exercise_group));
+
+        assert_eq!(PlanInstance::fetch_all_for_user(&conn, &b.user_id).unwrap(), vec![
+            b.plan_instance
+        ]);
+        assert_eq!(Session::fetch_all_for_user(&conn, &b.user_id).unwrap(), vec![b.session]);
+        assert_eq!(SessionExercise::fetch_all_for_user(&conn, &b.user_id).unwrap(), vec![
+            b.session_exercise
+        ]);
+    }
+
+    #[test]
+    fn ad_hoc_sessions_are_scoped_to_the_user() {
+        let mut conn = connection();
+        let a = create_user_with_data(&mut conn, "a");
+        let b = create_user_with_data(&mut conn, "b");
+
+        let mut session =
+            Session::create::<Nothing>(&mut conn, Session::new_ad_hoc(b.user_id)).unwrap();
+        assert!(session.is_ad_hoc());
+        assert_eq!(owned_session(&conn, &b.user_id, &session.id).unwrap(), session);
+        assert_not_found(owned_session(&conn, &a.user_id, &session.id));
+
+        // Only completed sessions can be adopted and only into the user's own plans
+        assert!(session.clone().adopt(&b.plan_instance).is_err());
+        session.performed_date = Some(Utc::now());
+        assert!(session.clone().adopt(&a.plan_instance).is_err());
+        session.adopt(&b.plan_instance).unwrap();
+        session.update::<Nothing>(&conn).unwrap();
+
+        let adopted = owned_session(&conn, &b.user_id, &session.id).unwrap();
+        assert_eq!(adopted.plan_instance_id, Some(b.plan_instance.id));
+    }
+
+    #[test]
+    fn global_rows_are_read_only() {
+        let mut conn = connection();
+        let a = create_user_with_data(&mut conn, "a");
+
+        let global_exercise =
+            Exercise::fetch_all(&conn).unwrap().into_iter().find(|e| e.owner_id.is_none()).unwrap();
+        assert!(visible_exercise(&conn, &a.user_id, &global_exercise.id).is_ok());
+        assert_not_owner(owned_exercise(&conn, &a.user_id, &global_exercise.id));
+
+        let global_group = ExerciseGroup::fetch_all(&conn)
+            .unwrap()
+            .into_iter()
+            .find(|e| e.owner_id.is_none())
+            .unwrap();
+        assert!(visible_exercise_group(&conn, &a.user_id, &global_group.id).is_ok());
+        assert_not_owner(owned_exercise_group(&conn, &a.user_id, &global_group.id));
+    }
+
+    #[test]
+    fn ownership_cant_be_claimed_for_another_user() {
+        let mut conn = connection();
+        let a = create_user_with_data(&mut conn, "a");
+        let b = create_user_with_data(&mut conn, "b");
+
+        assert!(ensure_owner(Some(&a.user_id), &a.user_id, &a.session.id).is_ok());
+        // e.g. b creating a session with a's user_id or moving one of theirs to a
+        assert_not_owner(ensure_owner(Some(&a.user_id), &b.user_id, &b.session.id));
+        // e.g. b creating a global exercise
+        assert_not_owner(ensure_owner(None, &b.user_id, &b.exercise.id));
+    }
+}
@@ -0,0 +1,204 @@
+use axum::{extract::Path, Json};
+use chrono::Utc;
+use shared::{
+    api::{error::ServerError, response_errors::ModelError},
+    model::{Model, Plan, PlanInstance, PlanInstanceIden, ValidateModel},
+    types::Uuid,
+};
+
+use super::{ensure_id_matches, ensure_owner};
+use crate::{db::DatabaseConnection, UserState};
+
+/// Fetches the plan, failing if it doesn't exist or belongs to another user
+fn owned_plan(
+    conn: &rusqlite::Connection,
+    user_id: &Uuid,
+    id: &Uuid,
+) -> Result<Plan, ServerError<ModelError>> {
+    let plan = Plan::fetch_by_id_maybe(conn, id)?.ok_or(ModelError::NotFound { id: *id })?;
+
+    ensure_owner(Some(&plan.owner_id), user_id, id)?;
+
+    Ok(plan)
+}
+
+/// Plans are public, any logged in user can read any of them, so all of them
+/// are listed rather than just the ones the user owns. Only the owner can
+/// change or delete one
+pub async fn list_plans(
+    DatabaseConnection(conn): DatabaseConnection,
+    _user_state: UserState,
+) -> Result<Json<Vec<Plan>>, ServerError<ModelError>> {
+    let plans =
+        conn.interact(move |conn| Ok::<_, ServerError<_>>(Plan::fetch_all(conn)?)).await??;
+
+    Ok(Json(plans))
+}
+
+pub async fn fetch_plan(
+    DatabaseConnection(conn): DatabaseConnection,
+    _user_state: UserState,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Plan>, ServerError<ModelError>> {
+    let plan = conn
+        .interact(move |conn| {
+            let plan = Plan::fetch_by_id_maybe(conn, &id)?.ok_or(ModelError::NotFound { id })?;
+
+            Ok::<_, ServerError<_>>(plan)
+        })
+        .await??;
+
+    Ok(Json(plan))
+}
+
+pub async fn create_plan(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Json(plan): Json<Plan>,
+) -> Result<Json<Plan>, ServerError<ModelError>> {
+    plan.validate().map_err(ModelError::from)?;
+
+    ensure_owner(Some(&plan.owner_id), &user_state.id, &plan.id)?;
+
+    let plan = conn.interact(move |conn| Plan::create(conn, plan)).await??;
+
+    Ok(Json(plan))
+}
+
+pub async fn update_plan(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<Uuid>,
+    Json(mut plan): Json<Plan>,
+) -> Result<Json<Plan>, ServerError<ModelError>> {
+    ensure_id_matches(id, plan.id)?;
+    plan.validate().map_err(ModelError::from)?;
+
+    ensure_owner(Some(&plan.owner_id), &user_state.id, &id)?;
+
+    let plan = conn
+        .interact(move |conn| {
+            let existing = owned_plan(conn, &user_state.id, &id)?;
+
+            plan.creation_date = existing.creation_date;
+            plan.last_updated_date = Utc::now();
+            plan.update(conn)?;
+
+            Ok::<_, ServerError<_>>(Plan::fetch_by_id(conn, id)?)
+        })
+        .await??;
+
+    Ok(Json(plan))
+}
+
+pub async fn delete_plan(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<Uuid>,
+) -> Result<Json<()>, ServerError<ModelError>> {
+    conn.interact(move |conn| {
+        let tx = conn.transaction()?;
+        let plan = owned_plan(&tx, &user_state.id, &id)?;
+        if plan.in_use(&tx)? {
+            Err(ModelError::InUse { id })?;
+        }
+        // The owner's sessions of it are kept as ad-hoc ones
+        for plan_instance in
+            PlanInstance::fetch_all_by_column(&tx, plan.id, PlanInstanceIden::PlanId)?
+        {
+            plan_instance.delete(&tx)?;
+        }
+        plan.delete(&tx)?;
+        tx.commit()?;
+
+        Ok::<_, ServerError<_>>(())
+    })
+    .await??;
+
+    Ok(Json(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use chrono::Utc;
+    use shared::{
+        api::{error::ServerError, response_errors::ModelError, Object},
+        model::{Model, Plan, Session},
+        types::Uuid,
+    };
+
+    use crate::test_support::{body_json, with_id, TestApp};
+
+    #[tokio::test]
+    async fn plans_are_validated() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        let existing = app.create_plan(&user).await;
+
+        let plan = Plan { id: Uuid::new_v4(), name: "Another plan".to_string(), ..existing };
+        let no_weeks = Plan { duration_weeks: 0, ..plan.clone() };
+        let response = app.post(Object::Plan.path(), &no_weeks).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let unnamed = Plan { name: String::new(), ..plan.clone() };
+        let response = app.post(Object::Plan.path(), &unnamed).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app.post(Object::Plan.path(), &plan).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn plans_are_public_but_only_changed_by_their_owner() {
+        let mut app = TestApp::new().await;
+        let someone = app.login("someone").await;
+        let plan = app.create_plan(&someone).await;
+
+        let mut other_app = app.client();
+        let other = other_app.login("other").await;
+        let path = with_id(Object::PlanId.path(), plan.id);
+
+        let fetched: Plan = body_json(other_app.get(&path).await).await;
+        assert_eq!(fetched, plan);
+        let plans: Vec<Plan> = body_json(other_app.get(Object::Plan.path()).await).await;
+        assert!(plans.contains(&plan));
+
+        let renamed = Plan { name: "Mine now".to_string(), ..plan.clone() };
+        assert_eq!(other_app.patch(&path, &renamed).await.status(), StatusCode::FORBIDDEN);
+        let claimed = Plan { owner_id: other.id, ..renamed };
+        assert_eq!(other_app.patch(&path, &claimed).await.status(), StatusCode::FORBIDDEN);
+        assert_eq!(other_app.delete(&path).await.status(), StatusCode::FORBIDDEN);
+
+        assert_eq!(app.delete(&path).await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn plans_other_users_follow_are_not_deleted() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        let plan = app.create_plan(&user).await;
+        let plan_instance = app.create_plan_instance(&user, &plan).await;
+        let session = app.create_session(&user, Some(&plan_instance), Utc::now()).await;
+        let mut follower_app = app.client();
+        let follower = follower_app.login("follower").await;
+        let followed = app.create_plan_instance(&follower, &plan).await;
+
+        let path = with_id(Object::PlanId.path(), plan.id);
+        let response = app.delete(&path).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let error: ServerError<ModelError> = body_json(response).await;
+        assert!(matches!(
+            error,
+            ServerError::Inner { inner: ModelError::InUse { id }, .. } if id == plan.id
+        ));
+
+        // Once they've stopped it goes, leaving the owner's sessions as ad-hoc
+        let followed_path = with_id(Object::PlanInstanceId.path(), followed.id);
+        assert_eq!(follower_app.delete(&followed_path).await.status(), StatusCode::OK);
+
+        assert_eq!(app.delete(&path).await.status(), StatusCode::OK);
+        let session_id = session.id;
+        let session = app.interact(move |conn| Session::fetch_by_id(conn, session_id)).await;
+        assert_eq!(session.unwrap().plan_instance_id, None);
+    }
+}
@@ -0,0 +1,156 @@
+use axum::{extract::Path, Json};
+use chrono::Utc;
+use shared::{
+    api::{error::ServerError, response_errors::ModelError},
+    model::{Model, Plan, PlanInstance, ValidateModel},
+    types::Uuid,
+};
+
+use super::{ensure_id_matches, ensure_owner, owned_plan_instance};
+use crate::{db::DatabaseConnection, UserState};
+
+pub async fn list_plan_instances(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+) -> Result<Json<Vec<PlanInstance>>, ServerError<ModelError>> {
+    let plan_instances = conn
+        .interact(move |conn| {
+            Ok::<_, ServerError<_>>(PlanInstance::fetch_all_for_user(conn, &user_state.id)?)
+        })
+        .await??;
+
+    Ok(Json(plan_instances))
+}
+
+pub async fn fetch_plan_instance(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<Uuid>,
+) -> Result<Json<PlanInstance>, ServerError<ModelError>> {
+    let plan_instance =
+        conn.interact(move |conn| owned_plan_instance(conn, &user_state.id, &id)).await??;
+
+    Ok(Json(plan_instance))
+}
+
+pub async fn create_plan_instance(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Json(plan_instance): Json<PlanInstance>,
+) -> Result<Json<PlanInstance>, ServerError<ModelError>> {
+    plan_instance.validate().map_err(ModelError::from)?;
+
+    ensure_owner(Some(&plan_instance.user_id), &user_state.id, &plan_instance.id)?;
+
+    let plan_instance = conn
+        .interact(move |conn| {
+            // Plans are global so any plan can be instanced
+            let plan_id = plan_instance.plan_id;
+            Plan::fetch_by_id_maybe(conn, &plan_id)?.ok_or(ModelError::NotFound { id: plan_id })?;
+
+            PlanInstance::create(conn, plan_instance)
+        })
+        .await??;
+
+    Ok(Json(plan_instance))
+}
+
+pub async fn update_plan_instance(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<Uuid>,
+    Json(mut plan_instance): Json<PlanInstance>,
+) -> Result<Json<PlanInstance>, ServerError<ModelError>> {
+    ensure_id_matches(id, plan_instance.id)?;
+    plan_instance.validate().map_err(ModelError::from)?;
+
+    ensure_owner(Some(&plan_instance.user_id), &user_state.id, &id)?;
+
+    let plan_instance = conn
+        .interact(move |conn| {
+            let existing = owned_plan_instance(conn, &user_state.id, &id)?;
+
+            let plan_id = plan_instance.plan_id;
+            Plan::fetch_by_id_maybe(conn, &plan_id)?.ok_or(ModelError::NotFound { id: plan_id })?;
+
+            plan_instance.creation_date = existing.creation_date;
+            plan_instance.last_updated_date = Utc::now();
+            plan_instance.update(conn)?;
+
+            Ok::<_, ServerError<_>>(PlanInstance::fetch_by_id(conn, id)?)
+        })
+        .await??;
+
+    Ok(Json(plan_instance))
+}
+
+pub async fn delete_plan_instance(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<Uuid>,
+) -> Result<Json<()>, ServerError<ModelError>> {
+    conn.interact(move |conn| {
+        let plan_instance = owned_plan_instance(conn, &user_state.id, &id)?;
+        plan_instance.delete(conn)?;
+
+        Ok::<_, ServerError<_>>(())
+    })
+    .await??;
+
+    Ok(Json(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use chrono::Duration;
+    use shared::{api::Object, model::PlanInstance, types::Uuid};
+
+    use crate::test_support::{with_id, TestApp};
+
+    #[tokio::test]
+    async fn plan_instances_are_validated() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        let plan = app.create_plan(&user).await;
+        let existing = app.create_plan_instance(&user, &plan).await;
+        let plan_instance = PlanInstance { id: Uuid::new_v4(), ..existing };
+
+        let backwards = PlanInstance {
+            last_updated_date: plan_instance.creation_date - Duration::days(1),
+            ..plan_instance.clone()
+        };
+        let response = app.post(Object::PlanInstance.path(), &backwards).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let no_plan = PlanInstance { plan_id: Uuid::new_v4(), ..plan_instance.clone() };
+        let response = app.post(Object::PlanInstance.path(), &no_plan).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = app.post(Object::PlanInstance.path(), &plan_instance).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn plan_instances_belong_to_one_user() {
+        let mut app = TestApp::new().await;
+        let someone = app.login("someone").await;
+        let plan = app.create_plan(&someone).await;
+        let plan_instance = app.create_plan_instance(&someone, &plan).await;
+
+        let mut other_app = app.client();
+        let other = other_app.login("other").await;
+        let path = with_id(Object::PlanInstanceId.path(), plan_instance.id);
+
+        assert_eq!(other_app.get(&path).await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(other_app.delete(&path).await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(other_app.patch(&path, &plan_instance).await.status(), StatusCode::FORBIDDEN);
+
+        // Someone else's plan can be followed, but not on their behalf
+        let own = PlanInstance { id: Uuid::new_v4(), user_id: other.id, ..plan_instance.clone() };
+        let response = other_app.post(Object::PlanInstance.path(), &own).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let theirs = PlanInstance { id: Uuid::new_v4(), ..plan_instance };
+        let response = other_app.post(Object::PlanInstance.path(), &theirs).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,189 @@
+use axum::{extract::Path, Json};
+use chrono::Utc;
+use shared::{
+    api::{error::ServerError, response_errors::ModelError},
+    model::{Model, Session, ValidateModel},
+    types::Uuid,
+};
+
+use super::{ensure_id_matches, ensure_owner, owned_plan_instance, owned_session};
+use crate::{db::DatabaseConnection, UserState};
+
+pub async fn list_sessions(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+) -> Result<Json<Vec<Session>>, ServerError<ModelError>> {
+    let sessions = conn
+        .interact(move |conn| {
+            Ok::<_, ServerError<_>>(Session::fetch_all_for_user(conn, &user_state.id)?)
+        })
+        .await??;
+
+    Ok(Json(sessions))
+}
+
+pub async fn fetch_session(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Session>, ServerError<ModelError>> {
+    let session = conn.interact(move |conn| owned_session(conn, &user_state.id, &id)).await??;
+
+    Ok(Json(session))
+}
+
+pub async fn create_session(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Json(session): Json<Session>,
+) -> Result<Json<Session>, ServerError<ModelError>> {
+    session.validate().map_err(ModelError::from)?;
+    ensure_owner(Some(&session.user_id), &user_state.id, &session.id)?;
+
+    let session = conn
+        .interact(move |conn| {
+            // Ad-hoc sessions don't belong to a plan
+            if let Some(plan_instance_id) = &session.plan_instance_id {
+                owned_plan_instance(conn, &user_state.id, plan_instance_id)?;
+            }
+
+            Session::create(conn, session)
+        })
+        .await??;
+
+    Ok(Json(session))
+}
+
+pub async fn update_session(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<Uuid>,
+    Json(mut session): Json<Session>,
+) -> Result<Json<Session>, ServerError<ModelError>> {
+    ensure_id_matches(id, session.id)?;
+    session.validate().map_err(ModelError::from)?;
+    ensure_owner(Some(&session.user_id), &user_state.id, &id)?;
+
+    let session = conn
+        .interact(move |conn| {
+            let existing = owned_session(conn, &user_state.id, &id)?;
+            // The only way into a plan is adopting an ad-hoc session. Sessions can't be taken
+            // out of or moved between plans
+            if session.plan_instance_id != existing.plan_instance_id {
+                let Some(plan_instance_id) = &session.plan_instance_id else {
+                    return Err(ModelError::Invalid {
+                        error_messages: vec!["Session can't be taken out of its plan".to_string()],
+                    }
+                    .into());
+                };
+                let plan_instance = owned_plan_instance(conn, &user_state.id, plan_instance_id)?;
+
+                let mut adopting =
+                    Session { plan_instance_id: existing.plan_instance_id, ..session.clone() };
+                adopting.adopt(&plan_instance).map_err(ModelError::from)?;
+            }
+
+            session.creation_date = existing.creation_date;
+            session.last_updated_date = Utc::now();
+            session.update(conn)?;
+
+            Ok::<_, ServerError<_>>(Session::fetch_by_id(conn, id)?)
+        })
+        .await??;
+
+    Ok(Json(session))
+}
+
+pub async fn delete_session(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<Uuid>,
+) -> Result<Json<()>, ServerError<ModelError>> {
+    conn.interact(move |conn| {
+        let session = owned_session(conn, &user_state.id, &id)?;
+        session.delete(conn)?;
+
+        Ok::<_, ServerError<_>>(())
+    })
+    .await??;
+
+    Ok(Json(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use chrono::{Duration, Utc};
+    use shared::{api::Object, model::Session, types::Uuid};
+
+    use crate::test_support::{with_id, TestApp};
+
+    #[tokio::test]
+    async fn sessions_are_validated() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        let existing = app.create_session(&user, None, Utc::now()).await;
+        let session = Session { id: Uuid::new_v4(), ..existing };
+
+        let performed_early = Session {
+            performed_date: Some(session.creation_date - Duration::days(1)),
+            ..session.clone()
+        };
+        let response = app.post(Object::Session.path(), &performed_early).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app.post(Object::Session.path(), &session).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let path = with_id(Object::SessionId.path(), session.id);
+        let performed = Session { performed_date: Some(session.creation_date), ..session.clone() };
+        assert_eq!(app.patch(&path, &performed).await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn sessions_only_join_plans_by_being_adopted() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        let plan = app.create_plan(&user).await;
+        let plan_instance = app.create_plan_instance(&user, &plan).await;
+        let other_instance = app.create_plan_instance(&user, &plan).await;
+        let session = app.create_session(&user, None, Utc::now()).await;
+        let path = with_id(Object::SessionId.path(), session.id);
+
+        // It hasn't been done yet
+        let adopted = Session { plan_instance_id: Some(plan_instance.id), ..session.clone() };
+        assert_eq!(app.patch(&path, &adopted).await.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(app.get(&path).await.status(), StatusCode::OK);
+
+        let adopted = Session { performed_date: Some(Utc::now()), ..adopted };
+        assert_eq!(app.patch(&path, &adopted).await.status(), StatusCode::OK);
+
+        // Once it's in a plan it stays there
+        let moved = Session { plan_instance_id: Some(other_instance.id), ..adopted.clone() };
+        assert_eq!(app.patch(&path, &moved).await.status(), StatusCode::BAD_REQUEST);
+        let removed = Session { plan_instance_id: None, ..adopted };
+        assert_eq!(app.patch(&path, &removed).await.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn sessions_belong_to_one_user() {
+        let mut app = TestApp::new().await;
+        let someone = app.login("someone").await;
+        let plan = app.create_plan(&someone).await;
+        let plan_instance = app.create_plan_instance(&someone, &plan).await;
+        let session = app.create_session(&someone, Some(&plan_instance), Utc::now()).await;
+
+        let mut other_app = app.client();
+        let other = other_app.login("other").await;
+        let path = with_id(Object::SessionId.path(), session.id);
+
+        assert_eq!(other_app.get(&path).await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(other_app.delete(&path).await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(other_app.patch(&path, &session).await.status(), StatusCode::FORBIDDEN);
+
+        // Nor can sessions be put in someone else's plan instance
+        let own = Session { id: Uuid::new_v4(), user_id: other.id, ..session };
+        let response = other_app.post(Object::Session.path(), &own).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
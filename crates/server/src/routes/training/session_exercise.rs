@@ -0,0 +1,181 @@
+use axum::{extract::Path, Json};
+use chrono::Utc;
+use rusqlite::Connection;
+use shared::{
+    api::{error::ServerError, response_errors::ModelError},
+    model::{Model, SessionExercise, ValidateModel},
+    types::Uuid,
+};
+
+use super::{
+    ensure_id_matches, ensure_owner, owned_session, owned_session_exercise, visible_exercise,
+};
+use crate::{db::DatabaseConnection, UserState};
+
+/// Checks the session is owned by the user and the exercise is visible to them
+fn check_references(
+    conn: &Connection,
+    user_id: &Uuid,
+    session_exercise: &SessionExercise,
+) -> Result<(), ServerError<ModelError>> {
+    owned_session(conn, user_id, &session_exercise.session_id)?;
+    visible_exercise(conn, user_id, &session_exercise.exercise_id)?;
+
+    Ok(())
+}
+
+pub async fn list_session_exercises(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+) -> Result<Json<Vec<SessionExercise>>, ServerError<ModelError>> {
+    let session_exercises = conn
+        .interact(move |conn| {
+            Ok::<_, ServerError<_>>(SessionExercise::fetch_all_for_user(conn, &user_state.id)?)
+        })
+        .await??;
+
+    Ok(Json(session_exercises))
+}
+
+pub async fn fetch_session_exercise(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<Uuid>,
+) -> Result<Json<SessionExercise>, ServerError<ModelError>> {
+    let session_exercise =
+        conn.interact(move |conn| owned_session_exercise(conn, &user_state.id, &id)).await??;
+
+    Ok(Json(session_exercise))
+}
+
+pub async fn create_session_exercise(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Json(session_exercise): Json<SessionExercise>,
+) -> Result<Json<SessionExercise>, ServerError<ModelError>> {
+    session_exercise.validate().map_err(ModelError::from)?;
+    ensure_owner(Some(&session_exercise.user_id), &user_state.id, &session_exercise.id)?;
+
+    let session_exercise = conn
+        .interact(move |conn| {
+            check_references(conn, &user_state.id, &session_exercise)?;
+
+            SessionExercise::create(conn, session_exercise)
+        })
+        .await??;
+
+    Ok(Json(session_exercise))
+}
+
+pub async fn update_session_exercise(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<Uuid>,
+    Json(mut session_exercise): Json<SessionExercise>,
+) -> Result<Json<SessionExercise>, ServerError<ModelError>> {
+    ensure_id_matches(id, session_exercise.id)?;
+    session_exercise.validate().map_err(ModelError::from)?;
+    ensure_owner(Some(&session_exercise.user_id), &user_state.id, &id)?;
+
+    let session_exercise = conn
+        .interact(move |conn| {
+            let existing = owned_session_exercise(conn, &user_state.id, &id)?;
+            check_references(conn, &user_state.id, &session_exercise)?;
+
+            session_exercise.creation_date = existing.creation_date;
+            session_exercise.last_updated_date = Utc::now();
+            session_exercise.update(conn)?;
+
+            Ok::<_, ServerError<_>>(SessionExercise::fetch_by_id(conn, id)?)
+        })
+        .await??;
+
+    Ok(Json(session_exercise))
+}
+
+pub async fn delete_session_exercise(
+    DatabaseConnection(conn): DatabaseConnection,
+    user_state: UserState,
+    Path(id): Path<Uuid>,
+) -> Result<Json<()>, ServerError<ModelError>> {
+    conn.interact(move |conn| {
+        let session_exercise = owned_session_exercise(conn, &user_state.id, &id)?;
+        session_exercise.delete(conn)?;
+
+        Ok::<_, ServerError<_>>(())
+    })
+    .await??;
+
+    Ok(Json(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use chrono::Utc;
+    use shared::{
+        api::Object,
+        model::{Reps, SessionExercise, Set, Sets, Weight},
+        types::Uuid,
+    };
+
+    use crate::test_support::{with_id, TestApp};
+
+    #[tokio::test]
+    async fn session_exercises_are_validated() {
+        let mut app = TestApp::new().await;
+        let user = app.login("someone").await;
+        let exercise = app.create_exercise(&user, "Curl").await;
+        let session = app.create_session(&user, None, Utc::now()).await;
+        let existing = app.create_session_exercise(&session, &exercise).await;
+        let session_exercise = SessionExercise { id: Uuid::new_v4(), ..existing };
+
+        let negative = SessionExercise {
+            planned_sets: Sets(vec![Set::new(Weight::Kilograms(-5.0), Reps::Reps(5))]),
+            ..session_exercise.clone()
+        };
+        let response = app.post(Object::SessionExercise.path(), &negative).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let mut completed = Set::new(Weight::Kilograms(20.0), Reps::Reps(5));
+        completed.completed_date = Some(Utc::now());
+        let planned_done = SessionExercise {
+            planned_sets: Sets(vec![completed.clone()]),
+            ..session_exercise.clone()
+        };
+        let response = app.post(Object::SessionExercise.path(), &planned_done).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let performed =
+            SessionExercise { performed_sets: Sets(vec![completed]), ..session_exercise.clone() };
+        let response = app.post(Object::SessionExercise.path(), &performed).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn session_exercises_belong_to_one_user() {
+        let mut app = TestApp::new().await;
+        let someone = app.login("someone").await;
+        let exercise = app.create_exercise(&someone, "Curl").await;
+        let session = app.create_session(&someone, None, Utc::now()).await;
+        let session_exercise = app.create_session_exercise(&session, &exercise).await;
+
+        let mut other_app = app.client();
+        let other = other_app.login("other").await;
+        let path = with_id(Object::SessionExerciseId.path(), session_exercise.id);
+
+        assert_eq!(other_app.get(&path).await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(other_app.delete(&path).await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(other_app.patch(&path, &session_exercise).await.status(), StatusCode::FORBIDDEN);
+
+        // Their own session exercise can't use someone else's session or custom exercise
+        let own_session = other_app.create_session(&other, None, Utc::now()).await;
+        let in_their_session =
+            SessionExercise { id: Uuid::new_v4(), user_id: other.id, ..session_exercise.clone() };
+        let response = other_app.post(Object::SessionExercise.path(), &in_their_session).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let their_exercise = SessionExercise { session_id: own_session.id, ..in_their_session };
+        let response = other_app.post(Object::SessionExercise.path(), &their_exercise).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
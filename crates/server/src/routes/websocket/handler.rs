@@ -1,4 +1,4 @@
-use std::net::{IpAddr, SocketAddr};
+use std::net::SocketAddr;
 
 use axum::{
     extract::{connect_info::ConnectInfo, ws::WebSocketUpgrade},
@@ -8,8 +8,8 @@ use axum::{
 use tracing::debug;
 
 use crate::{
-    constants::WEBSOCKET_CHANNEL_BOUND, routes::websocket::handle_socket, Client, Clients,
-    ClientsBySessionId, RtcRoomState, SessionId, UserState,
+    constants::WEBSOCKET_CHANNEL_BOUND, forwarded_ip, routes::websocket::handle_socket, Client,
+    Clients, ClientsBySessionId, RtcRoomState, SessionId, UserState,
 };
 
 pub async fn websocket_handler(
@@ -29,13 +29,7 @@ pub async fn websocket_handler(
 ) -> impl IntoResponse {
     debug!("Websocket upgrade headers: {:?}", headers);
 
-    let ip = headers
-        .get("x-forwarded-for")
-        .map(|v| v.to_str().ok())
-        .flatten()
-        .map(|v| v.parse::<IpAddr>().ok())
-        .flatten()
-        .unwrap_or(addr.ip());
+    let ip = forwarded_ip(&headers).unwrap_or(addr.ip());
 
     let port = headers
         .get("x-forwarded-port")
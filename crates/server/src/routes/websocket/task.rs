@@ -105,6 +105,10 @@ async fn handle_socket_inner(
                                 user_exit_room(&mut ws_sender, &rtc_room_state, peer_id, &user, &petname).await?;
                             }
                         },
+                        ClientControlMessage::Pairing { approved } => {
+                            debug!("{petname}: pairing {}", if approved { "approved" } else { "denied" });
+                            ws_sender.send(ServerUser::Pairing { approved }.try_into()?).await?;
+                        },
                         ClientControlMessage::RtcStp { sdp, peer_id, petname } => {
                             let message: ServerMessage = ServerRtc::PeerSdp { sdp, peer_id, petname }.into();
                             ws_sender.send(message.try_into()?).await?;
@@ -23,7 +23,7 @@ use shared::{
     types::Uuid,
 };
 use tower_sessions::{
-    session::{Error as SessionError, Id},
+    session::{Error as SessionError, Id, Record},
     Session,
 };
 use tracing::error;
@@ -80,6 +80,9 @@ impl From<Id> for SessionId {
 struct SessionData {
     passkey_registration_state: Option<PasskeyRegistrationState>,
     passkey_authentication_state: Option<PasskeyAuthenticationState>,
+    /// Kept apart from the login one so a login challenge can't be used to
+    /// confirm deleting the account
+    account_deletion_state: Option<PasskeyAuthenticationState>,
     user_state: Option<UserState>,
 }
 
@@ -126,6 +129,23 @@ impl SessionValue {
         Ok(())
     }
 
+    pub async fn take_account_deletion_state<T: Error>(
+        &mut self,
+    ) -> Result<Option<PasskeyAuthenticationState>, ServerError<T>> {
+        let state = self.data.account_deletion_state.take();
+        Self::update_session(&self.session, &self.data).await?;
+        Ok(state)
+    }
+
+    pub async fn set_account_deletion_state<T: Error>(
+        &mut self,
+        account_deletion: PasskeyAuthenticationState,
+    ) -> Result<(), ServerError<T>> {
+        self.data.account_deletion_state = Some(account_deletion);
+        Self::update_session(&self.session, &self.data).await?;
+        Ok(())
+    }
+
     pub async fn take_user_state<T: Error>(&mut self) -> Result<Option<UserState>, ServerError<T>> {
         let user_id = self.data.user_state.take();
         Self::update_session(&self.session, &self.data).await?;
@@ -142,6 +162,36 @@ impl SessionValue {
         self.session.id()
     }
 
+    /// The id of the session. New sessions don't get one until they're saved at
+    /// the end of the request so they're saved early
+    pub async fn saved_id<T: Error>(session: &Session) -> Result<Id, ServerError<T>> {
+        if session.id().is_none() {
+            session.save().await.map_err(session_error).context("Saving session")?;
+        }
+        session.id().ok_or_else(|| ServerError::Other {
+            message: "Session had no id after saving".to_string(),
+        })
+    }
+
+    /// The user logged in to the session, as the handlers have left it
+    pub async fn user_state_of(session: &Session) -> Option<UserState> {
+        session
+            .get::<SessionData>(Self::SESSION_DATA_KEY)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|d| d.user_state)
+    }
+
+    /// The user logged in to a session loaded straight from the store
+    pub fn record_user_state(record: &Record) -> Option<UserState> {
+        record
+            .data
+            .get(Self::SESSION_DATA_KEY)
+            .and_then(|v| serde_json::from_value::<SessionData>(v.clone()).ok())
+            .and_then(|d| d.user_state)
+    }
+
     async fn update_session<T: Error>(
         session: &Session,
         data: &SessionData,
@@ -149,15 +199,19 @@ impl SessionValue {
         session
             .insert(Self::SESSION_DATA_KEY, data.clone())
             .await
-            .map_err(|e| match e {
-                SessionError::SerdeJson(e) => ServerError::Json { message: e.to_string() },
-                SessionError::Store(e) => ServerError::Other { message: e.to_string() },
-            })
+            .map_err(session_error)
             .context("Updating session")?;
         Ok(())
     }
 }
 
+fn session_error<T: Error>(e: SessionError) -> ServerError<T> {
+    match e {
+        SessionError::SerdeJson(e) => ServerError::Json { message: e.to_string() },
+        SessionError::Store(e) => ServerError::Other { message: e.to_string() },
+    }
+}
+
 #[async_trait]
 impl<S> FromRequestParts<S> for SessionValue
 where
@@ -0,0 +1,156 @@
+use std::{
+    ops::Deref,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::extract::FromRef;
+use dashmap::DashMap;
+
+use crate::{
+    constants::{
+        RECOVERY_ATTEMPT_LIMIT, RECOVERY_ATTEMPT_WINDOW, TEMPORARY_LOGIN_ATTEMPT_LIMIT,
+        TEMPORARY_LOGIN_ATTEMPT_WINDOW,
+    },
+    AppState,
+};
+
+/// When attempts were made for each key, allowing `limit` of them within
+/// `window`. Kept in memory so a restart resets the limit, which is fine for
+/// slowing down guessing
+#[derive(Debug, Clone)]
+pub struct AttemptLimiter {
+    attempts: Arc<DashMap<String, Vec<Instant>>>,
+    last_sweep: Arc<Mutex<Instant>>,
+    limit: usize,
+    window: Duration,
+}
+
+impl AttemptLimiter {
+    pub fn new(limit: usize, window: Duration) -> Self {
+        Self {
+            attempts: Default::default(),
+            last_sweep: Arc::new(Mutex::new(Instant::now())),
+            limit,
+            window,
+        }
+    }
+
+    /// Records an attempt for the key. Returns false if it's over the limit in
+    /// which case the attempt shouldn't be made. The check and the record
+    /// happen under the same entry lock so attempts made at the same time
+    /// can't all get in before any of them is counted
+    pub fn attempt(&self, key: &str) -> bool {
+        self.sweep();
+
+        let now = Instant::now();
+        let mut attempts = self.attempts.entry(key.to_string()).or_default();
+        attempts.retain(|t| now.duration_since(*t) < self.window);
+        let allowed = attempts.len() < self.limit;
+        if allowed {
+            attempts.push(now);
+        }
+
+        allowed
+    }
+
+    /// Drops the keys without any attempts left in the window so every address
+    /// that's ever tried doesn't stay around. Done at most once per window
+    fn sweep(&self) {
+        let now = Instant::now();
+        {
+            let mut last_sweep = self.last_sweep.lock().unwrap();
+            if now.duration_since(*last_sweep) < self.window {
+                return;
+            }
+            *last_sweep = now;
+        }
+
+        self.attempts.retain(|_, attempts| {
+            attempts.retain(|t| now.duration_since(*t) < self.window);
+            !attempts.is_empty()
+        });
+    }
+}
+
+/// Recovery codes tried for each username, successful or not
+#[derive(Debug, Clone)]
+pub struct RecoveryAttempts(AttemptLimiter);
+
+impl Default for RecoveryAttempts {
+    fn default() -> Self {
+        Self(AttemptLimiter::new(RECOVERY_ATTEMPT_LIMIT, RECOVERY_ATTEMPT_WINDOW))
+    }
+}
+
+impl Deref for RecoveryAttempts {
+    type Target = AttemptLimiter;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl FromRef<AppState> for RecoveryAttempts {
+    fn from_ref(state: &AppState) -> Self {
+        state.recovery_attempts.clone()
+    }
+}
+
+/// Temporary logins tried, by address for codes and by username for pins
+#[derive(Debug, Clone)]
+pub struct TemporaryLoginAttempts(AttemptLimiter);
+
+impl Default for TemporaryLoginAttempts {
+    fn default() -> Self {
+        Self(AttemptLimiter::new(TEMPORARY_LOGIN_ATTEMPT_LIMIT, TEMPORARY_LOGIN_ATTEMPT_WINDOW))
+    }
+}
+
+impl Deref for TemporaryLoginAttempts {
+    type Target = AttemptLimiter;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl FromRef<AppState> for TemporaryLoginAttempts {
+    fn from_ref(state: &AppState) -> Self {
+        state.temporary_login_attempts.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn attempts_are_limited_per_key() {
+        let limiter = AttemptLimiter::new(5, Duration::from_secs(60));
+
+        let allowed = thread::scope(|scope| {
+            let threads = (0..8)
+                .map(|_| scope.spawn(|| (0..10).filter(|_| limiter.attempt("key")).count()))
+                .collect::<Vec<_>>();
+            threads.into_iter().map(|t| t.join().unwrap()).sum::<usize>()
+        });
+        assert_eq!(allowed, 5);
+
+        assert!(limiter.attempt("other"));
+    }
+
+    #[test]
+    fn stale_keys_are_dropped() {
+        let limiter = AttemptLimiter::new(1, Duration::from_millis(50));
+        assert!(limiter.attempt("stale"));
+        assert!(!limiter.attempt("stale"));
+
+        thread::sleep(Duration::from_millis(60));
+        assert!(limiter.attempt("fresh"));
+        assert!(!limiter.attempts.contains_key("stale"));
+        assert!(limiter.attempts.contains_key("fresh"));
+    }
+}
@@ -17,3 +17,9 @@ mod cli;
 mod pool;
 mod rtc;
 pub use rtc::*;
+
+mod attempts;
+pub use attempts::*;
+
+mod pairing;
+pub use pairing::*;
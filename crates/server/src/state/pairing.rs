@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use axum::extract::FromRef;
+use chrono::{DateTime, Utc};
+use dashmap::{DashMap, Entry};
+use rand::{seq::SliceRandom, thread_rng};
+use shared::api::payloads::{
+    normalize_pairing_code, PairingInfo, PAIRING_CODE_ALPHABET, PAIRING_CODE_LENGTH,
+};
+
+use crate::{constants::PAIRING_EXPIRY, AppState, ClientAddress, SessionId, UserState};
+
+/// A device that wants to be logged in by one that already is
+#[derive(Debug, Clone)]
+pub struct PairingRequest {
+    /// The session of the device asking to be logged in
+    pub session_id: SessionId,
+    pub address: ClientAddress,
+    pub creation_date: DateTime<Utc>,
+    pub expiry_date: DateTime<Utc>,
+    /// The user that approved the request, once one has
+    pub approved_by: Option<UserState>,
+}
+
+impl PairingRequest {
+    pub fn info(&self, code: &str) -> PairingInfo {
+        PairingInfo {
+            code: code.to_string(),
+            user_agent: self.address.user_agent.clone(),
+            ip_address: self.address.ip_address.clone(),
+            creation_date: self.creation_date,
+            expiry_date: self.expiry_date,
+        }
+    }
+
+    fn expired(&self) -> bool {
+        self.expiry_date < Utc::now()
+    }
+}
+
+/// Outstanding pairing requests by their code. They only last a few minutes so
+/// they're kept in memory
+#[derive(Debug, Clone, Default)]
+pub struct PairingRequests(Arc<DashMap<String, PairingRequest>>);
+
+impl PairingRequests {
+    /// Starts pairing for the session, replacing any request it already had.
+    /// Returns the new code and request
+    pub fn start(&self, session_id: SessionId, address: ClientAddress) -> (String, PairingRequest) {
+        self.0.retain(|_, r| !r.expired() && r.session_id != session_id);
+
+        let now = Utc::now();
+        let request = PairingRequest {
+            session_id,
+            address,
+            creation_date: now,
+            expiry_date: now + PAIRING_EXPIRY,
+            approved_by: None,
+        };
+
+        let mut rng = thread_rng();
+        loop {
+            let code = (0..PAIRING_CODE_LENGTH)
+                .map(|_| *PAIRING_CODE_ALPHABET.choose(&mut rng).unwrap() as char)
+                .collect::<String>();
+            if let Entry::Vacant(e) = self.0.entry(code.clone()) {
+                e.insert(request.clone());
+                return (code, request);
+            }
+        }
+    }
+
+    /// The unexpired request with the code
+    pub fn get(&self, code: &str) -> Option<PairingRequest> {
+        self.0.get(&normalize_pairing_code(code)).map(|r| r.clone()).filter(|r| !r.expired())
+    }
+
+    /// The session's unexpired request and its code
+    pub fn for_session(&self, session_id: &SessionId) -> Option<(String, PairingRequest)> {
+        self.0
+            .iter()
+            .find(|r| r.session_id == *session_id && !r.expired())
+            .map(|r| (r.key().clone(), r.value().clone()))
+    }
+
+    pub fn approve(&self, code: &str, user_state: UserState) -> Option<PairingRequest> {
+        let mut request = self.0.get_mut(&normalize_pairing_code(code))?;
+        if request.expired() {
+            return None;
+        }
+        request.approved_by = Some(user_state);
+
+        Some(request.clone())
+    }
+
+    pub fn remove(&self, code: &str) -> Option<PairingRequest> {
+        self.0.remove(&normalize_pairing_code(code)).map(|(_, r)| r).filter(|r| !r.expired())
+    }
+}
+
+impl FromRef<AppState> for PairingRequests {
+    fn from_ref(state: &AppState) -> Self {
+        state.pairing_requests.clone()
+    }
+}
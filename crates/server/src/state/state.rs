@@ -1,8 +1,14 @@
 use std::sync::Arc;
 
+use axum::extract::FromRef;
 use deadpool_sqlite::Pool;
+use tower_sessions_deadpool_sqlite_store::DeadpoolSqliteStore;
 
-use super::rtc::RtcRoomState;
+use super::{
+    attempts::{RecoveryAttempts, TemporaryLoginAttempts},
+    pairing::PairingRequests,
+    rtc::RtcRoomState,
+};
 use crate::{
     cli::Cli,
     state::{VapidPrivateKey, VapidPubKey},
@@ -19,4 +25,15 @@ pub struct AppState {
     pub websocket_clients: Clients,
     pub websocket_clients_by_user_id: ClientsBySessionId,
     pub rtc_room_state: RtcRoomState,
+    pub recovery_attempts: RecoveryAttempts,
+    pub temporary_login_attempts: TemporaryLoginAttempts,
+    pub pairing_requests: PairingRequests,
+    /// Uses the same pool as `pool`
+    pub session_store: DeadpoolSqliteStore,
+}
+
+impl FromRef<AppState> for DeadpoolSqliteStore {
+    fn from_ref(state: &AppState) -> Self {
+        state.session_store.clone()
+    }
 }
@@ -3,13 +3,27 @@ use std::{
     sync::Arc,
 };
 
+use anyhow::Context;
 use axum::{
     async_trait,
     extract::{FromRef, FromRequestParts},
     http::{request::Parts, StatusCode},
 };
+use webauthn_rs::{prelude::Url, WebauthnBuilder};
 
-use crate::AppState;
+use crate::{cli::Cli, AppState};
+
+pub fn build_webauthn(args: &Cli) -> Result<webauthn_rs::Webauthn, anyhow::Error> {
+    let rp_name = format!("eggercise.rs on {}", &args.origin);
+    let url = Url::parse(&args.origin)
+        .with_context(|| format!("Parsing \"{}\" as webauthn origin URL", &args.origin))?;
+
+    let builder = WebauthnBuilder::new(&args.webauthn_id, &url).with_context(|| {
+        format!("WebauthnBuilder::new({}, {})", &args.webauthn_id, &args.origin)
+    })?;
+
+    Ok(builder.rp_name(&rp_name).build()?)
+}
 
 #[derive(Debug)]
 pub struct Webauthn(Arc<webauthn_rs::Webauthn>);
@@ -20,6 +20,8 @@ pub enum ClientControlMessage {
     Logout,
     RtcStp { sdp: Sdp, peer_id: PeerId, petname: String },
     RtcIceCandidate { candidate: IceCandidate, peer_id: PeerId },
+    // A pairing request made by the session was approved or denied
+    Pairing { approved: bool },
 }
 
 type ClientKey = PeerId;
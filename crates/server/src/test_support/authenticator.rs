@@ -0,0 +1,198 @@
+//! A software authenticator that answers the challenges from the auth routes
+//! the way a browser and security key would. It only does what webauthn-rs
+//! needs for passkeys: ES256 keys, "none" attestation and user verification
+
+use std::collections::BTreeMap;
+
+use base64::prelude::{Engine as _, BASE64_URL_SAFE_NO_PAD};
+use openssl::{
+    bn::{BigNum, BigNumContext},
+    ec::{EcGroup, EcKey},
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{PKey, Private},
+    rand::rand_bytes,
+    sha::sha256,
+    sign::Signer,
+};
+use serde_cbor_2::Value as Cbor;
+use serde_json::{json, Value};
+
+use crate::cli::Cli;
+
+/// User present
+const FLAG_UP: u8 = 0x01;
+/// User verified
+const FLAG_UV: u8 = 0x04;
+/// Attested credential data included
+const FLAG_AT: u8 = 0x40;
+
+/// COSE values for an EC2 P-256 key used with ES256
+const COSE_KTY_EC2: i128 = 2;
+const COSE_ALG_ES256: i128 = -7;
+const COSE_CRV_P256: i128 = 1;
+
+pub struct SoftCredential {
+    pub id: Vec<u8>,
+    key: EcKey<Private>,
+    /// The signature counter, incremented before every assertion
+    pub counter: u32,
+}
+
+impl SoftCredential {
+    fn new() -> Self {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = EcKey::generate(&group).unwrap();
+        let mut id = vec![0; 16];
+        rand_bytes(&mut id).unwrap();
+
+        Self { id, key, counter: 0 }
+    }
+
+    fn cose_public_key(&self) -> Vec<u8> {
+        let mut ctx = BigNumContext::new().unwrap();
+        let mut x = BigNum::new().unwrap();
+        let mut y = BigNum::new().unwrap();
+        self.key
+            .public_key()
+            .affine_coordinates(self.key.group(), &mut x, &mut y, &mut ctx)
+            .unwrap();
+
+        let key = BTreeMap::from([
+            (Cbor::Integer(1), Cbor::Integer(COSE_KTY_EC2)),
+            (Cbor::Integer(3), Cbor::Integer(COSE_ALG_ES256)),
+            (Cbor::Integer(-1), Cbor::Integer(COSE_CRV_P256)),
+            (Cbor::Integer(-2), Cbor::Bytes(x.to_vec_padded(32).unwrap())),
+            (Cbor::Integer(-3), Cbor::Bytes(y.to_vec_padded(32).unwrap())),
+        ]);
+        serde_cbor_2::to_vec(&Cbor::Map(key)).unwrap()
+    }
+
+    fn sign(&self, auth_data: &[u8], client_data_json: &[u8]) -> Vec<u8> {
+        let pkey = PKey::from_ec_key(self.key.clone()).unwrap();
+        let mut signer = Signer::new(MessageDigest::sha256(), &pkey).unwrap();
+        signer.update(auth_data).unwrap();
+        signer.update(&sha256(client_data_json)).unwrap();
+        signer.sign_to_vec().unwrap()
+    }
+}
+
+/// Holds any number of credentials for the origin it was created for. The
+/// challenges and responses are the json the routes send and receive
+pub struct SoftAuthenticator {
+    origin: String,
+    rp_id: String,
+    pub credentials: Vec<SoftCredential>,
+}
+
+impl SoftAuthenticator {
+    pub fn new(args: &Cli) -> Self {
+        Self {
+            origin: args.origin.clone(),
+            rp_id: args.webauthn_id.clone(),
+            credentials: Vec::new(),
+        }
+    }
+
+    /// Creates a new credential in answer to a `CreationChallengeResponse`
+    /// and returns the `RegisterPublicKeyCredential` for it
+    pub fn register(&mut self, challenge: &Value) -> Value {
+        let credential = SoftCredential::new();
+        let client_data_json = self.client_data_json("webauthn.create", challenge);
+
+        let mut auth_data = self.auth_data(FLAG_UP | FLAG_UV | FLAG_AT, credential.counter);
+        // AAGUID is all zeros for authenticators without attestation
+        auth_data.extend_from_slice(&[0; 16]);
+        auth_data.extend_from_slice(&(credential.id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&credential.id);
+        auth_data.extend_from_slice(&credential.cose_public_key());
+
+        let attestation_object = BTreeMap::from([
+            (Cbor::Text("fmt".to_string()), Cbor::Text("none".to_string())),
+            (Cbor::Text("attStmt".to_string()), Cbor::Map(BTreeMap::new())),
+            (Cbor::Text("authData".to_string()), Cbor::Bytes(auth_data)),
+        ]);
+        let attestation_object = serde_cbor_2::to_vec(&Cbor::Map(attestation_object)).unwrap();
+
+        let id = BASE64_URL_SAFE_NO_PAD.encode(&credential.id);
+        self.credentials.push(credential);
+
+        json!({
+            "id": id,
+            "rawId": id,
+            "type": "public-key",
+            "response": {
+                "attestationObject": BASE64_URL_SAFE_NO_PAD.encode(attestation_object),
+                "clientDataJSON": BASE64_URL_SAFE_NO_PAD.encode(client_data_json),
+            },
+            "extensions": {},
+        })
+    }
+
+    /// Answers a `RequestChallengeResponse` with the first credential it
+    /// allows
+    pub fn authenticate(&mut self, challenge: &Value) -> Value {
+        let allowed = challenge["publicKey"]["allowCredentials"]
+            .as_array()
+            .expect("allowCredentials in the challenge")
+            .iter()
+            .map(|c| decode(&c["id"]))
+            .collect::<Vec<_>>();
+        let index = self
+            .credentials
+            .iter()
+            .position(|c| allowed.contains(&c.id))
+            .expect("A credential the challenge allows");
+
+        self.assertion(challenge, index)
+    }
+
+    /// Signs the challenge with the given credential whether it's allowed or
+    /// not. The counter is incremented first, as real authenticators do
+    pub fn assertion(&mut self, challenge: &Value, credential: usize) -> Value {
+        let client_data_json = self.client_data_json("webauthn.get", challenge);
+        self.credentials[credential].counter += 1;
+
+        let credential = &self.credentials[credential];
+        let auth_data = self.auth_data(FLAG_UP | FLAG_UV, credential.counter);
+        let signature = credential.sign(&auth_data, &client_data_json);
+        let id = BASE64_URL_SAFE_NO_PAD.encode(&credential.id);
+
+        json!({
+            "id": id,
+            "rawId": id,
+            "type": "public-key",
+            "response": {
+                "authenticatorData": BASE64_URL_SAFE_NO_PAD.encode(auth_data),
+                "clientDataJSON": BASE64_URL_SAFE_NO_PAD.encode(client_data_json),
+                "signature": BASE64_URL_SAFE_NO_PAD.encode(signature),
+            },
+            "extensions": {},
+        })
+    }
+
+    fn client_data_json(&self, type_: &str, challenge: &Value) -> Vec<u8> {
+        let challenge = decode(&challenge["publicKey"]["challenge"]);
+        serde_json::to_vec(&json!({
+            "type": type_,
+            "challenge": BASE64_URL_SAFE_NO_PAD.encode(challenge),
+            "origin": self.origin,
+            "crossOrigin": false,
+        }))
+        .unwrap()
+    }
+
+    /// The authenticator data up to and including the counter
+    fn auth_data(&self, flags: u8, counter: u32) -> Vec<u8> {
+        let mut auth_data = sha256(self.rp_id.as_bytes()).to_vec();
+        auth_data.push(flags);
+        auth_data.extend_from_slice(&counter.to_be_bytes());
+        auth_data
+    }
+}
+
+/// Base64 fields in the challenges may or may not be padded
+fn decode(value: &Value) -> Vec<u8> {
+    let value = value.as_str().expect("A base64 string");
+    BASE64_URL_SAFE_NO_PAD.decode(value.trim_end_matches('=')).unwrap()
+}
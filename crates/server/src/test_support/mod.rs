@@ -0,0 +1,440 @@
+//! Helpers for testing routes through the whole [`router`] with an in-memory
+//! database behind it
+
+mod authenticator;
+mod clock;
+mod push_endpoint;
+use std::{fmt::Display, sync::Arc};
+
+pub use authenticator::*;
+use axum::{
+    body::{to_bytes, Body},
+    http::{
+        header::{CONTENT_TYPE, COOKIE, SET_COOKIE},
+        request::Builder,
+        HeaderValue, Method, Request, StatusCode,
+    },
+    response::Response,
+    Router,
+};
+use chrono::{DateTime, Duration, Utc};
+use clap::Parser;
+pub use clock::*;
+use openssl::{
+    bn::BigNumContext,
+    ec::{EcGroup, EcKey, PointConversionForm},
+    nid::Nid,
+};
+pub use push_endpoint::*;
+use rusqlite::Connection;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use shared::{
+    api::{error::Nothing, Auth, Object, CSRF_HEADER},
+    model::{
+        Exercise, ExerciseGroup, ExerciseGroupMember, LoginUser, NewUser, Plan, PlanExerciseGroup,
+        PlanInstance, RegistrationUser, Session, SessionExercise, TemporaryLogin, User,
+    },
+    types::Uuid,
+};
+use tower::ServiceExt;
+use tower_sessions_deadpool_sqlite_store::DeadpoolSqliteStore;
+
+use crate::{
+    build_webauthn,
+    cli::Cli,
+    db::{create_pool, get_migrations, migrate_to_latest, run_migrations, run_pragmas},
+    router, AppState, VapidPrivateKey, VapidPubKey,
+};
+
+/// A fresh in-memory database with all the migrations run
+pub fn connection() -> Connection {
+    let mut conn = Connection::open_in_memory().unwrap();
+    run_pragmas(&conn).unwrap();
+    migrate_to_latest(&mut conn, &get_migrations().unwrap()).unwrap();
+    conn
+}
+
+/// A new key pair in the formats `scripts/generate_keys` leaves them in once
+/// they're loaded: the private key as PEM and the public key as the raw point
+pub fn vapid_keys() -> (VapidPubKey, VapidPrivateKey) {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    let key = EcKey::generate(&group).unwrap();
+    let mut ctx = BigNumContext::new().unwrap();
+    let public_key =
+        key.public_key().to_bytes(&group, PointConversionForm::UNCOMPRESSED, &mut ctx).unwrap();
+
+    (public_key.into(), key.private_key_to_pem().unwrap().into())
+}
+
+/// Replaces the `:id` in one of the [`Auth`] or [`Object`] paths
+pub fn with_id(path: &str, id: impl Display) -> String {
+    path.replace(":id", &id.to_string())
+}
+
+pub async fn body_string(response: Response) -> String {
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+pub async fn body_json<T: DeserializeOwned>(response: Response) -> T {
+    let body = body_string(response).await;
+    serde_json::from_str(&body).unwrap_or_else(|e| panic!("Deserializing {body}: {e}"))
+}
+
+/// The app with its own database. Requests made through it share a session
+/// like a browser would
+pub struct TestApp {
+    pub state: AppState,
+    router: Router,
+    /// The database is dropped when its last connection closes
+    _keep_alive: Arc<deadpool_sqlite::Object>,
+    cookie: Option<HeaderValue>,
+    csrf_token: Option<HeaderValue>,
+}
+
+impl TestApp {
+    pub async fn new() -> Self {
+        // Shared cache so every connection in the pool sees the same database
+        let connection_string = format!("file:{}?mode=memory&cache=shared", Uuid::new_v4());
+        let args = Cli {
+            sqlite_connection_string: connection_string.clone(),
+            ..Cli::parse_from(["server"])
+        };
+
+        let pool = create_pool(&connection_string).unwrap();
+        let keep_alive = Arc::new(pool.get().await.unwrap());
+        run_migrations(&connection_string, env!("CARGO_PKG_VERSION")).unwrap();
+
+        let session_store = DeadpoolSqliteStore::new(pool.clone());
+        session_store.migrate().await.unwrap();
+
+        let (vapid_pub_key, vapid_private_key) = vapid_keys();
+        let state = AppState {
+            pool,
+            webauthn: Arc::new(build_webauthn(&args).unwrap()),
+            args: Arc::new(args),
+            vapid_pub_key,
+            vapid_private_key,
+            websocket_clients: Default::default(),
+            websocket_clients_by_user_id: Default::default(),
+            rtc_room_state: Default::default(),
+            recovery_attempts: Default::default(),
+            temporary_login_attempts: Default::default(),
+            pairing_requests: Default::default(),
+            session_store,
+        };
+        let router = router(state.clone()).unwrap();
+
+        Self { state, router, _keep_alive: keep_alive, cookie: None, csrf_token: None }
+    }
+
+    /// Another browser using the same server. It starts without a session
+    pub fn client(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            router: self.router.clone(),
+            _keep_alive: self._keep_alive.clone(),
+            cookie: None,
+            csrf_token: None,
+        }
+    }
+
+    /// Sends the request with the session cookie, keeping hold of the cookie and
+    /// csrf token from the response
+    pub async fn send(&mut self, mut request: Request<Body>) -> Response {
+        if let Some(cookie) = &self.cookie {
+            request.headers_mut().insert(COOKIE, cookie.clone());
+        }
+
+        let response = self.router.clone().oneshot(request).await.unwrap();
+
+        if let Some(cookie) = response.headers().get(SET_COOKIE) {
+            // Only the name=value is sent back
+            let cookie = cookie.to_str().unwrap().split(';').next().unwrap();
+            self.cookie = Some(HeaderValue::from_str(cookie).unwrap());
+        }
+        if let Some(csrf_token) = response.headers().get(CSRF_HEADER) {
+            self.csrf_token = Some(csrf_token.clone());
+        }
+
+        response
+    }
+
+    /// A request with the csrf token from the last response, if there's been
+    /// one
+    pub fn request(&self, method: Method, path: &str) -> Builder {
+        let builder = Request::builder().method(method).uri(path);
+        match &self.csrf_token {
+            Some(csrf_token) => builder.header(CSRF_HEADER, csrf_token),
+            None => builder,
+        }
+    }
+
+    pub async fn get(&mut self, path: &str) -> Response {
+        let request = self.request(Method::GET, path).body(Body::empty()).unwrap();
+        self.send(request).await
+    }
+
+    pub async fn post<T: Serialize>(&mut self, path: &str, body: &T) -> Response {
+        self.ensure_csrf_token().await;
+        let request = self
+            .request(Method::POST, path)
+            .header(CONTENT_TYPE, mime::APPLICATION_JSON.essence_str())
+            .body(Body::from(serde_json::to_vec(body).unwrap()))
+            .unwrap();
+        self.send(request).await
+    }
+
+    pub async fn patch<T: Serialize>(&mut self, path: &str, body: &T) -> Response {
+        self.ensure_csrf_token().await;
+        let request = self
+            .request(Method::PATCH, path)
+            .header(CONTENT_TYPE, mime::APPLICATION_JSON.essence_str())
+            .body(Body::from(serde_json::to_vec(body).unwrap()))
+            .unwrap();
+        self.send(request).await
+    }
+
+    pub async fn delete(&mut self, path: &str) -> Response {
+        self.ensure_csrf_token().await;
+        let request = self.request(Method::DELETE, path).body(Body::empty()).unwrap();
+        self.send(request).await
+    }
+
+    pub async fn delete_with<T: Serialize>(&mut self, path: &str, body: &T) -> Response {
+        self.ensure_csrf_token().await;
+        let request = self
+            .request(Method::DELETE, path)
+            .header(CONTENT_TYPE, mime::APPLICATION_JSON.essence_str())
+            .body(Body::from(serde_json::to_vec(body).unwrap()))
+            .unwrap();
+        self.send(request).await
+    }
+
+    /// Unsafe requests are rejected without a token so get one the way the
+    /// client does
+    async fn ensure_csrf_token(&mut self) {
+        if self.csrf_token.is_none() {
+            self.get(Object::Ping.path()).await;
+        }
+    }
+
+    /// Runs `f` on one of the pool's connections
+    pub async fn interact<T, F>(&self, f: F) -> T
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> T + Send + 'static,
+    {
+        self.state.pool.get().await.unwrap().interact(f).await.unwrap()
+    }
+
+    pub async fn create_user(&self, username: &str) -> User {
+        let new_user = NewUser::new(Uuid::new_v4(), username);
+        self.interact(move |conn| User::create::<Nothing>(conn, new_user).unwrap()).await
+    }
+
+    pub async fn create_temporary_login(
+        &self,
+        user: &User,
+        expires_in: Duration,
+    ) -> TemporaryLogin {
+        let temporary_login = TemporaryLogin {
+            id: Uuid::new_v4(),
+            user_id: user.id,
+            expiry_date: Utc::now() + expires_in,
+            url: String::new(),
+            pin: None,
+        };
+        self.interact(move |conn| TemporaryLogin::create::<Nothing>(conn, temporary_login).unwrap())
+            .await
+    }
+
+    /// A custom exercise owned by `user`
+    pub async fn create_exercise(&self, user: &User, name: &str) -> Exercise {
+        let now = Utc::now();
+        let exercise = Exercise {
+            id: Uuid::new_v4(),
+            owner_id: Some(user.id),
+            name: name.to_string(),
+            description: None,
+            base_recovery_days: 2.0,
+            creation_date: now,
+            last_updated_date: now,
+        };
+        self.interact(move |conn| Exercise::create::<Nothing>(conn, exercise).unwrap()).await
+    }
+
+    /// A group of `exercises` owned by `user`
+    pub async fn create_exercise_group(
+        &self,
+        user: &User,
+        name: &str,
+        exercises: &[&Exercise],
+    ) -> ExerciseGroup {
+        let now = Utc::now();
+        let group = ExerciseGroup {
+            id: Uuid::new_v4(),
+            owner_id: Some(user.id),
+            name: name.to_string(),
+            description: None,
+            creation_date: now,
+            last_updated_date: now,
+        };
+        let exercise_ids = exercises.iter().map(|e| e.id).collect::<Vec<_>>();
+        self.interact(move |conn| {
+            let group = ExerciseGroup::create::<Nothing>(conn, group).unwrap();
+            for exercise_id in exercise_ids {
+                ExerciseGroupMember { id: Uuid::new_v4(), exercise_id, group_id: group.id }
+                    .insert(conn)
+                    .unwrap();
+            }
+            group
+        })
+        .await
+    }
+
+    /// `group` added to `plan` without any notes or config
+    pub async fn create_plan_exercise_group(
+        &self,
+        plan: &Plan,
+        group: &ExerciseGroup,
+    ) -> PlanExerciseGroup {
+        let now = Utc::now();
+        let plan_exercise_group = PlanExerciseGroup {
+            id: Uuid::new_v4(),
+            plan_id: plan.id,
+            exercise_group_id: group.id,
+            notes: None,
+            config: None,
+            creation_date: now,
+            last_updated_date: now,
+        };
+        self.interact(move |conn| {
+            plan_exercise_group.insert(conn).unwrap();
+            plan_exercise_group
+        })
+        .await
+    }
+
+    /// A plan owned by `user`. Plan names are unique so it's named after its id
+    pub async fn create_plan(&self, user: &User) -> Plan {
+        let now = Utc::now();
+        let id = Uuid::new_v4();
+        let plan = Plan {
+            id,
+            owner_id: user.id,
+            name: format!("Plan {id}"),
+            description: None,
+            duration_weeks: 4,
+            creation_date: now,
+            last_updated_date: now,
+        };
+        self.interact(move |conn| Plan::create::<Nothing>(conn, plan).unwrap()).await
+    }
+
+    /// `user` following `plan` from now
+    pub async fn create_plan_instance(&self, user: &User, plan: &Plan) -> PlanInstance {
+        let now = Utc::now();
+        let plan_instance = PlanInstance {
+            id: Uuid::new_v4(),
+            plan_id: plan.id,
+            user_id: user.id,
+            start_date: now,
+            creation_date: now,
+            last_updated_date: now,
+        };
+        self.interact(move |conn| PlanInstance::create::<Nothing>(conn, plan_instance).unwrap())
+            .await
+    }
+
+    /// A session that hasn't been done yet. Ad-hoc without a plan instance
+    pub async fn create_session(
+        &self,
+        user: &User,
+        plan_instance: Option<&PlanInstance>,
+        planned_date: DateTime<Utc>,
+    ) -> Session {
+        let now = Utc::now();
+        let session = Session {
+            id: Uuid::new_v4(),
+            user_id: user.id,
+            plan_instance_id: plan_instance.map(|p| p.id),
+            planned_date,
+            performed_date: None,
+            creation_date: now,
+            last_updated_date: now,
+        };
+        self.interact(move |conn| Session::create::<Nothing>(conn, session).unwrap()).await
+    }
+
+    /// `exercise` in `session` with nothing planned or performed
+    pub async fn create_session_exercise(
+        &self,
+        session: &Session,
+        exercise: &Exercise,
+    ) -> SessionExercise {
+        let now = Utc::now();
+        let session_exercise = SessionExercise {
+            id: Uuid::new_v4(),
+            user_id: session.user_id,
+            exercise_id: exercise.id,
+            session_id: session.id,
+            planned_sets: Default::default(),
+            performed_sets: Default::default(),
+            creation_date: now,
+            last_updated_date: now,
+            sort_order: 0,
+            finished: false,
+        };
+        self.interact(move |conn| {
+            SessionExercise::create::<Nothing>(conn, session_exercise).unwrap()
+        })
+        .await
+    }
+
+    /// Creates a user and logs the session in with a temporary login, which is
+    /// the only way in that doesn't need an authenticator
+    pub async fn login(&mut self, username: &str) -> User {
+        let user = self.create_user(username).await;
+        let temporary_login = self.create_temporary_login(&user, Duration::minutes(10)).await;
+
+        let response = self.get(&with_id(Auth::TemporaryLogin.path(), temporary_login.id)).await;
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+        user
+    }
+
+    /// Registers a new user with a new credential on `authenticator`,
+    /// returning the response from the finish route
+    pub async fn register_with(
+        &mut self,
+        authenticator: &mut SoftAuthenticator,
+        username: &str,
+    ) -> Response {
+        let response =
+            self.post(Auth::RegisterStart.path(), &RegistrationUser::new(username)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let challenge: Value = body_json(response).await;
+
+        self.post(Auth::RegisterFinish.path(), &authenticator.register(&challenge)).await
+    }
+
+    /// The challenge from the start of a passkey login
+    pub async fn login_challenge(&mut self, username: &str) -> Value {
+        let response = self.post(Auth::LoginStart.path(), &LoginUser::new(username)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        body_json(response).await
+    }
+
+    /// Logs in with one of the credentials on `authenticator`, returning the
+    /// response from the finish route
+    pub async fn login_with(
+        &mut self,
+        authenticator: &mut SoftAuthenticator,
+        username: &str,
+    ) -> Response {
+        let challenge = self.login_challenge(username).await;
+        self.post(Auth::LoginFinish.path(), &authenticator.authenticate(&challenge)).await
+    }
+}
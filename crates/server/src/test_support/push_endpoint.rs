@@ -0,0 +1,112 @@
+//! A push service on localhost for the real [`WebPushTransport`] to send to,
+//! so notifications can be tested end to end without the network. It can't
+//! decrypt what it's sent but keeps the headers the push service would act on
+//!
+//! [`WebPushTransport`]: crate::notifier::WebPushTransport
+
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Router,
+};
+use base64::prelude::{Engine as _, BASE64_URL_SAFE};
+use openssl::rand::rand_bytes;
+use shared::model::PushNotificationSubscription;
+use tokio::net::TcpListener;
+
+use super::vapid_keys;
+
+/// A push the endpoint has been sent
+#[derive(Debug, Clone)]
+pub struct ReceivedPush {
+    pub device: String,
+    pub ttl: Option<u32>,
+    pub urgency: Option<String>,
+    pub body_len: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct EndpointState {
+    received: Arc<Mutex<Vec<ReceivedPush>>>,
+    /// Statuses to answer with for each device before going back to 201
+    responses: Arc<Mutex<HashMap<String, VecDeque<StatusCode>>>>,
+}
+
+async fn receive(
+    State(state): State<EndpointState>,
+    Path(device): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    let header = |name| headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+    state.received.lock().unwrap().push(ReceivedPush {
+        device: device.clone(),
+        ttl: header("ttl").and_then(|v| v.parse().ok()),
+        urgency: header("urgency"),
+        body_len: body.len(),
+    });
+
+    state
+        .responses
+        .lock()
+        .unwrap()
+        .get_mut(&device)
+        .and_then(VecDeque::pop_front)
+        .unwrap_or(StatusCode::CREATED)
+}
+
+pub struct FakePushEndpoint {
+    address: SocketAddr,
+    state: EndpointState,
+}
+
+impl FakePushEndpoint {
+    pub async fn start() -> Self {
+        let state = EndpointState::default();
+        let router = Router::new().route("/push/:device", post(receive)).with_state(state.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        Self { address, state }
+    }
+
+    /// A subscription on this endpoint with keys the payload can be encrypted
+    /// to
+    pub fn subscription(&self, device: &str) -> PushNotificationSubscription {
+        let (public_key, _) = vapid_keys();
+        let mut auth = [0; 16];
+        rand_bytes(&mut auth).unwrap();
+
+        PushNotificationSubscription {
+            endpoint: format!("http://{}/push/{device}", self.address),
+            key: BASE64_URL_SAFE.encode(public_key.bytes()),
+            auth: BASE64_URL_SAFE.encode(auth),
+        }
+    }
+
+    /// Answers the next push to `device` with `status` instead of 201 Created.
+    /// Calls queue up
+    pub fn respond_with(&self, device: &str, status: StatusCode) {
+        self.state
+            .responses
+            .lock()
+            .unwrap()
+            .entry(device.to_string())
+            .or_default()
+            .push_back(status);
+    }
+
+    pub fn received(&self) -> Vec<ReceivedPush> {
+        self.state.received.lock().unwrap().clone()
+    }
+}
@@ -1,4 +1,13 @@
-use axum::http::StatusCode;
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts, Extensions, HeaderMap, StatusCode},
+};
 
 /// Utility function for mapping any error into a `500 Internal Server Error`
 /// response.
@@ -8,3 +17,42 @@ where
 {
     (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
 }
+
+/// The client's IP from `X-Forwarded-For` if a proxy has set it
+pub fn forwarded_ip(headers: &HeaderMap) -> Option<IpAddr> {
+    headers
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+        .and_then(|v| v.trim().parse::<IpAddr>().ok())
+}
+
+/// Where a request came from, as far as the headers and connection can tell
+#[derive(Debug, Clone, Default)]
+pub struct ClientAddress {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl ClientAddress {
+    pub fn new(headers: &HeaderMap, extensions: &Extensions) -> Self {
+        let user_agent = headers.get(USER_AGENT).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let ip_address = forwarded_ip(headers)
+            .or_else(|| extensions.get::<ConnectInfo<SocketAddr>>().map(|c| c.0.ip()))
+            .map(|ip| ip.to_string());
+
+        Self { ip_address, user_agent }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientAddress
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::new(&parts.headers, &parts.extensions))
+    }
+}
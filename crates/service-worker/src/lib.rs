@@ -11,7 +11,8 @@ use shared::{
     api::{
         browser::record_subscription,
         error::{FrontendError, Nothing, ServerError},
-        payloads::Notification,
+        fetch_fns::notifications::reminder_action,
+        payloads::{Notification, NotificationAction, NotificationData},
         API_BASE_PATH,
     },
     server_debug, server_error, server_info, server_trace,
@@ -402,6 +403,13 @@ pub fn worker_message(
     Ok(future_to_promise(message(sw, event)))
 }
 
+/// A button as the browser expects them in `NotificationOptions`
+#[derive(Serialize)]
+struct ActionOption {
+    action: &'static str,
+    title: &'static str,
+}
+
 async fn push(
     sw: ServiceWorkerGlobalScope,
     _version: String,
@@ -427,6 +435,20 @@ async fn push(
             options.icon(&icon);
         }
 
+        let actions = notification
+            .actions
+            .iter()
+            .map(|a| ActionOption { action: a.id(), title: a.title() })
+            .collect::<Vec<_>>();
+        let actions = log_frontend_nothing_err!(
+            <JsValue as JsValueSerdeExt>::from_serde(&actions),
+            "actions serialize",
+        )?;
+        options.actions(&actions);
+
+        // Kept whole so the click handler knows what the actions are for
+        options.data(&json);
+
         options.timestamp(notification.sent.timestamp_millis() as f64);
 
         let mut body = if let Some(mut body) = notification.body {
@@ -476,8 +498,13 @@ async fn push_subscription_change(
     // TODO: Should record this change in case the user is currently offline
     //       It should also probably be user aware instead of assuming the cookies
     //       match the subscription owner. As a minimum it should pass event.oldSub
-    //       so the server can check it's replacing the right sub
-    log_frontend_err!(record_subscription(&push_manager).await, _, "record_subscription error")?;
+    //       so the server can check it's replacing the right sub and keep its device label.
+    //       The old one is removed when sending to it fails
+    log_frontend_err!(
+        record_subscription(&push_manager, None).await,
+        _,
+        "record_subscription error"
+    )?;
     console_log!("push_subscription_change::record_subscription OK");
 
     Ok(JsValue::undefined())
@@ -501,25 +528,66 @@ async fn notification_click(
     // Close the notification (chrome doesn't do this by itself)
     event.notification().close();
 
+    // Notifications shown by older versions don't have any data
+    let notification: Option<Notification> =
+        JsValueSerdeExt::into_serde(&event.notification().data()).ok();
+
+    // Clicking the notification itself rather than one of its actions gives an
+    // empty action
+    let action = NotificationAction::from_id(&event.action());
+    let data = notification.as_ref().and_then(|n| n.data.as_ref());
+
+    // These are done without opening the app
+    if let (Some(action @ (NotificationAction::Snooze | NotificationAction::Skip)), Some(data)) =
+        (action, data)
+    {
+        let NotificationData::Reminder { reminder_id, .. } = data;
+        console_log!("{action:?} on reminder {reminder_id}");
+        log_frontend_err!(
+            reminder_action(*reminder_id, action).await,
+            _,
+            "reminder_action::{:?}",
+            action,
+        )?;
+        return Ok(JsValue::undefined());
+    }
+
+    let url = notification.and_then(|n| n.url);
     let clients: Array = JsFuture::from(sw.clients().match_all()).await?.into();
 
     let client: WindowClient = if clients.length() > 0 {
         clients.get(0).into()
     } else {
-        let origin = sw.origin();
-        console_log!("Opening {origin}");
+        let url = format!("{}{}", sw.origin(), url.as_deref().unwrap_or("/"));
+        console_log!("Opening {url}");
 
         // This is broken in firefox android and it doesn't seem to be being worked on
         // <https://bugzilla.mozilla.org/show_bug.cgi?id=1717431>
-        log_frontend_nothing_err!(
-            JsFuture::from(sw.clients().open_window(&origin)).await,
+        return log_frontend_nothing_err!(
+            JsFuture::from(sw.clients().open_window(&url)).await,
             "sw::clients::open_window",
-        )?
-        .into()
+        );
     };
 
     console_log!("Focusing tab");
-    log_frontend_nothing_err!(JsFuture::from(client.focus()?).await, "sw::clients[0]::focus",)
+    let focused =
+        log_frontend_nothing_err!(JsFuture::from(client.focus()?).await, "sw::clients[0]::focus",)?;
+
+    // Only navigated for a deep link so the app isn't reloaded for nothing, and
+    // not if it's already showing it
+    if let Some(url) = url {
+        if client.url() == format!("{}{url}", sw.origin()) {
+            console_log!("Already at {url}");
+        } else {
+            console_log!("Navigating to {url}");
+            log_frontend_nothing_err!(
+                JsFuture::from(client.navigate(&url)?).await,
+                "sw::clients[0]::navigate",
+            )?;
+        }
+    }
+
+    Ok(focused)
 }
 
 #[wasm_bindgen]
@@ -0,0 +1,2 @@
+DROP TABLE exercise_group_member;
+DROP TABLE exercise_group;
@@ -0,0 +1,90 @@
+-- Everything goes back to being global. Exercises and exercise groups owned by a user have no
+-- equivalent so they're dropped along with anything that refers to them
+DELETE FROM session_exercise
+WHERE exercise_id IN (SELECT id FROM exercise WHERE owner_id IS NOT NULL);
+DELETE FROM user_exercise
+WHERE exercise_id IN (SELECT id FROM exercise WHERE owner_id IS NOT NULL);
+DELETE FROM exercise_group_member
+WHERE exercise_id IN (SELECT id FROM exercise WHERE owner_id IS NOT NULL)
+    OR group_id IN (SELECT id FROM exercise_group WHERE owner_id IS NOT NULL);
+DELETE FROM plan_exercise_group
+WHERE exercise_group_id IN (SELECT id FROM exercise_group WHERE owner_id IS NOT NULL);
+
+DROP INDEX exercise_owner_id_name;
+DROP INDEX exercise_group_owner_id;
+DROP INDEX session_user_id;
+DROP INDEX session_exercise_user_id;
+
+CREATE TABLE exercise_new (
+    id                  TEXT PRIMARY KEY,
+
+    name                TEXT NOT NULL UNIQUE,
+    description         TEXT,
+    base_recovery_days  REAL NOT NULL DEFAULT 3.5, 
+    
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_updated_date   TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+) STRICT;
+INSERT INTO exercise_new (id, name, description, base_recovery_days, creation_date, last_updated_date)
+SELECT id, name, description, base_recovery_days, creation_date, last_updated_date
+FROM exercise
+WHERE owner_id IS NULL;
+DROP TABLE exercise;
+ALTER TABLE exercise_new RENAME TO exercise;
+
+CREATE TABLE exercise_group_new (
+    id                  TEXT PRIMARY KEY,
+
+    name                TEXT NOT NULL,
+    description         TEXT,
+
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_updated_date   TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+) STRICT;
+INSERT INTO exercise_group_new (id, name, description, creation_date, last_updated_date)
+SELECT id, name, description, creation_date, last_updated_date
+FROM exercise_group
+WHERE owner_id IS NULL;
+DROP TABLE exercise_group;
+ALTER TABLE exercise_group_new RENAME TO exercise_group;
+
+CREATE TABLE session_new (
+    id                  TEXT PRIMARY KEY,
+    plan_instance_id    TEXT NOT NULL,
+
+    planned_date        TEXT NOT NULL,
+    performed_date      TEXT,
+    
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_updated_date   TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (plan_instance_id) REFERENCES plan_instance(id)
+) STRICT;
+INSERT INTO session_new (id, plan_instance_id, planned_date, performed_date, creation_date,
+    last_updated_date)
+SELECT id, plan_instance_id, planned_date, performed_date, creation_date, last_updated_date
+FROM session;
+DROP TABLE session;
+ALTER TABLE session_new RENAME TO session;
+
+CREATE TABLE session_exercise_new (
+    id                  TEXT PRIMARY KEY,
+    exercise_id         TEXT NOT NULL,
+    session_id          TEXT NOT NULL,
+
+    planned_sets        TEXT NOT NULL,
+    performed_sets      TEXT,
+    
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_updated_date   TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (exercise_id) REFERENCES exercise(id),
+    FOREIGN KEY (session_id) REFERENCES session(id) ON DELETE CASCADE
+) STRICT;
+INSERT INTO session_exercise_new (id, exercise_id, session_id, planned_sets, performed_sets,
+    creation_date, last_updated_date)
+SELECT id, exercise_id, session_id, planned_sets, performed_sets, creation_date,
+    last_updated_date
+FROM session_exercise;
+DROP TABLE session_exercise;
+ALTER TABLE session_exercise_new RENAME TO session_exercise;
@@ -0,0 +1,96 @@
+-- Gives all training data an owner so it can be scoped per user. Exercises and exercise
+-- groups with a NULL owner_id are global and shared by all users.
+--
+-- The tables are rebuilt following https://www.sqlite.org/lang_altertable.html#otheralter
+-- which relies on foreign_keys being OFF while the migrations run
+CREATE TABLE exercise_new (
+    id                  TEXT PRIMARY KEY,
+    owner_id            TEXT,
+
+    name                TEXT NOT NULL,
+    description         TEXT,
+    base_recovery_days  REAL NOT NULL DEFAULT 3.5, 
+    
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_updated_date   TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (owner_id) REFERENCES user(id) ON DELETE CASCADE
+) STRICT;
+INSERT INTO exercise_new (id, name, description, base_recovery_days, creation_date, last_updated_date)
+SELECT id, name, description, base_recovery_days, creation_date, last_updated_date
+FROM exercise;
+DROP TABLE exercise;
+ALTER TABLE exercise_new RENAME TO exercise;
+
+CREATE TABLE exercise_group_new (
+    id                  TEXT PRIMARY KEY,
+    owner_id            TEXT,
+
+    name                TEXT NOT NULL,
+    description         TEXT,
+
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_updated_date   TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (owner_id) REFERENCES user(id) ON DELETE CASCADE
+) STRICT;
+INSERT INTO exercise_group_new (id, name, description, creation_date, last_updated_date)
+SELECT id, name, description, creation_date, last_updated_date
+FROM exercise_group;
+DROP TABLE exercise_group;
+ALTER TABLE exercise_group_new RENAME TO exercise_group;
+
+CREATE TABLE session_new (
+    id                  TEXT PRIMARY KEY,
+    user_id             TEXT NOT NULL,
+    plan_instance_id    TEXT NOT NULL,
+
+    planned_date        TEXT NOT NULL,
+    performed_date      TEXT,
+    
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_updated_date   TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (user_id) REFERENCES user(id) ON DELETE CASCADE,
+    FOREIGN KEY (plan_instance_id) REFERENCES plan_instance(id)
+) STRICT;
+INSERT INTO session_new (id, user_id, plan_instance_id, planned_date, performed_date, creation_date,
+    last_updated_date)
+SELECT s.id, pi.user_id, s.plan_instance_id, s.planned_date, s.performed_date, s.creation_date,
+    s.last_updated_date
+FROM session s
+INNER JOIN plan_instance pi ON pi.id = s.plan_instance_id;
+DROP TABLE session;
+ALTER TABLE session_new RENAME TO session;
+
+CREATE TABLE session_exercise_new (
+    id                  TEXT PRIMARY KEY,
+    user_id             TEXT NOT NULL,
+    exercise_id         TEXT NOT NULL,
+    session_id          TEXT NOT NULL,
+
+    planned_sets        TEXT NOT NULL,
+    performed_sets      TEXT,
+    
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_updated_date   TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (user_id) REFERENCES user(id) ON DELETE CASCADE,
+    FOREIGN KEY (exercise_id) REFERENCES exercise(id),
+    FOREIGN KEY (session_id) REFERENCES session(id) ON DELETE CASCADE
+) STRICT;
+INSERT INTO session_exercise_new (id, user_id, exercise_id, session_id, planned_sets, performed_sets,
+    creation_date, last_updated_date)
+SELECT se.id, s.user_id, se.exercise_id, se.session_id, se.planned_sets, se.performed_sets,
+    se.creation_date, se.last_updated_date
+FROM session_exercise se
+INNER JOIN session s ON s.id = se.session_id;
+DROP TABLE session_exercise;
+ALTER TABLE session_exercise_new RENAME TO session_exercise;
+
+-- Names are unique per owner, global exercises share a single namespace
+CREATE UNIQUE INDEX exercise_owner_id_name ON exercise (ifnull(owner_id, ''), name);
+
+CREATE INDEX exercise_group_owner_id ON exercise_group (owner_id);
+CREATE INDEX session_user_id ON session (user_id);
+CREATE INDEX session_exercise_user_id ON session_exercise (user_id);
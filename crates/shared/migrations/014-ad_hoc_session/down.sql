@@ -0,0 +1,29 @@
+-- Ad-hoc sessions can't be represented without a plan instance so they're dropped along with
+-- their exercises. Sessions that were adopted into a plan are kept
+DELETE FROM session_exercise
+WHERE session_id IN (SELECT id FROM session WHERE plan_instance_id IS NULL);
+
+CREATE TABLE session_new (
+    id                  TEXT PRIMARY KEY,
+    user_id             TEXT NOT NULL,
+    plan_instance_id    TEXT NOT NULL,
+
+    planned_date        TEXT NOT NULL,
+    performed_date      TEXT,
+    
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_updated_date   TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (user_id) REFERENCES user(id) ON DELETE CASCADE,
+    FOREIGN KEY (plan_instance_id) REFERENCES plan_instance(id)
+) STRICT;
+INSERT INTO session_new (id, user_id, plan_instance_id, planned_date, performed_date, creation_date,
+    last_updated_date)
+SELECT id, user_id, plan_instance_id, planned_date, performed_date, creation_date,
+    last_updated_date
+FROM session
+WHERE plan_instance_id IS NOT NULL;
+DROP TABLE session;
+ALTER TABLE session_new RENAME TO session;
+
+CREATE INDEX session_user_id ON session (user_id);
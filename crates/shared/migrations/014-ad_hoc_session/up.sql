@@ -0,0 +1,25 @@
+-- Ad-hoc sessions are free form workouts that aren't part of a plan so they have no
+-- plan_instance_id. They can be adopted into a plan instance later on
+CREATE TABLE session_new (
+    id                  TEXT PRIMARY KEY,
+    user_id             TEXT NOT NULL,
+    plan_instance_id    TEXT,
+
+    planned_date        TEXT NOT NULL,
+    performed_date      TEXT,
+    
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_updated_date   TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (user_id) REFERENCES user(id) ON DELETE CASCADE,
+    FOREIGN KEY (plan_instance_id) REFERENCES plan_instance(id)
+) STRICT;
+INSERT INTO session_new (id, user_id, plan_instance_id, planned_date, performed_date, creation_date,
+    last_updated_date)
+SELECT id, user_id, plan_instance_id, planned_date, performed_date, creation_date,
+    last_updated_date
+FROM session;
+DROP TABLE session;
+ALTER TABLE session_new RENAME TO session;
+
+CREATE INDEX session_user_id ON session (user_id);
@@ -0,0 +1,2 @@
+ALTER TABLE session_exercise DROP COLUMN finished;
+ALTER TABLE session_exercise DROP COLUMN sort_order;
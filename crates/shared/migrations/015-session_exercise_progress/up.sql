@@ -0,0 +1,5 @@
+-- Tracks progress through a session so a workout can be resumed exactly where it was left.
+-- sort_order is the order the exercises are performed in (ties are broken by creation_date)
+-- and finished is set once all the sets are done or the exercise is skipped
+ALTER TABLE session_exercise ADD COLUMN sort_order INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE session_exercise ADD COLUMN finished INTEGER NOT NULL DEFAULT 0;
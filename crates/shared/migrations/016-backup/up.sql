@@ -0,0 +1,13 @@
+-- A copy of a user's client database so it can be restored if the browser storage is lost.
+-- Only the latest one is kept, data is the base64 encoded sqlite file
+CREATE TABLE backup (
+    id                  TEXT PRIMARY KEY,
+    user_id             TEXT NOT NULL UNIQUE,
+
+    schema_version      INTEGER NOT NULL,
+    data                TEXT NOT NULL,
+
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (user_id) REFERENCES user(id) ON DELETE CASCADE
+) STRICT;
@@ -0,0 +1,30 @@
+-- performed_sets was nullable but it's always written (an empty list before any sets are
+-- performed) and reading a NULL back fails so existing NULLs become empty lists
+CREATE TABLE session_exercise_new (
+    id                  TEXT PRIMARY KEY,
+    user_id             TEXT NOT NULL,
+    exercise_id         TEXT NOT NULL,
+    session_id          TEXT NOT NULL,
+
+    planned_sets        TEXT NOT NULL,
+    performed_sets      TEXT NOT NULL,
+
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_updated_date   TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    sort_order          INTEGER NOT NULL DEFAULT 0,
+    finished            INTEGER NOT NULL DEFAULT 0,
+
+    FOREIGN KEY (user_id) REFERENCES user(id) ON DELETE CASCADE,
+    FOREIGN KEY (exercise_id) REFERENCES exercise(id),
+    FOREIGN KEY (session_id) REFERENCES session(id) ON DELETE CASCADE
+) STRICT;
+INSERT INTO session_exercise_new (id, user_id, exercise_id, session_id, planned_sets, performed_sets,
+    creation_date, last_updated_date, sort_order, finished)
+SELECT id, user_id, exercise_id, session_id, planned_sets, ifnull(performed_sets, '[]'),
+    creation_date, last_updated_date, sort_order, finished
+FROM session_exercise;
+DROP TABLE session_exercise;
+ALTER TABLE session_exercise_new RENAME TO session_exercise;
+
+CREATE INDEX session_exercise_user_id ON session_exercise (user_id);
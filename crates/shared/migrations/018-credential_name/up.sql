@@ -0,0 +1,2 @@
+-- A name the user gives each of their passkeys so they can tell them apart when revoking one
+ALTER TABLE credential ADD COLUMN name TEXT;
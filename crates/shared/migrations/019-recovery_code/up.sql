@@ -0,0 +1,15 @@
+-- One time codes a user can log in with after losing every device holding one of their
+-- passkeys. Only a hash of each code is kept and used_date is set once it's been redeemed
+CREATE TABLE recovery_code (
+    id                  TEXT PRIMARY KEY,
+    user_id             TEXT NOT NULL,
+
+    code_hash           TEXT NOT NULL,
+
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    used_date           TEXT,
+
+    FOREIGN KEY (user_id) REFERENCES user(id) ON DELETE CASCADE
+) STRICT;
+
+CREATE INDEX recovery_code_user_id ON recovery_code (user_id);
@@ -0,0 +1,17 @@
+-- The sessions a user is logged in with so they can see where they're logged in and log
+-- other devices out. session_id is the tower_sessions id which is never sent to the client
+CREATE TABLE login_session (
+    id                  TEXT PRIMARY KEY,
+    user_id             TEXT NOT NULL,
+    session_id          TEXT NOT NULL UNIQUE,
+
+    user_agent          TEXT,
+    ip_address          TEXT,
+
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_seen_date      TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (user_id) REFERENCES user(id) ON DELETE CASCADE
+) STRICT;
+
+CREATE INDEX login_session_user_id ON login_session (user_id);
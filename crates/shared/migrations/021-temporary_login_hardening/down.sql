@@ -0,0 +1,7 @@
+DROP TABLE temporary_login_audit;
+
+ALTER TABLE temporary_login DROP COLUMN pin;
+
+DROP INDEX idx_temporary_login_user_id;
+CREATE INDEX idx_temporary_login_user_id
+ON temporary_login(user_id);
@@ -0,0 +1,35 @@
+-- Only one outstanding code per user. Anything extra from before this was enforced is dropped,
+-- keeping the one that expires last
+DELETE FROM temporary_login
+WHERE id NOT IN (
+    SELECT id FROM temporary_login AS t
+    WHERE t.user_id = temporary_login.user_id
+    ORDER BY t.expiry_date DESC
+    LIMIT 1
+);
+
+DROP INDEX idx_temporary_login_user_id;
+CREATE UNIQUE INDEX idx_temporary_login_user_id
+ON temporary_login(user_id);
+
+-- A short numeric code that can be typed in along with the username instead of following the url
+ALTER TABLE temporary_login ADD COLUMN pin TEXT;
+
+-- Who created, used or revoked a temporary login, when and from where. temporary_login_id isn't a
+-- foreign key as the entries outlive the code
+CREATE TABLE temporary_login_audit (
+    id                  TEXT PRIMARY KEY,
+    user_id             TEXT NOT NULL,
+    temporary_login_id  TEXT NOT NULL,
+
+    event               TEXT NOT NULL,
+    ip_address          TEXT,
+    user_agent          TEXT,
+
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (user_id) REFERENCES user(id) ON DELETE CASCADE
+) STRICT;
+
+CREATE INDEX idx_temporary_login_audit_user_id
+ON temporary_login_audit(user_id);
@@ -0,0 +1,3 @@
+-- Settings that follow the user between devices as a json document. Missing fields take their
+-- defaults so an empty object is every default
+ALTER TABLE user ADD COLUMN preferences TEXT NOT NULL DEFAULT '{}';
@@ -0,0 +1,86 @@
+-- Deleting a user deletes everything they own through the foreign keys. Plans, their groups and
+-- plan instances didn't cascade so they're rebuilt the same way as in 013. Deleting a plan doesn't
+-- delete the instances of it, so a plan other users follow can't be deleted until it's handed over
+CREATE TABLE plan_new (
+    id                  TEXT PRIMARY KEY,
+    owner_id            TEXT NOT NULL,
+    
+    name                TEXT NOT NULL UNIQUE,
+    description         TEXT,
+    duration_weeks      INTEGER NOT NULL,
+    
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_updated_date   TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (owner_id) REFERENCES user(id) ON DELETE CASCADE
+) STRICT;
+INSERT INTO plan_new (id, owner_id, name, description, duration_weeks, creation_date,
+    last_updated_date)
+SELECT id, owner_id, name, description, duration_weeks, creation_date, last_updated_date
+FROM plan;
+DROP TABLE plan;
+ALTER TABLE plan_new RENAME TO plan;
+
+CREATE TABLE plan_instance_new (
+    id                  TEXT PRIMARY KEY,
+    plan_id             TEXT NOT NULL,
+    user_id             TEXT NOT NULL,
+
+    start_date          TEXT NOT NULL,
+
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_updated_date   TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (plan_id) REFERENCES plan(id),
+    FOREIGN KEY (user_id) REFERENCES user(id) ON DELETE CASCADE
+) STRICT;
+INSERT INTO plan_instance_new (id, plan_id, user_id, start_date, creation_date, last_updated_date)
+SELECT id, plan_id, user_id, start_date, creation_date, last_updated_date
+FROM plan_instance;
+DROP TABLE plan_instance;
+ALTER TABLE plan_instance_new RENAME TO plan_instance;
+
+CREATE TABLE plan_exercise_group_new (
+    id                  TEXT PRIMARY KEY,
+    plan_id             TEXT NOT NULL,
+    exercise_group_id   TEXT NOT NULL,
+    
+    notes               TEXT,
+    config              TEXT,
+    
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_updated_date   TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (plan_id) REFERENCES plan(id) ON DELETE CASCADE,
+    FOREIGN KEY (exercise_group_id) REFERENCES exercise_group(id) ON DELETE CASCADE
+) STRICT;
+INSERT INTO plan_exercise_group_new (id, plan_id, exercise_group_id, notes, config, creation_date,
+    last_updated_date)
+SELECT id, plan_id, exercise_group_id, notes, config, creation_date, last_updated_date
+FROM plan_exercise_group;
+DROP TABLE plan_exercise_group;
+ALTER TABLE plan_exercise_group_new RENAME TO plan_exercise_group;
+
+CREATE TABLE session_new (
+    id                  TEXT PRIMARY KEY,
+    user_id             TEXT NOT NULL,
+    plan_instance_id    TEXT,
+
+    planned_date        TEXT NOT NULL,
+    performed_date      TEXT,
+    
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_updated_date   TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (user_id) REFERENCES user(id) ON DELETE CASCADE,
+    FOREIGN KEY (plan_instance_id) REFERENCES plan_instance(id) ON DELETE SET NULL
+) STRICT;
+INSERT INTO session_new (id, user_id, plan_instance_id, planned_date, performed_date, creation_date,
+    last_updated_date)
+SELECT id, user_id, plan_instance_id, planned_date, performed_date, creation_date,
+    last_updated_date
+FROM session;
+DROP TABLE session;
+ALTER TABLE session_new RENAME TO session;
+
+CREATE INDEX session_user_id ON session (user_id);
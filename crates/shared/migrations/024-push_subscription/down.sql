@@ -0,0 +1,10 @@
+-- Only one subscription per user fits back in the user column so the newest is kept
+UPDATE user SET push_notification_subscription = (
+    SELECT json_object('endpoint', endpoint, 'key', key, 'auth', auth)
+    FROM push_subscription
+    WHERE push_subscription.user_id = user.id
+    ORDER BY creation_date DESC
+    LIMIT 1
+);
+
+DROP TABLE push_subscription;
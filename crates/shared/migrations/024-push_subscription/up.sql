@@ -0,0 +1,36 @@
+-- Every device a user gets push notifications on. The push service gives each subscription its
+-- own endpoint so that's what identifies it. failure_count is how many sends in a row have
+-- failed and is reset by a successful one
+CREATE TABLE push_subscription (
+    id                  TEXT PRIMARY KEY,
+    user_id             TEXT NOT NULL,
+
+    endpoint            TEXT NOT NULL UNIQUE,
+    key                 TEXT NOT NULL,
+    auth                TEXT NOT NULL,
+    device_label        TEXT,
+
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_success_date   TEXT,
+    failure_count       INTEGER NOT NULL DEFAULT 0,
+
+    FOREIGN KEY (user_id) REFERENCES user(id) ON DELETE CASCADE
+) STRICT;
+
+CREATE INDEX push_subscription_user_id ON push_subscription (user_id);
+
+-- Users had at most one subscription so their id is unique enough to be its id too. The user
+-- column is dropped in 025 which the client runs as well. A browser shared by several accounts
+-- has the same endpoint in each, the most recently updated user keeps it and the rest are
+-- ignored
+INSERT OR IGNORE INTO push_subscription (id, user_id, endpoint, key, auth, creation_date)
+SELECT
+    id,
+    id,
+    json_extract(push_notification_subscription, '$.endpoint'),
+    json_extract(push_notification_subscription, '$.key'),
+    json_extract(push_notification_subscription, '$.auth'),
+    last_updated_date
+FROM user
+WHERE push_notification_subscription IS NOT NULL
+ORDER BY last_updated_date DESC, id;
@@ -0,0 +1,2 @@
+-- Filled back in by 024's down on the server
+ALTER TABLE user ADD COLUMN push_notification_subscription TEXT;
@@ -0,0 +1,2 @@
+-- Subscriptions live in push_subscription (server only) since 024
+ALTER TABLE user DROP COLUMN push_notification_subscription;
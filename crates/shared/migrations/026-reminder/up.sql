@@ -0,0 +1,22 @@
+-- Push notifications to send about a user's planned sessions, one of each kind per session so
+-- rescheduling or restarting the server never sends the same one twice. planned_date is the
+-- session's when the reminder was scheduled, a session moved since gets its reminders again
+CREATE TABLE reminder (
+    id                  TEXT PRIMARY KEY,
+    user_id             TEXT NOT NULL,
+    session_id          TEXT NOT NULL,
+
+    kind                TEXT NOT NULL,
+    state               TEXT NOT NULL,
+    planned_date        TEXT NOT NULL,
+    due_date            TEXT NOT NULL,
+
+    creation_date       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_updated_date   TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    UNIQUE (session_id, kind),
+    FOREIGN KEY (user_id) REFERENCES user(id) ON DELETE CASCADE,
+    FOREIGN KEY (session_id) REFERENCES session(id) ON DELETE CASCADE
+) STRICT;
+
+CREATE INDEX reminder_user_id ON reminder (user_id);
@@ -0,0 +1,22 @@
+-- Push notifications waiting to go out, one per subscription they're going to. Rows are removed
+-- once they've been delivered or given up on. attempts is how many sends have failed so far and
+-- next_attempt_date is when the next one can be made. Anything still here after expiry_date
+-- isn't worth sending
+CREATE TABLE push_outbox (
+    id                      TEXT PRIMARY KEY,
+    push_subscription_id    TEXT NOT NULL,
+
+    kind                    TEXT NOT NULL,
+    notification            TEXT NOT NULL,
+
+    attempts                INTEGER NOT NULL DEFAULT 0,
+    next_attempt_date       TEXT NOT NULL,
+    expiry_date             TEXT NOT NULL,
+    last_error              TEXT,
+
+    creation_date           TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (push_subscription_id) REFERENCES push_subscription(id) ON DELETE CASCADE
+) STRICT;
+
+CREATE INDEX push_outbox_push_subscription_id ON push_outbox (push_subscription_id);
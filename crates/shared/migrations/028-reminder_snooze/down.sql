@@ -0,0 +1 @@
+ALTER TABLE reminder DROP COLUMN snoozed_until;
@@ -0,0 +1,2 @@
+-- When the user asked from the notification to be reminded again, sent instead of due_date
+ALTER TABLE reminder ADD COLUMN snoozed_until TEXT;
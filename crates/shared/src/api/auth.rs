@@ -11,7 +11,19 @@ pub enum Auth {
     RegisterNewKeyStart,
     RegisterNewKeyFinish,
     CreateTemporaryLogin,
+    RevokeTemporaryLogin,
     TemporaryLogin,
+    TemporaryLoginPin,
+    Recover,
+    RecoveryCodes,
+    Logout,
+    PairingStart,
+    PairingQrCode,
+    PairingDecision,
+    PairingFinish,
+    AccountExport,
+    DeleteAccountStart,
+    DeleteAccountFinish,
 }
 
 impl Auth {
@@ -25,7 +37,19 @@ impl Auth {
             RegisterNewKeyStart => concatcp!(API_BASE_PATH, "auth/register_key/start"),
             RegisterNewKeyFinish => concatcp!(API_BASE_PATH, "auth/register_key/finish"),
             CreateTemporaryLogin => concatcp!(API_BASE_PATH, "auth/temporary_login/create"),
+            RevokeTemporaryLogin => concatcp!(API_BASE_PATH, "auth/temporary_login/revoke"),
             TemporaryLogin => concatcp!(API_BASE_PATH, "auth/login/code/:id"),
+            TemporaryLoginPin => concatcp!(API_BASE_PATH, "auth/login/pin"),
+            Recover => concatcp!(API_BASE_PATH, "auth/recover"),
+            RecoveryCodes => concatcp!(API_BASE_PATH, "auth/recovery_codes"),
+            Logout => concatcp!(API_BASE_PATH, "auth/logout"),
+            PairingStart => concatcp!(API_BASE_PATH, "auth/pairing/start"),
+            PairingQrCode => concatcp!(API_BASE_PATH, "auth/pairing/qr_code"),
+            PairingDecision => concatcp!(API_BASE_PATH, "auth/pairing/decision"),
+            PairingFinish => concatcp!(API_BASE_PATH, "auth/pairing/finish"),
+            AccountExport => concatcp!(API_BASE_PATH, "auth/account/export"),
+            DeleteAccountStart => concatcp!(API_BASE_PATH, "auth/account/delete/start"),
+            DeleteAccountFinish => concatcp!(API_BASE_PATH, "auth/account/delete/finish"),
         }
     }
 }
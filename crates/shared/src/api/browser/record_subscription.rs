@@ -9,6 +9,7 @@ use crate::api::{
 
 pub async fn record_subscription(
     push_manager: &PushManager,
+    device_label: Option<String>,
 ) -> Result<(), FrontendError<ServerError<Nothing>>> {
     // Get the servers public key (it's not base64 encoded so no conversion is
     // needed)
@@ -25,7 +26,7 @@ pub async fn record_subscription(
         JsFuture::from(push_manager.subscribe_with_options(&options)?).await?.into();
 
     // Pass the sub to the backend
-    update_subscription(&subscription).await?;
+    update_subscription(&subscription, device_label).await?;
 
     Ok(())
 }
@@ -6,3 +6,6 @@ pub use update_subscription::*;
 
 mod remove_subscription;
 pub use remove_subscription::*;
+
+mod reminder_action;
+pub use reminder_action::*;
@@ -0,0 +1,38 @@
+use gloo::net::http::Request;
+use http::header::ACCEPT;
+use mime::APPLICATION_JSON;
+
+use crate::{
+    api::{
+        self,
+        error::{FrontendError, ServerError},
+        payloads::{NotificationAction, ReminderActionRequest},
+        response_errors::ModelError,
+    },
+    types::Uuid,
+    utils::csrf::Csrf,
+};
+
+/// Acts on a reminder for an action picked on its notification. It's sent from
+/// the service worker, which can't use `json_request` as there's no reactive
+/// owner to keep the CSRF token in, so a new token is fetched each time
+pub async fn reminder_action(
+    id: Uuid,
+    action: NotificationAction,
+) -> Result<(), FrontendError<ServerError<ModelError>>> {
+    let path = api::Object::ReminderId.path().replace(":id", &id.to_string());
+    let csrf = Csrf::fetch().await?;
+    let response = csrf
+        .add_to(Request::post(&path))
+        .header(ACCEPT.as_str(), APPLICATION_JSON.essence_str())
+        .json(&ReminderActionRequest { action })?
+        .send()
+        .await?;
+
+    if !response.ok() {
+        let inner = response.json::<ServerError<ModelError>>().await?;
+        return Err(FrontendError::Inner { inner });
+    }
+
+    Ok(())
+}
@@ -3,13 +3,21 @@ use gloo::net::http::Method;
 use crate::{
     api::{
         self,
-        error::{FrontendError, ServerError},
+        error::{FrontendError, NoValidation, ServerError},
+        payloads::RemoveSubscriptionRequest,
         response_errors::FetchError,
     },
     utils::fetch::json_request,
 };
 
-pub async fn remove_subscription() -> Result<(), FrontendError<ServerError<FetchError>>> {
-    json_request::<_, (), _>(Method::DELETE, api::Object::PushSubscription.path(), None::<&()>)
-        .await
+/// Removes the subscription with this endpoint, the user's others are kept
+pub async fn remove_subscription(
+    endpoint: String,
+) -> Result<(), FrontendError<ServerError<FetchError>>> {
+    json_request::<_, (), _>(
+        Method::DELETE,
+        api::Object::PushSubscription.path(),
+        Some(&NoValidation(RemoveSubscriptionRequest { endpoint })),
+    )
+    .await
 }
@@ -14,6 +14,7 @@ use crate::{
 
 pub async fn update_subscription(
     subscription: &PushSubscription,
+    device_label: Option<String>,
 ) -> Result<UpdateSubscriptionResponse, FrontendError<ServerError<FetchError>>> {
     let endpoint = subscription.endpoint();
     let key = get_subscription_key(&subscription, PushEncryptionKeyName::P256dh)?;
@@ -24,7 +25,7 @@ pub async fn update_subscription(
     json_request::<_, UpdateSubscriptionResponse, _>(
         Method::POST,
         api::Object::PushSubscription.path(),
-        Some(&NoValidation(UpdateSubscriptionRequest { subscription })),
+        Some(&NoValidation(UpdateSubscriptionRequest { subscription, device_label })),
     )
     .await
 }
@@ -16,6 +16,26 @@ pub enum Object {
     RtcOffer,
     RtcSignalling,
     RtcStun,
+    Exercise,
+    ExerciseId,
+    ExerciseGroup,
+    ExerciseGroupId,
+    Plan,
+    PlanId,
+    PlanInstance,
+    PlanInstanceId,
+    Session,
+    SessionId,
+    SessionExercise,
+    SessionExerciseId,
+    Backup,
+    Credential,
+    CredentialId,
+    LoginSession,
+    LoginSessionId,
+    PairingId,
+    Preferences,
+    ReminderId,
 }
 
 impl Object {
@@ -34,6 +54,26 @@ impl Object {
             RtcOffer => concatcp!(API_BASE_PATH, "rtc/offer"),
             RtcSignalling => concatcp!(API_BASE_PATH, "rtc/signalling"),
             RtcStun => concatcp!(API_BASE_PATH, "rtc/stun"),
+            Exercise => concatcp!(API_BASE_PATH, "exercise"),
+            ExerciseId => concatcp!(API_BASE_PATH, "exercise/:id"),
+            ExerciseGroup => concatcp!(API_BASE_PATH, "exercise_group"),
+            ExerciseGroupId => concatcp!(API_BASE_PATH, "exercise_group/:id"),
+            Plan => concatcp!(API_BASE_PATH, "plan"),
+            PlanId => concatcp!(API_BASE_PATH, "plan/:id"),
+            PlanInstance => concatcp!(API_BASE_PATH, "plan_instance"),
+            PlanInstanceId => concatcp!(API_BASE_PATH, "plan_instance/:id"),
+            Session => concatcp!(API_BASE_PATH, "session"),
+            SessionId => concatcp!(API_BASE_PATH, "session/:id"),
+            SessionExercise => concatcp!(API_BASE_PATH, "session_exercise"),
+            SessionExerciseId => concatcp!(API_BASE_PATH, "session_exercise/:id"),
+            Backup => concatcp!(API_BASE_PATH, "backup"),
+            Credential => concatcp!(API_BASE_PATH, "credential"),
+            CredentialId => concatcp!(API_BASE_PATH, "credential/:id"),
+            LoginSession => concatcp!(API_BASE_PATH, "login_session"),
+            LoginSessionId => concatcp!(API_BASE_PATH, "login_session/:id"),
+            PairingId => concatcp!(API_BASE_PATH, "pairing/:id"),
+            Preferences => concatcp!(API_BASE_PATH, "preferences"),
+            ReminderId => concatcp!(API_BASE_PATH, "reminder/:id"),
         }
     }
 }
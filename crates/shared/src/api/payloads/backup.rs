@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{api::error::ValidationError, model::ValidateModel};
+
+/// Largest backup the server will hold. This is the length of the base64 data
+/// so the database itself can be roughly 3/4 of this
+pub const MAX_BACKUP_SIZE: usize = 16 * 1024 * 1024;
+
+/// A copy of the client database. The server only holds on to it, the
+/// schema_version is there so the client can check it before importing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseBackup {
+    pub schema_version: u32,
+    pub creation_date: DateTime<Utc>,
+    /// The sqlite file, base64 encoded
+    pub data: String,
+}
+
+/// A [`DatabaseBackup`] without the data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseBackupInfo {
+    pub schema_version: u32,
+    pub creation_date: DateTime<Utc>,
+}
+
+impl ValidateModel for DatabaseBackup {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut error_messages = Vec::new();
+
+        if self.schema_version == 0 {
+            error_messages.push("Backup has no schema version".to_string());
+        }
+        if self.data.is_empty() {
+            error_messages.push("Backup is empty".to_string());
+        }
+        if self.data.len() > MAX_BACKUP_SIZE {
+            error_messages.push(format!(
+                "Backup is too large ({} bytes, max {MAX_BACKUP_SIZE})",
+                self.data.len()
+            ));
+        }
+
+        if error_messages.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError { error_messages })
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{api::error::ValidationError, model::ValidateModel};
+
+pub const MAX_CREDENTIAL_NAME_LENGTH: usize = 64;
+
+/// One of the user's passkeys without the key itself
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CredentialInfo {
+    /// The base64 credential id used in
+    /// [`Object::CredentialId`](crate::api::Object::CredentialId) paths
+    pub id: String,
+    pub name: Option<String>,
+    pub creation_date: DateTime<Utc>,
+    pub last_used_date: Option<DateTime<Utc>>,
+    /// The passkey can be synced between devices
+    pub backup_eligible: bool,
+    /// The passkey is currently synced
+    pub backup_state: bool,
+}
+
+/// An empty name removes the existing one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameCredentialRequest {
+    pub name: String,
+}
+
+impl ValidateModel for RenameCredentialRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        if self.name.trim().chars().count() > MAX_CREDENTIAL_NAME_LENGTH {
+            Err(ValidationError {
+                error_messages: vec![format!(
+                    "Name can't be longer than {MAX_CREDENTIAL_NAME_LENGTH} characters"
+                )],
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
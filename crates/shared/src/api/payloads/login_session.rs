@@ -0,0 +1,20 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::types::Uuid;
+
+/// One of the sessions the user is logged in with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoginSessionInfo {
+    /// Used in [`Object::LoginSessionId`](crate::api::Object::LoginSessionId)
+    /// paths
+    pub id: Uuid,
+    /// The browser's user agent, which is the best there is to tell devices
+    /// apart
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub creation_date: DateTime<Utc>,
+    pub last_seen_date: DateTime<Utc>,
+    /// It's the session making the request
+    pub current: bool,
+}
@@ -1,5 +1,26 @@
+mod backup;
+pub use backup::*;
+
+mod credential;
+pub use credential::*;
+
+mod login_session;
+pub use login_session::*;
+
+mod pairing;
+pub use pairing::*;
+
+mod profile;
+pub use profile::*;
+
 mod push_notifications;
 pub use push_notifications::*;
 
+mod recovery;
+pub use recovery::*;
+
 mod rtc;
 pub use rtc::*;
+
+mod temporary_login;
+pub use temporary_login::*;
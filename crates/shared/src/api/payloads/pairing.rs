@@ -0,0 +1,56 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{api::error::ValidationError, model::ValidateModel};
+
+/// The length of a pairing code
+pub const PAIRING_CODE_LENGTH: usize = 8;
+
+/// Leaves out characters that are easily confused like 0/O and 1/I
+pub const PAIRING_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// The code with whitespace and separators removed and in upper case, the form
+/// it's looked up in
+pub fn normalize_pairing_code(code: &str) -> String {
+    code.chars().filter(char::is_ascii_alphanumeric).map(|c| c.to_ascii_uppercase()).collect()
+}
+
+/// Shown by a device that wants to log in so a device that's already logged in
+/// can approve it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PairingCode {
+    pub code: String,
+    /// Opens the approval on the logged in device, it's what the QR code holds
+    pub url: String,
+    pub expiry_date: DateTime<Utc>,
+}
+
+/// What the logged in device is shown about the device asking to be logged in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PairingInfo {
+    pub code: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub creation_date: DateTime<Utc>,
+    pub expiry_date: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PairingDecision {
+    pub code: String,
+    pub approve: bool,
+}
+
+impl ValidateModel for PairingDecision {
+    fn validate(&self) -> Result<(), ValidationError> {
+        if normalize_pairing_code(&self.code).len() == PAIRING_CODE_LENGTH {
+            Ok(())
+        } else {
+            Err(ValidationError {
+                error_messages: vec![format!(
+                    "Pairing codes are {PAIRING_CODE_LENGTH} letters and numbers"
+                )],
+            })
+        }
+    }
+}
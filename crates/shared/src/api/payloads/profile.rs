@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{api::error::ValidationError, model::ValidateModel};
+
+pub const MAX_DISPLAY_NAME_LENGTH: usize = 64;
+/// The longest address that can be delivered to
+pub const MAX_EMAIL_LENGTH: usize = 254;
+
+/// The parts of the user they can change themselves. Blank values remove the
+/// existing ones
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct UpdateProfileRequest {
+    pub email: Option<String>,
+    pub display_name: Option<String>,
+}
+
+impl UpdateProfileRequest {
+    fn trimmed(value: &Option<String>) -> Option<String> {
+        value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string)
+    }
+
+    /// The email trimmed with blank as None
+    pub fn email(&self) -> Option<String> {
+        Self::trimmed(&self.email)
+    }
+
+    /// The display name trimmed with blank as None
+    pub fn display_name(&self) -> Option<String> {
+        Self::trimmed(&self.display_name)
+    }
+}
+
+impl ValidateModel for UpdateProfileRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut error_messages = Vec::new();
+
+        if let Some(email) = self.email() {
+            // Only catches obvious mistakes, there's no confirmation email to prove it's real
+            let valid = email.len() <= MAX_EMAIL_LENGTH
+                && !email.contains(char::is_whitespace)
+                && email
+                    .split_once('@')
+                    .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.'));
+            if !valid {
+                error_messages.push(format!("{email:?} isn't an email address"));
+            }
+        }
+        if self.display_name().is_some_and(|n| n.chars().count() > MAX_DISPLAY_NAME_LENGTH) {
+            error_messages.push(format!(
+                "Display name can't be longer than {MAX_DISPLAY_NAME_LENGTH} characters"
+            ));
+        }
+
+        if error_messages.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError { error_messages })
+        }
+    }
+}
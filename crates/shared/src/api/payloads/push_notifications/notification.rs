@@ -1,10 +1,109 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::types::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Notification {
     pub title: String,
     pub body: Option<String>,
     pub icon: Option<String>,
     pub sent: DateTime<Utc>,
+    /// Where in the app clicking it goes. The app's root without one
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Browsers only show the first few so the most useful go first
+    #[serde(default)]
+    pub actions: Vec<NotificationAction>,
+    /// What the actions act on
+    #[serde(default)]
+    pub data: Option<NotificationData>,
+}
+
+/// A button on a notification, handled by the service worker when it's clicked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NotificationAction {
+    /// Opens the app at the notification's url
+    StartWorkout,
+    /// Sends the reminder again later without opening the app
+    Snooze,
+    /// Drops the rest of the session's reminders without opening the app
+    Skip,
+}
+
+impl NotificationAction {
+    /// What the browser is told the action is called. It's what the click
+    /// event says was chosen
+    pub const fn id(&self) -> &'static str {
+        match self {
+            Self::StartWorkout => "start_workout",
+            Self::Snooze => "snooze",
+            Self::Skip => "skip",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        [Self::StartWorkout, Self::Snooze, Self::Skip].into_iter().find(|a| a.id() == id)
+    }
+
+    pub const fn title(&self) -> &'static str {
+        match self {
+            Self::StartWorkout => "Start workout",
+            Self::Snooze => "Snooze 1h",
+            Self::Skip => "Skip today",
+        }
+    }
+}
+
+/// What a notification is about
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum NotificationData {
+    Reminder { reminder_id: Uuid, session_id: Uuid },
+}
+
+/// Carries out an action picked on a reminder's notification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReminderActionRequest {
+    pub action: NotificationAction,
+}
+
+/// What a notification is about. The server decides how urgent it is and how
+/// long it's worth delivering for from this
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NotificationKind {
+    /// A new version of the app has started
+    Update,
+    /// A planned session is coming up
+    UpcomingReminder,
+    /// A planned session wasn't done
+    MissedReminder,
+}
+
+#[cfg(feature = "backend")]
+crate::json_sql!(Notification, NotificationKind);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notifications_queued_before_actions_still_deserialize() {
+        let notification: Notification = serde_json::from_str(
+            r#"{ "title": "Hi", "body": null, "icon": null, "sent": "2026-03-02T18:00:00Z" }"#,
+        )
+        .unwrap();
+        assert_eq!(notification.url, None);
+        assert_eq!(notification.actions, vec![]);
+        assert_eq!(notification.data, None);
+    }
+
+    #[test]
+    fn actions_round_trip_through_their_ids() {
+        for action in
+            [NotificationAction::StartWorkout, NotificationAction::Snooze, NotificationAction::Skip]
+        {
+            assert_eq!(NotificationAction::from_id(action.id()), Some(action));
+        }
+        assert_eq!(NotificationAction::from_id("open"), None);
+    }
 }
@@ -1,11 +1,36 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::model::PushNotificationSubscription;
+use crate::{model::PushNotificationSubscription, types::Uuid};
 
+/// Longer labels are cut short rather than turned away
+pub const MAX_DEVICE_LABEL_LENGTH: usize = 64;
+
+/// Adds the subscription or updates the one with the same endpoint
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateSubscriptionRequest {
     pub subscription: PushNotificationSubscription,
+    /// What the device is called. The user agent is used without one
+    #[serde(default)]
+    pub device_label: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateSubscriptionResponse {}
+
+/// Only the user's subscription with this endpoint is removed so their other
+/// devices keep getting notifications
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveSubscriptionRequest {
+    pub endpoint: String,
+}
+
+/// One of the devices the user gets push notifications on without its keys
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PushSubscriptionInfo {
+    pub id: Uuid,
+    pub device_label: Option<String>,
+    pub creation_date: DateTime<Utc>,
+    pub last_success_date: Option<DateTime<Utc>>,
+    pub failure_count: u32,
+}
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::error::ValidationError,
+    model::{constants::USERNAME_MIN_LENGTH, ValidateModel},
+};
+
+/// The length of a recovery code ignoring the separators
+pub const RECOVERY_CODE_LENGTH: usize = 16;
+
+/// The code with separators and whitespace removed and in upper case, the form
+/// it's hashed in
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars().filter(char::is_ascii_alphanumeric).map(|c| c.to_ascii_uppercase()).collect()
+}
+
+/// Newly generated recovery codes. Only their hashes are stored so this is the
+/// only time the user sees them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecoveryCodes {
+    pub codes: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecoverRequest {
+    pub username: String,
+    pub code: String,
+}
+
+impl ValidateModel for RecoverRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut error_messages = Vec::new();
+
+        if self.username.len() < USERNAME_MIN_LENGTH {
+            error_messages.push(format!(
+                "Username needs to be at least {USERNAME_MIN_LENGTH} characters long"
+            ));
+        }
+        if normalize_recovery_code(&self.code).len() != RECOVERY_CODE_LENGTH {
+            error_messages
+                .push(format!("Recovery codes are {RECOVERY_CODE_LENGTH} letters and numbers"));
+        }
+
+        if error_messages.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError { error_messages })
+        }
+    }
+}
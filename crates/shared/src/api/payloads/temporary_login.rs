@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::error::ValidationError,
+    model::{constants::USERNAME_MIN_LENGTH, ValidateModel},
+};
+
+/// The number of digits in a temporary login pin
+pub const TEMPORARY_LOGIN_PIN_LENGTH: usize = 6;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CreateTemporaryLoginRequest {
+    /// Also create a pin that can be typed in on the other device
+    pub pin: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemporaryLoginPinRequest {
+    pub username: String,
+    pub pin: String,
+}
+
+impl ValidateModel for TemporaryLoginPinRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut error_messages = Vec::new();
+
+        if self.username.len() < USERNAME_MIN_LENGTH {
+            error_messages.push(format!(
+                "Username needs to be at least {USERNAME_MIN_LENGTH} characters long"
+            ));
+        }
+        let pin = self.pin.trim();
+        if pin.len() != TEMPORARY_LOGIN_PIN_LENGTH || !pin.chars().all(|c| c.is_ascii_digit()) {
+            error_messages.push(format!("Pins are {TEMPORARY_LOGIN_PIN_LENGTH} digits"));
+        }
+
+        if error_messages.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError { error_messages })
+        }
+    }
+}
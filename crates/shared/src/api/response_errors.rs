@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
-use super::error::Nothing;
+use super::error::{Nothing, ValidationError};
+use crate::types::Uuid;
 
 #[macro_export]
 macro_rules! response_error {
@@ -34,6 +35,20 @@ macro_rules! response_error {
     };
 }
 
+/// Errors with an `Invalid { error_messages }` variant that failed validation
+/// is reported as
+macro_rules! invalid_from_validation_error {
+    ($($name:ident),*) => {
+        $(
+            impl From<ValidationError> for $name {
+                fn from(value: ValidationError) -> Self {
+                    Self::Invalid { error_messages: value.error_messages }
+                }
+            }
+        )*
+    };
+}
+
 response_error!(RegisterError {
     #[code(http::StatusCode::UNAUTHORIZED)]
     UsernameUnavailable,
@@ -57,4 +72,80 @@ pub type FetchError = Nothing;
 response_error!(TemporaryLoginError {
     #[code(http::StatusCode::BAD_REQUEST)]
     AlreadyExists,
+    #[code(http::StatusCode::UNAUTHORIZED)]
+    InvalidPin,
+    #[code(http::StatusCode::TOO_MANY_REQUESTS)]
+    TooManyAttempts,
+    #[code(http::StatusCode::BAD_REQUEST)]
+    Invalid { error_messages: Vec<String> },
+});
+
+response_error!(ModelError {
+    #[code(http::StatusCode::NOT_FOUND)]
+    NotFound { id: Uuid },
+    #[code(http::StatusCode::FORBIDDEN)]
+    NotOwner { id: Uuid },
+    #[code(http::StatusCode::BAD_REQUEST)]
+    IdMismatch { path_id: Uuid, body_id: Uuid },
+    #[code(http::StatusCode::BAD_REQUEST)]
+    Invalid { error_messages: Vec<String> },
+    #[code(http::StatusCode::CONFLICT)]
+    InUse { id: Uuid },
+});
+
+response_error!(BackupError {
+    #[code(http::StatusCode::NOT_FOUND)]
+    NoBackup,
+    #[code(http::StatusCode::BAD_REQUEST)]
+    Invalid { error_messages: Vec<String> },
+});
+
+response_error!(CredentialError {
+    #[code(http::StatusCode::NOT_FOUND)]
+    NotFound,
+    #[code(http::StatusCode::BAD_REQUEST)]
+    LastCredential,
+    #[code(http::StatusCode::BAD_REQUEST)]
+    Invalid { error_messages: Vec<String> },
+});
+
+response_error!(RecoveryError {
+    #[code(http::StatusCode::UNAUTHORIZED)]
+    InvalidCode,
+    #[code(http::StatusCode::TOO_MANY_REQUESTS)]
+    TooManyAttempts,
+    #[code(http::StatusCode::BAD_REQUEST)]
+    Invalid { error_messages: Vec<String> },
+});
+
+response_error!(PairingError {
+    #[code(http::StatusCode::NOT_FOUND)]
+    NotFound,
+    #[code(http::StatusCode::FORBIDDEN)]
+    NotApproved,
+    #[code(http::StatusCode::BAD_REQUEST)]
+    Invalid { error_messages: Vec<String> },
+});
+
+invalid_from_validation_error!(
+    TemporaryLoginError,
+    ModelError,
+    BackupError,
+    CredentialError,
+    RecoveryError,
+    PairingError
+);
+
+response_error!(LoginSessionError {
+    #[code(http::StatusCode::NOT_FOUND)]
+    NotFound,
+});
+
+// Deleting the account is confirmed with a passkey so a user without one has to
+// add one first
+response_error!(AccountError {
+    #[code(http::StatusCode::BAD_REQUEST)]
+    NoCredentials,
+    #[code(http::StatusCode::UNAUTHORIZED)]
+    NotConfirmed,
 });
@@ -1,5 +1,3 @@
-#![feature(macro_metavar_expr)]
-
 use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
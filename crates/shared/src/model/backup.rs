@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+
+use crate::{api::error::ServerError, feature_model_imports, types::Uuid};
+
+feature_model_imports!();
+
+use std::error::Error;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Model)]
+#[cfg_attr(feature = "sea-query-enum", enum_def)]
+#[model(table = "backup", check = "migrations/016-backup/up.sql")]
+pub struct Backup {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub schema_version: u32,
+    pub data: String,
+    pub creation_date: DateTime<Utc>,
+}
+
+impl Backup {
+    pub fn fetch_for_user<T: Error>(
+        conn: &Connection,
+        user_id: &Uuid,
+    ) -> Result<Option<Backup>, ServerError<T>> {
+        Ok(Self::fetch_by_column_maybe(conn, user_id, BackupIden::UserId)?)
+    }
+
+    /// Stores the backup, replacing the one the user already had
+    pub fn replace<T: Error>(
+        conn: &mut Connection,
+        backup: Backup,
+    ) -> Result<Backup, ServerError<T>> {
+        let tx = conn.transaction()?;
+        let backup = {
+            let (sql, values) = Query::delete()
+                .from_table(BackupIden::Table)
+                .and_where(Expr::col(BackupIden::UserId).eq(&backup.user_id))
+                .build_rusqlite(SqliteQueryBuilder);
+            tx.execute(&sql, &*values.as_params())?;
+
+            backup.insert(&tx)?;
+            Backup::fetch_by_id(&tx, backup.id)?
+        };
+        tx.commit()?;
+
+        Ok(backup)
+    }
+}
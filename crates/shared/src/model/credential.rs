@@ -1,6 +1,10 @@
 use chrono::{DateTime, Utc};
 
-use crate::{api::error::ServerError, feature_model_derives, feature_model_imports, types::Uuid};
+use crate::{
+    api::{error::ServerError, payloads::CredentialInfo},
+    feature_model_imports,
+    types::Uuid,
+};
 
 feature_model_imports!();
 
@@ -68,6 +72,19 @@ impl CredentialId {
     fn to_json_string(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string_pretty(self)
     }
+
+    /// The base64 the id is serialized as, which is how the client refers to
+    /// it in [`Object::CredentialId`](crate::api::Object::CredentialId) paths
+    pub fn to_base64(&self) -> String {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::String(id)) => id,
+            other => unreachable!("CredentialId serialized as {other:?}"),
+        }
+    }
+
+    pub fn from_base64(id: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_value(serde_json::Value::String(id.to_string()))
+    }
 }
 
 impl Deref for CredentialId {
@@ -99,21 +116,23 @@ impl FromSql for CredentialId {
     }
 }
 
-feature_model_derives!(
-    "credential",
-    "../../migrations/002-credential/up.sql",
-    pub struct Credential {
-        pub id: CredentialId,
-        pub user_id: Uuid,
-        pub passkey: Passkey,
-        pub counter: u32,
-        pub creation_date: DateTime<Utc>,
-        pub last_used_date: Option<DateTime<Utc>>,
-        pub last_updated_date: DateTime<Utc>,
-        pub backup_eligible: bool,
-        pub backup_state: bool,
-    }
-);
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Model)]
+#[cfg_attr(feature = "sea-query-enum", enum_def)]
+#[model(table = "credential", check = "migrations/018-credential_name/up.sql")]
+pub struct Credential {
+    #[model(json)]
+    pub id: CredentialId,
+    pub user_id: Uuid,
+    #[model(json)]
+    pub passkey: Passkey,
+    pub name: Option<String>,
+    pub counter: u32,
+    pub creation_date: DateTime<Utc>,
+    pub last_used_date: Option<DateTime<Utc>>,
+    pub last_updated_date: DateTime<Utc>,
+    pub backup_eligible: bool,
+    pub backup_state: bool,
+}
 
 impl Credential {
     pub fn fetch<T: Error>(
@@ -132,6 +151,13 @@ impl Credential {
         Ok(credential)
     }
 
+    pub fn fetch_for_user<T: Error>(
+        conn: &Connection,
+        user_id: &Uuid,
+    ) -> Result<Vec<Credential>, ServerError<T>> {
+        Ok(Self::fetch_all_by_column(conn, user_id, CredentialIden::UserId)?)
+    }
+
     pub fn fetch_passkeys<T: Error>(
         conn: &Connection,
         user_id: &Uuid,
@@ -174,6 +200,7 @@ impl Credential {
         let (sql, values) = Query::update()
             .table(CredentialIden::Table)
             .values([
+                (CredentialIden::Name, self.name.clone().into()),
                 (CredentialIden::Counter, self.counter.into()),
                 (CredentialIden::LastUsedDate, self.last_used_date.into()),
                 (CredentialIden::LastUpdatedDate, self.last_updated_date.into()),
@@ -188,6 +215,32 @@ impl Credential {
 
         Ok(())
     }
+
+    pub fn delete<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let id_value = self.id.to_json_string()?;
+        let (sql, values) = Query::delete()
+            .from_table(CredentialIden::Table)
+            .and_where(Expr::col(CredentialIden::Id).eq(id_value))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
+    }
+}
+
+impl From<&Credential> for CredentialInfo {
+    fn from(credential: &Credential) -> Self {
+        Self {
+            id: credential.id.to_base64(),
+            name: credential.name.clone(),
+            creation_date: credential.creation_date,
+            last_used_date: credential.last_used_date,
+            backup_eligible: credential.backup_eligible,
+            backup_state: credential.backup_state,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ExemplarModel)]
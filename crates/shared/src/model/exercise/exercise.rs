@@ -1,45 +1,172 @@
 use chrono::{DateTime, Utc};
 
-use crate::{feature_model_derives, feature_model_imports, types::Uuid};
+use crate::{
+    api::error::ValidationError, feature_model_imports, model::ValidateModel, types::Uuid,
+};
 
 feature_model_imports!();
 
-feature_model_derives!(
-    "exercise",
-    "../../../migrations/004-exercise/up.sql",
-    pub struct Exercise {
-        pub id: Uuid,
-        pub name: String,
-        pub description: Option<String>,
-        /// How many days recovery are required between sets of this exercise
-        pub base_recovery_days: f64,
-        pub creation_date: DateTime<Utc>,
-        pub last_updated_date: DateTime<Utc>,
-    }
-);
+#[cfg(feature = "backend")]
+use {
+    crate::{
+        api::error::ServerError,
+        model::{any_rows, ExerciseGroupMemberIden, SessionExerciseIden, UserExerciseIden},
+    },
+    sea_query::Cond,
+    std::error::Error,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Model)]
+#[cfg_attr(feature = "sea-query-enum", enum_def)]
+#[model(table = "exercise", check = "migrations/013-user_ownership/up.sql")]
+pub struct Exercise {
+    pub id: Uuid,
+    /// The user that created this exercise. None for the global exercises available to
+    /// everyone
+    pub owner_id: Option<Uuid>,
+    pub name: String,
+    pub description: Option<String>,
+    /// How many days recovery are required between sets of this exercise
+    pub base_recovery_days: f64,
+    pub creation_date: DateTime<Utc>,
+    pub last_updated_date: DateTime<Utc>,
+}
 
 #[cfg(feature = "wasm")]
 impl crate::model::model_into_view::UseDefaultModelView for Exercise {}
 
+impl ValidateModel for Exercise {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut error_messages = Vec::new();
+
+        if self.name.trim().is_empty() {
+            error_messages.push("Exercise name can't be empty".to_string());
+        }
+        if !self.base_recovery_days.is_finite() || self.base_recovery_days < 0.0 {
+            error_messages.push(format!(
+                "Base recovery days must be a positive number (got {})",
+                self.base_recovery_days
+            ));
+        }
+
+        if error_messages.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError { error_messages })
+        }
+    }
+}
+
 #[cfg(feature = "backend")]
 impl Exercise {
-    pub fn fetch_by_id(conn: &Connection, id: &Uuid) -> Result<Exercise, rusqlite::Error> {
+    /// Only global exercises and the ones owned by the given user are visible to them
+    fn visible_to(user_id: &Uuid) -> Cond {
+        Cond::any()
+            .add(Expr::col(ExerciseIden::OwnerId).is_null())
+            .add(Expr::col(ExerciseIden::OwnerId).eq(user_id))
+    }
+
+    /// Fetches the global exercises plus the ones owned by the given user
+    pub fn fetch_all_for_user(
+        conn: &Connection,
+        user_id: &Uuid,
+    ) -> Result<Vec<Exercise>, rusqlite::Error> {
         let (sql, values) = Self::select_star()
-            .and_where(Expr::col(ExerciseIden::Id).eq(id))
-            .limit(1)
+            .cond_where(Self::visible_to(user_id))
             .build_rusqlite(SqliteQueryBuilder);
 
         let mut stmt = conn.prepare_cached(&sql)?;
-        let res = stmt.query_row(&*values.as_params(), Exercise::from_row)?;
+        let res =
+            stmt.query_map(&*values.as_params(), Exercise::from_row)?.collect::<Result<_, _>>()?;
         Ok(res)
     }
 
-    pub fn fetch_all(conn: &Connection) -> Result<Vec<Exercise>, rusqlite::Error> {
-        let (sql, values) = Self::select_star().build_rusqlite(SqliteQueryBuilder);
+    /// Fetches the exercise if it exists and is visible to the given user
+    pub fn fetch_for_user(
+        conn: &Connection,
+        user_id: &Uuid,
+        id: &Uuid,
+    ) -> Result<Option<Exercise>, rusqlite::Error> {
+        use rusqlite::OptionalExtension;
+
+        let (sql, values) = Self::select_star()
+            .cond_where(
+                Cond::all().add(Expr::col(ExerciseIden::Id).eq(id)).add(Self::visible_to(user_id)),
+            )
+            .limit(1)
+            .build_rusqlite(SqliteQueryBuilder);
 
         let mut stmt = conn.prepare_cached(&sql)?;
-        let res =
-            stmt.query_map(&*values.as_params(), Exercise::from_row)?.collect::<Result<_, _>>()?;
+        let res = stmt.query_row(&*values.as_params(), Exercise::from_row).optional()?;
         Ok(res)
     }
+
+    pub fn create<T: Error>(
+        conn: &mut Connection,
+        exercise: Exercise,
+    ) -> Result<Exercise, ServerError<T>> {
+        let tx = conn.transaction()?;
+        let exercise = {
+            exercise.insert(&tx)?;
+            Exercise::fetch_by_id(&tx, exercise.id)?
+        };
+        tx.commit()?;
+
+        Ok(exercise)
+    }
+
+    pub fn update<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::update()
+            .table(ExerciseIden::Table)
+            .values([
+                (ExerciseIden::OwnerId, self.owner_id.into()),
+                (ExerciseIden::Name, self.name.clone().into()),
+                (ExerciseIden::Description, self.description.clone().into()),
+                (ExerciseIden::BaseRecoveryDays, self.base_recovery_days.into()),
+                (ExerciseIden::CreationDate, self.creation_date.into()),
+                (ExerciseIden::LastUpdatedDate, self.last_updated_date.into()),
+            ])
+            .and_where(Expr::col(ExerciseIden::Id).eq(&self.id))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
+    }
+
+    /// Whether it's been done in a session, is in a group or other users have
+    /// their own settings for it. The owner's settings go with it
+    pub fn in_use(&self, conn: &Connection) -> Result<bool, rusqlite::Error> {
+        any_rows(conn, [
+            Query::select()
+                .expr(Expr::val(1))
+                .from(SessionExerciseIden::Table)
+                .and_where(Expr::col(SessionExerciseIden::ExerciseId).eq(self.id))
+                .to_owned(),
+            Query::select()
+                .expr(Expr::val(1))
+                .from(ExerciseGroupMemberIden::Table)
+                .and_where(Expr::col(ExerciseGroupMemberIden::ExerciseId).eq(self.id))
+                .to_owned(),
+            Query::select()
+                .expr(Expr::val(1))
+                .from(UserExerciseIden::Table)
+                .and_where(Expr::col(UserExerciseIden::ExerciseId).eq(self.id))
+                .and_where(Expr::col(UserExerciseIden::UserId).ne(self.owner_id))
+                .to_owned(),
+        ])
+    }
+
+    pub fn delete<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::delete()
+            .from_table(ExerciseIden::Table)
+            .and_where(Expr::col(ExerciseIden::Id).eq(&self.id))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
+    }
 }
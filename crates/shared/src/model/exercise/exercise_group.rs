@@ -1,34 +1,147 @@
 use chrono::{DateTime, Utc};
 
-use crate::{feature_model_derives, feature_model_imports, types::Uuid};
+use crate::{
+    api::error::ValidationError, feature_model_imports, model::ValidateModel, types::Uuid,
+};
 
 feature_model_imports!();
 
-feature_model_derives!(
-    "exercise_group",
-    "../../../migrations/006-exercise_group/up.sql",
-    pub struct ExerciseGroup {
-        pub id: Uuid,
-        pub name: String,
-        pub description: Option<String>,
-        pub creation_date: DateTime<Utc>,
-        pub last_updated_date: DateTime<Utc>,
-    }
-);
+#[cfg(feature = "backend")]
+use {
+    crate::{
+        api::error::ServerError,
+        model::{any_rows, PlanExerciseGroupIden},
+    },
+    sea_query::Cond,
+    std::error::Error,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Model)]
+#[cfg_attr(feature = "sea-query-enum", enum_def)]
+#[model(table = "exercise_group", check = "migrations/013-user_ownership/up.sql")]
+pub struct ExerciseGroup {
+    pub id: Uuid,
+    /// The user that created this group. None for the global groups available to everyone
+    pub owner_id: Option<Uuid>,
+    pub name: String,
+    pub description: Option<String>,
+    pub creation_date: DateTime<Utc>,
+    pub last_updated_date: DateTime<Utc>,
+}
 
 #[cfg(feature = "wasm")]
 impl crate::model::model_into_view::UseDefaultModelView for ExerciseGroup {}
 
+impl ValidateModel for ExerciseGroup {
+    fn validate(&self) -> Result<(), ValidationError> {
+        if self.name.trim().is_empty() {
+            Err(ValidationError {
+                error_messages: vec!["Exercise group name can't be empty".to_string()],
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
 #[cfg(feature = "backend")]
 impl ExerciseGroup {
-    pub fn fetch_by_id(conn: &Connection, id: &Uuid) -> Result<ExerciseGroup, rusqlite::Error> {
+    /// Only global groups and the ones owned by the given user are visible to them
+    fn visible_to(user_id: &Uuid) -> Cond {
+        Cond::any()
+            .add(Expr::col(ExerciseGroupIden::OwnerId).is_null())
+            .add(Expr::col(ExerciseGroupIden::OwnerId).eq(user_id))
+    }
+
+    /// Fetches the global groups plus the ones owned by the given user
+    pub fn fetch_all_for_user(
+        conn: &Connection,
+        user_id: &Uuid,
+    ) -> Result<Vec<ExerciseGroup>, rusqlite::Error> {
+        let (sql, values) = Self::select_star()
+            .cond_where(Self::visible_to(user_id))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        let res = stmt
+            .query_map(&*values.as_params(), ExerciseGroup::from_row)?
+            .collect::<Result<_, _>>()?;
+        Ok(res)
+    }
+
+    /// Fetches the group if it exists and is visible to the given user
+    pub fn fetch_for_user(
+        conn: &Connection,
+        user_id: &Uuid,
+        id: &Uuid,
+    ) -> Result<Option<ExerciseGroup>, rusqlite::Error> {
+        use rusqlite::OptionalExtension;
+
         let (sql, values) = Self::select_star()
-            .and_where(Expr::col(ExerciseGroupIden::Id).eq(id))
+            .cond_where(
+                Cond::all()
+                    .add(Expr::col(ExerciseGroupIden::Id).eq(id))
+                    .add(Self::visible_to(user_id)),
+            )
             .limit(1)
             .build_rusqlite(SqliteQueryBuilder);
 
         let mut stmt = conn.prepare_cached(&sql)?;
-        let res = stmt.query_row(&*values.as_params(), ExerciseGroup::from_row)?;
+        let res = stmt.query_row(&*values.as_params(), ExerciseGroup::from_row).optional()?;
         Ok(res)
     }
+
+    pub fn create<T: Error>(
+        conn: &mut Connection,
+        exercise_group: ExerciseGroup,
+    ) -> Result<ExerciseGroup, ServerError<T>> {
+        let tx = conn.transaction()?;
+        let exercise_group = {
+            exercise_group.insert(&tx)?;
+            ExerciseGroup::fetch_by_id(&tx, exercise_group.id)?
+        };
+        tx.commit()?;
+
+        Ok(exercise_group)
+    }
+
+    pub fn update<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::update()
+            .table(ExerciseGroupIden::Table)
+            .values([
+                (ExerciseGroupIden::OwnerId, self.owner_id.into()),
+                (ExerciseGroupIden::Name, self.name.clone().into()),
+                (ExerciseGroupIden::Description, self.description.clone().into()),
+                (ExerciseGroupIden::CreationDate, self.creation_date.into()),
+                (ExerciseGroupIden::LastUpdatedDate, self.last_updated_date.into()),
+            ])
+            .and_where(Expr::col(ExerciseGroupIden::Id).eq(&self.id))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
+    }
+
+    /// Whether any plan has the group in it
+    pub fn in_use(&self, conn: &Connection) -> Result<bool, rusqlite::Error> {
+        any_rows(conn, [Query::select()
+            .expr(Expr::val(1))
+            .from(PlanExerciseGroupIden::Table)
+            .and_where(Expr::col(PlanExerciseGroupIden::ExerciseGroupId).eq(self.id))
+            .to_owned()])
+    }
+
+    pub fn delete<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::delete()
+            .from_table(ExerciseGroupIden::Table)
+            .and_where(Expr::col(ExerciseGroupIden::Id).eq(&self.id))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
+    }
 }
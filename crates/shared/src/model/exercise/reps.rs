@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[cfg(feature = "backend")]
 use rusqlite::{
     types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
@@ -18,6 +20,15 @@ pub enum Reps {
     Reps(u32),
 }
 
+impl fmt::Display for Reps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Amrap(r) => write!(f, "{r}+"),
+            Self::Reps(r) => write!(f, "{r}"),
+        }
+    }
+}
+
 #[cfg(feature = "backend")]
 impl ToSql for Reps {
     fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
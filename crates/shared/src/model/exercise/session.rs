@@ -1,35 +1,186 @@
 use chrono::{DateTime, Utc};
 
-use crate::{feature_model_derives, feature_model_imports, types::Uuid};
+use crate::{
+    api::error::ValidationError,
+    feature_model_imports,
+    model::{PlanInstance, ValidateModel},
+    types::Uuid,
+};
 
 feature_model_imports!();
 
-feature_model_derives!(
-    "session",
-    "../../../migrations/010-session/up.sql",
-    pub struct Session {
-        pub id: Uuid,
-        pub plan_instance_id: Uuid,
-        pub planned_date: DateTime<Utc>,
-        pub performed_date: Option<DateTime<Utc>>,
-        pub creation_date: DateTime<Utc>,
-        pub last_updated_date: DateTime<Utc>,
-    }
-);
+#[cfg(feature = "backend")]
+use {crate::api::error::ServerError, std::error::Error};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Model)]
+#[cfg_attr(feature = "sea-query-enum", enum_def)]
+#[model(table = "session", check = "migrations/023-cascade_user_deletion/up.sql")]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// None for ad-hoc sessions that aren't part of a plan
+    pub plan_instance_id: Option<Uuid>,
+    pub planned_date: DateTime<Utc>,
+    pub performed_date: Option<DateTime<Utc>>,
+    pub creation_date: DateTime<Utc>,
+    pub last_updated_date: DateTime<Utc>,
+}
 
 #[cfg(feature = "wasm")]
 impl crate::model::model_into_view::UseDefaultModelView for Session {}
 
+impl ValidateModel for Session {
+    fn validate(&self) -> Result<(), ValidationError> {
+        // The plan instance existing and ownership are checked against the database
+        let mut error_messages = Vec::new();
+
+        if self.performed_date.is_some_and(|d| d < self.creation_date) {
+            error_messages.push("Session can't be performed before it was created".to_string());
+        }
+        if self.last_updated_date < self.creation_date {
+            error_messages.push("Session can't be updated before it was created".to_string());
+        }
+
+        if error_messages.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError { error_messages })
+        }
+    }
+}
+
+impl Session {
+    /// Creates a new ad-hoc session starting now
+    pub fn new_ad_hoc(user_id: Uuid) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            user_id,
+            plan_instance_id: None,
+            planned_date: now,
+            performed_date: None,
+            creation_date: now,
+            last_updated_date: now,
+        }
+    }
+
+    pub fn is_ad_hoc(&self) -> bool {
+        self.plan_instance_id.is_none()
+    }
+
+    /// Moves a completed ad-hoc session into the history of the plan instance
+    /// so the planner can take it into account
+    pub fn adopt(&mut self, plan_instance: &PlanInstance) -> Result<(), ValidationError> {
+        let mut error_messages = Vec::new();
+
+        if !self.is_ad_hoc() {
+            error_messages.push("Session is already part of a plan".to_string());
+        }
+        if self.performed_date.is_none() {
+            error_messages.push("Only completed sessions can be adopted".to_string());
+        }
+        if self.user_id != plan_instance.user_id {
+            error_messages.push("Plan instance belongs to a different user".to_string());
+        }
+
+        if error_messages.is_empty() {
+            self.plan_instance_id = Some(plan_instance.id);
+            self.last_updated_date = Utc::now();
+            Ok(())
+        } else {
+            Err(ValidationError { error_messages })
+        }
+    }
+}
+
 #[cfg(feature = "backend")]
 impl Session {
-    pub fn fetch_by_id(conn: &Connection, id: &Uuid) -> Result<Session, rusqlite::Error> {
+    /// Fetches all the sessions belonging to the given user
+    pub fn fetch_all_for_user(
+        conn: &Connection,
+        user_id: &Uuid,
+    ) -> Result<Vec<Session>, rusqlite::Error> {
+        Self::fetch_all_by_column(conn, user_id, SessionIden::UserId)
+    }
+
+    /// Fetches every session planned between `since` and `until` that hasn't
+    /// been done yet, ad-hoc or from a plan, for all users
+    pub fn fetch_all_planned_not_performed(
+        conn: &Connection,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<Session>, rusqlite::Error> {
+        let stmt = Self::select_star()
+            .and_where(Expr::col(SessionIden::PerformedDate).is_null())
+            .and_where(Expr::col(SessionIden::PlannedDate).gte(since))
+            .and_where(Expr::col(SessionIden::PlannedDate).lte(until))
+            .to_owned();
+
+        Self::fetch_all_with(conn, &stmt)
+    }
+
+    /// Fetches the session if it exists and belongs to the given user
+    pub fn fetch_for_user(
+        conn: &Connection,
+        user_id: &Uuid,
+        id: &Uuid,
+    ) -> Result<Option<Session>, rusqlite::Error> {
+        use rusqlite::OptionalExtension;
+
         let (sql, values) = Self::select_star()
             .and_where(Expr::col(SessionIden::Id).eq(id))
+            .and_where(Expr::col(SessionIden::UserId).eq(user_id))
             .limit(1)
             .build_rusqlite(SqliteQueryBuilder);
 
         let mut stmt = conn.prepare_cached(&sql)?;
-        let res = stmt.query_row(&*values.as_params(), Session::from_row)?;
+        let res = stmt.query_row(&*values.as_params(), Session::from_row).optional()?;
         Ok(res)
     }
+
+    pub fn create<T: Error>(
+        conn: &mut Connection,
+        session: Session,
+    ) -> Result<Session, ServerError<T>> {
+        let tx = conn.transaction()?;
+        let session = {
+            session.insert(&tx)?;
+            Session::fetch_by_id(&tx, session.id)?
+        };
+        tx.commit()?;
+
+        Ok(session)
+    }
+
+    pub fn update<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::update()
+            .table(SessionIden::Table)
+            .values([
+                (SessionIden::UserId, self.user_id.into()),
+                (SessionIden::PlanInstanceId, self.plan_instance_id.into()),
+                (SessionIden::PlannedDate, self.planned_date.into()),
+                (SessionIden::PerformedDate, self.performed_date.into()),
+                (SessionIden::CreationDate, self.creation_date.into()),
+                (SessionIden::LastUpdatedDate, self.last_updated_date.into()),
+            ])
+            .and_where(Expr::col(SessionIden::Id).eq(&self.id))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
+    }
+
+    pub fn delete<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::delete()
+            .from_table(SessionIden::Table)
+            .and_where(Expr::col(SessionIden::Id).eq(&self.id))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
+    }
 }
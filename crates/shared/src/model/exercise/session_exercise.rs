@@ -1,37 +1,269 @@
 use chrono::{DateTime, Utc};
 
-use super::Sets;
-use crate::{feature_model_derives, feature_model_imports, types::Uuid};
+use super::{Set, Sets};
+use crate::{
+    api::error::ValidationError, feature_model_imports, model::ValidateModel, types::Uuid,
+};
 
 feature_model_imports!();
 
-feature_model_derives!(
-    "session_exercise",
-    "../../../migrations/011-session_exercise/up.sql",
-    pub struct SessionExercise {
-        pub id: Uuid,
-        pub exercise_id: Uuid,
-        pub session_id: Uuid,
-        pub planned_sets: Sets,
-        pub performed_sets: Sets,
-        pub creation_date: DateTime<Utc>,
-        pub last_updated_date: DateTime<Utc>,
-    }
-);
+#[cfg(feature = "backend")]
+use {crate::api::error::ServerError, std::error::Error};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Model)]
+#[cfg_attr(feature = "sea-query-enum", enum_def)]
+#[model(
+    table = "session_exercise",
+    check = "migrations/017-session_exercise_performed_sets/up.sql"
+)]
+pub struct SessionExercise {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub exercise_id: Uuid,
+    pub session_id: Uuid,
+    #[model(json)]
+    pub planned_sets: Sets,
+    #[model(json)]
+    pub performed_sets: Sets,
+    pub creation_date: DateTime<Utc>,
+    pub last_updated_date: DateTime<Utc>,
+    /// Position in the session, ties are broken by creation_date
+    pub sort_order: i64,
+    /// Set once all the planned sets have been performed or the exercise was
+    /// skipped
+    pub finished: bool,
+}
 
 #[cfg(feature = "wasm")]
 impl crate::model::model_into_view::UseDefaultModelView for SessionExercise {}
 
+impl ValidateModel for SessionExercise {
+    fn validate(&self) -> Result<(), ValidationError> {
+        // The session and exercise existing and ownership are checked against the database
+        let mut error_messages = Vec::new();
+
+        let mut sets = self.planned_sets.iter().chain(self.performed_sets.iter());
+        if sets.any(|s| !s.weight.is_valid()) {
+            error_messages.push("Set weights must be positive numbers".to_string());
+        }
+        if self.planned_sets.iter().any(|s| s.completed_date.is_some()) {
+            error_messages.push("Planned sets can't have been completed".to_string());
+        }
+        if self.last_updated_date < self.creation_date {
+            error_messages
+                .push("Session exercise can't be updated before it was created".to_string());
+        }
+
+        if error_messages.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError { error_messages })
+        }
+    }
+}
+
+impl SessionExercise {
+    /// The order session exercises are performed in
+    pub fn order_key(&self) -> (i64, DateTime<Utc>) {
+        (self.sort_order, self.creation_date)
+    }
+
+    /// The planned set that should be performed next. None if there's no plan
+    /// or all of it has been performed
+    pub fn next_planned_set(&self) -> Option<&Set> {
+        if self.finished {
+            None
+        } else {
+            self.planned_sets.get(self.performed_sets.len())
+        }
+    }
+
+    /// Records a performed set, finishing the exercise once all the planned sets
+    /// are done
+    pub fn log_set(&mut self, mut set: Set) {
+        let now = Utc::now();
+        set.completed_date = Some(now);
+        self.performed_sets.push(set);
+
+        if !self.planned_sets.is_empty() && self.performed_sets.len() >= self.planned_sets.len() {
+            self.finished = true;
+        }
+        self.last_updated_date = now;
+    }
+
+    /// Finishes the exercise regardless of how many sets have been performed
+    pub fn skip(&mut self) {
+        self.finished = true;
+        self.last_updated_date = Utc::now();
+    }
+}
+
 #[cfg(feature = "backend")]
 impl SessionExercise {
-    pub fn fetch_by_id(conn: &Connection, id: &Uuid) -> Result<SessionExercise, rusqlite::Error> {
+    /// Fetches all the session exercises belonging to the given user
+    pub fn fetch_all_for_user(
+        conn: &Connection,
+        user_id: &Uuid,
+    ) -> Result<Vec<SessionExercise>, rusqlite::Error> {
+        Self::fetch_all_by_column(conn, user_id, SessionExerciseIden::UserId)
+    }
+
+    /// Fetches the session exercise if it exists and belongs to the given user
+    pub fn fetch_for_user(
+        conn: &Connection,
+        user_id: &Uuid,
+        id: &Uuid,
+    ) -> Result<Option<SessionExercise>, rusqlite::Error> {
+        use rusqlite::OptionalExtension;
+
         let (sql, values) = Self::select_star()
             .and_where(Expr::col(SessionExerciseIden::Id).eq(id))
+            .and_where(Expr::col(SessionExerciseIden::UserId).eq(user_id))
             .limit(1)
             .build_rusqlite(SqliteQueryBuilder);
 
         let mut stmt = conn.prepare_cached(&sql)?;
-        let res = stmt.query_row(&*values.as_params(), SessionExercise::from_row)?;
+        let res = stmt.query_row(&*values.as_params(), SessionExercise::from_row).optional()?;
         Ok(res)
     }
+
+    pub fn create<T: Error>(
+        conn: &mut Connection,
+        session_exercise: SessionExercise,
+    ) -> Result<SessionExercise, ServerError<T>> {
+        let tx = conn.transaction()?;
+        let session_exercise = {
+            session_exercise.insert(&tx)?;
+            SessionExercise::fetch_by_id(&tx, session_exercise.id)?
+        };
+        tx.commit()?;
+
+        Ok(session_exercise)
+    }
+
+    pub fn update<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::update()
+            .table(SessionExerciseIden::Table)
+            .values([
+                (SessionExerciseIden::UserId, self.user_id.into()),
+                (SessionExerciseIden::ExerciseId, self.exercise_id.into()),
+                (SessionExerciseIden::SessionId, self.session_id.into()),
+                (
+                    SessionExerciseIden::PlannedSets,
+                    serde_json::to_string(&self.planned_sets)?.into(),
+                ),
+                (
+                    SessionExerciseIden::PerformedSets,
+                    serde_json::to_string(&self.performed_sets)?.into(),
+                ),
+                (SessionExerciseIden::CreationDate, self.creation_date.into()),
+                (SessionExerciseIden::LastUpdatedDate, self.last_updated_date.into()),
+                (SessionExerciseIden::SortOrder, self.sort_order.into()),
+                (SessionExerciseIden::Finished, self.finished.into()),
+            ])
+            .and_where(Expr::col(SessionExerciseIden::Id).eq(&self.id))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
+    }
+
+    pub fn delete<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::delete()
+            .from_table(SessionExerciseIden::Table)
+            .and_where(Expr::col(SessionExerciseIden::Id).eq(&self.id))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Reps, Weight};
+
+    fn with_planned_sets(planned_sets: usize) -> SessionExercise {
+        let now = Utc::now();
+        let set = Set::new(Weight::Kilograms(50.0), Reps::Reps(5));
+        SessionExercise {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            exercise_id: Uuid::new_v4(),
+            session_id: Uuid::new_v4(),
+            planned_sets: Sets(vec![set; planned_sets]),
+            performed_sets: Sets::default(),
+            creation_date: now,
+            last_updated_date: now,
+            sort_order: 0,
+            finished: false,
+        }
+    }
+
+    fn performed_set() -> Set {
+        Set::new(Weight::Kilograms(50.0), Reps::Reps(4))
+    }
+
+    #[test]
+    fn next_planned_set_follows_the_performed_sets() {
+        let mut session_exercise = with_planned_sets(2);
+        session_exercise.planned_sets[1].reps = Reps::Amrap(5);
+
+        assert_eq!(session_exercise.next_planned_set().unwrap().reps, Reps::Reps(5));
+        session_exercise.log_set(performed_set());
+        assert_eq!(session_exercise.next_planned_set().unwrap().reps, Reps::Amrap(5));
+        session_exercise.log_set(performed_set());
+        assert_eq!(session_exercise.next_planned_set(), None);
+
+        assert_eq!(with_planned_sets(0).next_planned_set(), None);
+
+        let mut skipped = with_planned_sets(2);
+        skipped.skip();
+        assert_eq!(skipped.next_planned_set(), None);
+    }
+
+    #[test]
+    fn log_set_finishes_once_the_plan_is_done() {
+        let mut session_exercise = with_planned_sets(2);
+        let created = session_exercise.last_updated_date;
+
+        session_exercise.log_set(performed_set());
+        assert!(!session_exercise.finished);
+        assert_eq!(session_exercise.performed_sets.len(), 1);
+        assert_eq!(session_exercise.performed_sets[0].reps, Reps::Reps(4));
+        assert_eq!(
+            session_exercise.performed_sets[0].completed_date,
+            Some(session_exercise.last_updated_date)
+        );
+        assert!(session_exercise.last_updated_date >= created);
+
+        session_exercise.log_set(performed_set());
+        assert!(session_exercise.finished);
+        assert_eq!(session_exercise.performed_sets.len(), 2);
+    }
+
+    #[test]
+    fn log_set_never_finishes_without_a_plan() {
+        let mut session_exercise = with_planned_sets(0);
+        for _ in 0..5 {
+            session_exercise.log_set(performed_set());
+        }
+        assert!(!session_exercise.finished);
+        assert_eq!(session_exercise.performed_sets.len(), 5);
+    }
+
+    #[test]
+    fn skip_finishes_without_performing_sets() {
+        let mut session_exercise = with_planned_sets(3);
+        session_exercise.log_set(performed_set());
+        session_exercise.skip();
+
+        assert!(session_exercise.finished);
+        assert_eq!(session_exercise.performed_sets.len(), 1);
+    }
 }
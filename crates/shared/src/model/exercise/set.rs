@@ -1,5 +1,6 @@
 use std::ops::{Deref, DerefMut};
 
+use chrono::{DateTime, Utc};
 #[cfg(feature = "backend")]
 use rusqlite::{
     types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
@@ -11,9 +12,18 @@ use super::{Reps, Weight};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Set {
-    weight: Weight,
-    reps: Reps,
-    notes: Vec<String>,
+    pub weight: Weight,
+    pub reps: Reps,
+    pub notes: Vec<String>,
+    /// When the set was performed. Always None for planned sets
+    #[serde(default)]
+    pub completed_date: Option<DateTime<Utc>>,
+}
+
+impl Set {
+    pub fn new(weight: Weight, reps: Reps) -> Self {
+        Self { weight, reps, notes: Vec::new(), completed_date: None }
+    }
 }
 
 #[cfg(feature = "backend")]
@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[cfg(feature = "backend")]
 use rusqlite::{
     types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
@@ -12,6 +14,26 @@ pub enum Weight {
     Bodyweight,
 }
 
+impl fmt::Display for Weight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Kilograms(w) => write!(f, "{w}kg"),
+            Self::Lbs(w) => write!(f, "{w}lbs"),
+            Self::Bodyweight => write!(f, "bodyweight"),
+        }
+    }
+}
+
+impl Weight {
+    /// Weights are a finite amount, zero or more
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Self::Kilograms(w) | Self::Lbs(w) => w.is_finite() && *w >= 0.0,
+            Self::Bodyweight => true,
+        }
+    }
+}
+
 #[cfg(feature = "backend")]
 impl ToSql for Weight {
     fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
@@ -0,0 +1,200 @@
+//! Reading models out of the json rows sqlite wasm returns and turning them
+//! back into values to bind. [`JsonRow`] is implemented by the Model derive
+
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use sea_query::SimpleExpr;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::types::Uuid;
+
+#[derive(Debug, Clone, Error)]
+#[error("{0}")]
+pub struct JsonRowError(pub String);
+
+pub trait JsonRow: Sized {
+    /// Reads the model from one of the rows of an exec result. `column_names`
+    /// lines up with `row`
+    fn from_json_row(column_names: &[String], row: &[Value]) -> Result<Self, JsonRowError>;
+    fn id(&self) -> &Uuid;
+    /// The value of every field, in the same order as
+    /// [`Model::field_idens`](super::Model::field_idens)
+    fn field_values(&self) -> Result<Vec<SimpleExpr>, JsonRowError>;
+    /// Only does anything for models that have a last_updated_date
+    fn set_last_updated_date(&mut self, _date: DateTime<Utc>) {}
+}
+
+/// How a column value sqlite wasm gives back is turned into a field
+pub trait FromJsonValue: Sized {
+    fn from_json_value(value: &Value) -> Result<Self, String>;
+}
+
+impl FromJsonValue for String {
+    fn from_json_value(value: &Value) -> Result<Self, String> {
+        value.as_str().map(str::to_string).ok_or_else(|| format!("Expected a string, got {value}"))
+    }
+}
+
+impl FromJsonValue for i64 {
+    fn from_json_value(value: &Value) -> Result<Self, String> {
+        value.as_i64().ok_or_else(|| format!("Expected an integer, got {value}"))
+    }
+}
+
+impl FromJsonValue for u32 {
+    fn from_json_value(value: &Value) -> Result<Self, String> {
+        value
+            .as_u64()
+            .and_then(|v| v.try_into().ok())
+            .ok_or_else(|| format!("Expected a u32, got {value}"))
+    }
+}
+
+impl FromJsonValue for f64 {
+    fn from_json_value(value: &Value) -> Result<Self, String> {
+        value.as_f64().ok_or_else(|| format!("Expected a number, got {value}"))
+    }
+}
+
+impl FromJsonValue for bool {
+    fn from_json_value(value: &Value) -> Result<Self, String> {
+        // Sqlite doesn't have a bool type
+        match value {
+            Value::Bool(b) => Ok(*b),
+            v => v.as_i64().map(|v| v != 0).ok_or_else(|| format!("Expected a bool, got {value}")),
+        }
+    }
+}
+
+impl FromJsonValue for Uuid {
+    fn from_json_value(value: &Value) -> Result<Self, String> {
+        Uuid::parse(&String::from_json_value(value)?).map_err(|e| e.to_string())
+    }
+}
+
+impl FromJsonValue for DateTime<Utc> {
+    fn from_json_value(value: &Value) -> Result<Self, String> {
+        parse_datetime(&String::from_json_value(value)?).map_err(|e| e.to_string())
+    }
+}
+
+impl<T: FromJsonValue> FromJsonValue for Option<T> {
+    fn from_json_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Null => Ok(None),
+            v => T::from_json_value(v).map(Some),
+        }
+    }
+}
+
+/// Copied from rusqlite FromSql
+pub fn parse_datetime(value: &str) -> Result<DateTime<Utc>, chrono::ParseError> {
+    {
+        // Try to parse value as rfc3339 first.
+        let fmt = if value.len() >= 11 && value.as_bytes()[10] == b'T' {
+            "%FT%T%.f%#z"
+        } else {
+            "%F %T%.f%#z"
+        };
+
+        if let Ok(dt) = DateTime::parse_from_str(value, fmt) {
+            return Ok(dt.with_timezone(&Utc));
+        }
+    }
+
+    // Couldn't parse as rfc3339 - fall back to NaiveDateTime.
+    let fmt =
+        if value.len() >= 11 && value.as_bytes()[10] == b'T' { "%FT%T%.f" } else { "%F %T%.f" };
+
+    NaiveDateTime::parse_from_str(value, fmt).map(|dt| Utc.from_utc_datetime(&dt))
+}
+
+fn column<'a>(
+    column_names: &[String],
+    row: &'a [Value],
+    column: &str,
+) -> Result<&'a Value, JsonRowError> {
+    let index = column_names
+        .iter()
+        .position(|c| c == column)
+        .ok_or_else(|| JsonRowError(format!("Missing column {column}")))?;
+
+    row.get(index).ok_or_else(|| {
+        JsonRowError(format!("column_names contained {column} but it was missing from the row"))
+    })
+}
+
+/// Reads a column through its [`FromJsonValue`] impl
+pub fn read_column<T: FromJsonValue>(
+    column_names: &[String],
+    row: &[Value],
+    name: &str,
+) -> Result<T, JsonRowError> {
+    T::from_json_value(column(column_names, row, name)?)
+        .map_err(|e| JsonRowError(format!("Error reading {name}: {e}")))
+}
+
+/// Reads a column that's stored as json text
+pub fn read_json_column<T: DeserializeOwned>(
+    column_names: &[String],
+    row: &[Value],
+    name: &str,
+) -> Result<T, JsonRowError> {
+    let value = column(column_names, row, name)?;
+    let result = match value {
+        Value::String(s) => serde_json::from_str(s),
+        v => serde_json::from_value(v.clone()),
+    };
+
+    result.map_err(|e| JsonRowError(format!("Error deserializing {name} from {value}: {e}")))
+}
+
+/// Turns a field stored as json text into a value to bind. None is bound as
+/// NULL rather than the string "null"
+pub fn json_field_value<T: Serialize>(field: &T) -> Result<SimpleExpr, JsonRowError> {
+    let value = serde_json::to_value(field).map_err(|e| JsonRowError(e.to_string()))?;
+    Ok(match value {
+        Value::Null => sea_query::Value::String(None).into(),
+        v => v.to_string().into(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_read_column() {
+        let names = ["id", "finished", "date", "missing"].map(String::from);
+        let id = Uuid::new_v4();
+        let row =
+            [json!(id.to_string()), json!(1), json!("2024-06-01 10:00:00+00:00"), json!(null)];
+
+        assert_eq!(read_column::<Uuid>(&names, &row, "id").unwrap(), id);
+        assert!(read_column::<bool>(&names, &row, "finished").unwrap());
+        assert_eq!(
+            read_column::<DateTime<Utc>>(&names, &row, "date").unwrap(),
+            Utc.with_ymd_and_hms(2024, 6, 1, 10, 0, 0).unwrap()
+        );
+        assert_eq!(read_column::<Option<String>>(&names, &row, "missing").unwrap(), None);
+        assert!(read_column::<String>(&names, &row, "missing").is_err());
+        assert!(read_column::<String>(&names, &row, "other").is_err());
+    }
+
+    #[test]
+    fn test_json_column() {
+        let names = ["sets", "none"].map(String::from);
+        let row = [json!("[1,2]"), json!(null)];
+
+        assert_eq!(read_json_column::<Vec<u32>>(&names, &row, "sets").unwrap(), vec![1, 2]);
+        assert_eq!(read_json_column::<Option<Vec<u32>>>(&names, &row, "none").unwrap(), None);
+        assert_eq!(
+            json_field_value(&None::<Vec<u32>>).unwrap(),
+            sea_query::Value::String(None).into()
+        );
+        assert_eq!(json_field_value(&vec![1, 2]).unwrap(), "[1,2]".into());
+    }
+}
@@ -0,0 +1,95 @@
+use chrono::{DateTime, Utc};
+
+use crate::{
+    api::{error::ServerError, payloads::LoginSessionInfo},
+    feature_model_imports,
+    types::Uuid,
+};
+
+feature_model_imports!();
+
+use std::error::Error;
+
+/// A session a user is logged in with. The session itself is kept by
+/// tower_sessions, this is what's shown to the user about it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Model)]
+#[cfg_attr(feature = "sea-query-enum", enum_def)]
+#[model(table = "login_session", check = "migrations/020-login_session/up.sql")]
+pub struct LoginSession {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// The tower_sessions id. It's what the session cookie holds so it mustn't
+    /// be sent to the client
+    pub session_id: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub creation_date: DateTime<Utc>,
+    pub last_seen_date: DateTime<Utc>,
+}
+
+impl LoginSession {
+    pub fn fetch_for_user<T: Error>(
+        conn: &Connection,
+        user_id: &Uuid,
+    ) -> Result<Vec<LoginSession>, ServerError<T>> {
+        Ok(Self::fetch_all_by_column(conn, user_id, LoginSessionIden::UserId)?)
+    }
+
+    pub fn fetch_by_session_id<T: Error>(
+        conn: &Connection,
+        session_id: &str,
+    ) -> Result<Option<LoginSession>, ServerError<T>> {
+        Ok(Self::fetch_by_column_maybe(conn, session_id, LoginSessionIden::SessionId)?)
+    }
+
+    pub fn update<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::update()
+            .table(LoginSessionIden::Table)
+            .values([
+                (LoginSessionIden::UserId, self.user_id.into()),
+                (LoginSessionIden::UserAgent, self.user_agent.clone().into()),
+                (LoginSessionIden::IpAddress, self.ip_address.clone().into()),
+                (LoginSessionIden::CreationDate, self.creation_date.into()),
+                (LoginSessionIden::LastSeenDate, self.last_seen_date.into()),
+            ])
+            .and_where(Expr::col(LoginSessionIden::Id).eq(&self.id))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
+    }
+
+    pub fn delete<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        Self::delete_by_session_id(conn, &self.session_id)
+    }
+
+    pub fn delete_by_session_id<T: Error>(
+        conn: &Connection,
+        session_id: &str,
+    ) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::delete()
+            .from_table(LoginSessionIden::Table)
+            .and_where(Expr::col(LoginSessionIden::SessionId).eq(session_id))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
+    }
+}
+
+impl LoginSessionInfo {
+    pub fn new(login_session: &LoginSession, current: bool) -> Self {
+        Self {
+            id: login_session.id,
+            user_agent: login_session.user_agent.clone(),
+            ip_address: login_session.ip_address.clone(),
+            creation_date: login_session.creation_date,
+            last_seen_date: login_session.last_seen_date,
+            current,
+        }
+    }
+}
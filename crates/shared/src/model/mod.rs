@@ -15,33 +15,147 @@ mod credential;
 #[cfg(feature = "backend")]
 pub use credential::*;
 
+#[cfg(feature = "backend")]
+mod backup;
+#[cfg(feature = "backend")]
+pub use backup::*;
+
+#[cfg(feature = "backend")]
+mod login_session;
+#[cfg(feature = "backend")]
+pub use login_session::*;
+
+#[cfg(feature = "backend")]
+mod push_outbox;
+#[cfg(feature = "backend")]
+pub use push_outbox::*;
+
+#[cfg(feature = "backend")]
+mod push_subscription;
+#[cfg(feature = "backend")]
+pub use push_subscription::*;
+
+#[cfg(feature = "backend")]
+mod recovery_code;
+#[cfg(feature = "backend")]
+pub use recovery_code::*;
+
+#[cfg(feature = "backend")]
+mod reminder;
+#[cfg(feature = "backend")]
+pub use reminder::*;
+
+#[cfg(feature = "backend")]
+mod temporary_login_audit;
+#[cfg(feature = "backend")]
+pub use temporary_login_audit::*;
+
 use crate::api::error::ValidationError;
 
 pub mod constants;
+#[cfg(feature = "wasm")]
+pub mod json_row;
+pub mod schema;
 
 pub trait ValidateModel {
     fn validate(&self) -> Result<(), ValidationError>;
 }
 
+/// Whether any of the queries return a row. Used to check nothing refers to a
+/// model before it's deleted
+#[cfg(feature = "backend")]
+pub(crate) fn any_rows(
+    conn: &rusqlite::Connection,
+    queries: impl IntoIterator<Item = sea_query::SelectStatement>,
+) -> Result<bool, rusqlite::Error> {
+    use sea_query::{Expr, Query, SimpleExpr, SqliteQueryBuilder};
+    use sea_query_rusqlite::RusqliteBinder;
+
+    let exists = queries
+        .into_iter()
+        .map(Expr::exists)
+        .reduce(SimpleExpr::or)
+        .unwrap_or_else(|| Expr::val(false).into());
+    let (sql, values) = Query::select().expr(exists).build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = conn.prepare_cached(&sql)?;
+    stmt.query_row(&*values.as_params(), |row| row.get(0))
+}
+
+/// How a field is stored in the database. Checked against the migrations by
+/// the server tests
+#[cfg(feature = "sea-query-enum")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnType {
+    /// The type in the CREATE TABLE, all the tables are STRICT so it's one of
+    /// TEXT, INTEGER, REAL or BLOB
+    pub sql_type: &'static str,
+    pub nullable: bool,
+}
+
+/// The sqlite type a field's Rust type is stored as. Option is unwrapped by
+/// the Model derive and json fields are always TEXT so neither need an impl
+#[cfg(feature = "sea-query-enum")]
+pub trait SqlType {
+    const SQL_TYPE: &'static str;
+}
+
+#[cfg(feature = "sea-query-enum")]
+mod sql_type_impls {
+    use chrono::{DateTime, Utc};
+
+    use super::SqlType;
+    use crate::types::Uuid;
+
+    macro_rules! sql_type {
+        ($sql_type:literal: $($ty:ty),*) => {
+            $(
+                impl SqlType for $ty {
+                    const SQL_TYPE: &'static str = $sql_type;
+                }
+            )*
+        };
+    }
+
+    sql_type!("TEXT": String, Uuid, DateTime<Utc>);
+    sql_type!("INTEGER": i64, u32, bool);
+    sql_type!("REAL": f64);
+}
+
 #[cfg(feature = "sea-query-enum")]
 pub trait Model: Sized {
     const NUM_FIELDS: usize;
+    /// The type of every field, in the same order as [`Model::field_idens`]
+    const COLUMN_TYPES: &'static [ColumnType];
     type Iden: sea_query::Iden;
     fn iden_for_field(field: usize) -> Self::Iden;
+    fn table_iden() -> Self::Iden;
+    fn id_iden() -> Self::Iden;
     fn field_idens() -> &'static [Self::Iden];
     fn select_star() -> sea_query::SelectStatement;
+    /// Same as select_star but with the columns qualified by the table name so
+    /// other tables can be joined on without the column names being ambiguous
+    fn select_star_qualified() -> sea_query::SelectStatement;
     /// Gets the insert query without values set
     fn insert_query() -> sea_query::InsertStatement;
     #[cfg(feature = "wasm")]
     fn fetch_all_sql() -> String;
+    /// Select where `column` equals `id`. The value is left for binding rather
+    /// than being inlined
     #[cfg(feature = "wasm")]
-    fn fetch_by_column_sql<T: Into<sea_query::Value>>(
+    fn fetch_by_column_query<T: Into<sea_query::Value>>(
         id: T,
         column: Self::Iden,
         limit_1: bool,
-    ) -> String;
+    ) -> sea_query::SelectStatement;
     #[cfg(feature = "backend")]
     fn fetch_all(conn: &rusqlite::Connection) -> Result<Vec<Self>, rusqlite::Error>;
+    /// Runs a select built from select_star or select_star_qualified
+    #[cfg(feature = "backend")]
+    fn fetch_all_with(
+        conn: &rusqlite::Connection,
+        stmt: &sea_query::SelectStatement,
+    ) -> Result<Vec<Self>, rusqlite::Error>;
     #[cfg(feature = "backend")]
     fn fetch_by_id<T: Into<sea_query::Value>>(
         conn: &rusqlite::Connection,
@@ -171,6 +285,8 @@ macro_rules! feature_model_imports {
         #[cfg(feature = "wasm")]
         #[allow(unused_imports)]
         use leptos::{view, IntoView};
+        #[allow(unused_imports)]
+        use model_derive::Model;
         #[cfg(feature = "sea-query-enum")]
         #[allow(unused_imports)]
         use sea_query::{
@@ -188,222 +304,31 @@ macro_rules! feature_model_imports {
     };
 }
 
+/// Stores each of the types as JSON text, the same as `#[model(json)]` fields
+#[cfg(feature = "backend")]
 #[macro_export]
-macro_rules! feature_model_derives {
-    ($table_name:literal, $migration_path:literal, $(#[$($attrs:tt)*])* pub struct $struct_name:ident {
-        $($(#[$($fattrs:tt)*])* pub $field_name:ident: $field_type:ty,)*
-    }) => {
-
-        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-        #[cfg_attr(feature = "exemplar-model", derive(ExemplarModel))]
-        #[cfg_attr(feature = "exemplar-model", table($table_name))]
-        #[cfg_attr(
-            feature = "exemplar-model",
-            check($migration_path))]
-        #[cfg_attr(feature = "sea-query-enum", enum_def)]
-        $(#[$($attrs)*])*
-        pub struct $struct_name {
-            $(
-                $(#[$($fattrs)*])*
-                pub $field_name: $field_type,
-            )*
-        }
-
-        #[cfg(feature = "sea-query-enum")]
-        impl crate::model::Model for $struct_name {
-            paste::paste! {
-                type Iden = [<$struct_name Iden>];
-
-                const NUM_FIELDS: usize = ${count($field_name)};
-
-                fn iden_for_field(field: usize) -> Self::Iden {
-                    match field {
-                        $(
-                            ${index()} => [<$struct_name Iden>]::[<$field_name:camel>],
-                        )*
-                        _ => [<$struct_name Iden>]::Table,
-                    }
-                }
-
-                fn field_idens() -> &'static [Self::Iden] {
-                    &[
-                        $(
-                            [<$struct_name Iden>]::[<$field_name:camel>],
-                        )*
-                    ]
-                }
-
-                fn select_star() -> SelectStatement {
-                    let mut stmt = Query::select();
-                    stmt
-                        .columns([
-                            $(
-                                [<$struct_name Iden>]::[<$field_name:camel>],
-                            )*
-                        ])
-                        .from([<$struct_name Iden>]::Table);
-                    stmt
-                }
-
-                fn insert_query() -> InsertStatement {
-                    let mut stmt = Query::insert();
-                    stmt
-                        .into_table([<$struct_name Iden>]::Table)
-                        .columns([
-                            $(
-                                [<$struct_name Iden>]::[<$field_name:camel>],
-                            )*
-                        ]);
-                    stmt
-                }
-
-                #[cfg(feature = "wasm")]
-                fn fetch_all_sql() -> String {
-                    Self::select_star().to_string(SqliteQueryBuilder)
-                }
-
-                #[cfg(feature = "wasm")]
-                fn fetch_by_column_sql<T: Into<sea_query::Value>>(id: T, column: Self::Iden, limit_1: bool) -> String {
-                    let mut stmt = Self::select_star();
-                    stmt.and_where(Expr::col(column).eq(id.into()));
-
-                    if limit_1 {
-                        stmt.limit(1);
-                    }
-
-                    stmt.to_string(SqliteQueryBuilder)
-                }
-
-                #[cfg(feature = "backend")]
-                fn fetch_all(conn: &rusqlite::Connection) -> Result<Vec<Self>, rusqlite::Error> {
-                    let (sql, values) = Self::select_star()
-                        .build_rusqlite(SqliteQueryBuilder);
-
-                    let mut stmt = conn.prepare_cached(&sql)?;
-
-                    let results = stmt
-                        .query_and_then(&*values.as_params(), Self::from_row)?
-                        .collect::<Result<Vec<_>, _>>()?;
-
-                    Ok(results)
-                }
-
-                #[cfg(feature = "backend")]
-                fn fetch_by_id<T: Into<sea_query::Value>>(conn: &rusqlite::Connection, id: T) -> Result<Self, rusqlite::Error> {
-                    Self::fetch_by_column(conn, id, [<$struct_name Iden>]::Id)
-                }
-
-                #[cfg(feature = "backend")]
-                fn fetch_by_column<T: Into<sea_query::Value>>(conn: &rusqlite::Connection, id: T, column: Self::Iden) -> Result<Self, rusqlite::Error> {
-                    let (sql, values) = Self::select_star()
-                        .and_where(Expr::col(column).eq(id.into()))
-                        .limit(1)
-                        .build_rusqlite(SqliteQueryBuilder);
-
-                    let mut stmt = conn.prepare_cached(&sql)?;
-
-                    let result = stmt
-                        .query_row(&*values.as_params(), Self::from_row)?;
-
-                    Ok(result)
-                }
-
-                #[cfg(feature = "backend")]
-                fn fetch_by_id_maybe<T: Into<sea_query::Value>>(conn: &rusqlite::Connection, id: T) -> Result<Option<Self>, rusqlite::Error> {
-                    Self::fetch_by_column_maybe(conn, id, [<$struct_name Iden>]::Id)
-                }
-
-                #[cfg(feature = "backend")]
-                fn fetch_by_column_maybe<T: Into<sea_query::Value>>(conn: &rusqlite::Connection, id: T, column: Self::Iden) -> Result<Option<Self>, rusqlite::Error> {
-                    use rusqlite::OptionalExtension;
-
-                    let (sql, values) = Self::select_star()
-                        .and_where(Expr::col(column).eq(id.into()))
-                        .limit(1)
-                        .build_rusqlite(SqliteQueryBuilder);
-
-                    let mut stmt = conn.prepare_cached(&sql)?;
-
-                    let result = stmt
-                        .query_row(&*values.as_params(), Self::from_row)
-                        .optional()?;
-
-                    Ok(result)
-                }
-
-                #[cfg(feature = "backend")]
-                fn fetch_all_by_column<T: Into<sea_query::Value>>(conn: &rusqlite::Connection, id: T, column: Self::Iden) -> Result<Vec<Self>, rusqlite::Error> {
-                    let (sql, values) = Self::select_star()
-                        .and_where(Expr::col(column).eq(id.into()))
-                        .build_rusqlite(SqliteQueryBuilder);
-
-                    let mut stmt = conn.prepare_cached(&sql)?;
-
-                    let results = stmt
-                        .query_and_then(&*values.as_params(), Self::from_row)?
-                        .collect::<Result<Vec<_>, _>>()?;
-
-                    Ok(results)
-                }
-            }
-        }
-
-        #[cfg(feature = "wasm")]
-        impl crate::model::model_into_view::DefaultModelIntoView for $struct_name {
-            fn header_str(iden: <Self as crate::model::Model>::Iden) -> &'static str {
-                paste::paste! {
-                    match iden {
-                        [<$struct_name Iden>]::Table => stringify!($table_name),
-                        $(
-                            [<$struct_name Iden>]::[<$field_name:camel>] => stringify!([<$field_name:camel>]),
-                        )*
-                    }
-                }
-            }
-
-            fn row_view(&self, iden: <Self as crate::model::Model>::Iden) -> impl leptos::IntoView {
-                paste::paste! {
-                    leptos::view! {
-                        <td>
-                            {
-                                match iden {
-                                    [<$struct_name Iden>]::Table => stringify!($table_name).to_string(),
-                                    $(
-                                        [<$struct_name Iden>]::[<$field_name:camel>] => format!("{:?}", &self.$field_name),
-                                    )*
-                                }
-                            }
-                        </td>
-                    }
+macro_rules! json_sql {
+    ($($ty:ty),*) => {
+        $(
+            impl rusqlite::ToSql for $ty {
+                fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+                    serde_json::to_string(self)
+                        .map(rusqlite::types::ToSqlOutput::from)
+                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
                 }
             }
-        }
 
-        #[cfg(test)]
-        paste::paste! {
-            mod [<$struct_name:snake:lower _tests>] {
-                #[allow(unused_imports)]
-                use super::*;
-
-                #[test]
-                #[cfg(feature = "sea-query-enum")]
-                fn [<test_ $struct_name:snake:lower _fetch_all_sql>]() {
-                    let sql = $struct_name::fetch_all_sql()
-                        .to_lowercase();
-
-                    assert!(sql.starts_with("select "));
-
-                    let mut fields = String::new();
-                    $(
-                        fields.push('"');
-                        fields.push_str(stringify!($field_name));
-                        fields.push_str("\", ");
-                    )*
-                    fields.truncate(fields.len() - 2);
-
-                    assert!(sql.contains(&fields));
+            impl rusqlite::types::FromSql for $ty {
+                fn column_result(
+                    value: rusqlite::types::ValueRef<'_>,
+                ) -> rusqlite::types::FromSqlResult<Self> {
+                    <serde_json::Value as rusqlite::types::FromSql>::column_result(value)
+                        .and_then(|v| {
+                            serde_json::from_value(v)
+                                .map_err(|e| rusqlite::types::FromSqlError::Other(Box::new(e)))
+                        })
                 }
             }
-        }
+        )*
     };
 }
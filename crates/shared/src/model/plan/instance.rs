@@ -1,47 +1,118 @@
 use chrono::{DateTime, Utc};
 
-use crate::{feature_model_derives, feature_model_imports, types::Uuid};
+use crate::{
+    api::error::ValidationError, feature_model_imports, model::ValidateModel, types::Uuid,
+};
 
 feature_model_imports!();
 
-feature_model_derives!(
-    "plan_instance",
-    "../../../migrations/008-plan_instance/up.sql",
-    /// A plan instance is an actual execution of a plan on a given start_date.
-    /// Local to a certain user. Can be multiple instances of the same plan
-    pub struct PlanInstance {
-        pub id: Uuid,
-        pub plan_id: Uuid,
-        pub user_id: Uuid,
-        pub start_date: DateTime<Utc>,
-        pub creation_date: DateTime<Utc>,
-        pub last_updated_date: DateTime<Utc>,
-    }
-);
+#[cfg(feature = "backend")]
+use {crate::api::error::ServerError, std::error::Error};
+
+/// A plan instance is an actual execution of a plan on a given start_date.
+/// Local to a certain user. Can be multiple instances of the same plan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Model)]
+#[cfg_attr(feature = "sea-query-enum", enum_def)]
+#[model(table = "plan_instance", check = "migrations/023-cascade_user_deletion/up.sql")]
+pub struct PlanInstance {
+    pub id: Uuid,
+    pub plan_id: Uuid,
+    pub user_id: Uuid,
+    pub start_date: DateTime<Utc>,
+    pub creation_date: DateTime<Utc>,
+    pub last_updated_date: DateTime<Utc>,
+}
 
 #[cfg(feature = "wasm")]
 impl crate::model::model_into_view::UseDefaultModelView for PlanInstance {}
 
+impl ValidateModel for PlanInstance {
+    fn validate(&self) -> Result<(), ValidationError> {
+        // The plan existing and ownership are checked against the database. Any start date is
+        // fine, instances can be planned ahead or backfilled
+        if self.last_updated_date < self.creation_date {
+            Err(ValidationError {
+                error_messages: vec![
+                    "Plan instance can't be updated before it was created".to_string()
+                ],
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
 #[cfg(feature = "backend")]
 impl PlanInstance {
-    pub fn fetch_by_id(conn: &Connection, id: &Uuid) -> Result<PlanInstance, rusqlite::Error> {
+    /// Fetches all the plan instances belonging to the given user
+    pub fn fetch_all_for_user(
+        conn: &Connection,
+        user_id: &Uuid,
+    ) -> Result<Vec<PlanInstance>, rusqlite::Error> {
+        Self::fetch_all_by_column(conn, user_id, PlanInstanceIden::UserId)
+    }
+
+    /// Fetches the plan instance if it exists and belongs to the given user
+    pub fn fetch_for_user(
+        conn: &Connection,
+        user_id: &Uuid,
+        id: &Uuid,
+    ) -> Result<Option<PlanInstance>, rusqlite::Error> {
+        use rusqlite::OptionalExtension;
+
         let (sql, values) = Self::select_star()
             .and_where(Expr::col(PlanInstanceIden::Id).eq(id))
+            .and_where(Expr::col(PlanInstanceIden::UserId).eq(user_id))
             .limit(1)
             .build_rusqlite(SqliteQueryBuilder);
 
         let mut stmt = conn.prepare_cached(&sql)?;
-        let res = stmt.query_row(&*values.as_params(), PlanInstance::from_row)?;
+        let res = stmt.query_row(&*values.as_params(), PlanInstance::from_row).optional()?;
         Ok(res)
     }
 
-    pub fn fetch_all(conn: &Connection) -> Result<Vec<PlanInstance>, rusqlite::Error> {
-        let (sql, values) = Self::select_star().build_rusqlite(SqliteQueryBuilder);
+    pub fn create<T: Error>(
+        conn: &mut Connection,
+        plan_instance: PlanInstance,
+    ) -> Result<PlanInstance, ServerError<T>> {
+        let tx = conn.transaction()?;
+        let plan_instance = {
+            plan_instance.insert(&tx)?;
+            PlanInstance::fetch_by_id(&tx, plan_instance.id)?
+        };
+        tx.commit()?;
+
+        Ok(plan_instance)
+    }
+
+    pub fn update<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::update()
+            .table(PlanInstanceIden::Table)
+            .values([
+                (PlanInstanceIden::PlanId, self.plan_id.into()),
+                (PlanInstanceIden::UserId, self.user_id.into()),
+                (PlanInstanceIden::StartDate, self.start_date.into()),
+                (PlanInstanceIden::CreationDate, self.creation_date.into()),
+                (PlanInstanceIden::LastUpdatedDate, self.last_updated_date.into()),
+            ])
+            .and_where(Expr::col(PlanInstanceIden::Id).eq(&self.id))
+            .build_rusqlite(SqliteQueryBuilder);
 
         let mut stmt = conn.prepare_cached(&sql)?;
-        let res = stmt
-            .query_map(&*values.as_params(), PlanInstance::from_row)?
-            .collect::<Result<_, _>>()?;
-        Ok(res)
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
+    }
+
+    pub fn delete<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::delete()
+            .from_table(PlanInstanceIden::Table)
+            .and_where(Expr::col(PlanInstanceIden::Id).eq(&self.id))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
     }
 }
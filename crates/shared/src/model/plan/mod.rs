@@ -9,3 +9,8 @@ pub use exercise_group::*;
 
 mod config;
 pub use config::*;
+
+#[cfg(feature = "sea-query-enum")]
+mod tree;
+#[cfg(feature = "sea-query-enum")]
+pub use tree::*;
@@ -1,47 +1,109 @@
 use chrono::{DateTime, Utc};
 
-use crate::{feature_model_derives, feature_model_imports, types::Uuid};
+use crate::{
+    api::error::ValidationError, feature_model_imports, model::ValidateModel, types::Uuid,
+};
 
 feature_model_imports!();
 
-feature_model_derives!(
-    "plan",
-    "../../../migrations/007-plan/up.sql",
-    /// A plan is the a methodology for the exercise programme. Global for all
-    /// users
-    pub struct Plan {
-        pub id: Uuid,
-        pub owner_id: Uuid,
-        pub name: String,
-        pub description: Option<String>,
-        pub duration_weeks: u32,
-        pub creation_date: DateTime<Utc>,
-        pub last_updated_date: DateTime<Utc>,
-    }
-);
+#[cfg(feature = "backend")]
+use {
+    crate::{
+        api::error::ServerError,
+        model::{any_rows, PlanInstanceIden},
+    },
+    std::error::Error,
+};
+
+/// A plan is the a methodology for the exercise programme. Global for all
+/// users
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Model)]
+#[cfg_attr(feature = "sea-query-enum", enum_def)]
+#[model(table = "plan", check = "migrations/023-cascade_user_deletion/up.sql")]
+pub struct Plan {
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub duration_weeks: u32,
+    pub creation_date: DateTime<Utc>,
+    pub last_updated_date: DateTime<Utc>,
+}
 
 #[cfg(feature = "wasm")]
 impl crate::model::model_into_view::UseDefaultModelView for Plan {}
 
+impl ValidateModel for Plan {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut error_messages = Vec::new();
+
+        if self.name.trim().is_empty() {
+            error_messages.push("Plan name can't be empty".to_string());
+        }
+        if self.duration_weeks == 0 {
+            error_messages.push("Plan duration must be at least 1 week".to_string());
+        }
+
+        if error_messages.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError { error_messages })
+        }
+    }
+}
+
 #[cfg(feature = "backend")]
 impl Plan {
-    pub fn fetch_by_id(conn: &Connection, id: &Uuid) -> Result<Plan, rusqlite::Error> {
-        let (sql, values) = Self::select_star()
-            .and_where(Expr::col(PlanIden::Id).eq(id))
-            .limit(1)
+    pub fn create<T: Error>(conn: &mut Connection, plan: Plan) -> Result<Plan, ServerError<T>> {
+        let tx = conn.transaction()?;
+        let plan = {
+            plan.insert(&tx)?;
+            Plan::fetch_by_id(&tx, plan.id)?
+        };
+        tx.commit()?;
+
+        Ok(plan)
+    }
+
+    pub fn update<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::update()
+            .table(PlanIden::Table)
+            .values([
+                (PlanIden::OwnerId, self.owner_id.into()),
+                (PlanIden::Name, self.name.clone().into()),
+                (PlanIden::Description, self.description.clone().into()),
+                (PlanIden::DurationWeeks, self.duration_weeks.into()),
+                (PlanIden::CreationDate, self.creation_date.into()),
+                (PlanIden::LastUpdatedDate, self.last_updated_date.into()),
+            ])
+            .and_where(Expr::col(PlanIden::Id).eq(&self.id))
             .build_rusqlite(SqliteQueryBuilder);
 
         let mut stmt = conn.prepare_cached(&sql)?;
-        let res = stmt.query_row(&*values.as_params(), Plan::from_row)?;
-        Ok(res)
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
     }
 
-    pub fn fetch_all(conn: &Connection) -> Result<Vec<Plan>, rusqlite::Error> {
-        let (sql, values) = Self::select_star().build_rusqlite(SqliteQueryBuilder);
+    /// Whether anyone besides the owner is following the plan
+    pub fn in_use(&self, conn: &Connection) -> Result<bool, rusqlite::Error> {
+        any_rows(conn, [Query::select()
+            .expr(Expr::val(1))
+            .from(PlanInstanceIden::Table)
+            .and_where(Expr::col(PlanInstanceIden::PlanId).eq(self.id))
+            .and_where(Expr::col(PlanInstanceIden::UserId).ne(self.owner_id))
+            .to_owned()])
+    }
+
+    pub fn delete<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::delete()
+            .from_table(PlanIden::Table)
+            .and_where(Expr::col(PlanIden::Id).eq(&self.id))
+            .build_rusqlite(SqliteQueryBuilder);
 
         let mut stmt = conn.prepare_cached(&sql)?;
-        let res =
-            stmt.query_map(&*values.as_params(), Plan::from_row)?.collect::<Result<_, _>>()?;
-        Ok(res)
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
     }
 }
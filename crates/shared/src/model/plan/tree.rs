@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use sea_query::{Expr, SelectStatement};
+
+use crate::{
+    model::{
+        Exercise, ExerciseGroup, ExerciseGroupIden, ExerciseGroupMember, ExerciseGroupMemberIden,
+        ExerciseIden, Model, Plan, PlanExerciseGroup, PlanExerciseGroupIden, PlanIden,
+        PlanInstance, PlanInstanceIden, Session, SessionExercise, SessionExerciseIden, SessionIden,
+        UserExercise, UserExerciseIden,
+    },
+    types::Uuid,
+};
+
+/// An exercise along with the user's overrides and every time it's been
+/// performed
+pub type ExerciseTree = (Exercise, Option<UserExercise>, Vec<(SessionExercise, Session)>);
+
+/// A group in a plan along with its exercises
+pub type PlanGroupTree = (PlanExerciseGroup, ExerciseGroup, Vec<ExerciseTree>);
+
+/// One of the user's plan instances along with everything hanging off it
+pub type PlanTree = (Plan, PlanInstance, Vec<PlanGroupTree>);
+
+/// Queries that load every row needed to build the [`PlanTree`]s for a user.
+/// There is one query per table no matter how many plans or how much history
+/// the user has, rather than one per row
+#[derive(Debug, Clone, Copy)]
+pub struct PlanTreeQueries {
+    user_id: Uuid,
+}
+
+impl PlanTreeQueries {
+    pub fn new(user_id: Uuid) -> Self {
+        Self { user_id }
+    }
+
+    /// Restricts the statement to rows belonging to one of the user's plan
+    /// instances. `plan_id` is the column in the statement that holds the plan
+    /// id
+    fn in_users_plans<C: sea_query::Iden + 'static, T: sea_query::Iden + 'static>(
+        &self,
+        mut stmt: SelectStatement,
+        plan_id: (T, C),
+    ) -> SelectStatement {
+        stmt.distinct()
+            .inner_join(
+                PlanInstanceIden::Table,
+                Expr::col((PlanInstanceIden::Table, PlanInstanceIden::PlanId)).equals(plan_id),
+            )
+            .and_where(
+                Expr::col((PlanInstanceIden::Table, PlanInstanceIden::UserId)).eq(&self.user_id),
+            );
+        stmt
+    }
+
+    pub fn plan_instances(&self) -> SelectStatement {
+        let mut stmt = PlanInstance::select_star_qualified();
+        stmt.and_where(Expr::col(PlanInstanceIden::UserId).eq(&self.user_id));
+        stmt
+    }
+
+    pub fn plans(&self) -> SelectStatement {
+        self.in_users_plans(Plan::select_star_qualified(), (PlanIden::Table, PlanIden::Id))
+    }
+
+    pub fn plan_exercise_groups(&self) -> SelectStatement {
+        self.in_users_plans(
+            PlanExerciseGroup::select_star_qualified(),
+            (PlanExerciseGroupIden::Table, PlanExerciseGroupIden::PlanId),
+        )
+    }
+
+    pub fn exercise_groups(&self) -> SelectStatement {
+        let mut stmt = ExerciseGroup::select_star_qualified();
+        stmt.inner_join(
+            PlanExerciseGroupIden::Table,
+            Expr::col((PlanExerciseGroupIden::Table, PlanExerciseGroupIden::ExerciseGroupId))
+                .equals((ExerciseGroupIden::Table, ExerciseGroupIden::Id)),
+        );
+        self.in_users_plans(stmt, (PlanExerciseGroupIden::Table, PlanExerciseGroupIden::PlanId))
+    }
+
+    pub fn exercise_group_members(&self) -> SelectStatement {
+        let mut stmt = ExerciseGroupMember::select_star_qualified();
+        stmt.inner_join(
+            PlanExerciseGroupIden::Table,
+            Expr::col((PlanExerciseGroupIden::Table, PlanExerciseGroupIden::ExerciseGroupId))
+                .equals((ExerciseGroupMemberIden::Table, ExerciseGroupMemberIden::GroupId)),
+        );
+        self.in_users_plans(stmt, (PlanExerciseGroupIden::Table, PlanExerciseGroupIden::PlanId))
+    }
+
+    pub fn exercises(&self) -> SelectStatement {
+        let mut stmt = Exercise::select_star_qualified();
+        stmt.inner_join(
+            ExerciseGroupMemberIden::Table,
+            Expr::col((ExerciseGroupMemberIden::Table, ExerciseGroupMemberIden::ExerciseId))
+                .equals((ExerciseIden::Table, ExerciseIden::Id)),
+        )
+        .inner_join(
+            PlanExerciseGroupIden::Table,
+            Expr::col((PlanExerciseGroupIden::Table, PlanExerciseGroupIden::ExerciseGroupId))
+                .equals((ExerciseGroupMemberIden::Table, ExerciseGroupMemberIden::GroupId)),
+        );
+        self.in_users_plans(stmt, (PlanExerciseGroupIden::Table, PlanExerciseGroupIden::PlanId))
+    }
+
+    pub fn user_exercises(&self) -> SelectStatement {
+        let mut stmt = UserExercise::select_star_qualified();
+        stmt.and_where(Expr::col(UserExerciseIden::UserId).eq(&self.user_id));
+        stmt
+    }
+
+    pub fn session_exercises(&self) -> SelectStatement {
+        let mut stmt = SessionExercise::select_star_qualified();
+        stmt.and_where(Expr::col(SessionExerciseIden::UserId).eq(&self.user_id));
+        stmt
+    }
+
+    pub fn sessions(&self) -> SelectStatement {
+        let mut stmt = Session::select_star_qualified();
+        stmt.and_where(Expr::col(SessionIden::UserId).eq(&self.user_id));
+        stmt
+    }
+}
+
+/// The flat results of the [`PlanTreeQueries`]
+#[derive(Debug, Clone, Default)]
+pub struct PlanTreeRows {
+    pub plan_instances: Vec<PlanInstance>,
+    pub plans: Vec<Plan>,
+    pub plan_exercise_groups: Vec<PlanExerciseGroup>,
+    pub exercise_groups: Vec<ExerciseGroup>,
+    pub exercise_group_members: Vec<ExerciseGroupMember>,
+    pub exercises: Vec<Exercise>,
+    pub user_exercises: Vec<UserExercise>,
+    pub session_exercises: Vec<SessionExercise>,
+    pub sessions: Vec<Session>,
+}
+
+impl PlanTreeRows {
+    #[cfg(feature = "backend")]
+    pub fn fetch(conn: &rusqlite::Connection, user_id: Uuid) -> Result<Self, rusqlite::Error> {
+        let queries = PlanTreeQueries::new(user_id);
+        Ok(Self {
+            plan_instances: PlanInstance::fetch_all_with(conn, &queries.plan_instances())?,
+            plans: Plan::fetch_all_with(conn, &queries.plans())?,
+            plan_exercise_groups: PlanExerciseGroup::fetch_all_with(
+                conn,
+                &queries.plan_exercise_groups(),
+            )?,
+            exercise_groups: ExerciseGroup::fetch_all_with(conn, &queries.exercise_groups())?,
+            exercise_group_members: ExerciseGroupMember::fetch_all_with(
+                conn,
+                &queries.exercise_group_members(),
+            )?,
+            exercises: Exercise::fetch_all_with(conn, &queries.exercises())?,
+            user_exercises: UserExercise::fetch_all_with(conn, &queries.user_exercises())?,
+            session_exercises: SessionExercise::fetch_all_with(conn, &queries.session_exercises())?,
+            sessions: Session::fetch_all_with(conn, &queries.sessions())?,
+        })
+    }
+
+    /// Stitches the rows back together. Rows keep the order they were fetched
+    /// in and anything that doesn't link up to a plan instance is dropped
+    pub fn into_trees(self) -> Vec<PlanTree> {
+        fn by_id<T>(rows: Vec<T>, id: impl Fn(&T) -> Uuid) -> HashMap<Uuid, T> {
+            rows.into_iter().map(|r| (id(&r), r)).collect()
+        }
+        fn group_by<T>(rows: Vec<T>, key: impl Fn(&T) -> Uuid) -> HashMap<Uuid, Vec<T>> {
+            let mut map = HashMap::<_, Vec<_>>::new();
+            for r in rows {
+                map.entry(key(&r)).or_default().push(r);
+            }
+            map
+        }
+
+        let plans = by_id(self.plans, |p| p.id);
+        let plan_exercise_groups = group_by(self.plan_exercise_groups, |peg| peg.plan_id);
+        let exercise_groups = by_id(self.exercise_groups, |eg| eg.id);
+        let members = group_by(self.exercise_group_members, |egm| egm.group_id);
+        let exercises = by_id(self.exercises, |e| e.id);
+        let user_exercises = by_id(self.user_exercises, |ue| ue.exercise_id);
+        let sessions = by_id(self.sessions, |s| s.id);
+        let session_exercises = group_by(self.session_exercises, |se| se.exercise_id);
+
+        let exercise_tree = |exercise: &Exercise| -> ExerciseTree {
+            let sessions = session_exercises
+                .get(&exercise.id)
+                .into_iter()
+                .flatten()
+                .filter_map(|se| sessions.get(&se.session_id).map(|s| (se.clone(), s.clone())))
+                .collect();
+            (exercise.clone(), user_exercises.get(&exercise.id).cloned(), sessions)
+        };
+
+        let group_tree = |peg: &PlanExerciseGroup| -> Option<PlanGroupTree> {
+            let exercise_group = exercise_groups.get(&peg.exercise_group_id)?;
+            let exercises = members
+                .get(&exercise_group.id)
+                .into_iter()
+                .flatten()
+                .filter_map(|egm| exercises.get(&egm.exercise_id))
+                .map(&exercise_tree)
+                .collect();
+            Some((peg.clone(), exercise_group.clone(), exercises))
+        };
+
+        self.plan_instances
+            .into_iter()
+            .filter_map(|plan_instance| {
+                let plan = plans.get(&plan_instance.plan_id)?;
+                let groups = plan_exercise_groups
+                    .get(&plan.id)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(&group_tree)
+                    .collect();
+                Some((plan.clone(), plan_instance, groups))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,119 @@
+use chrono::{DateTime, Utc};
+
+use crate::{
+    api::{
+        error::ServerError,
+        payloads::{Notification, NotificationKind},
+    },
+    feature_model_imports,
+    types::Uuid,
+};
+
+feature_model_imports!();
+
+use std::error::Error;
+
+use sea_query::Order;
+
+/// A push notification waiting to be delivered to one subscription
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Model)]
+#[cfg_attr(feature = "sea-query-enum", enum_def)]
+#[model(table = "push_outbox", check = "migrations/027-push_outbox/up.sql")]
+pub struct QueuedPush {
+    pub id: Uuid,
+    pub push_subscription_id: Uuid,
+    #[model(json)]
+    pub kind: NotificationKind,
+    #[model(json)]
+    pub notification: Notification,
+    /// Sends that have failed so far
+    pub attempts: u32,
+    pub next_attempt_date: DateTime<Utc>,
+    /// When it's too late to be worth delivering
+    pub expiry_date: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub creation_date: DateTime<Utc>,
+}
+
+impl QueuedPush {
+    pub fn new(
+        push_subscription_id: Uuid,
+        kind: NotificationKind,
+        notification: Notification,
+        expiry_date: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            push_subscription_id,
+            kind,
+            notification,
+            attempts: 0,
+            next_attempt_date: now,
+            expiry_date,
+            last_error: None,
+            creation_date: now,
+        }
+    }
+
+    pub fn fetch_for_subscription<T: Error>(
+        conn: &Connection,
+        push_subscription_id: &Uuid,
+    ) -> Result<Vec<QueuedPush>, ServerError<T>> {
+        Ok(Self::fetch_all_by_column(
+            conn,
+            push_subscription_id,
+            QueuedPushIden::PushSubscriptionId,
+        )?)
+    }
+
+    /// Up to `limit` of what's ready for another attempt at `now`, the ones
+    /// that have waited longest first
+    pub fn fetch_ready<T: Error>(
+        conn: &Connection,
+        now: DateTime<Utc>,
+        limit: u64,
+    ) -> Result<Vec<QueuedPush>, ServerError<T>> {
+        let stmt = Self::select_star()
+            .and_where(Expr::col(QueuedPushIden::NextAttemptDate).lte(now))
+            .order_by(QueuedPushIden::NextAttemptDate, Order::Asc)
+            .limit(limit)
+            .to_owned();
+
+        Ok(Self::fetch_all_with(conn, &stmt)?)
+    }
+
+    pub fn update<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::update()
+            .table(QueuedPushIden::Table)
+            .values([
+                (QueuedPushIden::PushSubscriptionId, self.push_subscription_id.into()),
+                (QueuedPushIden::Kind, serde_json::to_string(&self.kind)?.into()),
+                (QueuedPushIden::Notification, serde_json::to_string(&self.notification)?.into()),
+                (QueuedPushIden::Attempts, self.attempts.into()),
+                (QueuedPushIden::NextAttemptDate, self.next_attempt_date.into()),
+                (QueuedPushIden::ExpiryDate, self.expiry_date.into()),
+                (QueuedPushIden::LastError, self.last_error.clone().into()),
+                (QueuedPushIden::CreationDate, self.creation_date.into()),
+            ])
+            .and_where(Expr::col(QueuedPushIden::Id).eq(&self.id))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
+    }
+
+    pub fn delete<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::delete()
+            .from_table(QueuedPushIden::Table)
+            .and_where(Expr::col(QueuedPushIden::Id).eq(&self.id))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
+    }
+}
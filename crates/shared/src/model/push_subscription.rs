@@ -0,0 +1,134 @@
+use chrono::{DateTime, Utc};
+
+use crate::{
+    api::{error::ServerError, payloads::PushSubscriptionInfo},
+    feature_model_imports,
+    types::Uuid,
+};
+
+feature_model_imports!();
+
+use std::error::Error;
+
+use super::PushNotificationSubscription;
+
+/// One of the devices a user gets push notifications on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Model)]
+#[cfg_attr(feature = "sea-query-enum", enum_def)]
+#[model(table = "push_subscription", check = "migrations/024-push_subscription/up.sql")]
+pub struct PushSubscription {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// Unique to the subscription, it's what the push service is sent to
+    pub endpoint: String,
+    /// p256dh key
+    pub key: String,
+    pub auth: String,
+    pub device_label: Option<String>,
+    pub creation_date: DateTime<Utc>,
+    pub last_success_date: Option<DateTime<Utc>>,
+    /// Sends that have failed since the last one that didn't
+    pub failure_count: u32,
+}
+
+impl PushSubscription {
+    pub fn new(
+        user_id: Uuid,
+        subscription: PushNotificationSubscription,
+        device_label: Option<String>,
+    ) -> Self {
+        let PushNotificationSubscription { endpoint, key, auth } = subscription;
+
+        Self {
+            id: Uuid::new_v4(),
+            user_id,
+            endpoint,
+            key,
+            auth,
+            device_label,
+            creation_date: Utc::now(),
+            last_success_date: None,
+            failure_count: 0,
+        }
+    }
+
+    /// What's needed to send to it
+    pub fn subscription(&self) -> PushNotificationSubscription {
+        PushNotificationSubscription {
+            endpoint: self.endpoint.clone(),
+            key: self.key.clone(),
+            auth: self.auth.clone(),
+        }
+    }
+
+    pub fn fetch_for_user<T: Error>(
+        conn: &Connection,
+        user_id: &Uuid,
+    ) -> Result<Vec<PushSubscription>, ServerError<T>> {
+        Ok(Self::fetch_all_by_column(conn, user_id, PushSubscriptionIden::UserId)?)
+    }
+
+    pub fn fetch_by_endpoint<T: Error>(
+        conn: &Connection,
+        endpoint: &str,
+    ) -> Result<Option<PushSubscription>, ServerError<T>> {
+        Ok(Self::fetch_by_column_maybe(conn, endpoint, PushSubscriptionIden::Endpoint)?)
+    }
+
+    pub fn update<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::update()
+            .table(PushSubscriptionIden::Table)
+            .values([
+                (PushSubscriptionIden::UserId, self.user_id.into()),
+                (PushSubscriptionIden::Endpoint, self.endpoint.clone().into()),
+                (PushSubscriptionIden::Key, self.key.clone().into()),
+                (PushSubscriptionIden::Auth, self.auth.clone().into()),
+                (PushSubscriptionIden::DeviceLabel, self.device_label.clone().into()),
+                (PushSubscriptionIden::CreationDate, self.creation_date.into()),
+                (PushSubscriptionIden::LastSuccessDate, self.last_success_date.into()),
+                (PushSubscriptionIden::FailureCount, self.failure_count.into()),
+            ])
+            .and_where(Expr::col(PushSubscriptionIden::Id).eq(&self.id))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
+    }
+
+    pub fn delete<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::delete()
+            .from_table(PushSubscriptionIden::Table)
+            .and_where(Expr::col(PushSubscriptionIden::Id).eq(&self.id))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
+    }
+
+    pub fn record_success<T: Error>(&mut self, conn: &Connection) -> Result<(), ServerError<T>> {
+        self.last_success_date = Some(Utc::now());
+        self.failure_count = 0;
+        self.update(conn)
+    }
+
+    pub fn record_failure<T: Error>(&mut self, conn: &Connection) -> Result<(), ServerError<T>> {
+        self.failure_count += 1;
+        self.update(conn)
+    }
+}
+
+impl From<&PushSubscription> for PushSubscriptionInfo {
+    fn from(value: &PushSubscription) -> Self {
+        Self {
+            id: value.id,
+            device_label: value.device_label.clone(),
+            creation_date: value.creation_date,
+            last_success_date: value.last_success_date,
+            failure_count: value.failure_count,
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+
+use crate::{api::error::ServerError, feature_model_imports, types::Uuid};
+
+feature_model_imports!();
+
+use std::error::Error;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Model)]
+#[cfg_attr(feature = "sea-query-enum", enum_def)]
+#[model(table = "recovery_code", check = "migrations/019-recovery_code/up.sql")]
+pub struct RecoveryCode {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub code_hash: String,
+    pub creation_date: DateTime<Utc>,
+    pub used_date: Option<DateTime<Utc>>,
+}
+
+impl RecoveryCode {
+    pub fn fetch_unused_for_user<T: Error>(
+        conn: &Connection,
+        user_id: &Uuid,
+    ) -> Result<Vec<RecoveryCode>, ServerError<T>> {
+        let (sql, values) = Self::select_star()
+            .and_where(Expr::col(RecoveryCodeIden::UserId).eq(user_id))
+            .and_where(Expr::col(RecoveryCodeIden::UsedDate).is_null())
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        let codes = stmt
+            .query_map(&*values.as_params(), RecoveryCode::from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(codes)
+    }
+
+    /// Replaces all of the user's codes, used or not, with new ones made from
+    /// `code_hashes`. Callers need to be in a transaction so the old codes
+    /// survive a failed insert
+    pub fn replace_for_user<T: Error>(
+        tx: &Connection,
+        user_id: &Uuid,
+        code_hashes: Vec<String>,
+    ) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::delete()
+            .from_table(RecoveryCodeIden::Table)
+            .and_where(Expr::col(RecoveryCodeIden::UserId).eq(user_id))
+            .build_rusqlite(SqliteQueryBuilder);
+        tx.execute(&sql, &*values.as_params())?;
+
+        let now = Utc::now();
+        for code_hash in code_hashes {
+            RecoveryCode {
+                id: Uuid::new_v4(),
+                user_id: *user_id,
+                code_hash,
+                creation_date: now,
+                used_date: None,
+            }
+            .insert(tx)?;
+        }
+
+        Ok(())
+    }
+
+    /// Marks the user's unused code with the given hash as used. Returns
+    /// false if there isn't one
+    pub fn redeem<T: Error>(
+        conn: &Connection,
+        user_id: &Uuid,
+        code_hash: &str,
+    ) -> Result<bool, ServerError<T>> {
+        let (sql, values) = Query::update()
+            .table(RecoveryCodeIden::Table)
+            .values([(RecoveryCodeIden::UsedDate, Some(Utc::now()).into())])
+            .and_where(Expr::col(RecoveryCodeIden::UserId).eq(user_id))
+            .and_where(Expr::col(RecoveryCodeIden::CodeHash).eq(code_hash))
+            .and_where(Expr::col(RecoveryCodeIden::UsedDate).is_null())
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        let updated = stmt.execute(&*values.as_params())?;
+
+        Ok(updated > 0)
+    }
+}
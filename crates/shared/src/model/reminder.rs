@@ -0,0 +1,171 @@
+use chrono::{DateTime, Utc};
+
+use crate::{api::error::ServerError, feature_model_imports, json_sql, types::Uuid};
+
+feature_model_imports!();
+
+use std::error::Error;
+
+use sea_query::Func;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReminderKind {
+    /// Sent a little while before the session is planned for
+    Upcoming,
+    /// Sent the morning after if the session still hasn't been done
+    Missed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReminderState {
+    Pending,
+    /// Queued to go out to the user's devices
+    Sent,
+    /// Not sent because it was too late, the session was done or the user
+    /// doesn't want reminders. Also once the user skips the session from a
+    /// notification
+    Skipped,
+}
+
+json_sql!(ReminderKind, ReminderState);
+
+/// A push notification to send about one of a user's planned sessions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Model)]
+#[cfg_attr(feature = "sea-query-enum", enum_def)]
+#[model(table = "reminder", check = "migrations/028-reminder_snooze/up.sql")]
+pub struct Reminder {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub session_id: Uuid,
+    #[model(json)]
+    pub kind: ReminderKind,
+    #[model(json)]
+    pub state: ReminderState,
+    /// The session's planned date when this was scheduled
+    pub planned_date: DateTime<Utc>,
+    /// When it should be sent, before quiet hours are taken into account
+    pub due_date: DateTime<Utc>,
+    /// When the user asked for it to be sent again
+    pub snoozed_until: Option<DateTime<Utc>>,
+    pub creation_date: DateTime<Utc>,
+    pub last_updated_date: DateTime<Utc>,
+}
+
+impl Reminder {
+    pub fn new(
+        user_id: Uuid,
+        session_id: Uuid,
+        kind: ReminderKind,
+        planned_date: DateTime<Utc>,
+        due_date: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            user_id,
+            session_id,
+            kind,
+            state: ReminderState::Pending,
+            planned_date,
+            due_date,
+            snoozed_until: None,
+            creation_date: now,
+            last_updated_date: now,
+        }
+    }
+
+    pub fn fetch_for_user<T: Error>(
+        conn: &Connection,
+        user_id: &Uuid,
+    ) -> Result<Vec<Reminder>, ServerError<T>> {
+        Ok(Self::fetch_all_by_column(conn, user_id, ReminderIden::UserId)?)
+    }
+
+    pub fn fetch_for_session<T: Error>(
+        conn: &Connection,
+        session_id: &Uuid,
+    ) -> Result<Vec<Reminder>, ServerError<T>> {
+        Ok(Self::fetch_all_by_column(conn, session_id, ReminderIden::SessionId)?)
+    }
+
+    /// The reminders for all of the sessions in one go
+    pub fn fetch_for_sessions<T: Error>(
+        conn: &Connection,
+        session_ids: &[Uuid],
+    ) -> Result<Vec<Reminder>, ServerError<T>> {
+        let stmt = Self::select_star()
+            .and_where(Expr::col(ReminderIden::SessionId).is_in(session_ids.iter().copied()))
+            .to_owned();
+
+        Ok(Self::fetch_all_with(conn, &stmt)?)
+    }
+
+    /// The due date unless it's been snoozed
+    pub fn send_date(&self) -> DateTime<Utc> {
+        self.snoozed_until.unwrap_or(self.due_date)
+    }
+
+    /// Every reminder that hasn't been sent or skipped yet and whose
+    /// [`send_date`](Self::send_date) has come
+    pub fn fetch_pending<T: Error>(
+        conn: &Connection,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<Reminder>, ServerError<T>> {
+        let state = serde_json::to_string(&ReminderState::Pending)?;
+        let send_date = Func::coalesce([
+            Expr::col(ReminderIden::SnoozedUntil).into(),
+            Expr::col(ReminderIden::DueDate).into(),
+        ]);
+        let stmt = Self::select_star()
+            .and_where(Expr::col(ReminderIden::State).eq(state))
+            .and_where(Expr::expr(send_date).lte(now))
+            .to_owned();
+
+        Ok(Self::fetch_all_with(conn, &stmt)?)
+    }
+
+    pub fn update<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::update()
+            .table(ReminderIden::Table)
+            .values([
+                (ReminderIden::UserId, self.user_id.into()),
+                (ReminderIden::SessionId, self.session_id.into()),
+                (ReminderIden::Kind, serde_json::to_string(&self.kind)?.into()),
+                (ReminderIden::State, serde_json::to_string(&self.state)?.into()),
+                (ReminderIden::PlannedDate, self.planned_date.into()),
+                (ReminderIden::DueDate, self.due_date.into()),
+                (ReminderIden::SnoozedUntil, self.snoozed_until.into()),
+                (ReminderIden::CreationDate, self.creation_date.into()),
+                (ReminderIden::LastUpdatedDate, self.last_updated_date.into()),
+            ])
+            .and_where(Expr::col(ReminderIden::Id).eq(&self.id))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
+    }
+
+    pub fn set_state<T: Error>(
+        &mut self,
+        conn: &Connection,
+        state: ReminderState,
+        now: DateTime<Utc>,
+    ) -> Result<(), ServerError<T>> {
+        self.state = state;
+        self.last_updated_date = now;
+        self.update(conn)
+    }
+
+    /// Sends it again at `until`
+    pub fn snooze<T: Error>(
+        &mut self,
+        conn: &Connection,
+        until: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<(), ServerError<T>> {
+        self.snoozed_until = Some(until);
+        self.set_state(conn, ReminderState::Pending, now)
+    }
+}
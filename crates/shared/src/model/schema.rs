@@ -0,0 +1,106 @@
+//! Helpers shared by the client and server migration runners for keeping the
+//! databases in a known state
+
+use const_format::concatcp;
+
+/// The table the migration runners keep the down migration of every applied
+/// migration in. Older code doesn't ship the migrations a newer version added
+/// so this is what lets it roll them back
+pub const MIGRATION_DOWN_TABLE: &str = "migration_down";
+
+pub const CREATE_MIGRATION_DOWN_TABLE: &str = concatcp!(
+    "CREATE TABLE IF NOT EXISTS ",
+    MIGRATION_DOWN_TABLE,
+    " (
+    version     INTEGER PRIMARY KEY,
+    name        TEXT NOT NULL,
+    down        TEXT
+) STRICT"
+);
+
+/// The name a migration is stored under in [`MIGRATION_DOWN_TABLE`], its
+/// directory name without the numeric prefix. scripts/link_migrations numbers
+/// the migrations for each target and always puts the test data last so the
+/// same version can be a different migration in another release
+pub fn migration_name(dir_name: &str) -> &str {
+    dir_name.split_once('-').map_or(dir_name, |(_, name)| name)
+}
+
+/// The highest version up to `version` where each migration the database has
+/// applied is the one at the same position in `local`. Anything above it came
+/// from a different release and has to be rolled back with the stored down
+/// migrations. Versions without a stored name are trusted
+pub fn matching_version<S: AsRef<str>>(
+    local: &[&str],
+    stored: &[(usize, S)],
+    version: usize,
+) -> usize {
+    (1..=version)
+        .take_while(|v| {
+            local.get(v - 1).is_some_and(|name| {
+                stored.iter().find(|(s, _)| s == v).map_or(true, |(_, s)| s.as_ref() == *name)
+            })
+        })
+        .count()
+}
+
+/// Tables that only exist in the server database
+pub const SERVER_ONLY_TABLES: &[&str] = &[
+    "credential",
+    "temporary_login",
+    "temporary_login_audit",
+    "backup",
+    "recovery_code",
+    "login_session",
+    "push_subscription",
+    "reminder",
+    "push_outbox",
+];
+
+/// Describes the schema as one line per column, foreign key and index, each
+/// prefixed with the table name. It's built from the pragmas rather than the
+/// sql in sqlite_master so it doesn't matter how a table ended up the way it
+/// is (rebuilt, renamed, altered) as long as the result is the same
+pub const SCHEMA_QUERY: &str = concatcp!(
+    "WITH tables AS (
+    SELECT name FROM sqlite_master
+    WHERE type = 'table' AND name NOT LIKE 'sqlite_%' AND name != '",
+    MIGRATION_DOWN_TABLE,
+    "'
+)
+SELECT t.name || ' column ' || c.name || ' ' || c.type
+    || iif(c.\"notnull\", ' NOT NULL', '') || iif(c.pk, ' PRIMARY KEY', '')
+    || ifnull(' DEFAULT ' || c.dflt_value, '') AS line
+FROM tables t, pragma_table_info(t.name) c
+UNION ALL
+SELECT t.name || ' foreign key ' || f.\"from\" || ' REFERENCES ' || f.\"table\"
+    || '(' || ifnull(f.\"to\", '') || ') ON DELETE ' || f.on_delete || ' ON UPDATE ' || f.on_update
+FROM tables t, pragma_foreign_key_list(t.name) f
+UNION ALL
+SELECT t.name || iif(i.\"unique\", ' unique', '') || ' index '
+    || iif(i.origin = 'c', i.name, i.origin) || ' ('
+    || (
+        SELECT group_concat(ifnull(x.name, '<expression>'), ', ')
+        FROM (SELECT * FROM pragma_index_xinfo(i.name) WHERE key ORDER BY seqno) x
+    ) || ')'
+FROM tables t, pragma_index_list(t.name) i
+ORDER BY line"
+);
+
+/// The output of [`SCHEMA_QUERY`] for a client database migrated to the
+/// latest version. The server tests check it against the server migrations
+/// (minus [`SERVER_ONLY_TABLES`]) so it has to be updated along with them
+pub const CLIENT_SCHEMA: &str = include_str!("../../schema/client.txt");
+
+/// Compares the lines returned by [`SCHEMA_QUERY`] against the expected
+/// schema. Returns the lines that are missing prefixed with `-` and the ones
+/// that shouldn't be there prefixed with `+`. Empty if they match
+pub fn schema_diff<S: AsRef<str>>(expected: &str, actual: &[S]) -> Vec<String> {
+    let actual = actual.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+    let expected = expected.lines().filter(|l| !l.trim().is_empty()).collect::<Vec<_>>();
+
+    let missing = expected.iter().filter(|l| !actual.contains(l)).map(|l| format!("-{l}"));
+    let extra = actual.iter().filter(|l| !expected.contains(l)).map(|l| format!("+{l}"));
+
+    missing.chain(extra).collect()
+}
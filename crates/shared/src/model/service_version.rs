@@ -2,7 +2,7 @@ use std::fmt::Display;
 
 use chrono::{DateTime, Utc};
 
-use crate::{feature_model_derives, feature_model_imports, types::Uuid};
+use crate::{feature_model_imports, types::Uuid};
 
 feature_model_imports!();
 
@@ -15,15 +15,14 @@ use {
     std::error::Error,
 };
 
-feature_model_derives!(
-    "service_version",
-    "../../migrations/012-service_version/up.sql",
-    pub struct ServiceVersion {
-        pub id: Uuid,
-        pub version: String,
-        pub creation_date: DateTime<Utc>,
-    }
-);
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Model)]
+#[cfg_attr(feature = "sea-query-enum", enum_def)]
+#[model(table = "service_version", check = "migrations/012-service_version/up.sql")]
+pub struct ServiceVersion {
+    pub id: Uuid,
+    pub version: String,
+    pub creation_date: DateTime<Utc>,
+}
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[cfg_attr(feature = "backend", derive(ExemplarModel))]
@@ -64,8 +63,7 @@ impl ServiceVersion {
             .build_rusqlite(SqliteQueryBuilder);
 
         let mut stmt = conn.prepare_cached(&sql)?;
-        let value =
-            stmt.query_row(&*values.as_params(), <Self as ExemplarModel>::from_row).optional()?;
+        let value = stmt.query_row(&*values.as_params(), Self::from_row).optional()?;
 
         Ok(value)
     }
@@ -77,7 +75,7 @@ impl ServiceVersion {
         let tx = conn.transaction()?;
         let new_service_version = {
             new_service_version.insert(&tx)?;
-            ServiceVersion::fetch_by_id(&tx, &new_service_version.id)?
+            ServiceVersion::fetch_by_id(&tx, new_service_version.id)?
         };
         tx.commit()?;
 
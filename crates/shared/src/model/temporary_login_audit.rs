@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+
+use crate::{api::error::ServerError, feature_model_imports, types::Uuid};
+
+feature_model_imports!();
+
+use std::error::Error;
+
+use rusqlite::{
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
+    ToSql,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TemporaryLoginEvent {
+    Created,
+    /// Logged in with, by url or pin
+    Used,
+    Revoked,
+    /// A wrong pin was given for the user's code
+    Failed,
+}
+
+impl ToSql for TemporaryLoginEvent {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        serde_json::to_string(self)
+            .map(ToSqlOutput::from)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+    }
+}
+
+impl FromSql for TemporaryLoginEvent {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        <serde_json::Value as FromSql>::column_result(value)
+            .and_then(|v| serde_json::from_value(v).map_err(|e| FromSqlError::Other(Box::new(e))))
+    }
+}
+
+/// A record of something happening to one of a user's temporary logins. They're
+/// kept after the temporary login is gone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Model)]
+#[cfg_attr(feature = "sea-query-enum", enum_def)]
+#[model(table = "temporary_login_audit", check = "migrations/021-temporary_login_hardening/up.sql")]
+pub struct TemporaryLoginAudit {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub temporary_login_id: Uuid,
+    #[model(json)]
+    pub event: TemporaryLoginEvent,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub creation_date: DateTime<Utc>,
+}
+
+impl TemporaryLoginAudit {
+    pub fn record<T: Error>(
+        conn: &Connection,
+        user_id: &Uuid,
+        temporary_login_id: &Uuid,
+        event: TemporaryLoginEvent,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<(), ServerError<T>> {
+        TemporaryLoginAudit {
+            id: Uuid::new_v4(),
+            user_id: *user_id,
+            temporary_login_id: *temporary_login_id,
+            event,
+            ip_address,
+            user_agent,
+            creation_date: Utc::now(),
+        }
+        .insert(conn)?;
+
+        Ok(())
+    }
+
+    pub fn fetch_for_user<T: Error>(
+        conn: &Connection,
+        user_id: &Uuid,
+    ) -> Result<Vec<TemporaryLoginAudit>, ServerError<T>> {
+        Ok(Self::fetch_all_by_column(conn, user_id, TemporaryLoginAuditIden::UserId)?)
+    }
+}
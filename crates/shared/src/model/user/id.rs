@@ -1,8 +1,8 @@
 use std::{fmt, ops::Deref};
 
-#[cfg(feature = "backend")]
-use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "backend")]
+use {crate::model::Model as _, rusqlite::Connection};
 
 use crate::{model::User, types::Uuid};
 
@@ -33,7 +33,7 @@ impl From<&User> for UserId {
 #[cfg(feature = "backend")]
 impl UserId {
     pub fn fetch_full_user(&self, conn: &Connection) -> Result<User, rusqlite::Error> {
-        let user = User::fetch_by_id(conn, &self.id)?;
+        let user = User::fetch_by_id(conn, self.id)?;
         Ok(user)
     }
 }
@@ -13,6 +13,9 @@ pub use login::*;
 mod id;
 pub use id::*;
 
+mod preferences;
+pub use preferences::*;
+
 mod push_subscription;
 pub use push_subscription::*;
 
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use {
     crate::{
         api::error::{ServerError, ServerErrorContext},
-        model::{Credential, NewCredential, User},
+        model::{Credential, Model as _, NewCredential, User},
     },
     exemplar::Model,
     rusqlite::Connection,
@@ -46,30 +46,25 @@ impl NewUserWithPasskey {
         Self { id: id.into(), username: username.into(), passkey }
     }
 
-    pub fn create<T: Error>(
-        self,
-        conn: &mut Connection,
-    ) -> Result<(User, Credential), ServerError<T>> {
-        let tx = conn.transaction()?;
-
+    /// Inserts the user and their passkey. Doesn't start a transaction so it
+    /// can be combined with other setup, callers need to be in one
+    pub fn create<T: Error>(self, tx: &Connection) -> Result<(User, Credential), ServerError<T>> {
         let (new_user, passkey) = self.split();
         let user_id = new_user.id.clone();
         let new_credential = NewCredential::new(new_user.id.clone(), passkey.into());
 
         let user = {
-            new_user.insert(&tx).context("NewUserWithPasskey::insert(User)")?;
+            new_user.insert(tx).context("NewUserWithPasskey::insert(User)")?;
 
-            User::fetch_by_id(&tx, &user_id).context("NewUserWithPasskey::fetch(User)")?
+            User::fetch_by_id(tx, user_id).context("NewUserWithPasskey::fetch(User)")?
         };
 
         let credential = {
-            new_credential.insert(&tx).context("NewUserWithPasskey::insert(Credential)")?;
-            Credential::fetch(&tx, &new_credential.id)
+            new_credential.insert(tx).context("NewUserWithPasskey::insert(Credential)")?;
+            Credential::fetch(tx, &new_credential.id)
                 .context("NewUserWithPasskey::fetch(Credential)")?
         };
 
-        tx.commit()?;
-
         Ok((user, credential))
     }
 }
@@ -0,0 +1,202 @@
+use chrono::{NaiveTime, Weekday};
+use chrono_tz::Tz;
+#[cfg(feature = "backend")]
+use rusqlite::{
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
+    ToSql,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{api::error::ValidationError, model::ValidateModel};
+
+pub const MAX_REST_TIMER_SECONDS: u32 = 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum Units {
+    #[default]
+    Metric,
+    Imperial,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RestTimerPreferences {
+    /// How long the timer runs for between sets
+    pub seconds: u32,
+    /// Start the timer when a set is completed
+    pub auto_start: bool,
+}
+
+impl Default for RestTimerPreferences {
+    fn default() -> Self {
+        Self { seconds: 90, auto_start: true }
+    }
+}
+
+/// No notifications are sent between `start` and `end`, in the user's timezone.
+/// `start` after `end` spans midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationPreferences {
+    /// Reminders about planned sessions
+    pub session_reminders: bool,
+    pub quiet_hours: Option<QuietHours>,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self { session_reminders: true, quiet_hours: None }
+    }
+}
+
+/// Settings that follow the user between devices. Missing fields take their
+/// defaults so new ones can be added without a migration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub units: Units,
+    pub first_day_of_week: Weekday,
+    /// IANA name like `Europe/London`. None uses the device's timezone
+    pub timezone: Option<String>,
+    pub rest_timer: RestTimerPreferences,
+    pub notifications: NotificationPreferences,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            units: Default::default(),
+            first_day_of_week: Weekday::Mon,
+            timezone: None,
+            rest_timer: Default::default(),
+            notifications: Default::default(),
+        }
+    }
+}
+
+impl ValidateModel for Preferences {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut error_messages = Vec::new();
+
+        if let Some(timezone) = &self.timezone {
+            if timezone.parse::<Tz>().is_err() {
+                error_messages.push(format!("{timezone:?} isn't a timezone"));
+            }
+        }
+        if self.rest_timer.seconds > MAX_REST_TIMER_SECONDS {
+            error_messages.push(format!(
+                "The rest timer can't be longer than {} minutes",
+                MAX_REST_TIMER_SECONDS / 60
+            ));
+        }
+        if let Some(quiet_hours) = &self.notifications.quiet_hours {
+            if quiet_hours.start == quiet_hours.end {
+                error_messages.push("Quiet hours can't start and end at the same time".to_string());
+            }
+        }
+
+        if error_messages.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError { error_messages })
+        }
+    }
+}
+
+#[cfg(feature = "backend")]
+impl Preferences {
+    fn to_json_string(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+#[cfg(feature = "backend")]
+impl ToSql for Preferences {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        self.to_json_string()
+            .map(ToSqlOutput::from)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+    }
+}
+
+#[cfg(feature = "backend")]
+impl FromSql for Preferences {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        <serde_json::Value as FromSql>::column_result(value)
+            .and_then(|v| serde_json::from_value(v).map_err(|e| FromSqlError::Other(Box::new(e))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_fields_are_defaulted() {
+        assert_eq!(serde_json::from_str::<Preferences>("{}").unwrap(), Preferences::default());
+
+        let preferences: Preferences =
+            serde_json::from_str(r#"{ "units": "Imperial", "rest_timer": { "seconds": 30 } }"#)
+                .unwrap();
+        assert_eq!(preferences.units, Units::Imperial);
+        assert_eq!(preferences.rest_timer.seconds, 30);
+        assert!(preferences.rest_timer.auto_start);
+        assert_eq!(preferences.first_day_of_week, Weekday::Mon);
+    }
+
+    #[test]
+    fn quiet_hours_can_span_midnight() {
+        let time = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+
+        let night = QuietHours { start: time(22), end: time(7) };
+        assert!(night.contains(time(23)));
+        assert!(night.contains(time(0)));
+        assert!(!night.contains(time(7)));
+        assert!(!night.contains(time(12)));
+
+        let afternoon = QuietHours { start: time(13), end: time(15) };
+        assert!(afternoon.contains(time(13)));
+        assert!(!afternoon.contains(time(15)));
+        assert!(!afternoon.contains(time(23)));
+    }
+
+    #[test]
+    fn preferences_are_validated() {
+        assert!(Preferences::default().validate().is_ok());
+
+        for timezone in ["Europe/London", "UTC", "America/Argentina/Buenos_Aires", "Etc/GMT+10"] {
+            let preferences =
+                Preferences { timezone: Some(timezone.to_string()), ..Default::default() };
+            assert!(preferences.validate().is_ok(), "{timezone}");
+        }
+
+        for timezone in ["Europe//London", "Mars/Olympus_Mons", "utc", ""] {
+            let preferences =
+                Preferences { timezone: Some(timezone.to_string()), ..Default::default() };
+            assert!(preferences.validate().is_err(), "{timezone}");
+        }
+
+        let mut preferences =
+            Preferences { timezone: Some("Europe//London".to_string()), ..Default::default() };
+        preferences.rest_timer.seconds = MAX_REST_TIMER_SECONDS + 1;
+        let midnight = NaiveTime::MIN;
+        preferences.notifications.quiet_hours = Some(QuietHours { start: midnight, end: midnight });
+        assert_eq!(preferences.validate().unwrap_err().error_messages.len(), 3);
+    }
+}
@@ -1,6 +1,6 @@
 use chrono::{DateTime, Utc};
 
-use crate::{api::Object, feature_model_derives, feature_model_imports, types::Uuid};
+use crate::{api::Object, feature_model_imports, types::Uuid};
 
 feature_model_imports!();
 
@@ -8,16 +8,18 @@ use percent_encoding::{percent_encode, NON_ALPHANUMERIC};
 #[cfg(feature = "backend")]
 use {crate::api::error::ServerError, rusqlite::OptionalExtension, std::error::Error};
 
-feature_model_derives!(
-    "temporary_login",
-    "../../../migrations/003-temporary_login/up.sql",
-    pub struct TemporaryLogin {
-        pub id: Uuid,
-        pub user_id: Uuid,
-        pub expiry_date: DateTime<Utc>,
-        pub url: String,
-    }
-);
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Model)]
+#[cfg_attr(feature = "sea-query-enum", enum_def)]
+#[model(table = "temporary_login", check = "migrations/021-temporary_login_hardening/up.sql")]
+pub struct TemporaryLogin {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub expiry_date: DateTime<Utc>,
+    pub url: String,
+    /// Only set if the user asked for one. Typed in with the username on the
+    /// other device instead of following the url
+    pub pin: Option<String>,
+}
 
 impl TemporaryLogin {
     pub fn qr_code_url(&self) -> String {
@@ -61,6 +63,8 @@ impl TemporaryLogin {
         Ok(temporary_login)
     }
 
+    /// A user has at most one temporary login at a time, which is enforced by a
+    /// unique index. It may have expired
     pub fn fetch_by_user_id<T: Error>(
         conn: &Connection,
         id: &Uuid,
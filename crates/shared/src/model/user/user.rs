@@ -1,6 +1,6 @@
 use chrono::{DateTime, Utc};
 
-use crate::{feature_model_derives, feature_model_imports, types::Uuid};
+use crate::{feature_model_imports, types::Uuid};
 
 feature_model_imports!();
 
@@ -14,39 +14,28 @@ use {
     webauthn_rs::prelude::Passkey,
 };
 
-use super::PushNotificationSubscription;
-
-feature_model_derives!(
-    "user",
-    "../../../migrations/001-user/up.sql",
-    pub struct User {
-        pub id: Uuid,
-        pub username: String,
-        pub email: Option<String>,
-        pub display_name: Option<String>,
-        pub push_notification_subscription: Option<PushNotificationSubscription>,
-        pub creation_date: DateTime<Utc>,
-        pub last_updated_date: DateTime<Utc>,
-        pub last_login_date: Option<DateTime<Utc>>,
-    }
-);
+use super::Preferences;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Model)]
+#[cfg_attr(feature = "sea-query-enum", enum_def)]
+#[model(table = "user", check = "migrations/025-user_drop_push_column/up.sql")]
+pub struct User {
+    pub id: Uuid,
+    pub username: String,
+    pub email: Option<String>,
+    pub display_name: Option<String>,
+    pub creation_date: DateTime<Utc>,
+    pub last_updated_date: DateTime<Utc>,
+    pub last_login_date: Option<DateTime<Utc>>,
+    #[model(json)]
+    pub preferences: Preferences,
+}
 
 #[cfg(feature = "wasm")]
 impl crate::model::model_into_view::UseDefaultModelView for User {}
 
 #[cfg(feature = "backend")]
 impl User {
-    pub fn fetch_by_id(conn: &Connection, id: &Uuid) -> Result<User, rusqlite::Error> {
-        let (sql, values) = Self::select_star()
-            .and_where(Expr::col(UserIden::Id).eq(id))
-            .limit(1)
-            .build_rusqlite(SqliteQueryBuilder);
-
-        let mut stmt = conn.prepare_cached(&sql)?;
-        let user = stmt.query_row(&*values.as_params(), User::from_row)?;
-        Ok(user)
-    }
-
     pub fn create<T: Error>(
         conn: &mut Connection,
         new_user: NewUser,
@@ -54,7 +43,7 @@ impl User {
         let tx = conn.transaction()?;
         let user = {
             new_user.insert(&tx)?;
-            User::fetch_by_id(&tx, &new_user.id)?
+            User::fetch_by_id(&tx, new_user.id)?
         };
         tx.commit()?;
 
@@ -68,13 +57,10 @@ impl User {
                 (UserIden::Username, self.username.clone().into()),
                 (UserIden::Email, self.email.clone().into()),
                 (UserIden::DisplayName, self.display_name.clone().into()),
-                (
-                    UserIden::PushNotificationSubscription,
-                    self.push_notification_subscription.clone().into(),
-                ),
                 (UserIden::CreationDate, self.creation_date.into()),
                 (UserIden::LastUpdatedDate, self.last_updated_date.into()),
                 (UserIden::LastLoginDate, self.last_login_date.into()),
+                (UserIden::Preferences, serde_json::to_string(&self.preferences)?.into()),
             ])
             .and_where(Expr::col(UserIden::Id).eq(&self.id))
             .build_rusqlite(SqliteQueryBuilder);
@@ -85,6 +71,19 @@ impl User {
         Ok(())
     }
 
+    /// Everything belonging to the user goes with them through the foreign keys
+    pub fn delete<T: Error>(&self, conn: &Connection) -> Result<(), ServerError<T>> {
+        let (sql, values) = Query::delete()
+            .from_table(UserIden::Table)
+            .and_where(Expr::col(UserIden::Id).eq(&self.id))
+            .build_rusqlite(SqliteQueryBuilder);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        stmt.execute(&*values.as_params())?;
+
+        Ok(())
+    }
+
     pub fn add_passkey<T: Error>(
         mut self,
         conn: &mut Connection,
@@ -114,20 +113,4 @@ impl User {
     ) -> Result<Option<TemporaryLogin>, ServerError<T>> {
         TemporaryLogin::fetch_by_user_id(conn, &self.id)
     }
-
-    pub fn fetch_all_with_push_notifications_enabled(
-        conn: &Connection,
-    ) -> Result<Vec<User>, rusqlite::Error> {
-        let (sql, values) = Self::select_star()
-            .and_where(Expr::col(UserIden::PushNotificationSubscription).is_not_null())
-            .build_rusqlite(SqliteQueryBuilder);
-
-        let mut stmt = conn.prepare_cached(&sql)?;
-
-        let results = stmt
-            .query_and_then(&*values.as_params(), Self::from_row)?
-            .collect::<Result<Vec<_>, _>>()?;
-
-        Ok(results)
-    }
 }
@@ -65,6 +65,13 @@ impl From<Uuid> for sea_query::Value {
     }
 }
 
+#[cfg(feature = "sea-query-enum")]
+impl sea_query::Nullable for Uuid {
+    fn null() -> sea_query::Value {
+        sea_query::Value::String(None)
+    }
+}
+
 impl Uuid {
     pub fn new_v4() -> Self {
         uuid::Uuid::new_v4().into()
@@ -7,6 +7,9 @@ pub enum ServerUser {
     Login,
     /// User logged out
     Logout,
+    /// A logged in device has decided on this device's pairing request. If it
+    /// was approved the pairing can be finished to log in
+    Pairing { approved: bool },
 }
 
 impl From<ServerUser> for ServerMessage {
@@ -19,16 +19,25 @@ impl Csrf {
         Ok(if let Some(csrf) = use_context::<Self>() {
             csrf
         } else {
-            let response = Request::get(Object::Ping.path()).send().await?;
-
-            let token = Self::token_from_response(&response)?;
-
-            let csrf = Csrf { token };
+            let csrf = Self::fetch().await?;
             csrf.provide_context();
             csrf
         })
     }
 
+    /// Gets a new token without keeping it in the context. For the service
+    /// worker which has no reactive owner to provide it with
+    pub async fn fetch<E>() -> Result<Self, FrontendError<E>>
+    where
+        E: Error + Display,
+    {
+        let response = Request::get(Object::Ping.path()).send().await?;
+
+        let token = Self::token_from_response(&response)?;
+
+        Ok(Csrf { token })
+    }
+
     pub fn add_to(&self, builder: RequestBuilder) -> RequestBuilder {
         builder.header(CSRF_HEADER, self.token())
     }
@@ -0,0 +1,32 @@
+-- Removes the test data along with anything the test user has done with it since. Rows are
+-- deleted children first since not all of the foreign keys cascade
+DELETE FROM session_exercise
+WHERE session_id IN (SELECT id FROM session WHERE user_id = 'cb4d23ae-ac0e-455e-ab70-c16c65894009');
+DELETE FROM session
+WHERE user_id = 'cb4d23ae-ac0e-455e-ab70-c16c65894009';
+
+DELETE FROM plan_instance
+WHERE id = 'd90f7e50-77f5-49cf-b70a-f98fdfc3a410';
+DELETE FROM plan_exercise_group
+WHERE id IN ('5f7d726a-a440-4afa-9e20-b5108442a34d', 'ef1a6ad2-1005-4ed1-ace6-5dbf4634b1d3');
+DELETE FROM plan
+WHERE id = '173cbd20-4fa0-4de4-bed8-40eb2111a92d';
+
+DELETE FROM exercise_group
+WHERE id IN ('f448d7a6-a044-4818-9c98-e9f22f2f1fed', '9b358007-2ce7-40c9-8367-497d9c55e50e');
+DELETE FROM exercise
+WHERE id IN (
+    '3d551aeb-6294-4634-b138-d29159e1ea5d',
+    '5539be81-057a-4b25-92ce-475927d140a2',
+    '553a5fed-905d-4df3-a9c1-53af8ba8bc91',
+    '5c8c1e48-44ef-437b-9a66-cab3fea26f79',
+    'f7cecea2-ed71-44ef-b301-a47224dce895',
+    'e61c4c8e-de48-4653-ac3b-07da2ff2e351',
+    'cbca86c3-b296-4741-a1d1-9b61b6acc191',
+    '16a8d0bc-b08d-4bb1-877d-d98cc096f52c',
+    'fcc517c0-2d3b-421b-b3b2-ce8109996ac1',
+    'a6be39f2-344a-471f-ad07-8b555f638806'
+);
+
+DELETE FROM user
+WHERE id = 'cb4d23ae-ac0e-455e-ab70-c16c65894009';
@@ -1,12 +1,8 @@
 -- Note the IDs need to be provided (rather than a generation fn in the db) so they match
 -- between the server and client databases
 
-INSERT INTO user (id, username, push_notification_subscription) 
-VALUES ('cb4d23ae-ac0e-455e-ab70-c16c65894009', 'daniel', '{
-  "endpoint": "https://updates.push.services.mozilla.com/wpush/v2/gAAAAABmfZ1to_S5Rch9W0YTKrweajQpOdtBK18jNLEHN7MaHJXBOzrQ6N7-c77Au8_ifhcaL1NTYmVx5dAVBbWSqs2fdlioc9Gedg_4yLJxsI57Y5gMoUnzd9B3AsKddTtRJ5SQ93IAfFOgErpKIK9x_b7Tb4JrkO2xdA1acM9sZL-u3gx5dvw",
-  "key": "BJfDBLhI5TdKNWqChltn36zYmHHYrforWD94jJ3A98cXmclUrOId5HZDnQuH1WEn4zR6pSR2l0Tnat5fZL9yEV0=",
-  "auth": "haauF_uaL24NyIk_yZYaVQ=="
-}');
+INSERT INTO user (id, username) 
+VALUES ('cb4d23ae-ac0e-455e-ab70-c16c65894009', 'daniel');
 
 INSERT INTO exercise (id, name) 
 VALUES ('3d551aeb-6294-4634-b138-d29159e1ea5d', 'Squat, front (barbell)');
@@ -0,0 +1,8 @@
+DELETE FROM push_subscription
+WHERE id = '7d0b8a3e-5c2f-4f6e-9b1a-2e8c4d6f0a13';
+DELETE FROM credential
+WHERE id IN (
+    '"mIIyu1duiNH8yVjxH50Th4Dw2kzLXGn0fnGSVS3gTVA"',
+    '"kM90RFXtSaA-qFuPdqB1xt1HKHCxHmq1-LwYDyRUhHY"',
+    '"AbIge4AAM-yySxAovbIACv0uUeA9lwCE4oL9x_vbSi9EaUOplI_lHC8MphJkweXh4QzlleIagJQDmOKwozbH56w"'
+);
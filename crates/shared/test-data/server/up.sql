@@ -116,4 +116,14 @@ VALUES (
     },
     "attestation_format": "none"
   }
-}');
\ No newline at end of file
+}');
+
+INSERT INTO push_subscription (id, user_id, endpoint, key, auth, device_label)
+VALUES (
+'7d0b8a3e-5c2f-4f6e-9b1a-2e8c4d6f0a13',
+'cb4d23ae-ac0e-455e-ab70-c16c65894009',
+'https://updates.push.services.mozilla.com/wpush/v2/gAAAAABmfZ1to_S5Rch9W0YTKrweajQpOdtBK18jNLEHN7MaHJXBOzrQ6N7-c77Au8_ifhcaL1NTYmVx5dAVBbWSqs2fdlioc9Gedg_4yLJxsI57Y5gMoUnzd9B3AsKddTtRJ5SQ93IAfFOgErpKIK9x_b7Tb4JrkO2xdA1acM9sZL-u3gx5dvw',
+'BJfDBLhI5TdKNWqChltn36zYmHHYrforWD94jJ3A98cXmclUrOId5HZDnQuH1WEn4zR6pSR2l0Tnat5fZL9yEV0=',
+'haauF_uaL24NyIk_yZYaVQ==',
+'Firefox'
+);